@@ -332,6 +332,11 @@ impl<'o> WalkProc<'o> {
     }
 
     fn visit_var_stmt(&mut self, location: Location, var: &'o VarStatement) {
+        if !var.has_implied_value() {
+            for size in var.dimensions.iter().flatten() {
+                self.visit_expression(location, size, None);
+            }
+        }
         self.visit_var(location, &var.var_type, &var.name, var.value.as_ref())
     }
 
@@ -417,7 +422,7 @@ impl<'o> WalkProc<'o> {
             Term::Null => StaticType::None,
             Term::Int(_) => StaticType::None,
             Term::Float(_) => StaticType::None,
-            Term::String(_) => StaticType::None,
+            Term::String(_) | Term::RawString(_) => StaticType::None,
             Term::Resource(_) => StaticType::None,
             Term::As(_) => StaticType::None,
 
@@ -426,6 +431,17 @@ impl<'o> WalkProc<'o> {
                 self.visit_prefab(location, prefab);
                 StaticType::None
             },
+            Term::ProcReference(reference) => {
+                let owner = match reference.ty {
+                    None => Some(self.ty),
+                    Some(ref path) if path.is_empty() => Some(self.objtree.root()),
+                    Some(ref path) => self.ty.navigate_path(path).map(|nav| nav.ty()),
+                };
+                if let Some(decl) = owner.and_then(|ty| ty.get_proc_declaration(&reference.name)) {
+                    self.tab.use_symbol(decl.id, location);
+                }
+                StaticType::None
+            },
             Term::InterpString(_, parts) => {
                 for (ref expr, _) in parts.iter() {
                     if let Some(expr) = expr {
@@ -637,7 +653,8 @@ impl<'o> WalkProc<'o> {
             if let Expression::AssignOp { op: AssignOp::Assign, lhs, rhs } = arg {
                 match lhs.as_term() {
                     Some(Term::Ident(_name)) |
-                    Some(Term::String(_name)) => {
+                    Some(Term::String(_name)) |
+                    Some(Term::RawString(_name)) => {
                         // Don't visit_expression the kwarg key.
                         argument_value = rhs;
 
@@ -660,7 +677,8 @@ impl<'o> WalkProc<'o> {
             if let Expression::AssignOp { op: AssignOp::Assign, lhs, rhs } = arg {
                 match lhs.as_term() {
                     Some(Term::Ident(_name)) |
-                    Some(Term::String(_name)) => {
+                    Some(Term::String(_name)) |
+                    Some(Term::RawString(_name)) => {
                         // Don't visit_expression the kwarg key.
                         argument_value = rhs;
                     }
@@ -499,6 +499,12 @@ impl<'a> Engine<'a> {
             table
         });
 
+        let defines = pp.finalize();
+        if ctx.config().langserver.dreamchecker {
+            dreamchecker::check_macro_names(ctx, &defines);
+        }
+        self.defines = Some(defines);
+
         // Lock the diagnostics tracker now to avoid dreamchecker winning the race.
         let mut diagnostics_lock = self.diagnostics_tracker.lock().unwrap();
 
@@ -540,8 +546,6 @@ impl<'a> Engine<'a> {
         diagnostics_lock.send(map);
         drop(diagnostics_lock);
 
-        self.defines = Some(pp.finalize());
-
         let elapsed = start.elapsed(); start += elapsed;
         eprint!(" - diagnostics {}.{:03}s", elapsed.as_secs(), elapsed.subsec_millis());
 
@@ -1962,23 +1966,26 @@ handle_method_call! {
                 if span.start.file != file_id {
                     continue;
                 }
-                match annotation {
-                    Annotation::Include(path) |
-                    Annotation::Resource(path) => {
-                        let pathbuf = if path.is_relative() {
-                            std::env::current_dir().map_err(invalid_request)?.join(&path)
-                        } else {
-                            path.to_owned()
-                        };
-                        results.push(DocumentLink {
-                            range: span_to_range(span.start..span.end.add_columns(1)),
-                            target: Some(path_to_url(pathbuf)?),
-                            tooltip: None,
-                            data: None,
-                        });
-                    }
-                    _ => {}
-                }
+                let path = match annotation {
+                    Annotation::Include(path) => path.to_owned(),
+                    // Resources are looked up through FILE_DIR.
+                    Annotation::Resource(path) => match self.context.resolve_resource(&path.to_string_lossy()) {
+                        Some(resolved) => resolved,
+                        None => path.to_owned(),
+                    },
+                    _ => continue,
+                };
+                let pathbuf = if path.is_relative() {
+                    std::env::current_dir().map_err(invalid_request)?.join(&path)
+                } else {
+                    path
+                };
+                results.push(DocumentLink {
+                    range: span_to_range(span.start..span.end.add_columns(1)),
+                    target: Some(path_to_url(pathbuf)?),
+                    tooltip: None,
+                    data: None,
+                });
             }
 
             Some(results)
@@ -0,0 +1,189 @@
+//! Detection of typed locals which are dereferenced while they can only be
+//! null.
+//!
+//! This is stronger than a local merely possibly being null: every path to
+//! the dereference leaves the local holding `null`, which usually means the
+//! call which was meant to initialize it has been removed or renamed.
+
+use dm::{Context, DMError, Location, Severity};
+use dm::ast::*;
+use dm::objtree::ProcRef;
+
+use crate::cfg::{declared_once, local_declarations, ControlFlowGraph, LocalKind, Step};
+use crate::visit::visit;
+
+pub fn check_always_null(context: &Context, proc: ProcRef, graph: &ControlFlowGraph) {
+    let vars = TypedLocals::collect(proc, graph);
+    if vars.names.is_empty() {
+        return;
+    }
+
+    // Forward may-be-non-null to a fixed point. Before its declaration a
+    // local has no value, so everything starts out null.
+    let count = vars.names.len();
+    let mut state_in = vec![vec![false; count]; graph.blocks.len()];
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (id, block) in graph.blocks.iter().enumerate() {
+            let mut state = state_in[id].clone();
+            for step in block.steps.iter() {
+                vars.transfer(&mut state, step);
+            }
+            for &(_, next) in block.successors.iter() {
+                for (each, &other) in state_in[next].iter_mut().zip(state.iter()) {
+                    if other && !*each {
+                        *each = true;
+                        changed = true;
+                    }
+                }
+            }
+        }
+    }
+
+    // A spawned block runs after the proc sleeps or returns, by which time
+    // the locals it reads may have been assigned, so only check the main flow.
+    let reachable = graph.reachable(false);
+    let mut first_deref: Vec<Option<Location>> = vec![None; count];
+    for (id, block) in graph.blocks.iter().enumerate() {
+        if !reachable[id] {
+            continue;
+        }
+        let mut state = state_in[id].clone();
+        for step in block.steps.iter() {
+            let mut assigned = vec![false; count];
+            let mut derefs = Vec::new();
+            for expr in step.expressions() {
+                visit(expr, &mut |expr| match expr {
+                    Expression::AssignOp { lhs, .. } => {
+                        if let Some(var) = vars.local(lhs) {
+                            assigned[var] = true;
+                        }
+                    }
+                    Expression::Base { term, follow } => {
+                        if let (Term::Ident(name), Some(first)) = (&term.elem, follow.first()) {
+                            if let Some(var) = vars.index(name) {
+                                if is_deref(&first.elem) {
+                                    derefs.push((var, term.location));
+                                }
+                            }
+                        }
+                    }
+                    _ => {}
+                });
+            }
+            // A local assigned within the same step may be assigned before
+            // it is dereferenced, so give it the benefit of the doubt.
+            for (var, location) in derefs {
+                if !state[var] && !assigned[var] && first_deref[var].is_none_or(|first| location < first) {
+                    first_deref[var] = Some(location);
+                }
+            }
+            vars.transfer(&mut state, step);
+        }
+    }
+
+    for (var, location) in first_deref.iter().enumerate() {
+        if let Some(location) = *location {
+            DMError::new(location, format!("{} is always null here, as nothing but null is ever assigned to it", vars.names[var]))
+                .with_component(dm::Component::DreamChecker)
+                .set_severity(Severity::Error)
+                .with_note(vars.declarations[var], "declared here")
+                .with_errortype("always_null")
+                .register(context);
+        }
+    }
+}
+
+/// Whether following a value this way raises a runtime error if it's null.
+fn is_deref(follow: &Follow) -> bool {
+    match follow {
+        Follow::Field(kind, _) | Follow::Call(kind, ..) => matches!(kind, PropertyAccessKind::Dot | PropertyAccessKind::Colon),
+        Follow::Index(kind, _) => *kind == ListAccessKind::Normal,
+        Follow::Unary(_) => false,
+    }
+}
+
+fn is_null(expr: &Expression) -> bool {
+    matches!(expr.as_term(), Some(Term::Null))
+}
+
+/// The locals of a proc declared with a type, such as `var/obj/O`.
+struct TypedLocals {
+    names: Vec<String>,
+    declarations: Vec<Location>,
+}
+
+impl TypedLocals {
+    fn collect(proc: ProcRef, graph: &ControlFlowGraph) -> TypedLocals {
+        let declarations = local_declarations(proc, graph);
+        let mut locals = TypedLocals { names: Vec::new(), declarations: Vec::new() };
+        for declaration in declared_once(&declarations) {
+            if let LocalKind::Var(location, var) = declaration.kind {
+                if let Some(location) = typed(location, var) {
+                    locals.names.push(declaration.name.to_owned());
+                    locals.declarations.push(location);
+                }
+            }
+        }
+        locals
+    }
+
+    fn index(&self, name: &str) -> Option<usize> {
+        self.names.iter().position(|each| each == name)
+    }
+
+    /// The tracked local a bare identifier expression names, if any.
+    fn local(&self, expr: &Expression) -> Option<usize> {
+        match expr.as_term() {
+            Some(Term::Ident(name)) => self.index(name),
+            _ => None,
+        }
+    }
+
+    fn transfer(&self, state: &mut [bool], step: &Step) {
+        match *step {
+            Step::Statement(_, Statement::Var(var)) => self.declare(state, var),
+            Step::Statement(_, Statement::Vars(vars)) => {
+                for var in vars.iter() {
+                    self.declare(state, var);
+                }
+            }
+            Step::Header(_, Statement::ForList(for_list)) => {
+                if let Some(var) = self.index(&for_list.name) {
+                    state[var] = true;
+                }
+            }
+            Step::Header(_, Statement::ForRange(for_range)) => {
+                if let Some(var) = self.index(&for_range.name) {
+                    state[var] = true;
+                }
+            }
+            _ => {}
+        }
+        for expr in step.expressions() {
+            visit(expr, &mut |expr| {
+                if let Expression::AssignOp { op, lhs, rhs } = expr {
+                    if let Some(var) = self.local(lhs) {
+                        state[var] = *op != AssignOp::Assign || !is_null(rhs);
+                    }
+                }
+            });
+        }
+    }
+
+    fn declare(&self, state: &mut [bool], var: &VarStatement) {
+        if let Some(index) = self.index(&var.name) {
+            state[index] = var.value.as_ref().is_some_and(|value| !is_null(value));
+        }
+    }
+}
+
+/// The declaration location of a local which is worth tracking.
+fn typed(location: Location, var: &VarStatement) -> Option<Location> {
+    if var.var_type.type_path.is_empty() || var.var_type.flags.is_static() {
+        None
+    } else {
+        Some(location)
+    }
+}
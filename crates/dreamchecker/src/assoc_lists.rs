@@ -0,0 +1,112 @@
+//! Checking the entries of `list()` literals.
+//!
+//! `list("health" = 50)` and `list("health", 50)` build very different lists,
+//! so a literal using both forms is reported, as are associations whose key
+//! is null or repeats an earlier constant key, since the later value silently
+//! replaces the earlier one.
+
+use dm::{Context, DMError, Location, Severity};
+use dm::ast::*;
+use dm::constants::Constant;
+use dm::objtree::ObjectTree;
+
+use crate::visit::visit;
+
+/// What is known about the key of one entry of a list literal.
+pub enum ListKey {
+    /// A plain entry, with no association.
+    Plain,
+    Null,
+    Constant(Constant),
+    Unknown,
+}
+
+impl ListKey {
+    /// The key of an entry whose key isn't a bare name or any string, from its
+    /// constant value if it has one.
+    pub fn from_value(value: Option<&Constant>, is_null: bool) -> ListKey {
+        match value {
+            Some(Constant::Null(_)) => ListKey::Null,
+            Some(constant) => ListKey::Constant(constant.clone()),
+            None if is_null => ListKey::Null,
+            None => ListKey::Unknown,
+        }
+    }
+}
+
+/// The key of an entry whose key is a bare name, string, or raw string, if
+/// it has one.
+pub fn named_key(lhs: &Expression) -> Option<ListKey> {
+    match lhs.as_term() {
+        Some(Term::Ident(name)) | Some(Term::String(name)) | Some(Term::RawString(name)) => Some(ListKey::Constant(Constant::String(name.as_str().into()))),
+        _ => None,
+    }
+}
+
+/// Check the keys of the entries of one list literal, in order.
+pub fn check_list_keys(context: &Context, location: Location, keys: &[ListKey]) {
+    let plain = keys.iter().filter(|key| matches!(key, ListKey::Plain)).count();
+    if plain != 0 && plain != keys.len() {
+        DMError::new(location, "list() mixes associations with plain entries")
+            .with_component(dm::Component::DreamChecker)
+            .set_severity(Severity::Info)
+            .with_errortype("mixed_list_entries")
+            .with_note(location, "list(\"a\" = 1) associates a with 1, but list(\"a\", 1) holds both")
+            .register(context);
+    }
+
+    let mut seen: Vec<&Constant> = Vec::new();
+    for key in keys {
+        match key {
+            ListKey::Null => {
+                DMError::new(location, "list() association has a null key")
+                    .with_component(dm::Component::DreamChecker)
+                    .set_severity(Severity::Warning)
+                    .with_errortype("null_list_key")
+                    .register(context);
+            }
+            ListKey::Constant(constant) if seen.contains(&constant) => {
+                DMError::new(location, format!("list() associates {} more than once, so the later value wins", constant))
+                    .with_component(dm::Component::DreamChecker)
+                    .set_severity(Severity::Warning)
+                    .with_errortype("duplicate_list_key")
+                    .register(context);
+            }
+            ListKey::Constant(constant) => seen.push(constant),
+            _ => {}
+        }
+    }
+}
+
+/// Check the list literals in var initializers, whose keys are only known if
+/// they fold to constants.
+pub fn check_var_lists(context: &Context, objtree: &ObjectTree) {
+    for ty in objtree.iter_types() {
+        for var in ty.get().vars.values() {
+            if var.value.location.is_builtins() {
+                continue;
+            }
+            let location = var.value.location;
+            if let Some(ref expr) = var.value.expression {
+                visit(expr, &mut |expr| {
+                    if let Expression::Base { term, .. } = expr {
+                        if let Term::List(ref args) = term.elem {
+                            let keys: Vec<_> = args.iter().map(|arg| entry_key(arg, location)).collect();
+                            check_list_keys(context, location, &keys);
+                        }
+                    }
+                });
+            }
+        }
+    }
+}
+
+fn entry_key(arg: &Expression, location: Location) -> ListKey {
+    match arg {
+        Expression::AssignOp { op: AssignOp::Assign, lhs, .. } => named_key(lhs).unwrap_or_else(|| {
+            let value = lhs.clone().simple_evaluate(location).ok();
+            ListKey::from_value(value.as_ref(), false)
+        }),
+        _ => ListKey::Plain,
+    }
+}
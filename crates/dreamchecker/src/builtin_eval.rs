@@ -0,0 +1,253 @@
+//! Evaluation of pure builtin procs on constant arguments.
+//!
+//! Results follow BYOND: text positions count bytes from 1, an end position
+//! of 0 means the end of the text, and numbers are single precision. Calls
+//! whose result isn't certain are left unevaluated, such as those with
+//! negative or fractional positions, an empty search text, or text containing
+//! escapes or macros, which are kept unparsed in constants.
+
+use dm::constants::Constant;
+
+/// The result of calling a builtin proc with constant positional arguments,
+/// if it's pure and the result is certain.
+pub fn evaluate(name: &str, args: &[Constant]) -> Option<Constant> {
+    let result = match name {
+        "isnull" => Constant::from(one(args)?.is_null()),
+        "isnum" => Constant::from(matches!(one(args)?, Constant::Float(_))),
+        "istext" => Constant::from(matches!(one(args)?, Constant::String(_))),
+
+        "length" => match one(args)? {
+            Constant::Null(_) => Constant::from(0),
+            other => Constant::from(text(other)?.len() as i32),
+        },
+        "length_char" => Constant::from(text(one(args)?)?.chars().count() as i32),
+        "uppertext" => Constant::string(text(one(args)?)?.to_ascii_uppercase()),
+        "lowertext" => Constant::string(text(one(args)?)?.to_ascii_lowercase()),
+        "findtext" => find(args, false)?,
+        "findtextEx" => find(args, true)?,
+        "copytext" => {
+            let (source, start, end) = match args {
+                [source] => (text(source)?, 1, 0),
+                [source, start] => (text(source)?, position(start)?, 0),
+                [source, start, end] => (text(source)?, position(start)?, position(end)?),
+                _ => return None,
+            };
+            let (start, end) = byte_range(source, start, end)?;
+            Constant::string(source.get(start..end)?)
+        }
+        "replacetext" => replace(args, false)?,
+        "replacetextEx" => replace(args, true)?,
+        "cmptext" => compare(args, false)?,
+        "cmptextEx" => compare(args, true)?,
+        "text2ascii" => {
+            let (source, index) = match args {
+                [source] => (text(source)?, 1),
+                [source, index] => (text(source)?, position(index)?),
+                _ => return None,
+            };
+            match source.as_bytes().get(index.checked_sub(1)?) {
+                Some(byte) if byte.is_ascii() => Constant::from(*byte as i32),
+                Some(_) => return None,
+                None => Constant::from(0),
+            }
+        }
+        "ascii2text" => match whole(number(one(args)?)?)? {
+            code @ 1..=127 => Constant::string((code as u8 as char).to_string()),
+            _ => return None,
+        },
+        "text2num" => match one(args)? {
+            Constant::Float(value) => Constant::Float(*value),
+            other => Constant::Float(decimal(text(other)?)?),
+        },
+        "num2text" => match whole(number(one(args)?)?)? {
+            value if value.abs() < 1_000_000 => Constant::string(value.to_string()),
+            _ => return None,
+        },
+
+        "abs" => Constant::Float(number(one(args)?)?.abs()),
+        "sqrt" => match number(one(args)?)? {
+            value if value >= 0. => Constant::Float(value.sqrt()),
+            _ => return None,
+        },
+        // One argument rounds down, two round to the nearest multiple.
+        "round" => match args {
+            [value] => Constant::Float(number(value)?.floor()),
+            [value, multiple] => match (number(value)?, number(multiple)?) {
+                (_, 0.) => return None,
+                (value, multiple) => Constant::Float((value / multiple + 0.5).floor() * multiple),
+            },
+            _ => return None,
+        },
+        "floor" => Constant::Float(number(one(args)?)?.floor()),
+        "ceil" => Constant::Float(number(one(args)?)?.ceil()),
+        "trunc" => Constant::Float(number(one(args)?)?.trunc()),
+        "fract" => Constant::Float(number(one(args)?)?.fract()),
+        "min" | "max" if !args.is_empty() => {
+            let mut values = args.iter().map(number);
+            let first = values.next()??;
+            let pick = if name == "min" { f32::min } else { f32::max };
+            Constant::Float(values.try_fold(first, |acc, value| Some(pick(acc, value?)))?)
+        }
+        "clamp" => match args {
+            [value, low, high] => match (number(value)?, number(low)?, number(high)?) {
+                (value, low, high) if low <= high => Constant::Float(value.max(low).min(high)),
+                _ => return None,
+            },
+            _ => return None,
+        },
+        // Trigonometry is in degrees.
+        "sin" => Constant::Float(degrees(args, f64::sin)?),
+        "cos" => Constant::Float(degrees(args, f64::cos)?),
+        "tan" => Constant::Float(degrees(args, f64::tan)?),
+        "arcsin" => Constant::Float(inverse(args, f64::asin)?),
+        "arccos" => Constant::Float(inverse(args, f64::acos)?),
+        "arctan" => match args {
+            [value] => Constant::Float((number(value)? as f64).atan().to_degrees() as f32),
+            [x, y] => Constant::Float((number(y)? as f64).atan2(number(x)? as f64).to_degrees() as f32),
+            _ => return None,
+        },
+        // `log(X)` is the natural logarithm of X, and `log(Y, X)` is the
+        // logarithm of X in base Y.
+        "log" => match args {
+            [value] => match number(value)? {
+                value if value > 0. => Constant::Float((value as f64).ln() as f32),
+                _ => return None,
+            },
+            [base, value] => match (number(base)?, number(value)?) {
+                (base, value) if base > 0. && base != 1. && value > 0. => Constant::Float((value as f64).log(base as f64) as f32),
+                _ => return None,
+            },
+            _ => return None,
+        },
+        _ => return None,
+    };
+    Some(result)
+}
+
+fn one(args: &[Constant]) -> Option<&Constant> {
+    match args {
+        [arg] => Some(arg),
+        _ => None,
+    }
+}
+
+/// The contents of a text argument, if they have no escapes to interpret.
+fn text(arg: &Constant) -> Option<&str> {
+    match arg {
+        Constant::String(text) if !text.contains('\\') => Some(text),
+        _ => None,
+    }
+}
+
+fn number(arg: &Constant) -> Option<f32> {
+    match arg {
+        Constant::Float(value) => Some(*value),
+        _ => None,
+    }
+}
+
+fn whole(value: f32) -> Option<i32> {
+    if value.fract() == 0. && value.abs() < 16_777_216. {
+        Some(value as i32)
+    } else {
+        None
+    }
+}
+
+/// A text position, which is a whole number of 0 or more.
+fn position(arg: &Constant) -> Option<usize> {
+    match whole(number(arg)?)? {
+        index if index >= 0 => Some(index as usize),
+        _ => None,
+    }
+}
+
+/// The byte range from a 1-based start position and an exclusive end
+/// position, where an end of 0 is the end of the text.
+fn byte_range(source: &str, start: usize, end: usize) -> Option<(usize, usize)> {
+    let end = if end == 0 { source.len() + 1 } else { end.min(source.len() + 1) };
+    if start < 1 || start > source.len() + 1 || end < start {
+        return None;
+    }
+    Some((start - 1, end - 1))
+}
+
+fn find(args: &[Constant], case_sensitive: bool) -> Option<Constant> {
+    let (haystack, needle, start, end) = match args {
+        [haystack, needle] => (text(haystack)?, text(needle)?, 1, 0),
+        [haystack, needle, start] => (text(haystack)?, text(needle)?, position(start)?, 0),
+        [haystack, needle, start, end] => (text(haystack)?, text(needle)?, position(start)?, position(end)?),
+        _ => return None,
+    };
+    if needle.is_empty() {
+        return None;
+    }
+    let (start, end) = byte_range(haystack, start, end)?;
+    let window = haystack.get(start..end)?;
+    let found = if case_sensitive {
+        window.find(needle)
+    } else {
+        window.to_ascii_lowercase().find(&needle.to_ascii_lowercase())
+    };
+    Some(Constant::from(found.map_or(0, |index| (start + index + 1) as i32)))
+}
+
+fn replace(args: &[Constant], case_sensitive: bool) -> Option<Constant> {
+    let (haystack, needle, replacement) = match args {
+        [haystack, needle, replacement] => (text(haystack)?, text(needle)?, text(replacement)?),
+        _ => return None,
+    };
+    if needle.is_empty() {
+        return None;
+    }
+    if case_sensitive {
+        return Some(Constant::string(haystack.replace(needle, replacement)));
+    }
+    let lower = haystack.to_ascii_lowercase();
+    let needle = needle.to_ascii_lowercase();
+    let mut out = String::with_capacity(haystack.len());
+    let mut last = 0;
+    for (index, _) in lower.match_indices(&needle) {
+        out.push_str(&haystack[last..index]);
+        out.push_str(replacement);
+        last = index + needle.len();
+    }
+    out.push_str(&haystack[last..]);
+    Some(Constant::string(out))
+}
+
+fn compare(args: &[Constant], case_sensitive: bool) -> Option<Constant> {
+    let (first, rest) = args.split_first()?;
+    let first = text(first)?;
+    let mut same = true;
+    for other in rest {
+        let other = text(other)?;
+        same &= if case_sensitive { first == other } else { first.eq_ignore_ascii_case(other) };
+    }
+    Some(Constant::from(same))
+}
+
+/// A number in plain decimal notation, such as `-12` or `0.5`.
+fn decimal(text: &str) -> Option<f32> {
+    let digits = text.strip_prefix('-').unwrap_or(text);
+    let mut parts = digits.splitn(2, '.');
+    let whole = parts.next()?;
+    let fraction = parts.next();
+    let all_digits = |part: &str| !part.is_empty() && part.bytes().all(|byte| byte.is_ascii_digit());
+    if !all_digits(whole) || !fraction.is_none_or(all_digits) {
+        return None;
+    }
+    text.parse().ok()
+}
+
+fn degrees(args: &[Constant], op: fn(f64) -> f64) -> Option<f32> {
+    let value = number(one(args)?)? as f64;
+    Some(op(value.to_radians()) as f32)
+}
+
+fn inverse(args: &[Constant], op: fn(f64) -> f64) -> Option<f32> {
+    match number(one(args)?)? {
+        value if (-1. ..=1.).contains(&value) => Some(op(value as f64).to_degrees() as f32),
+        _ => None,
+    }
+}
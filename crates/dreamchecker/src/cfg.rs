@@ -0,0 +1,653 @@
+//! Control flow graphs of proc bodies.
+//!
+//! A graph is a list of basic blocks, each holding a straight-line run of
+//! steps and labelled edges to the blocks which may run next. The exit block
+//! holds no steps. Code following a `return`, `break`, or similar
+//! is still given blocks, but they have no predecessors, as do branches ruled
+//! out by a constant condition or switch input. A graph built for a proc also
+//! resolves the `var/const` locals and fields which a switch input may name.
+//! A `spawn` block is built as a detached subgraph, reachable only through a
+//! `Spawn` edge, which never rejoins the main flow or reaches the exit.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use ahash::RandomState;
+
+use dm::Location;
+use dm::ast::*;
+use dm::constants::Constant;
+use dm::objtree::{ProcRef, TypeRef};
+use dm::printer::{FormatExpr, FormatStatement, PrintOptions};
+
+use crate::switch_overlap::{taken_case, Taken};
+
+/// An index into `ControlFlowGraph::blocks`.
+pub type BlockId = usize;
+
+/// The reason control may pass from one block to another.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EdgeKind {
+    /// Unconditional flow, including falling off the end of the proc.
+    Jump,
+    /// A condition held, or a loop has another iteration.
+    True,
+    /// A condition failed, or a loop is finished.
+    False,
+    /// The switch case with the given index matched.
+    Case(usize),
+    /// No switch case matched.
+    Default,
+    /// A `return` statement, to the exit.
+    Return,
+    /// A `throw` or `CRASH()`, to the innermost `catch` or the exit.
+    Throw,
+    /// A runtime error anywhere in a `try` block, to its `catch`.
+    Exception,
+    /// The start of a `spawn` block's detached subgraph.
+    Spawn,
+}
+
+/// A single unit of work within a basic block.
+#[derive(Debug, Clone, Copy)]
+pub enum Step<'a> {
+    /// A statement with no blocks of its own.
+    Statement(Location, &'a Statement),
+    /// The parts of a compound statement which are not its blocks, such as
+    /// the list of a `for (x in L)` loop or the parameters of a `catch`.
+    Header(Location, &'a Statement),
+    /// A condition, switch input, case value, or spawn delay.
+    Expression(Location, &'a Expression),
+}
+
+impl<'a> Step<'a> {
+    pub fn location(&self) -> Location {
+        match *self {
+            Step::Statement(location, _) |
+            Step::Header(location, _) |
+            Step::Expression(location, _) => location,
+        }
+    }
+
+    /// The expressions this step evaluates, outermost first.
+    pub fn expressions(&self) -> Vec<&'a Expression> {
+        match *self {
+            Step::Statement(_, statement) => match statement {
+                Statement::Expr(expr) |
+                Statement::Return(Some(expr)) |
+                Statement::Throw(expr) |
+                Statement::Crash(Some(expr)) |
+                Statement::Del(expr) => vec![expr],
+                Statement::Var(var) => var.value.iter().collect(),
+                Statement::Vars(vars) => vars.iter().filter_map(|var| var.value.as_ref()).collect(),
+                _ => Vec::new(),
+            },
+            Step::Header(_, Statement::ForList(for_list)) => for_list.in_list.iter().collect(),
+            Step::Header(_, Statement::ForRange(for_range)) => {
+                let mut exprs = vec![&for_range.start, &for_range.end];
+                exprs.extend(for_range.step.iter());
+                exprs
+            }
+            Step::Header(..) => Vec::new(),
+            Step::Expression(_, expr) => vec![expr],
+        }
+    }
+}
+
+/// A straight-line run of steps.
+#[derive(Debug, Default)]
+pub struct BasicBlock<'a> {
+    pub steps: Vec<Step<'a>>,
+    pub successors: Vec<(EdgeKind, BlockId)>,
+    pub predecessors: Vec<(EdgeKind, BlockId)>,
+}
+
+/// The control flow graph of a proc body.
+#[derive(Debug)]
+pub struct ControlFlowGraph<'a> {
+    pub blocks: Vec<BasicBlock<'a>>,
+    pub entry: BlockId,
+    pub exit: BlockId,
+}
+
+impl<'a> ControlFlowGraph<'a> {
+    /// Build the graph of a block of statements.
+    pub fn new(code: &'a [Spanned<Statement>]) -> ControlFlowGraph<'a> {
+        Builder::default().build(code)
+    }
+
+    /// Build the graph of the body of a proc, also pruning the cases of a
+    /// switch on a constant local or field of the proc's type.
+    pub fn for_proc(proc: ProcRef<'a>, code: &'a [Spanned<Statement>]) -> ControlFlowGraph<'a> {
+        Builder {
+            ty: Some(proc.ty()),
+            locals: proc.get().parameters.iter().map(|param| (param.name.as_str(), None)).collect(),
+            ..Builder::default()
+        }.build(code)
+    }
+
+    /// Find which blocks may run, optionally following `Spawn` edges.
+    pub fn reachable(&self, through_spawns: bool) -> Vec<bool> {
+        let mut seen = vec![false; self.blocks.len()];
+        let mut stack = vec![self.entry];
+        while let Some(id) = stack.pop() {
+            if std::mem::replace(&mut seen[id], true) {
+                continue;
+            }
+            for &(kind, next) in self.blocks[id].successors.iter() {
+                if through_spawns || kind != EdgeKind::Spawn {
+                    stack.push(next);
+                }
+            }
+        }
+        seen
+    }
+
+    /// Format the graph in Graphviz `dot` syntax, for debugging.
+    pub fn dot(&self) -> Dot<'_, 'a> {
+        Dot(self)
+    }
+}
+
+/// A declaration of a local within a proc.
+#[derive(Debug, Clone, Copy)]
+pub struct LocalDeclaration<'a> {
+    pub name: &'a str,
+    pub kind: LocalKind<'a>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum LocalKind<'a> {
+    Parameter(&'a Parameter),
+    Var(Location, &'a VarStatement),
+    /// A `for` loop variable or a `catch` parameter.
+    Other,
+}
+
+/// Every declaration of a local in a proc whose body is `graph`, parameters
+/// first.
+pub fn local_declarations<'a>(proc: ProcRef<'a>, graph: &ControlFlowGraph<'a>) -> Vec<LocalDeclaration<'a>> {
+    let mut declarations: Vec<_> = proc.get().parameters.iter()
+        .map(|param| LocalDeclaration { name: &param.name, kind: LocalKind::Parameter(param) })
+        .collect();
+    for step in graph.blocks.iter().flat_map(|block| block.steps.iter()) {
+        match *step {
+            Step::Statement(location, Statement::Var(var)) => {
+                declarations.push(LocalDeclaration { name: &var.name, kind: LocalKind::Var(location, var) });
+            }
+            Step::Statement(location, Statement::Vars(vars)) => {
+                for var in vars.iter() {
+                    declarations.push(LocalDeclaration { name: &var.name, kind: LocalKind::Var(location, var) });
+                }
+            }
+            Step::Header(_, Statement::ForList(for_list)) => {
+                declarations.push(LocalDeclaration { name: &for_list.name, kind: LocalKind::Other });
+            }
+            Step::Header(_, Statement::ForRange(for_range)) => {
+                declarations.push(LocalDeclaration { name: &for_range.name, kind: LocalKind::Other });
+            }
+            Step::Header(_, Statement::TryCatch { catch_params, .. }) => {
+                for name in catch_params.iter().filter_map(|param| param.last()) {
+                    declarations.push(LocalDeclaration { name, kind: LocalKind::Other });
+                }
+            }
+            _ => {}
+        }
+    }
+    declarations
+}
+
+/// The declarations of the locals which are declared only once, and so can
+/// be told apart by name.
+pub fn declared_once<'d, 'a>(declarations: &'d [LocalDeclaration<'a>]) -> impl Iterator<Item = &'d LocalDeclaration<'a>> {
+    declarations.iter().filter(move |each| declarations.iter().filter(|other| other.name == each.name).count() == 1)
+}
+
+/// Displays a `ControlFlowGraph` as a `dot` digraph.
+pub struct Dot<'g, 'a>(&'g ControlFlowGraph<'a>);
+
+impl fmt::Display for Dot<'_, '_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let graph = self.0;
+        let options = PrintOptions::default();
+        writeln!(f, "digraph cfg {{")?;
+        writeln!(f, "    node [shape=box];")?;
+        for (id, block) in graph.blocks.iter().enumerate() {
+            let mut label = if id == graph.entry {
+                "entry".to_owned()
+            } else if id == graph.exit {
+                "exit".to_owned()
+            } else {
+                format!("#{}", id)
+            };
+            for step in block.steps.iter() {
+                let text = match *step {
+                    Step::Statement(_, statement) => FormatStatement(statement, &options).to_string(),
+                    Step::Header(_, statement) => {
+                        // Only the first line, without the nested blocks.
+                        let text = FormatStatement(statement, &options).to_string();
+                        text.lines().next().unwrap_or_default().to_owned()
+                    }
+                    Step::Expression(_, expression) => FormatExpr(expression).to_string(),
+                };
+                label.push('\n');
+                label.push_str(text.trim_end());
+            }
+            writeln!(f, "    b{} [label=\"{}\"];", id, escape(&label))?;
+        }
+        for (id, block) in graph.blocks.iter().enumerate() {
+            for &(kind, next) in block.successors.iter() {
+                writeln!(f, "    b{} -> b{} [label=\"{:?}\"];", id, next, kind)?;
+            }
+        }
+        writeln!(f, "}}")
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\l"),
+            _ => out.push(ch),
+        }
+    }
+    out
+}
+
+// ----------------------------------------------------------------------------
+// Construction
+
+/// A statement which `break` or `continue` may target.
+struct Scope<'a> {
+    label: Option<&'a str>,
+    break_to: BlockId,
+    /// `None` for labelled blocks, which can't be continued.
+    continue_to: Option<BlockId>,
+}
+
+#[derive(Default)]
+struct Builder<'a> {
+    blocks: Vec<BasicBlock<'a>>,
+    /// Where `return` goes, or `None` inside a `spawn`.
+    exit: Option<BlockId>,
+    /// The innermost `catch` block, if any.
+    catch: Option<BlockId>,
+    scopes: Vec<Scope<'a>>,
+    /// A label for the next statement, if it's a loop.
+    pending_label: Option<&'a str>,
+    labels: HashMap<&'a str, BlockId, RandomState>,
+    gotos: Vec<(BlockId, &'a str)>,
+    /// The type whose const vars a switch input may name, if known.
+    ty: Option<TypeRef<'a>>,
+    /// The locals in scope, innermost last, with their values if they are
+    /// `var/const`.
+    locals: Vec<(&'a str, Option<Constant>)>,
+}
+
+/// The value of an expression, if it is constant or names a `var/const`
+/// local or a const var of `ty`. `local` looks up a local by name, giving
+/// `Some(None)` for one which isn't constant.
+pub(crate) fn constant_value(location: Location, expr: &Expression, ty: Option<TypeRef>, local: impl FnOnce(&str) -> Option<Option<Constant>>) -> Option<Constant> {
+    if let Ok(value) = expr.clone().simple_evaluate(location) {
+        return Some(value);
+    }
+    let name = match expr.as_term()? {
+        Term::Ident(name) => name.as_str(),
+        _ => return None,
+    };
+    if let Some(value) = local(name) {
+        return value;
+    }
+    let ty = ty?;
+    if !ty.get_var_declaration(name)?.var_type.flags.is_const() {
+        return None;
+    }
+    ty.get_value(name)?.constant.clone()
+}
+
+impl<'a> Builder<'a> {
+    fn build(mut self, code: &'a [Spanned<Statement>]) -> ControlFlowGraph<'a> {
+        let entry = self.new_block();
+        let exit = self.new_block();
+        self.exit = Some(exit);
+        let end = self.block(entry, code);
+        self.edge(end, EdgeKind::Jump, exit);
+        for (from, label) in std::mem::take(&mut self.gotos) {
+            if let Some(&to) = self.labels.get(label) {
+                self.edge(from, EdgeKind::Jump, to);
+            }
+        }
+        ControlFlowGraph {
+            blocks: self.blocks,
+            entry,
+            exit,
+        }
+    }
+
+    fn new_block(&mut self) -> BlockId {
+        let id = self.blocks.len();
+        self.blocks.push(BasicBlock::default());
+        if let Some(catch) = self.catch {
+            self.edge(id, EdgeKind::Exception, catch);
+        }
+        id
+    }
+
+    fn edge(&mut self, from: BlockId, kind: EdgeKind, to: BlockId) {
+        self.blocks[from].successors.push((kind, to));
+        self.blocks[to].predecessors.push((kind, from));
+    }
+
+    fn push(&mut self, block: BlockId, step: Step<'a>) {
+        self.blocks[block].steps.push(step);
+    }
+
+    /// Add a conditional branch, omitting the edge which can't be taken if
+    /// the condition is constant.
+    fn branch(&mut self, from: BlockId, condition: &Expression, if_true: BlockId, if_false: BlockId) {
+        let truthy = condition.is_truthy();
+        if truthy != Some(false) {
+            self.edge(from, EdgeKind::True, if_true);
+        }
+        if truthy != Some(true) {
+            self.edge(from, EdgeKind::False, if_false);
+        }
+    }
+
+    /// Append a block of statements, returning the block in which control
+    /// continues afterwards.
+    fn block(&mut self, mut current: BlockId, code: &'a [Spanned<Statement>]) -> BlockId {
+        let scope = self.locals.len();
+        for statement in code.iter() {
+            current = self.statement(current, statement);
+        }
+        self.locals.truncate(scope);
+        current
+    }
+
+    fn declare(&mut self, location: Location, var: &'a VarStatement) {
+        let value = if var.var_type.flags.is_const() {
+            var.value.as_ref().and_then(|value| self.constant(location, value))
+        } else {
+            None
+        };
+        self.locals.push((var.name.as_str(), value));
+    }
+
+    /// The value of an expression, if it is constant or names a constant
+    /// local or field.
+    fn constant(&self, location: Location, expr: &Expression) -> Option<Constant> {
+        constant_value(location, expr, self.ty, |name| {
+            self.locals.iter().rev().find(|(local, _)| *local == name).map(|(_, value)| value.clone())
+        })
+    }
+
+    /// Build the body of a loop which continues at `continue_to` and exits
+    /// to `break_to`.
+    fn loop_body(&mut self, label: Option<&'a str>, body: BlockId, code: &'a [Spanned<Statement>], continue_to: BlockId, break_to: BlockId) {
+        self.scopes.push(Scope { label, break_to, continue_to: Some(continue_to) });
+        let end = self.block(body, code);
+        self.scopes.pop();
+        self.edge(end, EdgeKind::Jump, continue_to);
+    }
+
+    /// Jump unconditionally, returning a fresh unreachable block for any
+    /// code which follows.
+    fn jump(&mut self, current: BlockId, kind: EdgeKind, to: Option<BlockId>) -> BlockId {
+        if let Some(to) = to {
+            self.edge(current, kind, to);
+        }
+        self.new_block()
+    }
+
+    fn find_scope(&self, label: Option<&Ident>, want_continue: bool) -> Option<&Scope<'a>> {
+        self.scopes.iter().rev().find(|scope| {
+            let label_ok = match label {
+                Some(label) => scope.label == Some(label.as_str()),
+                None => scope.continue_to.is_some(),
+            };
+            label_ok && (!want_continue || scope.continue_to.is_some())
+        })
+    }
+
+    fn statement(&mut self, current: BlockId, statement: &'a Spanned<Statement>) -> BlockId {
+        let location = statement.location;
+        // Only a loop directly inside a labelled block takes its label.
+        let label = self.pending_label.take();
+        match &statement.elem {
+            Statement::Var(var) => {
+                self.push(current, Step::Statement(location, &statement.elem));
+                self.declare(location, var);
+                current
+            }
+            Statement::Vars(vars) => {
+                self.push(current, Step::Statement(location, &statement.elem));
+                for var in vars.iter() {
+                    self.declare(location, var);
+                }
+                current
+            }
+            Statement::Expr(_) |
+            Statement::Setting { .. } |
+            Statement::Del(_) => {
+                self.push(current, Step::Statement(location, &statement.elem));
+                current
+            }
+            Statement::Return(_) => {
+                self.push(current, Step::Statement(location, &statement.elem));
+                let exit = self.exit;
+                self.jump(current, EdgeKind::Return, exit)
+            }
+            Statement::Throw(_) | Statement::Crash(_) => {
+                self.push(current, Step::Statement(location, &statement.elem));
+                let target = self.catch.or(self.exit);
+                self.jump(current, EdgeKind::Throw, target)
+            }
+            Statement::Break(name) => {
+                self.push(current, Step::Statement(location, &statement.elem));
+                let target = self.find_scope(name.as_ref(), false).map(|scope| scope.break_to);
+                self.jump(current, EdgeKind::Jump, target)
+            }
+            Statement::Continue(name) => {
+                self.push(current, Step::Statement(location, &statement.elem));
+                let target = self.find_scope(name.as_ref(), true).and_then(|scope| scope.continue_to);
+                self.jump(current, EdgeKind::Jump, target)
+            }
+            Statement::Goto(name) => {
+                self.push(current, Step::Statement(location, &statement.elem));
+                self.gotos.push((current, name.as_str()));
+                self.new_block()
+            }
+            Statement::Label { name, block } => {
+                let start = self.new_block();
+                self.edge(current, EdgeKind::Jump, start);
+                self.push(start, Step::Header(location, &statement.elem));
+                self.labels.insert(name.as_str(), start);
+                let after = self.new_block();
+                self.scopes.push(Scope { label: Some(name.as_str()), break_to: after, continue_to: None });
+                self.pending_label = Some(name.as_str());
+                let end = self.block(start, block);
+                self.pending_label = None;
+                self.scopes.pop();
+                self.edge(end, EdgeKind::Jump, after);
+                after
+            }
+            Statement::If { arms, else_arm } => {
+                let mut ends = Vec::with_capacity(arms.len() + 1);
+                let mut test = current;
+                for (condition, block) in arms.iter() {
+                    self.push(test, Step::Expression(condition.location, &condition.elem));
+                    let body = self.new_block();
+                    let next = self.new_block();
+                    self.branch(test, &condition.elem, body, next);
+                    ends.push(self.block(body, block));
+                    test = next;
+                }
+                match else_arm {
+                    Some(block) => ends.push(self.block(test, block)),
+                    None => ends.push(test),
+                }
+                let after = self.new_block();
+                for end in ends {
+                    self.edge(end, EdgeKind::Jump, after);
+                }
+                after
+            }
+            Statement::While { condition, block } => {
+                let header = self.new_block();
+                self.edge(current, EdgeKind::Jump, header);
+                self.push(header, Step::Expression(location, condition));
+                let body = self.new_block();
+                let after = self.new_block();
+                self.branch(header, condition, body, after);
+                self.loop_body(label, body, block, header, after);
+                after
+            }
+            Statement::DoWhile { block, condition } => {
+                let body = self.new_block();
+                self.edge(current, EdgeKind::Jump, body);
+                let test = self.new_block();
+                let after = self.new_block();
+                self.loop_body(label, body, block, test, after);
+                self.push(test, Step::Expression(condition.location, &condition.elem));
+                self.branch(test, &condition.elem, body, after);
+                after
+            }
+            Statement::ForInfinite { block } => {
+                let body = self.new_block();
+                self.edge(current, EdgeKind::Jump, body);
+                let after = self.new_block();
+                self.loop_body(label, body, block, body, after);
+                after
+            }
+            Statement::ForLoop { init, test, inc, block } => {
+                if let Some(init) = init {
+                    self.push(current, Step::Statement(location, init));
+                    if let Statement::Var(var) = &**init {
+                        self.declare(location, var);
+                    }
+                }
+                let header = self.new_block();
+                self.edge(current, EdgeKind::Jump, header);
+                let body = self.new_block();
+                let after = self.new_block();
+                match test {
+                    Some(test) => {
+                        self.push(header, Step::Expression(location, test));
+                        self.branch(header, test, body, after);
+                    }
+                    None => self.edge(header, EdgeKind::Jump, body),
+                }
+                let increment = self.new_block();
+                if let Some(inc) = inc {
+                    self.push(increment, Step::Statement(location, inc));
+                }
+                self.edge(increment, EdgeKind::Jump, header);
+                self.loop_body(label, body, block, increment, after);
+                after
+            }
+            Statement::ForList(for_list) => {
+                if for_list.var_type.is_some() {
+                    self.locals.push((for_list.name.as_str(), None));
+                }
+                self.for_each(current, location, &statement.elem, label, &for_list.block)
+            }
+            Statement::ForRange(for_range) => {
+                if for_range.var_type.is_some() {
+                    self.locals.push((for_range.name.as_str(), None));
+                }
+                self.for_each(current, location, &statement.elem, label, &for_range.block)
+            }
+            Statement::Spawn { delay, block } => {
+                if let Some(delay) = delay {
+                    self.push(current, Step::Expression(location, delay));
+                }
+                let exit = self.exit.take();
+                let catch = self.catch.take();
+                let scopes = std::mem::take(&mut self.scopes);
+                let start = self.new_block();
+                self.edge(current, EdgeKind::Spawn, start);
+                self.block(start, block);
+                self.exit = exit;
+                self.catch = catch;
+                self.scopes = scopes;
+                let after = self.new_block();
+                self.edge(current, EdgeKind::Jump, after);
+                after
+            }
+            Statement::Switch { input, cases, default } => {
+                self.push(current, Step::Expression(location, input));
+                for (case_list, _) in cases.iter() {
+                    for case in case_list.elem.iter() {
+                        match case {
+                            Case::Exact(value) => self.push(current, Step::Expression(case_list.location, value)),
+                            Case::Range(start, end) => {
+                                self.push(current, Step::Expression(case_list.location, start));
+                                self.push(current, Step::Expression(case_list.location, end));
+                            }
+                        }
+                    }
+                }
+                // A switch on a literal, a define, or a constant var only
+                // takes one branch.
+                let taken = self.constant(location, input)
+                    .and_then(|value| taken_case(cases, &value));
+                let taken_or_unknown = |branch| taken.is_none_or(|taken| taken == branch);
+                let mut ends = Vec::with_capacity(cases.len() + 1);
+                for (i, (_, block)) in cases.iter().enumerate() {
+                    let body = self.new_block();
+                    if taken_or_unknown(Taken::Case(i)) {
+                        self.edge(current, EdgeKind::Case(i), body);
+                    }
+                    ends.push(self.block(body, block));
+                }
+                if let Some(block) = default {
+                    let body = self.new_block();
+                    if taken_or_unknown(Taken::Default) {
+                        self.edge(current, EdgeKind::Default, body);
+                    }
+                    ends.push(self.block(body, block));
+                }
+                let after = self.new_block();
+                if default.is_none() && taken_or_unknown(Taken::Default) {
+                    self.edge(current, EdgeKind::Default, after);
+                }
+                for end in ends {
+                    self.edge(end, EdgeKind::Jump, after);
+                }
+                after
+            }
+            Statement::TryCatch { try_block, catch_block, .. } => {
+                let catch = self.new_block();
+                self.push(catch, Step::Header(location, &statement.elem));
+                let outer = self.catch.replace(catch);
+                let body = self.new_block();
+                self.edge(current, EdgeKind::Jump, body);
+                let try_end = self.block(body, try_block);
+                self.catch = outer;
+                let catch_end = self.block(catch, catch_block);
+                let after = self.new_block();
+                self.edge(try_end, EdgeKind::Jump, after);
+                self.edge(catch_end, EdgeKind::Jump, after);
+                after
+            }
+        }
+    }
+
+    /// Build a `for (x in L)` or `for (x = a to b)` loop, whose header is
+    /// evaluated once before the first iteration.
+    fn for_each(&mut self, current: BlockId, location: Location, statement: &'a Statement, label: Option<&'a str>, code: &'a [Spanned<Statement>]) -> BlockId {
+        self.push(current, Step::Header(location, statement));
+        let header = self.new_block();
+        self.edge(current, EdgeKind::Jump, header);
+        let body = self.new_block();
+        let after = self.new_block();
+        self.edge(header, EdgeKind::True, body);
+        self.edge(header, EdgeKind::False, after);
+        self.loop_body(label, body, code, header, after);
+        after
+    }
+}
@@ -0,0 +1,185 @@
+//! Validation of constant colors and `rgb()` calls.
+//!
+//! A color is checked where a constant string is given to a var named in the
+//! `color_vars` config option, to a proc parameter declared `as color`, or to
+//! a color parameter of a builtin proc. Values are constant-folded first, so
+//! colors built from defines and concatenations are covered. An `rgb()` call
+//! in proc code is checked if all its arguments are constant; those in var
+//! initializers are already checked when the object tree is built.
+
+use dm::{Context, DMError, Location, Severity};
+use dm::ast::*;
+use dm::constants::Constant;
+use dm::objtree::{ObjectTree, ProcRef};
+
+use crate::cfg::ControlFlowGraph;
+use crate::visit::visit;
+
+/// The color parameters of builtin procs, by the type they're on and name.
+const BUILTIN_COLOR_PARAMS: &[(&str, &str, &[&str])] = &[
+    ("", "animate", &["color"]),
+    ("", "filter", &["color"]),
+    ("", "rgb2num", &["color"]),
+    ("/icon", "DrawBox", &["rgb"]),
+    ("/icon", "SwapColor", &["old_rgba", "new_rgba"]),
+];
+
+/// Check the constant values of color vars on every type.
+pub fn check_color_vars(context: &Context, objtree: &ObjectTree) {
+    let names = &context.config().dreamchecker.color_vars;
+    for ty in objtree.iter_types() {
+        for (name, var) in ty.get().vars.iter() {
+            if var.value.location.is_builtins() || !names.contains(name) {
+                continue;
+            }
+            if let Some(Constant::String(ref color)) = var.value.constant {
+                check_color(context, var.value.location, color);
+            }
+        }
+    }
+}
+
+/// Check the colors assigned to color vars and the `rgb()` calls in a proc.
+pub fn check_proc_colors(context: &Context, graph: &ControlFlowGraph) {
+    let names = &context.config().dreamchecker.color_vars;
+    for step in graph.blocks.iter().flat_map(|block| block.steps.iter()) {
+        let location = step.location();
+        for expr in step.expressions() {
+            // Assignments within expressions are keyword arguments.
+            if let Expression::AssignOp { op: AssignOp::Assign, lhs, rhs } = expr {
+                if assigned_name(lhs).is_some_and(|name| names.iter().any(|each| each == name)) {
+                    check_constant_color(context, location, rhs);
+                }
+            }
+            visit(expr, &mut |expr| {
+                if let Expression::Base { term, .. } = expr {
+                    if let Term::Call(ref name, ref args) = term.elem {
+                        if name == "rgb" {
+                            check_rgb(context, term.location, args);
+                        }
+                    }
+                }
+            });
+        }
+    }
+}
+
+/// Check the arguments given to the color parameters of a call.
+pub fn check_color_args(context: &Context, location: Location, proc: ProcRef, args: &[Expression]) {
+    let builtin_params = if proc.is_builtin() {
+        let path = &proc.ty().get().path;
+        BUILTIN_COLOR_PARAMS.iter()
+            .find(|(ty, name, _)| ty == path && *name == proc.name())
+            .map_or(&[][..], |(_, _, params)| params)
+    } else {
+        &[]
+    };
+    let parameters = &proc.get().parameters;
+    let is_color = |name: &str| builtin_params.contains(&name) || parameters.iter().any(|param| {
+        param.name == name && param.input_type.is_some_and(|input_type| input_type.contains(InputType::COLOR))
+    });
+
+    for (index, arg) in args.iter().enumerate() {
+        let (name, value) = match arg {
+            Expression::AssignOp { op: AssignOp::Assign, lhs, rhs } => match lhs.as_term() {
+                Some(Term::Ident(name)) | Some(Term::String(name)) | Some(Term::RawString(name)) => (name.as_str(), &**rhs),
+                _ => continue,
+            },
+            _ => match parameters.get(index) {
+                Some(param) => (param.name.as_str(), arg),
+                None => continue,
+            },
+        };
+        if is_color(name) {
+            check_constant_color(context, location, value);
+        }
+    }
+}
+
+fn check_constant_color(context: &Context, location: Location, expr: &Expression) {
+    if let Ok(Constant::String(color)) = expr.clone().simple_evaluate(location) {
+        check_color(context, location, &color);
+    }
+}
+
+fn check_color(context: &Context, location: Location, color: &str) {
+    if let Some(problem) = color_problem(color) {
+        DMError::new(location, format!("invalid color {:?}: {}", color, problem))
+            .with_component(dm::Component::DreamChecker)
+            .set_severity(Severity::Warning)
+            .with_errortype("invalid_color")
+            .register(context);
+    }
+}
+
+/// Check a constant `rgb()` call by folding it.
+fn check_rgb(context: &Context, location: Location, args: &[Expression]) {
+    let constant = |expr: &Expression| expr.clone().simple_evaluate(location).is_ok();
+    let all_constant = args.iter().all(|arg| match arg {
+        Expression::AssignOp { op: AssignOp::Assign, rhs, .. } => constant(rhs),
+        _ => constant(arg),
+    });
+    if !all_constant {
+        return;
+    }
+    let call = Expression::from(Term::Call("rgb".into(), args.to_vec().into()));
+    if let Err(error) = call.simple_evaluate(location) {
+        DMError::new(location, error.description())
+            .with_component(dm::Component::DreamChecker)
+            .set_severity(Severity::Warning)
+            .with_errortype("malformed_rgb")
+            .register(context);
+    }
+}
+
+/// What's wrong with a color string, if anything.
+fn color_problem(color: &str) -> Option<String> {
+    let is_hex_length = |digits: &str| matches!(digits.len(), 3 | 4 | 6 | 8);
+    if color.is_empty() {
+        return None;
+    }
+    if let Some(digits) = color.strip_prefix('#') {
+        if let Some(start) = digits.find(|ch: char| !ch.is_ascii_hexdigit()) {
+            let rest = &digits[start..];
+            let end = rest.find(|ch: char| ch.is_ascii_hexdigit()).unwrap_or(rest.len());
+            return Some(format!("{:?} is not hexadecimal", &rest[..end]));
+        }
+        if !is_hex_length(digits) {
+            return Some(format!("{} digits, but must be 3, 4, 6, or 8", digits.len()));
+        }
+        return None;
+    }
+    if is_color_name(color) {
+        None
+    } else if is_hex_length(color) && color.chars().all(|ch| ch.is_ascii_hexdigit()) {
+        Some("missing the leading \"#\"".to_owned())
+    } else {
+        Some("not a color name".to_owned())
+    }
+}
+
+/// Whether a string is one of the named colors, from "tags (text)" in the DM
+/// reference.
+fn is_color_name(name: &str) -> bool {
+    const NAMES: &[&str] = &[
+        "black", "silver", "gray", "grey", "white", "maroon", "red", "purple",
+        "fuchsia", "magenta", "green", "lime", "olive", "gold", "yellow",
+        "navy", "blue", "teal", "aqua", "cyan",
+    ];
+    NAMES.iter().any(|each| each.eq_ignore_ascii_case(name))
+}
+
+/// The name of the var or local an assignment is to.
+fn assigned_name(lhs: &Expression) -> Option<&str> {
+    match lhs {
+        Expression::Base { term, follow } => match (&term.elem, follow.last()) {
+            (Term::Ident(name), None) => Some(name),
+            (_, Some(last)) => match last.elem {
+                Follow::Field(_, ref name) => Some(name),
+                _ => None,
+            },
+            _ => None,
+        },
+        _ => None,
+    }
+}
@@ -0,0 +1,205 @@
+//! The names which can be used on a type or at a position, such as for
+//! editor completions.
+//!
+//! The members of a type are its vars and procs and those of its ancestors,
+//! each listed once. At a position inside a proc, the proc is analyzed again on
+//! demand to also find the locals in scope there.
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use ahash::RandomState;
+
+use dm::{Context, Location};
+use dm::ast::FormatTreePath;
+use dm::objtree::{ObjectTree, TypeRef};
+
+use crate::{AnalyzeObjectTree, AnalyzeProc, LocalVar};
+use crate::hover::{proc_at, HoverInfo};
+use crate::type_expr::TypeExpr;
+
+/// The kind of name a member is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MemberKind {
+    /// A local var or parameter of the proc containing the position.
+    Local,
+    Var,
+    Proc,
+    Verb,
+}
+
+impl MemberKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            MemberKind::Local => "local",
+            MemberKind::Var => "var",
+            MemberKind::Proc => "proc",
+            MemberKind::Verb => "verb",
+        }
+    }
+}
+
+/// A name which can be used.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Member {
+    pub name: String,
+    pub kind: MemberKind,
+    /// The declared type of a var, the return type of a proc if it's set and
+    /// doesn't depend on the arguments, or the type a local is known to have.
+    pub type_path: Option<String>,
+    /// The path of the type which declares a var or proc.
+    pub declared_on: Option<String>,
+    /// Where it's declared, if it has a location.
+    pub location: Option<Location>,
+    /// The doc comment of the most derived definition which has one.
+    pub docs: Option<String>,
+    /// For a local, what is known about its value at the position.
+    pub analysis: Option<HoverInfo>,
+}
+
+/// List the vars and procs of a type, sorted by name.
+///
+/// An override is listed once, with the declaration it overrides.
+pub fn members(objtree: &ObjectTree, ty: TypeRef) -> Vec<Member> {
+    let scratch = Context::default();
+    let mut env = AnalyzeObjectTree::new(&scratch, objtree);
+    // Return types are set by settings anywhere in the tree.
+    env.gather_all_settings();
+    members_of(&env, ty)
+}
+
+/// List the locals in scope at a position inside a proc, followed by the
+/// members of the type the proc is on.
+///
+/// The locals are those in scope before the last statement starting before
+/// the position, along with any that statement declares if it started on an
+/// earlier line. Diagnostics raised while analyzing the proc again are
+/// discarded.
+pub fn members_at(objtree: &ObjectTree, location: Location) -> Option<Vec<Member>> {
+    let proc = proc_at(objtree, location)?;
+    let code = proc.get().code.as_ref()?;
+
+    let scratch = Context::default();
+    let mut env = AnalyzeObjectTree::new(&scratch, objtree);
+    env.gather_all_settings();
+
+    let mut analyzer = AnalyzeProc::new(&mut env, &scratch, objtree, proc);
+    analyzer.completion = Some(Scope { target: location, best: None, locals: Vec::new() });
+    analyzer.run(code);
+    let mut found = analyzer.completion.take()?.locals;
+    if found.is_empty() {
+        // The position is before the first statement.
+        return None;
+    }
+    found.extend(members_of(&env, proc.ty()));
+    Some(found)
+}
+
+fn members_of(env: &AnalyzeObjectTree, ty: TypeRef) -> Vec<Member> {
+    let mut seen = HashSet::new();
+    let mut found = Vec::new();
+    let mut current = Some(ty);
+    while let Some(each) = current {
+        for (name, var) in each.get().vars.iter() {
+            let decl = match var.declaration {
+                Some(ref decl) => decl,
+                None => continue,
+            };
+            if !seen.insert((MemberKind::Var, name.as_str())) {
+                continue;
+            }
+            let docs = ancestors(ty)
+                .filter_map(|ty| ty.get().vars.get(name))
+                .find(|var| !var.value.docs.is_empty())
+                .map(|var| var.value.docs.text());
+            found.push(Member {
+                name: name.clone(),
+                kind: MemberKind::Var,
+                type_path: Some(FormatTreePath(&decl.var_type.type_path).to_string()).filter(|path| !path.is_empty()),
+                declared_on: Some(each.get().path.clone()),
+                location: Some(decl.location).filter(|&location| location != Location::default()),
+                docs,
+                analysis: None,
+            });
+        }
+        for (name, proc) in each.get().procs.iter() {
+            let decl = match proc.declaration {
+                Some(ref decl) => decl,
+                None => continue,
+            };
+            if !seen.insert((MemberKind::Proc, name.as_str())) {
+                continue;
+            }
+            let most_derived = ty.get_proc(name);
+            let type_path = match most_derived.and_then(|proc| env.return_type.get(&proc)) {
+                Some(TypeExpr::Static(static_ty)) => static_ty.basic_type().map(|ty| ty.get().path.clone()),
+                _ => None,
+            };
+            let docs = ancestors(ty)
+                .filter_map(|ty| ty.get().procs.get(name))
+                .flat_map(|proc| proc.value.iter().rev())
+                .find(|value| !value.docs.is_empty())
+                .map(|value| value.docs.text());
+            found.push(Member {
+                name: name.clone(),
+                kind: if decl.kind.is_verb() { MemberKind::Verb } else { MemberKind::Proc },
+                type_path,
+                declared_on: Some(each.get().path.clone()),
+                location: Some(decl.location).filter(|&location| location != Location::default()),
+                docs,
+                analysis: None,
+            });
+        }
+        current = each.parent_type();
+    }
+    found.sort_by(|a, b| a.name.cmp(&b.name));
+    found
+}
+
+/// A type and its ancestors, most derived first.
+fn ancestors(ty: TypeRef) -> impl Iterator<Item=TypeRef> {
+    std::iter::successors(Some(ty), |ty| ty.parent_type())
+}
+
+/// The position being queried, and the locals in scope at the best match for
+/// it so far.
+pub(crate) struct Scope {
+    target: Location,
+    best: Option<Location>,
+    locals: Vec<Member>,
+}
+
+impl Scope {
+    /// Note the locals in scope before, or if `after`, after the statement at
+    /// a location.
+    pub(crate) fn observe(&mut self, location: Location, after: bool, local_vars: &HashMap<String, LocalVar, RandomState>) {
+        let starts_before = if after {
+            location.line < self.target.line
+        } else {
+            location <= self.target
+        };
+        if location.file != self.target.file
+            || !starts_before
+            || self.best.is_some_and(|best| best > location)
+        {
+            return;
+        }
+        self.best = Some(location);
+        self.locals = local_vars.iter()
+            .filter(|(name, _)| name.as_str() != ".")
+            .map(|(name, local)| {
+                let info = HoverInfo::new(location, &local.analysis, Some(local.location));
+                Member {
+                    name: name.clone(),
+                    kind: MemberKind::Local,
+                    type_path: info.type_path.clone(),
+                    declared_on: None,
+                    location: info.declaration,
+                    docs: None,
+                    analysis: Some(info),
+                }
+            })
+            .collect();
+        self.locals.sort_by(|a, b| a.name.cmp(&b.name));
+    }
+}
@@ -0,0 +1,37 @@
+//! Cyclomatic complexity of proc bodies.
+//!
+//! The complexity of a proc is one more than its number of decision points,
+//! each of which is one of:
+//!
+//! * the condition of an `if` or `else if` arm,
+//! * the condition of a `while`, `do while`, or `for` loop, including the
+//!   implicit "is there another item" check of `for (x in L)` and
+//!   `for (x in 1 to N)`,
+//! * each `if` arm of a `switch`, not counting its `else`,
+//! * each `&&` or `||` within one of those conditions.
+//!
+//! Code in `spawn` blocks counts towards the proc it is written in.
+
+use dm::ast::*;
+
+use crate::cfg::{ControlFlowGraph, EdgeKind, Step};
+use crate::visit::visit;
+
+pub fn cyclomatic_complexity(graph: &ControlFlowGraph) -> u32 {
+    let mut decisions = 0;
+    for block in graph.blocks.iter() {
+        let branches = block.successors.iter().any(|&(kind, _)| matches!(kind, EdgeKind::True | EdgeKind::False));
+        if branches {
+            decisions += 1;
+            if let Some(Step::Expression(_, condition)) = block.steps.last() {
+                visit(condition, &mut |expr| {
+                    if let Expression::BinaryOp { op: BinaryOp::And, .. } | Expression::BinaryOp { op: BinaryOp::Or, .. } = expr {
+                        decisions += 1;
+                    }
+                });
+            }
+        }
+        decisions += block.successors.iter().filter(|&&(kind, _)| matches!(kind, EdgeKind::Case(_))).count() as u32;
+    }
+    1 + decisions
+}
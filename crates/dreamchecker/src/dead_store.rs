@@ -0,0 +1,368 @@
+//! Detection of assignments to locals whose values are never read.
+//!
+//! Locals are tracked by name, so names declared more than once in a proc are
+//! skipped rather than risk confusing one declaration for another. Liveness
+//! follows every edge of the control flow graph, including into spawned code,
+//! so a value read by a `spawn` block counts as read where it is spawned.
+
+use std::collections::HashMap;
+
+use ahash::RandomState;
+
+use dm::{Context, DMError, Location, Severity};
+use dm::ast::*;
+use dm::objtree::ProcRef;
+
+use crate::cfg::{declared_once, local_declarations, ControlFlowGraph, LocalKind, Step};
+use crate::visit::has_call;
+
+/// A read or write of a tracked local, in evaluation order.
+enum Event {
+    Read(usize),
+    /// A read of every parameter, as through `args` or a bare `..()`.
+    ReadParams,
+    Write(Store),
+}
+
+struct Store {
+    var: usize,
+    location: Location,
+    /// Whether this is a declaration's initializer.
+    init: bool,
+    /// Whether computing the value calls anything.
+    has_call: bool,
+    /// Compound assignments and increments both read and write, and aren't
+    /// reported.
+    reported: bool,
+}
+
+pub fn check_dead_stores(context: &Context, proc: ProcRef, graph: &ControlFlowGraph) {
+    let vars = Locals::collect(proc, graph);
+    if vars.names.is_empty() {
+        return;
+    }
+
+    let events: Vec<Vec<Event>> = graph.blocks.iter().map(|block| {
+        let mut walker = Walker { vars: &vars, events: Vec::new() };
+        for step in block.steps.iter() {
+            walker.step(step);
+        }
+        walker.events
+    }).collect();
+
+    // Backwards liveness to a fixed point.
+    let count = vars.names.len();
+    let mut live_in = vec![vec![false; count]; graph.blocks.len()];
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for id in (0..graph.blocks.len()).rev() {
+            let mut live = live_out(graph, &live_in, id, count);
+            for event in events[id].iter().rev() {
+                transfer(&vars, &mut live, event);
+            }
+            if live != live_in[id] {
+                live_in[id] = live;
+                changed = true;
+            }
+        }
+    }
+
+    let reachable = graph.reachable(true);
+    let mut found = Vec::new();
+    for id in 0..graph.blocks.len() {
+        if !reachable[id] {
+            continue;
+        }
+        let mut live = live_out(graph, &live_in, id, count);
+        for event in events[id].iter().rev() {
+            if let Event::Write(store) = event {
+                if store.reported && !live[store.var] {
+                    found.push(store);
+                }
+            }
+            transfer(&vars, &mut live, event);
+        }
+    }
+
+    // The value passed for a parameter is dead if it's always overwritten
+    // first. Parameters which are never read at all are left alone.
+    for (var, &location) in vars.params.iter().enumerate() {
+        let read = events.iter().flatten().any(|event| match event {
+            Event::Read(other) => *other == var,
+            Event::ReadParams => true,
+            Event::Write(_) => false,
+        });
+        let written = events.iter().flatten().any(|event| matches!(event, Event::Write(store) if store.var == var));
+        if read && written && !live_in[graph.entry][var] {
+            DMError::new(location, format!("parameter {} is overwritten before being read", vars.names[var]))
+                .with_component(dm::Component::DreamChecker)
+                .set_severity(Severity::Warning)
+                .with_errortype("dead_store")
+                .register(context);
+        }
+    }
+
+    found.sort_by_key(|store| store.location);
+    for store in found {
+        let name = &vars.names[store.var];
+        let mut message = if store.init {
+            format!("initial value of {} is never read", name)
+        } else {
+            format!("value assigned to {} is never read", name)
+        };
+        if store.has_call {
+            message.push_str(", but the call still runs for its side effects");
+        }
+        DMError::new(store.location, message)
+            .with_component(dm::Component::DreamChecker)
+            .set_severity(Severity::Warning)
+            .with_errortype("dead_store")
+            .register(context);
+    }
+}
+
+fn live_out(graph: &ControlFlowGraph, live_in: &[Vec<bool>], id: usize, count: usize) -> Vec<bool> {
+    let mut live = vec![false; count];
+    for &(_, next) in graph.blocks[id].successors.iter() {
+        for (each, &other) in live.iter_mut().zip(live_in[next].iter()) {
+            *each |= other;
+        }
+    }
+    live
+}
+
+fn transfer(vars: &Locals, live: &mut [bool], event: &Event) {
+    match event {
+        Event::Read(var) => live[*var] = true,
+        Event::ReadParams => {
+            for each in live[..vars.params.len()].iter_mut() {
+                *each = true;
+            }
+        }
+        Event::Write(store) => live[store.var] = false,
+    }
+}
+
+/// The locals of a proc which are tracked. Parameters come first.
+struct Locals {
+    names: Vec<String>,
+    /// Where each tracked parameter is declared.
+    params: Vec<Location>,
+    index: HashMap<String, usize, RandomState>,
+}
+
+impl Locals {
+    fn collect(proc: ProcRef, graph: &ControlFlowGraph) -> Locals {
+        let declarations = local_declarations(proc, graph);
+        let mut locals = Locals { names: Vec::new(), params: Vec::new(), index: Default::default() };
+        for declaration in declared_once(&declarations) {
+            match declaration.kind {
+                LocalKind::Parameter(param) => locals.params.push(param.location),
+                LocalKind::Var(_, var) if !var.var_type.flags.is_static() => {}
+                _ => continue,
+            }
+            locals.index.insert(declaration.name.to_owned(), locals.names.len());
+            locals.names.push(declaration.name.to_owned());
+        }
+        locals
+    }
+}
+
+struct Walker<'v> {
+    vars: &'v Locals,
+    events: Vec<Event>,
+}
+
+impl Walker<'_> {
+    fn step(&mut self, step: &Step) {
+        match *step {
+            Step::Statement(location, statement) => self.statement(location, statement),
+            Step::Header(_, Statement::ForList(for_list)) => {
+                if let Some(in_list) = &for_list.in_list {
+                    self.expression(in_list);
+                }
+            }
+            Step::Header(_, Statement::ForRange(for_range)) => {
+                self.expression(&for_range.start);
+                self.expression(&for_range.end);
+                if let Some(step) = &for_range.step {
+                    self.expression(step);
+                }
+            }
+            Step::Header(..) => {}
+            Step::Expression(location, expression) => self.store_or_expression(location, expression),
+        }
+    }
+
+    fn statement(&mut self, location: Location, statement: &Statement) {
+        match statement {
+            Statement::Expr(expr) => self.store_or_expression(location, expr),
+            Statement::Var(var) => self.var(location, var),
+            Statement::Vars(vars) => {
+                for var in vars.iter() {
+                    self.var(location, var);
+                }
+            }
+            Statement::Return(Some(expr)) |
+            Statement::Throw(expr) |
+            Statement::Crash(Some(expr)) |
+            Statement::Del(expr) => self.expression(expr),
+            _ => {}
+        }
+    }
+
+    fn var(&mut self, location: Location, var: &VarStatement) {
+        if let Some(value) = &var.value {
+            self.expression(value);
+            if let Some(&index) = self.vars.index.get(&var.name) {
+                self.events.push(Event::Write(Store {
+                    var: index,
+                    location,
+                    init: true,
+                    has_call: has_call(value),
+                    reported: true,
+                }));
+            }
+        }
+    }
+
+    fn store_or_expression(&mut self, location: Location, expr: &Expression) {
+        if let Expression::AssignOp { op, lhs, rhs } = expr {
+            if let Some(index) = self.local(lhs) {
+                self.expression(rhs);
+                let reported = *op == AssignOp::Assign;
+                if !reported {
+                    self.events.push(Event::Read(index));
+                }
+                self.events.push(Event::Write(Store {
+                    var: index,
+                    location,
+                    init: false,
+                    has_call: has_call(rhs),
+                    reported,
+                }));
+                return;
+            }
+        }
+        self.expression(expr);
+    }
+
+    /// The tracked local a bare identifier expression names, if any.
+    fn local(&self, expr: &Expression) -> Option<usize> {
+        match expr.as_term() {
+            Some(Term::Ident(name)) => self.vars.index.get(name).copied(),
+            _ => None,
+        }
+    }
+
+    fn expressions(&mut self, exprs: &[Expression]) {
+        for expr in exprs.iter() {
+            self.expression(expr);
+        }
+    }
+
+    fn expression(&mut self, expr: &Expression) {
+        match expr {
+            Expression::Base { term, follow } => {
+                self.term(&term.elem);
+                for each in follow.iter() {
+                    match &each.elem {
+                        Follow::Index(_, index) => self.expression(index),
+                        Follow::Call(_, _, args) => self.expressions(args),
+                        Follow::Field(..) | Follow::Unary(_) => {}
+                    }
+                }
+            }
+            Expression::BinaryOp { lhs, rhs, .. } => {
+                self.expression(lhs);
+                self.expression(rhs);
+            }
+            Expression::AssignOp { lhs, rhs, .. } => {
+                // Nested assignments are treated as reads, which is never
+                // wrong, only less precise.
+                self.expression(lhs);
+                self.expression(rhs);
+            }
+            Expression::TernaryOp { cond, if_, else_ } => {
+                self.expression(cond);
+                self.expression(if_);
+                self.expression(else_);
+            }
+        }
+    }
+
+    fn term(&mut self, term: &Term) {
+        match term {
+            Term::Ident(name) if name == "args" => self.events.push(Event::ReadParams),
+            Term::Ident(name) => {
+                if let Some(&index) = self.vars.index.get(name) {
+                    self.events.push(Event::Read(index));
+                }
+            }
+            Term::Expr(expr) => self.expression(expr),
+            Term::Prefab(prefab) => {
+                for (_, value) in prefab.vars.iter() {
+                    self.expression(value);
+                }
+            }
+            Term::InterpString(_, parts) => {
+                for (expr, _) in parts.iter() {
+                    if let Some(expr) = expr {
+                        self.expression(expr);
+                    }
+                }
+            }
+            Term::Call(_, args) |
+            Term::List(args) => self.expressions(args),
+            Term::SelfCall(args) | Term::ParentCall(args) => {
+                // Without arguments, the proc's own arguments are passed.
+                if args.is_empty() {
+                    self.events.push(Event::ReadParams);
+                }
+                self.expressions(args);
+            }
+            Term::NewImplicit { args } |
+            Term::NewPrefab { args, .. } => {
+                if let Some(args) = args {
+                    self.expressions(args);
+                }
+            }
+            Term::NewMiniExpr { expr, args } => {
+                if let Some(&index) = self.vars.index.get(expr.ident.as_str()) {
+                    self.events.push(Event::Read(index));
+                }
+                if let Some(args) = args {
+                    self.expressions(args);
+                }
+            }
+            Term::Input { args, in_list, .. } |
+            Term::Locate { args, in_list } => {
+                self.expressions(args);
+                if let Some(in_list) = in_list {
+                    self.expression(in_list);
+                }
+            }
+            Term::Pick(choices) => {
+                for (weight, value) in choices.iter() {
+                    if let Some(weight) = weight {
+                        self.expression(weight);
+                    }
+                    self.expression(value);
+                }
+            }
+            Term::DynamicCall(first, second) => {
+                self.expressions(first);
+                self.expressions(second);
+            }
+            Term::Null |
+            Term::Int(_) |
+            Term::Float(_) |
+            Term::String(_) |
+            Term::RawString(_) |
+            Term::Resource(_) |
+            Term::As(_) |
+            Term::ProcReference(_) => {}
+        }
+    }
+}
@@ -0,0 +1,205 @@
+//! An index from each name used in proc code to what it was resolved to.
+//!
+//! Identifiers, field accesses, proc calls, prefab paths, and proc references
+//! such as `.proc/name` are recorded with the location of the local, var,
+//! proc, or type they refer to, or as unresolved if the analysis couldn't
+//! tell.
+
+use std::collections::HashMap;
+
+use ahash::RandomState;
+
+use dm::{Context, FileId, Location};
+use dm::ast::*;
+use dm::objtree::{NavigatePathResult, ObjectTree, ProcRef, TypeRef};
+
+use crate::{AnalyzeObjectTree, AnalyzeProc, LocalVar};
+
+/// The kind of thing a name resolved to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DefinitionKind {
+    /// A local var or parameter of the proc.
+    Local,
+    /// A var declared on a type, including global vars.
+    Var,
+    /// A proc or verb.
+    Proc,
+    /// A type, named by a prefab path.
+    Type,
+    /// Nothing the analysis could find.
+    Unresolved,
+}
+
+impl DefinitionKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            DefinitionKind::Local => "local",
+            DefinitionKind::Var => "var",
+            DefinitionKind::Proc => "proc",
+            DefinitionKind::Type => "type",
+            DefinitionKind::Unresolved => "unresolved",
+        }
+    }
+}
+
+/// A single use of a name.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Occurrence {
+    /// Where the term or follow naming it starts.
+    pub location: Location,
+    pub name: String,
+    pub kind: DefinitionKind,
+    /// Where it's declared, if it has a location. Built-in locals such as
+    /// `usr` and unresolved names have none.
+    pub definition: Option<Location>,
+    /// Whether this is a field access or proc call on a value, as in
+    /// `x.name` or `x:name()`, rather than a bare name.
+    pub is_field: bool,
+}
+
+/// Every name used in the analyzed procs, in order of location.
+#[derive(Debug, Default, Clone)]
+pub struct DefinitionIndex {
+    pub occurrences: Vec<Occurrence>,
+}
+
+impl DefinitionIndex {
+    /// The occurrence starting exactly at a location, if any.
+    pub fn at(&self, location: Location) -> Option<&Occurrence> {
+        let index = self.occurrences.binary_search_by_key(&location, |each| each.location).ok()?;
+        self.occurrences.get(index)
+    }
+
+    /// The occurrences within a file.
+    pub fn in_file(&self, file: FileId) -> impl Iterator<Item=&Occurrence> {
+        self.occurrences.iter().filter(move |each| each.location.file == file)
+    }
+
+    pub(crate) fn record(&mut self, location: Location, resolution: Resolution) {
+        self.occurrences.push(Occurrence {
+            location,
+            name: resolution.name,
+            kind: resolution.kind,
+            definition: resolution.definition.filter(|&each| each != Location::default()),
+            is_field: resolution.is_field,
+        });
+    }
+}
+
+/// Build the index for the procs of the whole tree, or those in one file.
+///
+/// Diagnostics raised while analyzing the procs are discarded.
+pub fn index_definitions(objtree: &ObjectTree, file: Option<FileId>) -> DefinitionIndex {
+    let scratch = Context::default();
+    let mut env = AnalyzeObjectTree::new(&scratch, objtree);
+    env.gather_all_settings();
+    env.definitions = Some(DefinitionIndex::default());
+    objtree.root().recurse(&mut |ty| {
+        for proc in ty.iter_self_procs() {
+            if file.is_some_and(|file| proc.get().location.file != file) {
+                continue;
+            }
+            if let Some(ref code) = proc.get().code {
+                AnalyzeProc::new(&mut env, &scratch, objtree, proc).run(code);
+            }
+        }
+    });
+
+    let mut index = env.definitions.take().unwrap_or_default();
+    // Some expressions are visited more than once.
+    index.occurrences.sort_by_key(|each| each.location);
+    index.occurrences.dedup_by_key(|each| each.location);
+    index
+}
+
+/// What a name resolved to.
+pub(crate) struct Resolution {
+    name: String,
+    kind: DefinitionKind,
+    definition: Option<Location>,
+    is_field: bool,
+}
+
+impl Resolution {
+    fn new(name: &str, kind: DefinitionKind, definition: Option<Location>) -> Resolution {
+        Resolution { name: name.to_owned(), kind, definition, is_field: false }
+    }
+
+    fn unresolved(name: &str) -> Resolution {
+        Resolution::new(name, DefinitionKind::Unresolved, None)
+    }
+
+    pub(crate) fn definition(&self) -> Option<Location> {
+        self.definition
+    }
+
+    /// Resolve the name a term uses, if it uses one.
+    pub(crate) fn of_term<'o>(term: &Term, proc: ProcRef<'o>, local_vars: &HashMap<String, LocalVar<'o>, RandomState>) -> Option<Resolution> {
+        let ty = proc.ty();
+        Some(match term {
+            Term::Ident(name) => {
+                if let Some(local) = local_vars.get(name) {
+                    Resolution::new(name, DefinitionKind::Local, Some(local.location))
+                } else {
+                    var(ty, name)
+                }
+            }
+            Term::Call(name, _) => self::proc(ty, name),
+            Term::SelfCall(_) => Resolution::new(".", DefinitionKind::Proc, Some(proc.get().location)),
+            Term::ParentCall(_) => match proc.parent_proc() {
+                Some(parent) => Resolution::new("..", DefinitionKind::Proc, Some(parent.get().location)),
+                None => Resolution::unresolved(".."),
+            },
+            Term::Prefab(prefab) |
+            Term::NewPrefab { prefab, .. } => {
+                let name = FormatTypePath(&prefab.path).to_string();
+                match ty.navigate_path(&prefab.path) {
+                    Some(NavigatePathResult::ProcPath(found, _)) => match found.ty().get_proc_declaration(found.name()) {
+                        Some(decl) => Resolution::new(&name, DefinitionKind::Proc, Some(decl.location)),
+                        None => Resolution::unresolved(&name),
+                    },
+                    Some(found) => Resolution::new(&name, DefinitionKind::Type, Some(found.ty().get().location)),
+                    None => Resolution::unresolved(&name),
+                }
+            }
+            Term::ProcReference(reference) => {
+                let owner = match reference.ty {
+                    None => Some(ty),
+                    Some(ref path) if path.is_empty() => Some(ty.tree().root()),
+                    Some(ref path) => ty.navigate_path(path).map(|nav| nav.ty()),
+                };
+                match owner {
+                    Some(owner) => self::proc(owner, &reference.name),
+                    None => Resolution::unresolved(&reference.name),
+                }
+            }
+            _ => return None,
+        })
+    }
+
+    /// Resolve the name a follow uses on a value of a type, if it uses one.
+    pub(crate) fn of_follow(follow: &Follow, ty: Option<TypeRef>) -> Option<Resolution> {
+        let resolution = match (follow, ty) {
+            (Follow::Field(_, name), Some(ty)) => var(ty, name),
+            (Follow::Call(_, name, _), Some(ty)) => proc(ty, name),
+            (Follow::Field(_, name), None) |
+            (Follow::Call(_, name, _), None) => Resolution::unresolved(name),
+            _ => return None,
+        };
+        Some(Resolution { is_field: true, ..resolution })
+    }
+}
+
+fn var(ty: TypeRef, name: &str) -> Resolution {
+    match ty.get_var_declaration(name) {
+        Some(decl) => Resolution::new(name, DefinitionKind::Var, Some(decl.location)),
+        None => Resolution::unresolved(name),
+    }
+}
+
+fn proc(ty: TypeRef, name: &str) -> Resolution {
+    match ty.get_proc_declaration(name) {
+        Some(decl) => Resolution::new(name, DefinitionKind::Proc, Some(decl.location)),
+        None => Resolution::unresolved(name),
+    }
+}
@@ -0,0 +1,69 @@
+//! `if` chains with copied conditions or branches.
+//!
+//! An `else if` whose condition is the same as an earlier one in its chain
+//! never runs, since the earlier condition was already false. Conditions
+//! which may have side effects, such as calls to procs other than a few
+//! builtins, could give a different result the second time and are skipped.
+//! An `if` with an `else` whose two blocks are the same runs the same code
+//! either way, which may be scaffolding left for later, so it's a lesser
+//! diagnostic.
+
+use dm::{Context, DMError, Location, Severity};
+use dm::ast::*;
+
+use crate::same_ast::{same_block, same_expression};
+use crate::visit::{is_call, visit};
+
+/// Builtins which give the same result for the same arguments and don't
+/// change anything.
+const PURE_BUILTINS: &[&str] = &[
+    "istype", "ispath", "isnull", "isnum", "istext", "islist", "isloc",
+    "ismob", "isobj", "isturf", "isarea", "isicon", "isfile",
+    "length", "findtext", "findtextEx", "text2num", "num2text",
+    "abs", "max", "min", "round",
+];
+
+pub fn check_if_chain(context: &Context, location: Location, arms: &[(Spanned<Expression>, Block)], else_arm: Option<&[Spanned<Statement>]>) {
+    for (index, (condition, _)) in arms.iter().enumerate() {
+        if has_side_effects(&condition.elem) {
+            continue;
+        }
+        let earlier = arms[..index].iter().find(|(other, _)| same_expression(&other.elem, &condition.elem));
+        if let Some((earlier, _)) = earlier {
+            DMError::new(condition.location, "condition is the same as an earlier one in this if chain, so this branch never runs")
+                .with_component(dm::Component::DreamChecker)
+                .with_errortype("duplicate_condition")
+                .with_note(earlier.location, "first checked here")
+                .register(context);
+        }
+    }
+
+    if let ([(_, block)], Some(else_arm)) = (arms, else_arm) {
+        if !block.is_empty() && same_block(block, else_arm) {
+            DMError::new(location, "both branches of this if are the same, so its condition makes no difference")
+                .with_component(dm::Component::DreamChecker)
+                .set_severity(Severity::Info)
+                .with_errortype("duplicate_branches")
+                .register(context);
+        }
+    }
+}
+
+fn has_side_effects(condition: &Expression) -> bool {
+    let mut found = false;
+    visit(condition, &mut |expr| match expr {
+        Expression::AssignOp { .. } => found = true,
+        Expression::Base { term, follow } => {
+            found |= match term.elem {
+                Term::Call(ref name, _) => !PURE_BUILTINS.contains(&name.as_str()),
+                ref term => is_call(term),
+            };
+            found |= follow.iter().any(|each| matches!(each.elem,
+                Follow::Call(..) |
+                Follow::Unary(UnaryOp::PreIncr | UnaryOp::PostIncr | UnaryOp::PreDecr | UnaryOp::PostDecr)
+            ));
+        }
+        _ => {}
+    });
+    found
+}
@@ -0,0 +1,116 @@
+//! Queries for what is known about the expression at a position, such as for
+//! editor hovers.
+//!
+//! The proc containing the position is analyzed again on demand, recording
+//! the analysis of each term and follow whose span covers the position.
+
+use dm::{Context, Location};
+use dm::constants::Constant;
+use dm::objtree::{ObjectTree, ProcRef};
+
+use crate::{Analysis, AnalyzeObjectTree, AnalyzeProc, Assumption};
+
+/// What is known about the value of an expression.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HoverInfo {
+    /// Where the innermost term or follow at the position starts.
+    pub location: Location,
+    /// The path of the type the value is an instance of, if it's known.
+    pub type_path: Option<String>,
+    /// Whether the value is always null, `Some(true)`, or never null,
+    /// `Some(false)`.
+    pub null: Option<bool>,
+    /// The value, if it's constant.
+    pub value: Option<Constant>,
+    /// Where the var or proc an identifier, field, or call names is declared.
+    pub declaration: Option<Location>,
+}
+
+impl HoverInfo {
+    pub(crate) fn new(location: Location, analysis: &Analysis, declaration: Option<Location>) -> HoverInfo {
+        let type_path = analysis.static_ty.basic_type()
+            .or_else(|| analysis.aset.set.iter().find_map(|each| match *each {
+                Assumption::IsType(true, ty) => Some(ty),
+                _ => None,
+            }))
+            .map(|ty| ty.path.clone());
+        let null = match analysis.value {
+            Some(ref value) => Some(value.is_null()),
+            None if analysis.aset.set.contains(&Assumption::IsNull(true)) => Some(true),
+            None if analysis.aset.set.contains(&Assumption::IsNull(false)) => Some(false),
+            None => None,
+        };
+        HoverInfo {
+            location,
+            type_path,
+            null,
+            value: analysis.value.clone(),
+            declaration: declaration.filter(|&each| each != Location::default()),
+        }
+    }
+}
+
+/// Find what is known about the innermost expression at a position.
+///
+/// Diagnostics raised while analyzing the proc again are discarded.
+pub fn hover(objtree: &ObjectTree, location: Location) -> Option<HoverInfo> {
+    let proc = proc_at(objtree, location)?;
+    let code = proc.get().code.as_ref()?;
+
+    let scratch = Context::default();
+    let mut env = AnalyzeObjectTree::new(&scratch, objtree);
+    // Return types are set by settings anywhere in the tree.
+    env.gather_all_settings();
+
+    let mut analyzer = AnalyzeProc::new(&mut env, &scratch, objtree, proc);
+    analyzer.hover = Some(Probe { target: location, found: None });
+    analyzer.run(code);
+    analyzer.hover.take()?.found
+}
+
+/// The proc with code which starts last before a position in the same file.
+pub(crate) fn proc_at(objtree: &ObjectTree, location: Location) -> Option<ProcRef<'_>> {
+    let mut best: Option<ProcRef> = None;
+    objtree.root().recurse(&mut |ty| {
+        for proc in ty.iter_self_procs() {
+            let start = proc.get().location;
+            if proc.get().code.is_some()
+                && start.file == location.file
+                && start <= location
+                && best.is_none_or(|best| best.get().location < start)
+            {
+                best = Some(proc);
+            }
+        }
+    });
+    best
+}
+
+/// The position being queried, and the best match for it so far.
+pub(crate) struct Probe {
+    target: Location,
+    found: Option<HoverInfo>,
+}
+
+impl Probe {
+    /// Whether something spanning from `location` to `end` covers the
+    /// position and is nested within the current match, if any.
+    pub(crate) fn covers(&self, location: Location, end: Location) -> bool {
+        location.file == self.target.file
+            && location <= self.target
+            && self.target < end
+            && self.is_inner(location)
+    }
+
+    /// Record the analysis of something which covers the position, unless
+    /// something nested within it has been recorded already.
+    pub(crate) fn record(&mut self, location: Location, analysis: &Analysis, declaration: Option<Location>) {
+        if self.is_inner(location) {
+            self.found = Some(HoverInfo::new(location, analysis, declaration));
+        }
+    }
+
+    fn is_inner(&self, location: Location) -> bool {
+        self.found.as_ref().is_none_or(|found| found.location <= location)
+    }
+}
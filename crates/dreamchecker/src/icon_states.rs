@@ -0,0 +1,215 @@
+//! Checking constant `icon_state` values against the states in constant icons.
+//!
+//! An `icon_state` is checked where both it and the icon it will be looked up
+//! in are known: on each type which sets either var to a constant, and on
+//! assignments of string literals in procs. An assignment is checked against
+//! the icon of the type the assigned-to value is declared as, if that type and
+//! all its subtypes have the same icon and the proc doesn't assign any `icon`
+//! itself. Icons which can't be found or read and icons without states are
+//! skipped, as is the empty state.
+
+use std::collections::{HashMap, HashSet};
+
+use ahash::RandomState;
+
+use dm::{Context, DMError, Location, Severity};
+use dm::ast::*;
+use dm::constants::Constant;
+use dm::dmi::Metadata;
+use dm::objtree::{ObjectTree, ProcRef, TypeRef};
+
+use crate::cfg::{ControlFlowGraph, Step};
+use crate::visit::visit;
+
+/// The states of the icons read so far.
+#[derive(Default)]
+pub struct IconStates<'o> {
+    /// The state names of each icon by its resource literal, or `None` if it
+    /// can't be read or has no states.
+    states: HashMap<String, Option<HashSet<String, RandomState>>, RandomState>,
+    /// The icon each type and all its subtypes have, if they agree.
+    fixed_icons: HashMap<TypeRef<'o>, Option<(&'o str, Location)>, RandomState>,
+}
+
+impl<'o> IconStates<'o> {
+    /// Check every type which sets `icon` or `icon_state`.
+    pub fn check_vars(&mut self, context: &Context, objtree: &'o ObjectTree) {
+        for ty in objtree.iter_types() {
+            let own_state = ty.get().vars.get("icon_state").map(|var| &var.value);
+            let own_icon = ty.get().vars.get("icon").map(|var| &var.value);
+            if own_state.is_none() && own_icon.is_none() {
+                continue;
+            }
+            let (state, state_location) = match ty.get_value("icon_state") {
+                Some(value) => match value.constant {
+                    Some(Constant::String(ref state)) => (state, value.location),
+                    _ => continue,
+                },
+                None => continue,
+            };
+            let (icon, icon_location) = match icon_of(ty) {
+                Some(icon) => icon,
+                None => continue,
+            };
+            let location = match own_state {
+                Some(_) => state_location,
+                None => icon_location,
+            };
+            self.check(context, location, state, icon, icon_location);
+        }
+    }
+
+    /// Check the `icon_state` assignments in a proc.
+    pub fn check_proc(&mut self, context: &Context, proc: ProcRef<'o>, graph: &ControlFlowGraph<'o>) {
+        let objtree = proc.ty().tree();
+        let mut declared: HashMap<&str, Option<TypeRef<'o>>, RandomState> = Default::default();
+        for param in proc.get().parameters.iter() {
+            declared.insert(&param.name, declared_type(objtree, &param.var_type));
+        }
+
+        let mut assignments = Vec::new();
+        let mut assigns_icon = false;
+        for step in graph.blocks.iter().flat_map(|block| block.steps.iter()) {
+            match *step {
+                Step::Statement(_, Statement::Var(var)) => declare(&mut declared, objtree, var),
+                Step::Statement(_, Statement::Vars(vars)) => {
+                    for var in vars.iter() {
+                        declare(&mut declared, objtree, var);
+                    }
+                }
+                _ => {}
+            }
+            for expr in step.expressions() {
+                visit(expr, &mut |expr| {
+                    if let Expression::AssignOp { lhs, rhs, .. } = expr {
+                        match assigned_var(lhs) {
+                            Some((_, "icon")) => assigns_icon = true,
+                            Some((receiver, "icon_state")) => assignments.push((receiver, rhs, step.location())),
+                            _ => {}
+                        }
+                    }
+                });
+            }
+        }
+        if assigns_icon {
+            return;
+        }
+
+        for (receiver, rhs, location) in assignments {
+            let state = match &**rhs {
+                Expression::Base { term, follow } if follow.is_empty() => match term.elem {
+                    Term::String(ref state) | Term::RawString(ref state) => state,
+                    _ => continue,
+                },
+                _ => continue,
+            };
+            let ty = match receiver {
+                Receiver::Implicit if declared.contains_key("icon_state") => continue,
+                Receiver::Implicit | Receiver::Named("src") if !proc.ty().is_root() => proc.ty(),
+                Receiver::Named(name) => match declared.get(name) {
+                    Some(&Some(ty)) => ty,
+                    _ => continue,
+                },
+                _ => continue,
+            };
+            if let Some((icon, icon_location)) = self.fixed_icon(ty) {
+                self.check(context, location, state, icon, icon_location);
+            }
+        }
+    }
+
+    fn check(&mut self, context: &Context, location: Location, state: &str, icon: &str, icon_location: Location) {
+        if state.is_empty() {
+            return;
+        }
+        let states = self.states.entry(icon.to_owned()).or_insert_with(|| read_states(context, icon));
+        if let Some(states) = states {
+            if !states.contains(state) {
+                DMError::new(location, format!("icon_state {:?} is not in '{}'", state, icon))
+                    .with_component(dm::Component::DreamChecker)
+                    .set_severity(Severity::Warning)
+                    .with_errortype("missing_icon_state")
+                    .with_note(icon_location, "icon is set here")
+                    .register(context);
+            }
+        }
+    }
+
+    /// The icon a type and all its subtypes have, if they agree.
+    fn fixed_icon(&mut self, ty: TypeRef<'o>) -> Option<(&'o str, Location)> {
+        if let Some(&known) = self.fixed_icons.get(&ty) {
+            return known;
+        }
+        let icon = icon_of(ty).filter(|&(icon, _)| {
+            ty.children().all(|child| self.fixed_icon(child).map(|(other, _)| other) == Some(icon))
+        });
+        self.fixed_icons.insert(ty, icon);
+        icon
+    }
+}
+
+/// The constant icon a type has, and where it's set.
+fn icon_of(ty: TypeRef<'_>) -> Option<(&str, Location)> {
+    let value = ty.get_value("icon")?;
+    match value.constant {
+        Some(Constant::Resource(ref icon)) => Some((icon, value.location)),
+        _ => None,
+    }
+}
+
+/// The state names of an icon, if it can be found and read and has any.
+fn read_states(context: &Context, icon: &str) -> Option<HashSet<String, RandomState>> {
+    if context.resources().is_dry() {
+        return None;
+    }
+    let path = context.resolve_resource(icon)?;
+    let metadata = Metadata::read_file(&path).ok()?;
+    if metadata.states.is_empty() {
+        return None;
+    }
+    Some(metadata.state_names.into_keys().collect())
+}
+
+fn declared_type<'o>(objtree: &'o ObjectTree, var_type: &VarType) -> Option<TypeRef<'o>> {
+    if var_type.type_path.is_empty() {
+        return None;
+    }
+    objtree.type_by_path(var_type.type_path.iter())
+}
+
+/// Remember the type of a local, forgetting it if declared more than once.
+fn declare<'a, 'o>(declared: &mut HashMap<&'a str, Option<TypeRef<'o>>, RandomState>, objtree: &'o ObjectTree, var: &'a VarStatement) {
+    let ty = declared_type(objtree, &var.var_type);
+    declared.entry(&var.name)
+        .and_modify(|existing| if *existing != ty { *existing = None })
+        .or_insert(ty);
+}
+
+/// What an assigned var is on.
+#[derive(Clone, Copy)]
+enum Receiver<'a> {
+    /// A bare var name, which may be on `src`.
+    Implicit,
+    /// A var on a local, parameter, or `src`, as in `I.icon_state`.
+    Named(&'a str),
+    Other,
+}
+
+/// The var an assignment is to, if any, and what it's on.
+fn assigned_var(lhs: &Expression) -> Option<(Receiver<'_>, &str)> {
+    let (term, follow) = match lhs {
+        Expression::Base { term, follow } => (term, follow),
+        _ => return None,
+    };
+    match (&term.elem, &follow[..]) {
+        (Term::Ident(name), []) => Some((Receiver::Implicit, name)),
+        (_, [.., last]) => match last.elem {
+            Follow::Field(kind, ref name) => match (&term.elem, follow.len(), kind) {
+                (Term::Ident(receiver), 1, PropertyAccessKind::Dot) => Some((Receiver::Named(receiver), name)),
+                _ => Some((Receiver::Other, name)),
+            },
+            _ => None,
+        },
+        _ => None,
+    }
+}
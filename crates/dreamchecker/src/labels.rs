@@ -0,0 +1,115 @@
+//! Detection of labels which are never used and `goto`s with no label to go
+//! to.
+
+use dm::{Context, DMError, Location, Severity};
+use dm::ast::*;
+
+/// A label, or a `goto` referring to one. The spawn context is a number
+/// identifying the `spawn` block it's in, or zero for the proc body itself.
+struct Site<'a> {
+    name: &'a str,
+    location: Location,
+    spawn_context: usize,
+}
+
+#[derive(Default)]
+struct Collector<'a> {
+    labels: Vec<Site<'a>>,
+    gotos: Vec<Site<'a>>,
+    /// Labels named by `break` and `continue`.
+    loop_refs: Vec<&'a str>,
+    spawn_count: usize,
+}
+
+pub fn check_labels(context: &Context, code: &[Spanned<Statement>]) {
+    let mut collector = Collector::default();
+    collector.block(code, 0);
+
+    for goto in collector.gotos.iter() {
+        let mut same_name = collector.labels.iter().filter(|label| label.name == goto.name).peekable();
+        let first = match same_name.peek() {
+            Some(label) => label.location,
+            None => {
+                DMError::new(goto.location, format!("goto {}: no such label in this proc", goto.name))
+                    .with_component(dm::Component::DreamChecker)
+                    .set_severity(Severity::Warning)
+                    .with_errortype("missing_label")
+                    .register(context);
+                continue;
+            }
+        };
+        if !same_name.any(|label| label.spawn_context == goto.spawn_context) {
+            DMError::new(goto.location, format!("goto {}: cannot jump into or out of a spawn block", goto.name))
+                .with_component(dm::Component::DreamChecker)
+                .set_severity(Severity::Warning)
+                .with_note(first, "label is here")
+                .with_errortype("goto_across_spawn")
+                .register(context);
+        }
+    }
+
+    for label in collector.labels.iter() {
+        let used = collector.gotos.iter().any(|goto| goto.name == label.name)
+            || collector.loop_refs.contains(&label.name);
+        if !used {
+            DMError::new(label.location, format!("label {} is never used", label.name))
+                .with_component(dm::Component::DreamChecker)
+                .set_severity(Severity::Info)
+                .with_errortype("unused_label")
+                .register(context);
+        }
+    }
+}
+
+impl<'a> Collector<'a> {
+    fn block(&mut self, block: &'a [Spanned<Statement>], spawn_context: usize) {
+        for statement in block.iter() {
+            self.statement(statement, spawn_context);
+        }
+    }
+
+    fn statement(&mut self, statement: &'a Spanned<Statement>, spawn_context: usize) {
+        let location = statement.location;
+        match &statement.elem {
+            Statement::Label { name, block } => {
+                self.labels.push(Site { name, location, spawn_context });
+                self.block(block, spawn_context);
+            }
+            Statement::Goto(name) => self.gotos.push(Site { name, location, spawn_context }),
+            Statement::Break(Some(name)) |
+            Statement::Continue(Some(name)) => self.loop_refs.push(name),
+            Statement::Spawn { block, .. } => {
+                self.spawn_count += 1;
+                let inner = self.spawn_count;
+                self.block(block, inner);
+            }
+            Statement::While { block, .. } |
+            Statement::DoWhile { block, .. } |
+            Statement::ForInfinite { block } |
+            Statement::ForLoop { block, .. } => self.block(block, spawn_context),
+            Statement::ForList(for_list) => self.block(&for_list.block, spawn_context),
+            Statement::ForRange(for_range) => self.block(&for_range.block, spawn_context),
+            Statement::If { arms, else_arm } => {
+                for (_, block) in arms.iter() {
+                    self.block(block, spawn_context);
+                }
+                if let Some(block) = else_arm {
+                    self.block(block, spawn_context);
+                }
+            }
+            Statement::Switch { cases, default, .. } => {
+                for (_, block) in cases.iter() {
+                    self.block(block, spawn_context);
+                }
+                if let Some(block) = default {
+                    self.block(block, spawn_context);
+                }
+            }
+            Statement::TryCatch { try_block, catch_block, .. } => {
+                self.block(try_block, spawn_context);
+                self.block(catch_block, spawn_context);
+            }
+            _ => {}
+        }
+    }
+}
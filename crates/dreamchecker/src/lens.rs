@@ -0,0 +1,107 @@
+//! Counts of the overrides of each proc definition and the calls which can
+//! run it, such as for editor code lenses.
+//!
+//! A call can run a definition if it resolved to that definition, or if it
+//! isn't exact and resolved to a proc on a type the definition's type is
+//! below, since the value called on may be of that type at runtime. Only the
+//! last definition of a proc on a type can be run this way; earlier
+//! redefinitions are only reachable by `..()` from the ones after them.
+
+use std::collections::HashMap;
+
+use dm::{Context, FileId, Location};
+use dm::ast::ProcDeclKind;
+use dm::objtree::{ObjectTree, ProcRef, TypeRef};
+
+use crate::{AnalyzeObjectTree, AnalyzeProc};
+
+/// A call which resolved to a proc.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct CallSite<'o> {
+    pub(crate) location: Location,
+    /// The static type of what the proc is called on.
+    pub(crate) src: TypeRef<'o>,
+    pub(crate) proc: ProcRef<'o>,
+    /// Whether the call can't run an override, as for `..()` and `new`.
+    pub(crate) is_exact: bool,
+}
+
+/// The overrides and callers of one proc definition.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProcLens {
+    /// The path of the proc, as in `/obj/item/proc/attack`.
+    pub path: String,
+    pub location: Location,
+    /// The definitions which override this one, including redefinitions
+    /// after it on the same type, in order of location.
+    pub overrides: Vec<Location>,
+    /// The calls which can run this definition, in order of location.
+    pub references: Vec<Location>,
+}
+
+/// Count the overrides and callers of each proc defined in a file, in order of
+/// location.
+///
+/// Every proc in the tree is analyzed to find the calls. Diagnostics raised
+/// while doing so are discarded.
+pub fn proc_lenses(objtree: &ObjectTree, file: FileId) -> Vec<ProcLens> {
+    let scratch = Context::default();
+    let mut env = AnalyzeObjectTree::new(&scratch, objtree);
+    env.gather_all_settings();
+    env.call_sites = Some(Vec::new());
+    objtree.root().recurse(&mut |ty| {
+        for proc in ty.iter_self_procs() {
+            if let Some(ref code) = proc.get().code {
+                AnalyzeProc::new(&mut env, &scratch, objtree, proc).run(code);
+            }
+        }
+    });
+    let mut call_sites = env.call_sites.take().unwrap_or_default();
+    // Some expressions are visited more than once.
+    call_sites.sort_by_key(|site| site.location);
+    call_sites.dedup_by(|a, b| a.location == b.location && a.proc == b.proc);
+
+    let mut by_name: HashMap<&str, Vec<&CallSite>> = HashMap::new();
+    for site in call_sites.iter() {
+        by_name.entry(site.proc.name()).or_default().push(site);
+    }
+
+    let mut lenses = Vec::new();
+    for ty in objtree.iter_types() {
+        for proc in ty.iter_self_procs().filter(|proc| proc.get().location.file == file) {
+            let sites = by_name.get(proc.name()).map_or(&[][..], |sites| &sites[..]);
+            lenses.push(lens(proc, sites));
+        }
+    }
+    lenses.sort_by_key(|lens| lens.location);
+    lenses
+}
+
+fn lens(proc: ProcRef, sites: &[&CallSite]) -> ProcLens {
+    let ty = proc.ty();
+    let name = proc.name();
+    let mut overrides = Vec::new();
+    ty.recurse(&mut |each| {
+        if let Some(type_proc) = each.get().procs.get(name) {
+            let values = if each == ty { &type_proc.value[proc.index() + 1..] } else { &type_proc.value[..] };
+            overrides.extend(values.iter().map(|value| value.location));
+        }
+    });
+    overrides.sort();
+
+    let dispatched = proc.is_externally_visible();
+    let mut references: Vec<Location> = sites.iter()
+        .filter(|site| site.proc == proc
+            || (dispatched && !site.is_exact && site.src != ty && ty.is_subtype_of(&site.src)))
+        .map(|site| site.location)
+        .collect();
+    references.dedup();
+
+    let kind = ty.get_proc_declaration(name).map_or(ProcDeclKind::Proc, |decl| decl.kind);
+    ProcLens {
+        path: format!("{}/{}/{}", ty.get().path, kind, name),
+        location: proc.get().location,
+        overrides,
+        references,
+    }
+}
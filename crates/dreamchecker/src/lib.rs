@@ -9,7 +9,9 @@ use dm::objtree::{ObjectTree, TypeRef, ProcRef};
 use dm::constants::{Constant, ConstFn};
 use dm::ast::*;
 
-use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+pub use dm::constants::evaluate_const_expression;
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
 
 use ahash::RandomState;
 
@@ -17,6 +19,46 @@ mod type_expr;
 use type_expr::TypeExpr;
 mod switch_rand_range;
 use switch_rand_range::check_switch_rand_range;
+mod switch_overlap;
+use switch_overlap::{check_switch_overlap, Taken};
+pub mod cfg;
+mod returns;
+mod dead_store;
+mod loop_invariant;
+mod visit;
+mod taint;
+mod spawn_loop;
+mod try_catch;
+mod labels;
+mod list_bounds;
+mod always_null;
+mod new_recursion;
+mod icon_states;
+mod colors;
+mod assoc_lists;
+mod naming;
+pub use naming::check_macro_names;
+mod unsupplied_params;
+mod random_args;
+mod null_results;
+mod operand_kinds;
+mod param_defaults;
+mod output_operator;
+mod duplicate_branches;
+pub mod builtin_eval;
+pub mod same_ast;
+pub mod type_coverage;
+pub mod complexity;
+pub mod hover;
+pub mod completion;
+pub mod signature;
+pub mod symbols;
+pub mod definitions;
+pub mod references;
+pub mod lens;
+pub mod rename;
+pub mod semantic_tokens;
+pub mod session;
 
 #[doc(hidden)]  // Intended for the tests only.
 pub mod test_helpers;
@@ -219,6 +261,14 @@ impl<'o> Analysis<'o> {
         }
     }
 
+    /// The type this is known to be a typepath of, rather than an instance.
+    fn typepath(&self) -> Option<TypeRef<'o>> {
+        self.aset.set.iter().find_map(|each| match *each {
+            Assumption::IsPath(true, ty) => Some(ty),
+            _ => None,
+        })
+    }
+
     fn from_static_type(ty: TypeRef<'o>) -> Analysis<'o> {
         Analysis::from(StaticType::Type(ty))
     }
@@ -247,6 +297,95 @@ impl<'o> Analysis<'o> {
     }
 }
 
+/// `a &&= b`, `a ||= b`, and `a ?= b` only assign when `a` is truthy, falsy,
+/// or null respectively, so the result may be either side.
+fn conditional_assign<'o>(op: AssignOp, lhs: Analysis<'o>, rhs: Analysis<'o>) -> Analysis<'o> {
+    if let Some(value) = lhs.value.as_ref() {
+        let keeps_lhs = match op {
+            AssignOp::AndAssign => !value.to_bool(),
+            AssignOp::OrAssign => value.to_bool(),
+            _ => !value.is_null(),
+        };
+        return if keeps_lhs { lhs } else { rhs };
+    }
+    if lhs.static_ty == rhs.static_ty {
+        lhs.static_ty.into()
+    } else {
+        Analysis::empty()
+    }
+}
+
+/// Whether an assumption is about the kind of value a var holds, which an
+/// assignment may change.
+fn is_kind_assumption(each: &Assumption) -> bool {
+    matches!(each, Assumption::IsText(_) | Assumption::IsNum(_) | Assumption::IsPath(..) | Assumption::IsType(..))
+}
+
+/// Keep only the kinds of outer locals which still hold at the end of a
+/// nested scope, since the scope may or may not have run.
+fn merge_scope<'o>(local_vars: &mut HashMap<String, LocalVar<'o>, RandomState>, scoped: &HashMap<String, LocalVar<'o>, RandomState>) {
+    for (name, var) in local_vars.iter_mut() {
+        if let Some(inner) = scoped.get(name) {
+            var.analysis.aset.set.retain(|each| !is_kind_assumption(each) || inner.analysis.aset.set.contains(each));
+        }
+    }
+}
+
+/// Forget the kinds of locals a loop may change, since each iteration sees
+/// the values left by the one before.
+fn forget_kinds(local_vars: &mut HashMap<String, LocalVar, RandomState>, names: &HashSet<String, RandomState>) {
+    for name in names {
+        if let Some(var) = local_vars.get_mut(name) {
+            var.analysis.aset.set.retain(|each| !is_kind_assumption(each));
+        }
+    }
+}
+
+/// Whether an analysis is known to be text or a number.
+fn value_kind(analysis: &Analysis) -> Option<&'static str> {
+    if analysis.aset.set.contains(&Assumption::IsText(true)) {
+        Some("text")
+    } else if analysis.aset.set.contains(&Assumption::IsNum(true)) {
+        Some("a number")
+    } else {
+        None
+    }
+}
+
+/// The operands of a `+` chain as one string with embedded expressions, if
+/// they're string literals and plain vars such as `name` or `src.name`.
+fn interpolate(operands: &[&Expression]) -> Option<String> {
+    let mut begin = String::new();
+    let mut parts: Vec<(Option<Expression>, String)> = Vec::new();
+    let mut any_literal = false;
+    for &operand in operands {
+        let (term, follow) = match operand {
+            Expression::Base { term, follow } => (&term.elem, follow),
+            _ => return None,
+        };
+        match term {
+            Term::String(text) if follow.is_empty() => {
+                match parts.last_mut() {
+                    Some((_, after)) => after.push_str(text),
+                    None => begin.push_str(text),
+                }
+                any_literal = true;
+            }
+            Term::Ident(_) if follow.iter().all(|each| matches!(each.elem, Follow::Field(PropertyAccessKind::Dot, _))) => {
+                parts.push((Some(operand.clone()), String::new()));
+            }
+            _ => return None,
+        }
+    }
+    if !any_literal {
+        return None;
+    }
+    // Printed as the printer quotes strings, so that the contents stay valid.
+    let parts: Vec<_> = parts.into_iter().map(|(expr, text)| (expr, text.into_boxed_str())).collect();
+    let string = Expression::from(Term::InterpString(begin.into(), parts.into_boxed_slice()));
+    Some(dm::printer::FormatExpr(&string).to_string())
+}
+
 trait WithFixHint {
     fn with_fix_hint(self, analysis: &Analysis) -> Self;
 }
@@ -310,15 +449,21 @@ impl<'o> From<StaticType<'o>> for Analysis<'o> {
 
 /// Run DreamChecker, registering diagnostics to the context.
 pub fn run(context: &Context, objtree: &ObjectTree) {
-    run_inner(context, objtree, false)
+    run_inner(context, objtree, false, false);
 }
 
 /// Run DreamChecker, registering diagnostics and printing progress to stdout.
 pub fn run_cli(context: &Context, objtree: &ObjectTree) {
-    run_inner(context, objtree, true)
+    run_inner(context, objtree, true, false);
+}
+
+/// Run DreamChecker like `run_cli`, also measuring the type coverage of the
+/// proc bodies it analyzes.
+pub fn run_cli_with_coverage(context: &Context, objtree: &ObjectTree) -> type_coverage::Coverage {
+    run_inner(context, objtree, true, true).unwrap_or_default()
 }
 
-fn run_inner(context: &Context, objtree: &ObjectTree, cli: bool) {
+fn run_inner(context: &Context, objtree: &ObjectTree, cli: bool, coverage: bool) -> Option<type_coverage::Coverage> {
     macro_rules! cli_println {
         ($($rest:tt)*) => {
             if cli { println!($($rest)*) }
@@ -329,21 +474,27 @@ fn run_inner(context: &Context, objtree: &ObjectTree, cli: bool) {
     cli_println!("Analyzing variables...\n");
 
     check_var_defs(&objtree, &context);
+    colors::check_color_vars(context, objtree);
+    assoc_lists::check_var_lists(context, objtree);
+    naming::check_naming(context, objtree);
 
     let mut analyzer = AnalyzeObjectTree::new(context, objtree);
 
+    if let Some(ref mut icon_states) = analyzer.icon_states {
+        cli_println!("============================================================");
+        cli_println!("Analyzing icon states...\n");
+        icon_states.check_vars(context, objtree);
+    }
+
     cli_println!("============================================================");
     cli_println!("Gathering proc settings...\n");
-    objtree.root().recurse(&mut |ty| {
-        for proc in ty.iter_self_procs() {
-            if let Some(ref code) = proc.get().code {
-                analyzer.gather_settings(proc, code);
-            }
-        }
-    });
+    analyzer.gather_all_settings();
 
     cli_println!("============================================================");
     cli_println!("Analyzing proc bodies...\n");
+    if coverage {
+        analyzer.type_coverage = Some(HashMap::new());
+    }
     objtree.root().recurse(&mut |ty| {
         for proc in ty.iter_self_procs() {
             if let Some(ref code) = proc.get().code {
@@ -363,9 +514,23 @@ fn run_inner(context: &Context, objtree: &ObjectTree, cli: bool) {
 
     analyzer.finish_check_kwargs();
 
+    if let Some(ref supplied_args) = analyzer.supplied_args {
+        cli_println!("============================================================");
+        cli_println!("Analyzing call arguments...\n");
+        supplied_args.check(context, objtree, &analyzer.referenced_procs);
+    }
+
+    if context.config().dreamchecker.unassigned_fields {
+        cli_println!("============================================================");
+        cli_println!("Analyzing field assignments...\n");
+        analyzer.check_unassigned_fields();
+    }
+
     cli_println!("============================================================");
     cli_println!("Analyzing proc call tree...\n");
     analyzer.check_proc_call_tree();
+
+    analyzer.type_coverage.take().map(type_coverage::summarize)
 }
 
 // ----------------------------------------------------------------------------
@@ -512,6 +677,106 @@ impl DMErrorExt for DMError {
     }
 }
 
+/// Why a proc must not sleep.
+#[derive(Clone, Copy)]
+enum NoSleepReason<'a> {
+    /// It sets `SpacemanDMM_should_not_sleep`, here.
+    Directive(Location),
+    /// It overrides a proc listed in `never_sleep`.
+    Config(&'a str),
+}
+
+impl NoSleepReason<'_> {
+    fn describe(self, procref: ProcRef) -> String {
+        match self {
+            NoSleepReason::Directive(_) => format!("{} sets SpacemanDMM_should_not_sleep", procref),
+            NoSleepReason::Config(entry) => format!("{} overrides {}, which must not sleep,", procref, entry),
+        }
+    }
+
+    fn error(self, procref: ProcRef, message: String) -> DMError {
+        let error = error(procref.get().location, message).with_errortype("must_not_sleep");
+        match self {
+            NoSleepReason::Directive(location) => error.with_note(location, "SpacemanDMM_should_not_sleep set here"),
+            NoSleepReason::Config(_) => error,
+        }
+    }
+}
+
+/// The location of the call an expression's value comes from, if any.
+fn final_call_location(expr: &Expression) -> Option<Location> {
+    match expr {
+        Expression::Base { term, follow } => match follow.last() {
+            Some(last) if matches!(last.elem, Follow::Call(..)) => Some(last.location),
+            None if matches!(term.elem, Term::Call(..) | Term::SelfCall(_) | Term::ParentCall(_)) => Some(term.location),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// The location of the `..()` in `. = ..()`, which passes on whatever the
+/// parent returns and is kept for when it does.
+fn forwarded_parent_call(expr: &Expression) -> Option<Location> {
+    match expr {
+        Expression::AssignOp { op: AssignOp::Assign, lhs, rhs } => match (lhs.as_term(), &**rhs) {
+            (Some(Term::Ident(dot)), Expression::Base { term, follow }) if dot == "." && follow.is_empty() => match term.elem {
+                Term::ParentCall(_) => Some(term.location),
+                _ => None,
+            },
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn final_field_location(expr: &Expression) -> Option<Location> {
+    match expr {
+        Expression::Base { follow, .. } => match follow.last() {
+            Some(last) if matches!(last.elem, Follow::Field(..)) => Some(last.location),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// The type a var, or a proc if `proc` is set, visible on `ty` is declared
+/// on.
+pub(crate) fn declaring_type<'o>(ty: TypeRef<'o>, name: &str, proc: bool) -> Option<TypeRef<'o>> {
+    let mut current = Some(ty);
+    while let Some(each) = current {
+        let declared = if proc {
+            each.get().procs.get(name).is_some_and(|proc| proc.declaration.is_some())
+        } else {
+            each.get().vars.get(name).is_some_and(|var| var.declaration.is_some())
+        };
+        if declared {
+            return Some(each);
+        }
+        current = each.parent_type();
+    }
+    None
+}
+
+/// Look up a proc by a path such as `/atom/movable/proc/Move`.
+fn find_proc<'o>(objtree: &'o ObjectTree, path: &str) -> Option<ProcRef<'o>> {
+    let (type_path, name) = path.rsplit_once('/')?;
+    let type_path = type_path.strip_suffix("/proc").or_else(|| type_path.strip_suffix("/verb"))?;
+    objtree.find(type_path)?.get_proc(name)
+}
+
+/// Whether a proc is, or overrides, the given proc.
+fn overrides(proc: ProcRef, base: ProcRef) -> bool {
+    let mut next = Some(proc);
+    while let Some(current) = next {
+        if current.ty() == base.ty() {
+            return true;
+        }
+        next = current.parent_proc();
+    }
+    false
+}
+
 #[derive(Default)]
 pub struct ViolatingProcs<'o> {
     violators: HashMap<ProcRef<'o>, Vec<(String, Location)>>,
@@ -556,17 +821,45 @@ pub struct AnalyzeObjectTree<'o> {
     sleep_exempt: ProcDirective<'o>,
     must_be_pure: ProcDirective<'o>,
     can_be_redefined: ProcDirective<'o>,
+    must_return: ProcDirective<'o>,
     // Debug(ProcRef) -> KwargInfo
     used_kwargs: BTreeMap<String, KwargInfo>,
 
     call_tree: HashMap<ProcRef<'o>, Vec<(ProcRef<'o>, Location, bool)>>,
+    /// Procs named by `.proc/name` style references, such as callbacks.
+    referenced_procs: HashSet<ProcRef<'o>>,
+    /// The fields assigned to by any proc, by the type declaring them.
+    assigned_fields: HashSet<(TypeRef<'o>, String)>,
+    /// The names of fields assigned to through values of unknown type.
+    assigned_untyped_fields: HashSet<String>,
 
     sleeping_procs: ViolatingProcs<'o>,
     impure_procs: ViolatingProcs<'o>,
-    waitfor_procs: HashSet<ProcRef<'o>>,
+    waitfor_procs: HashMap<ProcRef<'o>, Location>,
+    /// Calls whose return value is used, rather than discarded, and whether
+    /// they can't run an override.
+    used_results: Vec<(ProcRef<'o>, Location, bool)>,
+    /// Where `. = ..()` passes on whatever the parent returns.
+    forwarded_results: BTreeSet<Location>,
+    /// Procs whose code never returns a value. Empty procs are left out, since
+    /// they're usually stubs for overrides to fill in.
+    returns_null: HashSet<ProcRef<'o>>,
 
     sleeping_overrides: ViolatingOverrides<'o>,
     impure_overrides: ViolatingOverrides<'o>,
+
+    /// What each name used in proc code resolved to, if being recorded.
+    definitions: Option<definitions::DefinitionIndex>,
+    /// Every call which resolved to a proc, if being recorded.
+    call_sites: Option<Vec<lens::CallSite<'o>>>,
+    /// The icons read to check `icon_state` values, if enabled.
+    icon_states: Option<icon_states::IconStates<'o>>,
+    /// The arguments given to each proc, if checking for parameters which
+    /// are never given a value.
+    supplied_args: Option<unsupplied_params::SuppliedArgs<'o>>,
+    /// The typed and untyped expressions in each proc, if measuring type
+    /// coverage.
+    type_coverage: Option<HashMap<ProcRef<'o>, type_coverage::Count>>,
 }
 
 impl<'o> AnalyzeObjectTree<'o> {
@@ -586,13 +879,25 @@ impl<'o> AnalyzeObjectTree<'o> {
             sleep_exempt: ProcDirective::new("SpacemanDMM_allowed_to_sleep", false, true, true),
             must_be_pure: ProcDirective::new("SpacemanDMM_should_be_pure", false, true, true),
             can_be_redefined: ProcDirective::new("SpacemanDMM_can_be_redefined", false, false, false),
+            must_return: ProcDirective::new("SpacemanDMM_must_return", true, false, true),
             used_kwargs: Default::default(),
             call_tree: Default::default(),
+            referenced_procs: Default::default(),
+            assigned_fields: Default::default(),
+            assigned_untyped_fields: Default::default(),
             sleeping_procs: Default::default(),
             impure_procs: Default::default(),
             waitfor_procs: Default::default(),
+            used_results: Default::default(),
+            forwarded_results: Default::default(),
+            returns_null: Default::default(),
             sleeping_overrides: Default::default(),
             impure_overrides: Default::default(),
+            definitions: None,
+            call_sites: None,
+            icon_states: if context.config().dreamchecker.icon_states { Some(Default::default()) } else { None },
+            supplied_args: if context.config().dreamchecker.unsupplied_params { Some(Default::default()) } else { None },
+            type_coverage: None,
         }
     }
 
@@ -601,7 +906,64 @@ impl<'o> AnalyzeObjectTree<'o> {
         self.must_not_sleep.try_copy_from_parent(proc);
         self.must_be_pure.try_copy_from_parent(proc);
 
-        AnalyzeProc::new(self, self.context, self.objtree, proc).run(code)
+        AnalyzeProc::new(self, self.context, self.objtree, proc).run(code);
+
+        let graph = cfg::ControlFlowGraph::for_proc(proc, code);
+        if self.return_type.contains_key(&proc) || matches!(self.must_return.get_self_or_parent(proc), Some((_, true, _))) {
+            self.check_must_return(proc, &graph);
+        }
+        if !code.is_empty() && !null_results::may_return_value(&graph, &self.context.config().dreamchecker.never_returns) {
+            self.returns_null.insert(proc);
+        }
+        if self.context.config().dreamchecker.dead_stores {
+            dead_store::check_dead_stores(self.context, proc, &graph);
+        }
+        loop_invariant::check_loop_invariants(self.context, proc, code, &graph);
+        spawn_loop::check_spawn_in_loops(self.context, code);
+        labels::check_labels(self.context, code);
+        list_bounds::check_list_bounds(self.context, proc, &graph);
+        colors::check_proc_colors(self.context, &graph);
+        if let Some(ref mut icon_states) = self.icon_states {
+            icon_states.check_proc(self.context, proc, &graph);
+        }
+        always_null::check_always_null(self.context, proc, &graph);
+        if let Some(ref mut supplied_args) = self.supplied_args {
+            supplied_args.record_reads(proc, &graph);
+        }
+        new_recursion::check_new_recursion(self.context, proc, &graph);
+        if let Some(threshold) = self.context.config().dreamchecker.complexity_threshold {
+            let score = complexity::cyclomatic_complexity(&graph);
+            if score > threshold {
+                error(proc.get().location, format!("{} has a cyclomatic complexity of {}, more than the limit of {}", proc, score, threshold))
+                    .set_severity(Severity::Warning)
+                    .with_errortype("proc_complexity")
+                    .register(self.context);
+            }
+        }
+        if self.context.config().dreamchecker.taint.enabled {
+            taint::check_taint(self.context, &graph);
+        }
+    }
+
+    /// Warn if a proc which is expected to return a value can finish without
+    /// one.
+    fn check_must_return(&self, proc: ProcRef<'o>, graph: &cfg::ControlFlowGraph) {
+        let path = match returns::find_missing_return(graph, &self.context.config().dreamchecker.never_returns) {
+            Some(path) => path,
+            None => return,
+        };
+        let mut error = error(proc.location, format!("{} can reach the end without returning a value", proc))
+            .set_severity(Severity::Warning)
+            .with_errortype("missing_return");
+        if let Some((location, truthy)) = returns::last_condition(graph, &path) {
+            error.add_note(location, format!("when the condition at line {} is {}", location.line, truthy));
+        }
+        if let Some(&(from, cfg::EdgeKind::Return, _)) = path.last() {
+            if let Some(step) = graph.blocks[from].steps.last() {
+                error.add_note(step.location(), "returns without a value here");
+            }
+        }
+        error.register(self.context);
     }
 
     #[inline]
@@ -615,6 +977,7 @@ impl<'o> AnalyzeObjectTree<'o> {
             "SpacemanDMM_allowed_to_sleep" => &mut self.sleep_exempt,
             "SpacemanDMM_should_be_pure" => &mut self.must_be_pure,
             "SpacemanDMM_can_be_redefined" => &mut self.can_be_redefined,
+            "SpacemanDMM_must_return" => &mut self.must_return,
             other => {
                 error(location, format!("unknown linter setting {:?}", directive))
                     .with_errortype("unknown_linter_setting")
@@ -645,61 +1008,199 @@ impl<'o> AnalyzeObjectTree<'o> {
         }
     }
 
-    pub fn check_proc_call_tree(&mut self) {
-        for (procref, &(_, location)) in self.must_not_sleep.directive.iter() {
-            if let Some(sleepvec) = self.sleeping_procs.get_violators(*procref) {
-                error(procref.get().location, format!("{} sets SpacemanDMM_should_not_sleep but calls blocking built-in(s)", procref))
-                    .with_note(location, "SpacemanDMM_should_not_sleep set here")
-                    .with_errortype("must_not_sleep")
-                    .with_blocking_builtins(sleepvec)
-                    .register(self.context)
-            }
-            let mut visited = HashSet::<ProcRef<'o>>::new();
-            let mut to_visit = VecDeque::<(ProcRef<'o>, CallStack, bool)>::new();
-            if let Some(procscalled) = self.call_tree.get(procref) {
-                for (proccalled, location, new_context) in procscalled {
-                    let mut callstack = CallStack::default();
-                    callstack.add_step(*proccalled, *location, *new_context);
-                    to_visit.push_back((*proccalled, callstack, *new_context));
+    /// Warn about fields declared with a type which are never given a value,
+    /// neither by an initializer on any type nor by assignment in any proc.
+    pub fn check_unassigned_fields(&self) {
+        let mut initialized: HashSet<(TypeRef<'o>, &str)> = HashSet::new();
+        for ty in self.objtree.iter_types() {
+            for (name, var) in ty.get().vars.iter() {
+                if var.value.expression.as_ref().is_some_and(|expr| !matches!(expr.as_term(), Some(Term::Null))) {
+                    if let Some(declaring) = declaring_type(ty, name, false) {
+                        initialized.insert((declaring, name));
+                    }
                 }
             }
-            while let Some((nextproc, callstack, new_context)) = to_visit.pop_front() {
-                if !visited.insert(nextproc) {
-                    continue
-                }
-                if let Some(_) = self.waitfor_procs.get(&nextproc) {
-                    continue
-                }
-                if let Some(_) = self.sleep_exempt.get(nextproc) {
-                    continue
-                }
-                if new_context {
-                    continue
+        }
+
+        for ty in self.objtree.iter_types() {
+            for (name, var) in ty.vars.iter() {
+                let decl = match &var.declaration {
+                    Some(decl) => decl,
+                    None => continue,
+                };
+                if decl.location.is_builtins()
+                    || decl.var_type.type_path.is_empty()
+                    || decl.var_type.flags.is_static()
+                    || initialized.contains(&(ty, name.as_str()))
+                    || self.assigned_fields.contains(&(ty, name.clone()))
+                    || self.assigned_untyped_fields.contains(name)
+                {
+                    continue;
                 }
-                if let Some(sleepvec) = self.sleeping_procs.get_violators(nextproc) {
-                    error(procref.get().location, format!("{} sets SpacemanDMM_should_not_sleep but calls blocking proc {}", procref, nextproc))
-                        .with_note(location, "SpacemanDMM_should_not_sleep set here")
-                        .with_errortype("must_not_sleep")
+                let type_path: String = decl.var_type.type_path.iter().map(|part| format!("/{}", part)).collect();
+                DMError::new(decl.location, format!("{}/var{}/{} is never assigned a value", ty.path, type_path, name))
+                    .with_component(dm::Component::DreamChecker)
+                    .set_severity(Severity::Warning)
+                    .with_errortype("unassigned_field")
+                    .register(self.context);
+            }
+        }
+    }
+
+    /// Report the blocking calls made by a proc which must not sleep.
+    fn check_not_sleeping(&self, procref: ProcRef<'o>, reason: NoSleepReason) {
+        if let Some(sleepvec) = self.sleeping_procs.get_violators(procref) {
+            reason.error(procref, format!("{} but calls blocking built-in(s)", reason.describe(procref)))
+                .with_blocking_builtins(sleepvec)
+                .register(self.context)
+        }
+        let mut visited = HashSet::<ProcRef<'o>>::new();
+        let mut to_visit = VecDeque::<(ProcRef<'o>, CallStack, bool)>::new();
+        if let Some(procscalled) = self.call_tree.get(&procref) {
+            for (proccalled, location, new_context) in procscalled {
+                let mut callstack = CallStack::default();
+                callstack.add_step(*proccalled, *location, *new_context);
+                to_visit.push_back((*proccalled, callstack, *new_context));
+            }
+        }
+        while let Some((nextproc, callstack, new_context)) = to_visit.pop_front() {
+            if !visited.insert(nextproc) {
+                continue
+            }
+            if let Some(_) = self.waitfor_procs.get(&nextproc) {
+                continue
+            }
+            if let Some(_) = self.sleep_exempt.get(nextproc) {
+                continue
+            }
+            if new_context {
+                continue
+            }
+            if let Some(sleepvec) = self.sleeping_procs.get_violators(nextproc) {
+                reason.error(procref, format!("{} but calls blocking proc {}", reason.describe(procref), nextproc))
+                    .with_callstack(&callstack)
+                    .with_blocking_builtins(sleepvec)
+                    .register(self.context)
+            } else if let Some(overridesleep) = self.sleeping_overrides.get_override_violators(nextproc) {
+                for child_violator in overridesleep {
+                    // Calling `..()` from a proc which sleeps itself has already been reported.
+                    if *child_violator == procref {
+                        continue
+                    }
+                    if procref.ty().is_subtype_of(&nextproc.ty()) && !child_violator.ty().is_subtype_of(&procref.ty()) {
+                        continue
+                    }
+                    let message = match reason {
+                        NoSleepReason::Directive(_) => format!("{} calls {} which has override child proc that sleeps {}", procref, nextproc, child_violator),
+                        NoSleepReason::Config(_) => format!("{} but calls {} which has override child proc that sleeps {}", reason.describe(procref), nextproc, child_violator),
+                    };
+                    reason.error(procref, message)
                         .with_callstack(&callstack)
-                        .with_blocking_builtins(sleepvec)
+                        .with_blocking_builtins(self.sleeping_procs.get_violators(*child_violator).unwrap())
                         .register(self.context)
-                } else if let Some(overridesleep) = self.sleeping_overrides.get_override_violators(nextproc) {
-                    for child_violator in overridesleep {
-                        if procref.ty().is_subtype_of(&nextproc.ty()) && !child_violator.ty().is_subtype_of(&procref.ty()) {
-                            continue
-                        }
-                        error(procref.get().location, format!("{} calls {} which has override child proc that sleeps {}", procref, nextproc, child_violator))
-                            .with_note(location, "SpacemanDMM_should_not_sleep set here")
-                            .with_errortype("must_not_sleep")
-                            .with_callstack(&callstack)
-                            .with_blocking_builtins(self.sleeping_procs.get_violators(*child_violator).unwrap())
-                            .register(self.context)
+                }
+            } else if let Some(calledvec) = self.call_tree.get(&nextproc) {
+                for (proccalled, location, new_context) in calledvec.iter() {
+                    let mut newstack = callstack.clone();
+                    newstack.add_step(*proccalled, *location, *new_context);
+                    to_visit.push_back((*proccalled, newstack, *new_context));
+                }
+            }
+        }
+    }
+
+    /// Whether a proc may sleep before returning to its caller.
+    fn may_sleep(&self, proc: ProcRef<'o>) -> bool {
+        let mut visited = HashSet::<ProcRef<'o>>::new();
+        let mut to_visit = vec![proc];
+        while let Some(nextproc) = to_visit.pop() {
+            if !visited.insert(nextproc) {
+                continue
+            }
+            if self.sleeping_procs.get_violators(nextproc).is_some() {
+                return true
+            }
+            if let Some(calledvec) = self.call_tree.get(&nextproc) {
+                for &(proccalled, _, new_context) in calledvec.iter() {
+                    if !new_context && !self.waitfor_procs.contains_key(&proccalled) {
+                        to_visit.push(proccalled);
                     }
-                } else if let Some(calledvec) = self.call_tree.get(&nextproc) {
-                    for (proccalled, location, new_context) in calledvec.iter() {
-                        let mut newstack = callstack.clone();
-                        newstack.add_step(*proccalled, *location, *new_context);
-                        to_visit.push_back((*proccalled, newstack, *new_context));
+                }
+            }
+        }
+        false
+    }
+
+    /// Warn where the return value of a `set waitfor = 0` proc which may
+    /// sleep is used.
+    fn check_waitfor_results(&self) {
+        let mut may_sleep = HashMap::<ProcRef<'o>, bool>::new();
+        for &(proc, location, _) in self.used_results.iter() {
+            let setting = match self.waitfor_procs.get(&proc) {
+                Some(&setting) => setting,
+                None => continue,
+            };
+            if !*may_sleep.entry(proc).or_insert_with(|| self.may_sleep(proc)) {
+                continue
+            }
+            error(location, format!("{} sets waitfor = 0, so it returns to its caller as soon as it sleeps, and the value used here is whatever . held at that point rather than what it finally returns", proc))
+                .set_severity(Severity::Warning)
+                .with_note(setting, "waitfor = 0 set here")
+                .with_errortype("waitfor_result_used")
+                .register(self.context);
+        }
+    }
+
+    /// Report calls whose result is used, where every definition the call
+    /// may run never returns a value.
+    fn check_null_results(&self) {
+        let mut calls: Vec<_> = self.used_results.iter()
+            .map(|&(proc, location, is_exact)| (location, proc, is_exact))
+            .collect();
+        calls.sort_by_key(|&(location, _, _)| location);
+        calls.dedup();
+
+        let mut returns_null = HashMap::<ProcRef<'o>, bool>::new();
+        for (location, proc, is_exact) in calls {
+            let declared = self.return_type.contains_key(&proc) || self.must_return.get_self_or_parent(proc).is_some();
+            // An exact call such as `..()` runs only the definition it names,
+            // while any other call may run an override.
+            let never_returns = !declared && if is_exact {
+                self.returns_null.contains(&proc) && !self.forwarded_results.contains(&location)
+            } else {
+                *returns_null.entry(proc).or_insert_with(|| {
+                    null_results::definitions_run_by(proc).iter().all(|each| self.returns_null.contains(each))
+                })
+            };
+            if never_returns {
+                error(location, format!("{} never returns a value, so the result used here is always null", proc))
+                    .set_severity(Severity::Warning)
+                    .with_note(proc.get().location, "defined here without a return value")
+                    .with_errortype("null_result_used")
+                    .register(self.context);
+            }
+        }
+    }
+
+    pub fn check_proc_call_tree(&mut self) {
+        for (procref, &(_, location)) in self.must_not_sleep.directive.iter() {
+            self.check_not_sleeping(*procref, NoSleepReason::Directive(location));
+        }
+        self.check_waitfor_results();
+        self.check_null_results();
+        for entry in self.context.config().dreamchecker.never_sleep.iter() {
+            let base = match find_proc(self.objtree, entry) {
+                Some(base) => base,
+                None => continue,
+            };
+            for ty in self.objtree.iter_types() {
+                for procref in ty.iter_self_procs() {
+                    if procref.name() == base.name()
+                        && procref.get().code.is_some()
+                        && self.must_not_sleep.get_self_or_parent(procref).is_none()
+                        && overrides(procref, base)
+                    {
+                        self.check_not_sleeping(procref, NoSleepReason::Config(entry));
                     }
                 }
             }
@@ -756,6 +1257,18 @@ impl<'o> AnalyzeObjectTree<'o> {
         }
     }
 
+    /// Gather and store set directives for every proc in the tree.
+    fn gather_all_settings(&mut self) {
+        let objtree = self.objtree;
+        objtree.root().recurse(&mut |ty| {
+            for proc in ty.iter_self_procs() {
+                if let Some(ref code) = proc.get().code {
+                    self.gather_settings(proc, code);
+                }
+            }
+        });
+    }
+
     /// Gather and store set directives for the given proc using the provided code body
     pub fn gather_settings(&mut self, proc: ProcRef<'o>, code: &'o [Spanned<Statement>]) {
         for statement in code.iter() {
@@ -793,7 +1306,7 @@ impl<'o> AnalyzeObjectTree<'o> {
                             if let Some(term) = value.as_term() {
                                 match term {
                                     // TODO: detect procs-as-verbs here
-                                    Term::String(_) | Term::InterpString(_, _) => {},
+                                    Term::String(_) | Term::RawString(_) | Term::InterpString(_, _) => {},
                                     // category can be set null to hide it
                                     Term::Null if name.as_str() == "category" => {},
                                     other => {
@@ -1099,11 +1612,13 @@ impl ControlFlow {
 struct LocalVar<'o> {
     location: Location,
     analysis: Analysis<'o>,
+    /// Whether the var is declared `const`, so its value never changes.
+    is_const: bool,
 }
 
 impl<'o> From<Analysis<'o>> for LocalVar<'o> {
     fn from(analysis: Analysis<'o>) -> Self {
-        LocalVar { location: Location::default(), analysis }
+        LocalVar { location: Location::default(), analysis, is_const: false }
     }
 }
 
@@ -1115,6 +1630,24 @@ struct AnalyzeProc<'o, 's> {
     proc_ref: ProcRef<'o>,
     calls_parent: bool,
     inside_newcontext: u32,
+    /// The enclosing `try` blocks, with the exception type their catch
+    /// parameter is declared as and the spawn depth they were entered at.
+    catching: Vec<(Location, Option<TypeRef<'o>>, u32)>,
+    /// The call whose value the current statement discards, if any.
+    discarded_call: Option<Location>,
+    /// The field the current assignment writes to, if any.
+    assigned_field: Option<Location>,
+    /// The left side of the `<<` which the current statement is, if any.
+    statement_lshift: Option<&'o Expression>,
+    /// Whether the `<<` visited last was output rather than a bit shift.
+    last_lshift_output: bool,
+    /// The position being queried by `hover::hover`, if any.
+    hover: Option<hover::Probe>,
+    /// The position being queried by `completion::members_at`, if any.
+    completion: Option<completion::Scope>,
+    /// The argument list being queried by `signature::signature_help`, if
+    /// any.
+    signature: Option<signature::Probe<'o>>,
 }
 
 impl<'o, 's> AnalyzeProc<'o, 's> {
@@ -1129,6 +1662,14 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
             proc_ref,
             calls_parent: false,
             inside_newcontext: 0,
+            catching: Vec::new(),
+            discarded_call: None,
+            assigned_field: None,
+            statement_lshift: None,
+            last_lshift_output: false,
+            hover: None,
+            completion: None,
+            signature: None,
         }
     }
 
@@ -1148,15 +1689,22 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
             is_impure: Some(true),
         }.into());
 
+        param_defaults::check_param_defaults(self.context, self.proc_ref);
         for param in self.proc_ref.get().parameters.iter() {
             let mut analysis = self.static_type(param.location, &param.var_type.type_path);
             analysis.is_impure = Some(true); // all params are impure
             local_vars.insert(param.name.to_owned(), LocalVar {
-                location: self.proc_ref.location,
+                location: param.location,
                 analysis,
+                is_const: false,
             });
             //println!("adding parameters {:#?}", self.local_vars);
         }
+        for param in self.proc_ref.get().parameters.iter() {
+            if let Some(ref default) = param.default {
+                self.visit_expression(param.location, default, None, &mut local_vars);
+            }
+        }
 
         self.visit_block(block, &mut local_vars);
 
@@ -1207,12 +1755,40 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
                     .register(self.context);
                 return term // stop evaluating
             }
+            if let Some(scope) = self.completion.as_mut() {
+                scope.observe(stmt.location, false, local_vars);
+            }
             let state = self.visit_statement(stmt.location, &stmt.elem, local_vars);
+            if let Some(scope) = self.completion.as_mut() {
+                scope.observe(stmt.location, true, local_vars);
+            }
             term.merge(state);
         }
         return term
     }
 
+    /// Report a switch whose input is a literal, a define, or a const var,
+    /// returning the branch it always takes.
+    fn check_constant_switch(&mut self, location: Location, input: &'o Expression, cases: &'o [(Spanned<Vec<Case>>, Block)], has_default: bool, local_vars: &HashMap<String, LocalVar<'o>, RandomState>) -> Option<Taken> {
+        let value = cfg::constant_value(location, input, Some(self.ty), |name| {
+            local_vars.get(name).map(|var| if var.is_const { var.analysis.value.clone() } else { None })
+        })?;
+        let taken = switch_overlap::taken_case(cases, &value)?;
+        let message = match taken {
+            Taken::Case(index) => format!("switch input is always {}, so only the case on line {} runs", value, cases[index].0.location.line),
+            Taken::Default if has_default => format!("switch input is always {}, so only the else branch runs", value),
+            Taken::Default => format!("switch input is always {}, which no case matches", value),
+        };
+        let mut error = error(location, message)
+            .set_severity(Severity::Warning)
+            .with_errortype("switch_input_constant");
+        if let Taken::Case(index) = taken {
+            error.add_note(cases[index].0.location, "always taken");
+        }
+        error.register(self.context);
+        Some(taken)
+    }
+
     fn loop_condition_check(&mut self, location: Location, expression: &'o Expression) {
         match expression.is_truthy() {
             Some(true) => {
@@ -1261,17 +1837,18 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
                             }
                         }
                     },
-                    Expression::BinaryOp { op: BinaryOp::LShift, lhs, rhs } => {
-                        let lhsanalysis = self.visit_expression(location, lhs, None, local_vars);
-                        if let Some(impurity) = lhsanalysis.is_impure {
-                            if impurity {
-                                self.env.impure_procs.insert_violator(self.proc_ref, "purity breaking << on expression", location);
-                            }
-                        }
+                    Expression::BinaryOp { op: BinaryOp::LShift, lhs, .. } => {
+                        self.statement_lshift = Some(lhs);
                     },
                     _ => {},
                 }
+                self.discarded_call = final_call_location(expr);
+                if let Some(location) = forwarded_parent_call(expr) {
+                    self.env.forwarded_results.insert(location);
+                }
                 self.visit_expression(location, expr, None, local_vars);
+                self.discarded_call = None;
+                self.statement_lshift = None;
             },
             Statement::Return(Some(expr)) => {
                 // TODO: factor in the previous return type if there was one
@@ -1291,31 +1868,49 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
                 }
                 return ControlFlow { returns: true, continues: false, breaks: false, fuzzy: false }
             },
-            Statement::Throw(expr) => { self.visit_expression(location, expr, None, local_vars); },
+            Statement::Throw(expr) => {
+                let analysis = self.visit_expression(location, expr, None, local_vars);
+                if let Some(&(try_location, Some(caught), depth)) = self.catching.last() {
+                    if let (Some(kind), true) = (value_kind(&analysis), depth == self.inside_newcontext) {
+                        error(location, format!("throwing {}, which is caught as {}, will runtime error on field access", kind, caught))
+                            .set_severity(Severity::Warning)
+                            .with_note(try_location, "caught by this try")
+                            .with_errortype("thrown_non_exception")
+                            .register(self.context);
+                    }
+                }
+            },
             Statement::While { condition, block } => {
                 let mut scoped_locals = local_vars.clone();
+                forget_kinds(&mut scoped_locals, &loop_invariant::modified_in_loop(&[condition], &[], block));
                 // We don't check for static/determine conditions because while(TRUE) is so common.
                 self.visit_expression(location, condition, None, &mut scoped_locals);
                 let mut state = self.visit_block(block, &mut scoped_locals);
+                merge_scope(local_vars, &scoped_locals);
                 state.end_loop();
                 return state
             },
             Statement::DoWhile { block, condition } => {
                 let mut scoped_locals = local_vars.clone();
+                forget_kinds(&mut scoped_locals, &loop_invariant::modified_in_loop(&[&condition.elem], &[], block));
                 let mut state = self.visit_block(block, &mut scoped_locals);
+                merge_scope(local_vars, &scoped_locals);
                 if state.terminates_loop() {
                     error(location,"do while terminates without ever reaching condition")
                         .register(self.context);
                     return state
                 }
                 self.visit_expression(condition.location, &condition.elem, None, &mut scoped_locals);
+                merge_scope(local_vars, &scoped_locals);
 
                 state.end_loop();
                 return state
             },
             Statement::If { arms, else_arm } => {
+                duplicate_branches::check_if_chain(self.context, location, arms, else_arm.as_deref());
                 let mut allterm = ControlFlow::alltrue();
                 let mut alwaystrue = false;
+                let mut scopes = Vec::new();
                 for (condition, ref block) in arms.iter() {
                     let mut scoped_locals = local_vars.clone();
                     self.visit_control_condition(condition.location, &condition.elem);
@@ -1326,6 +1921,7 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
                     }
                     self.visit_expression(condition.location, &condition.elem, None, &mut scoped_locals);
                     let state = self.visit_block(block, &mut scoped_locals);
+                    scopes.push(scoped_locals);
                     match condition.elem.is_truthy() {
                         Some(true) => {
                             error(condition.location,"if condition is always true")
@@ -1350,9 +1946,15 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
                                 .register(self.context);
                         }
                     }
-                    let state = self.visit_block(else_arm, &mut local_vars.clone());
+                    let mut scoped_locals = local_vars.clone();
+                    let state = self.visit_block(else_arm, &mut scoped_locals);
+                    scopes.push(scoped_locals);
                     allterm.merge_false(state);
-                } else {
+                }
+                for scope in scopes.iter() {
+                    merge_scope(local_vars, scope);
+                }
+                if else_arm.is_none() {
                     allterm.no_else();
                     return allterm
                 }
@@ -1361,7 +1963,9 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
             },
             Statement::ForInfinite { block } => {
                 let mut scoped_locals = local_vars.clone();
+                forget_kinds(&mut scoped_locals, &loop_invariant::modified_in_loop(&[], &[], block));
                 let mut state = self.visit_block(block, &mut scoped_locals);
+                merge_scope(local_vars, &scoped_locals);
                 state.end_loop();
                 return state
             }
@@ -1370,6 +1974,9 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
                 if let Some(init) = init {
                     self.visit_statement(location, init, &mut scoped_locals);
                 }
+                let conditions: Vec<_> = test.iter().map(|test| &**test).collect();
+                let incs: Vec<_> = inc.iter().map(|inc| &**inc).collect();
+                forget_kinds(&mut scoped_locals, &loop_invariant::modified_in_loop(&conditions, &incs, block));
                 if let Some(test) = test {
                     self.loop_condition_check(location, test);
                     self.visit_control_condition(location, test);
@@ -1379,6 +1986,7 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
                     self.visit_statement(location, inc, &mut scoped_locals);
                 }
                 let mut state = self.visit_block(block, &mut scoped_locals);
+                merge_scope(local_vars, &scoped_locals);
                 state.end_loop();
                 return state
             },
@@ -1416,8 +2024,14 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
                 }
                 if let Some(var_type) = var_type {
                     self.visit_var(location, var_type, name, None, &mut scoped_locals);
+                } else if !scoped_locals.contains_key(name.as_str()) {
+                    self.record_field_write(self.ty, name);
                 }
+                let mut modified = loop_invariant::modified_in_loop(&[], &[], block);
+                modified.insert(name.to_string());
+                forget_kinds(&mut scoped_locals, &modified);
                 let mut state = self.visit_block(block, &mut scoped_locals);
+                merge_scope(local_vars, &scoped_locals);
                 state.end_loop();
                 return state
             },
@@ -1431,7 +2045,13 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
                 if let Some(var_type) = var_type {
                     self.visit_var(location, var_type, name, Some(start), &mut scoped_locals);
                 }
+                let mut modified = loop_invariant::modified_in_loop(&[], &[], block);
+                if var_type.is_none() {
+                    modified.insert(name.to_string());
+                }
+                forget_kinds(&mut scoped_locals, &modified);
                 let mut state = self.visit_block(block, &mut scoped_locals);
+                merge_scope(local_vars, &scoped_locals);
                 if let Some(startterm) = start.as_term() {
                     if let Some(endterm) = end.as_term() {
                         if let Some(validity) = startterm.valid_for_range(endterm, step.as_ref()) {
@@ -1462,7 +2082,7 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
                     Some(Term::Ident(i)) if i == "FALSE" => Some(true),
                     _ => None,
                 } {
-                    Some(_) => { self.env.waitfor_procs.insert(self.proc_ref); },
+                    Some(_) => { self.env.waitfor_procs.insert(self.proc_ref, location); },
                     None => (),
                 }
             },
@@ -1471,17 +2091,29 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
                 self.inside_newcontext = self.inside_newcontext.wrapping_add(1);
                 let mut scoped_locals = local_vars.clone();
                 if let Some(delay) = delay {
-                    self.visit_expression(location, delay, None, &mut scoped_locals);
+                    let analysis = self.visit_expression(location, delay, None, &mut scoped_locals);
+                    if let Some(kind @ (operand_kinds::Kind::Text | operand_kinds::Kind::List)) = operand_kinds::kind_of(&analysis) {
+                        error(location, format!("spawn() delay is {}, not a number of deciseconds", kind.name()))
+                            .set_severity(Severity::Warning)
+                            .with_errortype("spawn_delay_type")
+                            .register(self.context);
+                    }
                 }
                 self.visit_block(block, &mut scoped_locals);
+                merge_scope(local_vars, &scoped_locals);
                 self.inside_newcontext = self.inside_newcontext.wrapping_sub(1);
             },
             Statement::Switch { input, cases, default } => {
                 check_switch_rand_range(input, cases, default, location, self.context);
+                check_switch_overlap(cases, self.context);
+                let taken = self.check_constant_switch(location, input, cases, default.is_some(), local_vars);
+                // Branches which are never taken don't count towards how it ends.
+                let counts = |branch| taken.is_none_or(|taken| taken == branch);
                 let mut allterm = ControlFlow::alltrue();
                 self.visit_control_condition(location, input);
                 self.visit_expression(location, input, None, local_vars);
-                for (case, ref block) in cases.iter() {
+                let mut scopes = Vec::new();
+                for (index, (case, ref block)) in cases.iter().enumerate() {
                     let mut scoped_locals = local_vars.clone();
                     if let [dm::ast::Case::Exact(Expression::BinaryOp{op: BinaryOp::Or, ..})] = case.elem[..] {
                         error(case.location, "Elements in a switch-case branch separated by ||, this is likely in error and should be replaced by a comma")
@@ -1498,12 +2130,23 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
                         }
                     }
                     let state = self.visit_block(block, &mut scoped_locals);
-                    allterm.merge_false(state);
+                    scopes.push(scoped_locals);
+                    if counts(Taken::Case(index)) {
+                        allterm.merge_false(state);
+                    }
                 }
                 if let Some(default) = default {
-                    let state = self.visit_block(default, &mut local_vars.clone());
-                    allterm.merge_false(state);
-                } else {
+                    let mut scoped_locals = local_vars.clone();
+                    let state = self.visit_block(default, &mut scoped_locals);
+                    scopes.push(scoped_locals);
+                    if counts(Taken::Default) {
+                        allterm.merge_false(state);
+                    }
+                }
+                for scope in scopes.iter() {
+                    merge_scope(local_vars, scope);
+                }
+                if default.is_none() && counts(Taken::Default) {
                     allterm.no_else();
                     return allterm
                 }
@@ -1511,7 +2154,19 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
                 return allterm
             },
             Statement::TryCatch { try_block, catch_params, catch_block } => {
-                self.visit_block(try_block, &mut local_vars.clone());
+                let caught = catch_params.first()
+                    .and_then(|caught| self.caught_exception_type(caught));
+                self.catching.push((location, caught, self.inside_newcontext));
+                let mut try_locals = local_vars.clone();
+                self.visit_block(try_block, &mut try_locals);
+                self.catching.pop();
+                if !catch_block.is_empty() && !try_catch::can_throw(try_block) {
+                    error(location, "try block cannot throw, so its catch block never runs")
+                        .set_severity(Severity::Warning)
+                        .with_note(catch_block[0].location, "catch block is here")
+                        .with_errortype("unreachable_catch")
+                        .register(self.context);
+                }
                 if catch_params.len() > 1 {
                     error(location, format!("Expected 0 or 1 catch parameters, got {}", catch_params.len()))
                         .set_severity(Severity::Warning)
@@ -1531,17 +2186,53 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
                     self.visit_var(location, &var_type, var_name, None, &mut catch_locals);
                 }
                 self.visit_block(catch_block, &mut catch_locals);
+                merge_scope(local_vars, &try_locals);
+                merge_scope(local_vars, &catch_locals);
             },
             Statement::Continue(_) => { return ControlFlow { returns: false, continues: true, breaks: false, fuzzy: true } },
             Statement::Break(_) => { return ControlFlow { returns: false, continues: false, breaks: true, fuzzy: true } },
             Statement::Goto(_) => {},
-            Statement::Label { name: _, block } => { self.visit_block(block, &mut local_vars.clone()); },
+            Statement::Label { name: _, block } => {
+                let mut scoped_locals = local_vars.clone();
+                self.visit_block(block, &mut scoped_locals);
+                merge_scope(local_vars, &scoped_locals);
+            },
             Statement::Del(expr) => { self.visit_expression(location, expr, None, local_vars); },
         }
         return ControlFlow::allfalse()
     }
 
+    /// Note that a field visible on `ty` is written to.
+    fn record_field_write(&mut self, ty: TypeRef<'o>, name: &str) {
+        if let Some(declaring) = declaring_type(ty, name, false) {
+            self.env.assigned_fields.insert((declaring, name.to_owned()));
+        }
+    }
+
+    /// The type a catch parameter is declared as, if it is an exception.
+    fn caught_exception_type(&self, caught: &[String]) -> Option<TypeRef<'o>> {
+        let (_, mut type_path) = caught.split_last()?;
+        if let Some((first, rest)) = type_path.split_first() {
+            if first == "var" {
+                type_path = rest;
+            }
+        }
+        let ty = self.objtree.type_by_path(type_path)?;
+        let exception = self.objtree.type_by_path(&["exception"])?;
+        if ty.is_subtype_of(&exception) {
+            Some(ty)
+        } else {
+            None
+        }
+    }
+
     fn visit_var_stmt(&mut self, location: Location, var: &'o VarStatement, local_vars: &mut HashMap<String, LocalVar<'o>, RandomState>) {
+        // Sizes are visited as part of the value they imply, if it's used.
+        if !var.has_implied_value() {
+            for size in var.dimensions.iter().flatten() {
+                self.visit_expression(location, size, None, local_vars);
+            }
+        }
         self.visit_var(location, &var.var_type, &var.name, var.value.as_ref(), local_vars)
     }
 
@@ -1556,10 +2247,29 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
         analysis.static_ty = static_type;
 
         // Save var to locals
-        local_vars.insert(name.to_owned(), LocalVar { location, analysis });
+        local_vars.insert(name.to_owned(), LocalVar { location, analysis, is_const: var_type.flags.is_const() });
+    }
+
+    /// Record what a term or follow resolved to for a hover or the
+    /// definition index.
+    fn record_resolution(&mut self, location: Location, analysis: &Analysis<'o>, hovered: bool, resolution: Option<definitions::Resolution>) {
+        if let (true, Some(probe)) = (hovered, self.hover.as_mut()) {
+            probe.record(location, analysis, resolution.as_ref().and_then(|each| each.definition()));
+        }
+        if let (Some(index), Some(resolution)) = (self.env.definitions.as_mut(), resolution) {
+            index.record(location, resolution);
+        }
     }
 
     fn visit_expression(&mut self, location: Location, expression: &'o Expression, type_hint: Option<TypeRef<'o>>, local_vars: &mut HashMap<String, LocalVar<'o>, RandomState>) -> Analysis<'o> {
+        let analysis = self.visit_expression_uncounted(location, expression, type_hint, local_vars);
+        if let Some(coverage) = self.env.type_coverage.as_mut() {
+            coverage.entry(self.proc_ref).or_default().record(type_coverage::is_typed(&analysis));
+        }
+        analysis
+    }
+
+    fn visit_expression_uncounted(&mut self, location: Location, expression: &'o Expression, type_hint: Option<TypeRef<'o>>, local_vars: &mut HashMap<String, LocalVar<'o>, RandomState>) -> Analysis<'o> {
         match expression {
             Expression::Base { term, follow } => {
                 let base_type_hint = if follow.is_empty() {
@@ -1568,13 +2278,28 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
                     None
                 };
                 let mut ty = self.visit_term(term.location, &term.elem, base_type_hint, local_vars);
+                let hovered = self.hover.as_ref().is_some_and(|probe| probe.covers(term.location, term.end));
+                if hovered || self.env.definitions.is_some() {
+                    let resolution = definitions::Resolution::of_term(&term.elem, self.proc_ref, local_vars);
+                    self.record_resolution(term.location, &ty, hovered, resolution);
+                }
                 for each in follow.iter() {
+                    let hovered = self.hover.as_ref().is_some_and(|probe| probe.covers(each.location, each.end));
+                    let resolution = if hovered || self.env.definitions.is_some() {
+                        definitions::Resolution::of_follow(&each.elem, ty.static_ty.basic_type())
+                    } else {
+                        None
+                    };
                     ty = self.visit_follow(each.location, ty, &each.elem, local_vars);
+                    self.record_resolution(each.location, &ty, hovered, resolution);
                 }
                 ty
             },
             Expression::BinaryOp { op: BinaryOp::LShift, lhs, rhs } => {
                 let lty = self.visit_expression(location, lhs, None, local_vars);
+                if self.statement_lshift.is_some_and(|statement| std::ptr::eq(statement, &**lhs)) && lty.is_impure == Some(true) {
+                    self.env.impure_procs.insert_violator(self.proc_ref, "purity breaking << on expression", location);
+                }
 
                 if lty.static_ty == StaticType::Type(self.objtree.expect("/mob")) {
                     self.env.impure_procs.insert_violator(self.proc_ref, "LShift onto mob", location);
@@ -1584,8 +2309,17 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
                     self.env.impure_procs.insert_violator(self.proc_ref, "LShift onto list", location);
                 }
 
+                let meaning = output_operator::meaning(self.objtree, lhs, &lty);
+                self.last_lshift_output = false;
                 let rty = self.visit_expression(location, rhs, None, local_vars);
-                self.visit_binary(lty, rty, BinaryOp::LShift)
+                let rhs_is_output = self.last_lshift_output
+                    && matches!(same_ast::unparenthesize(rhs), Expression::BinaryOp { op: BinaryOp::LShift, .. });
+                output_operator::check_lshift(self.context, location, meaning, &lty, rhs, &rty, rhs_is_output);
+                if self.context.config().dreamchecker.world_output {
+                    output_operator::check_world_output(self.context, location, lhs);
+                }
+                self.last_lshift_output = matches!(meaning, output_operator::Meaning::Output { .. });
+                self.visit_binary(location, lty, rty, BinaryOp::LShift)
             },
             Expression::BinaryOp { op: BinaryOp::In, lhs, rhs } => {
                 // check for incorrect/ambiguous in statements
@@ -1630,7 +2364,7 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
                 };
                 let lty = self.visit_expression(location, lhs, None, local_vars);
                 let rty = self.visit_expression(location, rhs, None, local_vars);
-                self.visit_binary(lty, rty, BinaryOp::In)
+                self.visit_binary(location, lty, rty, BinaryOp::In)
             },
             Expression::BinaryOp { op: BinaryOp::Or, lhs, rhs } => {
                 // It appears that DM does this in more cases than this, but
@@ -1638,8 +2372,9 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
                 // ex: var/datum/cache_entry/E = cache[key] || new
                 let lty = self.visit_expression(location, lhs, type_hint, local_vars);
                 let rty = self.visit_expression(location, rhs, type_hint, local_vars);
-                self.visit_binary(lty, rty, BinaryOp::Or)
+                self.visit_binary(location, lty, rty, BinaryOp::Or)
             },
+            Expression::BinaryOp { op: BinaryOp::Add, .. } => self.visit_add_chain(location, expression, local_vars),
             Expression::BinaryOp { op, lhs, rhs } => {
                 let lty = self.visit_expression(location, lhs, None, local_vars);
                 let rty = self.visit_expression(location, rhs, None, local_vars);
@@ -1649,14 +2384,35 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
                     BinaryOp::BitXor => self.check_negated_bitwise(lhs, location, BinaryOp::BitXor, BinaryOp::NotEq),
                     _ => {}
                 }
-                self.visit_binary(lty, rty, *op)
+                random_args::check_random_comparison(self.context, location, *op, lhs, rhs);
+                self.visit_binary(location, lty, rty, *op)
             },
-            Expression::AssignOp { lhs, rhs, .. } => {
-                let lhs = self.visit_expression(location, lhs, None, local_vars);
+            Expression::AssignOp { op, lhs: lhs_expr, rhs } => {
+                if let Some(Term::Ident(name)) = lhs_expr.as_term() {
+                    if !local_vars.contains_key(name) {
+                        self.record_field_write(self.ty, name);
+                    }
+                }
+                self.assigned_field = final_field_location(lhs_expr);
+                let lhs = self.visit_expression(location, lhs_expr, None, local_vars);
+                self.assigned_field = None;
                 if let Some(true) = lhs.is_impure {
                     self.env.impure_procs.insert_violator(self.proc_ref, "Assignment on purity breaking expression", location);
                 }
-                self.visit_expression(location, rhs, lhs.static_ty.basic_type(), local_vars)
+                let rhs = self.visit_expression(location, rhs, lhs.static_ty.basic_type(), local_vars);
+                if let Some(Term::Ident(name)) = lhs_expr.as_term() {
+                    if let Some(var) = local_vars.get_mut(name) {
+                        // The declared value's kind no longer holds unless
+                        // the new value shares it.
+                        var.analysis.aset.set.retain(|each| !is_kind_assumption(each) || rhs.aset.set.contains(each));
+                    }
+                }
+                match op {
+                    AssignOp::AndAssign |
+                    AssignOp::OrAssign |
+                    AssignOp::NullAssign => conditional_assign(*op, lhs, rhs),
+                    _ => rhs,
+                }
             },
             Expression::TernaryOp { cond, if_, else_ } => {
                 // TODO: be sensible
@@ -1673,7 +2429,7 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
             Term::Null => Analysis::null(),
             Term::Int(number) => Analysis::from_value(self.objtree, Constant::from(*number), type_hint),
             Term::Float(number) => Analysis::from_value(self.objtree, Constant::from(*number), type_hint),
-            Term::String(text) => Analysis::from_value(self.objtree, Constant::String(text.as_str().into()), type_hint),
+            Term::String(text) | Term::RawString(text) => Analysis::from_value(self.objtree, Constant::String(text.as_str().into()), type_hint),
             Term::Resource(text) => Analysis::from_value(self.objtree, Constant::Resource(text.as_str().into()), type_hint),
             Term::As(_) => assumption_set![Assumption::IsNum(true)].into(),
 
@@ -1713,6 +2469,35 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
                     Analysis::empty()
                 }
             },
+            Term::ProcReference(reference) => {
+                let owner = match reference.ty {
+                    None => Some(self.ty),
+                    Some(ref path) if path.is_empty() => Some(self.objtree.root()),
+                    Some(ref path) => self.ty.navigate_path(path).map(|nav| nav.ty()),
+                };
+                guard!(let Some(owner) = owner else {
+                    error(location, format!("failed to resolve path {}", reference))
+                        .register(self.context);
+                    return Analysis::empty()
+                });
+                if let Some(proc) = owner.get_proc(&reference.name) {
+                    match proc.get_declaration() {
+                        Some(decl) if decl.kind != reference.kind => {
+                            error(location, format!("{:?} on {} is a {}, not a {}", reference.name, owner, decl.kind, reference.kind))
+                                .with_errortype("undefined_proc_reference")
+                                .register(self.context);
+                        }
+                        _ => {
+                            self.env.referenced_procs.insert(proc);
+                        }
+                    }
+                } else {
+                    error(location, format!("undefined proc: {:?} on {}", reference.name, owner))
+                        .with_errortype("undefined_proc_reference")
+                        .register(self.context);
+                }
+                Analysis::empty()
+            },
             Term::InterpString(_, parts) => {
                 for (ref expr, _) in parts.iter() {
                     if let Some(expr) = expr {
@@ -1735,6 +2520,15 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
                 let src = self.ty;
                 if let Some(proc) = self.ty.get_proc(unscoped_name) {
                     self.visit_call(location, src, proc, args, false, local_vars)
+                } else if unscoped_name == "spawn" {
+                    // A `spawn` which isn't a statement parses as a call.
+                    self.visit_arguments(location, args, local_vars);
+                    error(location, "spawn() is a statement and has no value")
+                        .set_severity(Severity::Warning)
+                        .with_errortype("spawn_value_used")
+                        .with_note(location, "put the code to run later in a block under the spawn() statement")
+                        .register(self.context);
+                    Analysis::empty()
                 } else if unscoped_name == "SpacemanDMM_unlint" {
                     // Escape hatch for cases like `src` in macros used in
                     // global procs.
@@ -1798,7 +2592,7 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
             },
 
             Term::List(args) => {
-                self.visit_arguments(location, args, local_vars);
+                self.visit_list(location, args, local_vars);
                 Analysis::from_static_type(self.objtree.expect("/list"))
             },
             Term::Input { args, input_type, in_list } => {
@@ -1849,15 +2643,29 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
                 }
             },
             Term::Pick(choices) => {
+                let mut types = Vec::with_capacity(choices.len());
                 for (weight, choice) in choices.iter() {
                     if let Some(ref weight) = weight {
+                        // The `prob(P); value` form is a weight, not a call.
+                        let weight = match weight.as_term() {
+                            Some(Term::Call(name, args)) if name == "prob" && args.len() == 1 => &args[0],
+                            _ => weight,
+                        };
                         self.visit_expression(location, weight, None, local_vars);
                     }
-                    self.visit_expression(location, choice, None, local_vars);
+                    types.push(self.visit_expression(location, choice, None, local_vars).static_ty);
                 }
+                self.check_pick_weights(location, choices);
 
-                // TODO: common superset of all choices
-                Analysis::empty()
+                match &choices[..] {
+                    // pick(L) chooses from the list.
+                    [(None, _)] => match types.pop() {
+                        Some(StaticType::List { keys, .. }) => Analysis::from(*keys),
+                        _ => Analysis::empty(),
+                    },
+                    _ if !types.is_empty() && types.iter().all(|ty| *ty == types[0]) => Analysis::from(types.swap_remove(0)),
+                    _ => Analysis::empty(),
+                }
             },
             Term::DynamicCall(lhs_args, rhs_args) => {
                 self.visit_arguments(location, lhs_args, local_vars);
@@ -1867,8 +2675,50 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
         }
     }
 
+    /// Warn about weighted `pick()` calls whose constant weights make most
+    /// choices impossible.
+    fn check_pick_weights(&mut self, location: Location, choices: &'o [(Option<Expression>, Expression)]) {
+        if choices.len() < 2 || choices.iter().all(|(weight, _)| weight.is_none()) {
+            return;
+        }
+        let mut weights = Vec::with_capacity(choices.len());
+        for (weight, _) in choices.iter() {
+            let weight = match weight {
+                // Unweighted choices have a weight of 100.
+                None => 100.,
+                Some(weight) => {
+                    // The `prob(P); value` form has the same meaning as `P; value`.
+                    let weight = match weight.as_term() {
+                        Some(Term::Call(name, args)) if name == "prob" && args.len() == 1 => &args[0],
+                        _ => weight,
+                    };
+                    match weight.clone().simple_evaluate(location) {
+                        Ok(Constant::Float(weight)) => weight,
+                        _ => return,
+                    }
+                }
+            };
+            weights.push(weight);
+        }
+
+        let nonzero: Vec<_> = weights.iter().enumerate().filter(|&(_, &w)| w > 0.).map(|(i, _)| i).collect();
+        if nonzero.is_empty() {
+            error(location, "pick() weights sum to zero")
+                .set_severity(Severity::Warning)
+                .with_errortype("suspicious_pick_weights")
+                .register(self.context);
+        } else if nonzero.len() == 1 {
+            error(location, format!("pick() always chooses entry {} of {}", nonzero[0] + 1, weights.len()))
+                .set_severity(Severity::Warning)
+                .with_errortype("suspicious_pick_weights")
+                .register(self.context);
+        }
+    }
+
     fn visit_new(&mut self, location: Location, typepath: TypeRef<'o>, args: &'o Option<Box<[Expression]>>, local_vars: &mut HashMap<String, LocalVar<'o>, RandomState>) -> Analysis<'o> {
         if let Some(new_proc) = typepath.get_proc("New") {
+            // The value of `new` is the object, never what New() returns.
+            let discarded = self.discarded_call.replace(location);
             self.visit_call(
                 location,
                 typepath,
@@ -1878,7 +2728,13 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
                 // `/datum/New()` and never an override.
                 true,
                 local_vars);
-        } else if typepath.path != "/list" {
+            self.discarded_call = discarded;
+        } else if typepath.path == "/list" {
+            // The arguments of `new /list()` are its sizes.
+            if let Some(args) = args {
+                self.visit_arguments(location, args, local_vars);
+            }
+        } else {
             error(location, format!("couldn't find {}/proc/New", typepath.path))
                 .register(self.context);
         }
@@ -1905,17 +2761,26 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
         match rhs {
             Follow::Unary(op) => self.visit_unary(lhs, op, location, local_vars),
 
-            Follow::Field(PropertyAccessKind::Colon, _) => Analysis::empty(),
-            Follow::Field(PropertyAccessKind::SafeColon, _) => Analysis::empty(),
-            Follow::Call(PropertyAccessKind::Colon, _, args) |
-            Follow::Call(PropertyAccessKind::SafeColon, _, args) => {
+            Follow::Field(PropertyAccessKind::Colon, name) |
+            Follow::Field(PropertyAccessKind::SafeColon, name) => {
+                if self.assigned_field == Some(location) {
+                    self.env.assigned_untyped_fields.insert(name.to_string());
+                }
+                Analysis::empty()
+            },
+            Follow::Call(PropertyAccessKind::Colon, name, args) |
+            Follow::Call(PropertyAccessKind::SafeColon, name, args) => {
+                if let Some(supplied_args) = self.env.supplied_args.as_mut() {
+                    supplied_args.record_unresolved(name);
+                }
                 // No analysis yet, but be sure to visit the arguments
                 for arg in args.iter() {
                     let mut argument_value = arg;
                     if let Expression::AssignOp { op: AssignOp::Assign, lhs, rhs } = arg {
                         match lhs.as_term() {
                             Some(Term::Ident(name)) |
-                            Some(Term::String(name)) => {
+                            Some(Term::String(name)) |
+                            Some(Term::RawString(name)) => {
                                 // Don't visit_expression the kwarg key.
                                 argument_value = rhs;
                             },
@@ -1938,10 +2803,25 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
                         }
                         res
                     },
+                    // An untyped list's entries could be anything.
+                    ref static_ty if static_ty.is_list() => Analysis {
+                        fix_hint: lhs.fix_hint.clone(),
+                        .. Analysis::empty()
+                    },
                     _ => lhs.clone()  // carry through fix_hint
                 }
             },
+            Follow::Field(_, name) if lhs.typepath().is_some() => {
+                self.visit_typepath_field(location, lhs.typepath().unwrap(), name)
+            },
             Follow::Field(kind, name) => {
+                if self.assigned_field == Some(location) {
+                    if let Some(ty) = lhs.static_ty.basic_type() {
+                        self.record_field_write(ty, name);
+                    } else {
+                        self.env.assigned_untyped_fields.insert(name.to_string());
+                    }
+                }
                 if let Some(ty) = lhs.static_ty.basic_type() {
                     if let Some(decl) = ty.get_var_declaration(name) {
                         if ty != self.ty && decl.var_type.flags.is_private() {
@@ -1973,6 +2853,16 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
                     Analysis::empty()
                 }
             },
+            Follow::Call(_, name, arguments) if lhs.typepath().is_some() => {
+                let ty = lhs.typepath().unwrap();
+                error(location, format!("{}() called on the typepath {}, not an instance of it", name, ty))
+                    .with_errortype("typepath_instance_use")
+                    .register(self.context);
+                for arg in arguments.iter() {
+                    self.visit_expression(location, arg, None, local_vars);
+                }
+                Analysis::empty()
+            },
             Follow::Call(kind, name, arguments) => {
                 if let Some(ty) = lhs.static_ty.basic_type() {
                     self.check_type_sleepers(ty, location, name);
@@ -2001,6 +2891,9 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
                         Analysis::empty()
                     }
                 } else {
+                    if let Some(supplied_args) = self.env.supplied_args.as_mut() {
+                        supplied_args.record_unresolved(name);
+                    }
                     error(location, format!("proc call requires static type: {:?}", name))
                         .set_severity(Severity::Warning)
                         .with_errortype("proc_call_static_type")
@@ -2012,6 +2905,46 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
         }
     }
 
+    /// Report a typepath passed to a parameter declared as an instance of
+    /// some type.
+    fn check_typepath_argument(&mut self, location: Location, proc: ProcRef<'o>, param: &Parameter, path: TypeRef<'o>) {
+        let expected = match static_type(self.objtree, location, &param.var_type.type_path) {
+            Ok(StaticType::Type(ty)) => ty,
+            Ok(StaticType::List { list, .. }) => list,
+            _ => return,
+        };
+        error(location, format!("passing the typepath {} to parameter {} of {}, which expects an instance of {}", path, param.name, proc, expected))
+            .with_errortype("typepath_instance_use")
+            .with_note(param.location, "parameter declared here")
+            .register(self.context);
+    }
+
+    /// A field of a typepath, which reads the type's default for it and can't
+    /// be written to.
+    fn visit_typepath_field(&mut self, location: Location, ty: TypeRef<'o>, name: &str) -> Analysis<'o> {
+        let decl = match ty.get_var_declaration(name) {
+            Some(decl) => decl,
+            None => {
+                error(location, format!("undefined field: {:?} on {}", name, ty))
+                    .register(self.context);
+                return Analysis::empty();
+            }
+        };
+        if self.assigned_field == Some(location) {
+            error(location, format!("field {:?} assigned on the typepath {}, not an instance of it", name, ty))
+                .with_errortype("typepath_instance_use")
+                .register(self.context);
+            return Analysis::empty();
+        }
+        let static_ty = self.static_type(location, &decl.var_type.type_path).static_ty;
+        let mut analysis = match ty.get_value(name).and_then(|var| var.constant.clone()) {
+            Some(value) => Analysis::from_value(self.objtree, value, None),
+            None => Analysis::empty(),
+        };
+        analysis.static_ty = static_ty;
+        analysis
+    }
+
     // checks operatorX overloads on types
     fn check_operator_overload(&mut self, rhs: Analysis<'o>, location: Location, operator: &str, local_vars: &mut HashMap<String, LocalVar<'o>, RandomState>) -> Analysis<'o> {
         if let Some(impurity) = rhs.is_impure {
@@ -2027,7 +2960,11 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
             StaticType::Type(typeref) => {
                 // Its been overloaded, assume they really know they want to do this
                 if let Some(proc) = typeref.get_proc(operator) {
-                    return self.visit_call(location, typeref, proc, &[], true, local_vars)
+                    // Whether the result of an operator is used isn't known.
+                    let discarded = self.discarded_call.replace(location);
+                    let analysis = self.visit_call(location, typeref, proc, &[], true, local_vars);
+                    self.discarded_call = discarded;
+                    return analysis
                 }
                 typeerror = typeref.get().pretty_path();
             },
@@ -2041,6 +2978,13 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
     }
 
     fn visit_unary(&mut self, rhs: Analysis<'o>, op: &UnaryOp, location: Location, local_vars: &mut HashMap<String, LocalVar<'o>, RandomState>) -> Analysis<'o> {
+        if let Some(kind) = operand_kinds::kind_of(&rhs) {
+            if operand_kinds::is_unary_error(*op, kind) {
+                error(location, format!("`{}` on {} is always a runtime error", op.name(), kind.name()))
+                    .with_errortype("operand_type_error")
+                    .register(self.context);
+            }
+        }
         match op {
             // !x just evaluates the "truthiness" of x and negates it, returning 1 or 0
             UnaryOp::Not => Analysis::from(assumption_set![Assumption::IsNum(true)]),
@@ -2073,7 +3017,43 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
         }
     }
 
-    fn visit_binary(&mut self, lhs: Analysis<'o>, rhs: Analysis<'o>, op: BinaryOp) -> Analysis<'o> {
+    /// Visit a chain of `+` operands, as in `a + b + c`, left to right, and
+    /// report the first join of text and a number.
+    fn visit_add_chain(&mut self, location: Location, expression: &'o Expression, local_vars: &mut HashMap<String, LocalVar<'o>, RandomState>) -> Analysis<'o> {
+        let mut operands = Vec::new();
+        let mut first = expression;
+        while let Expression::BinaryOp { op: BinaryOp::Add, lhs, rhs } = first {
+            operands.push(&**rhs);
+            first = lhs;
+        }
+        operands.push(first);
+        operands.reverse();
+
+        let mut running = self.visit_expression(location, operands[0], None, local_vars);
+        let mut reported = false;
+        for &operand in &operands[1..] {
+            let next = self.visit_expression(location, operand, None, local_vars);
+            if !reported {
+                let kinds = (value_kind(&running), value_kind(&next));
+                if let (Some(left), Some(right)) = kinds {
+                    if left != right {
+                        let mut error = error(location, format!("adding {} to {} is a runtime error: `{}`", right, left, dm::printer::FormatExpr(operand)))
+                            .with_errortype("text_number_concat");
+                        if let Some(interpolated) = interpolate(&operands) {
+                            error = error.with_note(location, format!("use an embedded expression instead: {}", interpolated));
+                        }
+                        error.register(self.context);
+                        reported = true;
+                    }
+                }
+            }
+            running = self.visit_binary(location, running, next, BinaryOp::Add);
+        }
+        running
+    }
+
+    fn visit_binary(&mut self, location: Location, lhs: Analysis<'o>, rhs: Analysis<'o>, op: BinaryOp) -> Analysis<'o> {
+        self.check_operand_kinds(location, &lhs, &rhs, op);
         //println!("visit_binary: don't know anything about {}", op);
         if lhs.static_ty.is_list() {
             // If the LHS of these operators is a list, so is the result.
@@ -2086,9 +3066,41 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
                 _ => {}
             }
         }
+        if op == BinaryOp::Add {
+            // Text joins with text and numbers add to numbers.
+            for kind in [Assumption::IsText(true), Assumption::IsNum(true)] {
+                if lhs.aset.set.contains(&kind) && rhs.aset.set.contains(&kind) {
+                    return assumption_set![kind].into();
+                }
+            }
+        }
+        if matches!(op, BinaryOp::LShift | BinaryOp::RShift) {
+            // Bit shifts of numbers are numbers.
+            let number = Assumption::IsNum(true);
+            if lhs.aset.set.contains(&number) && rhs.aset.set.contains(&number) {
+                return assumption_set![number].into();
+            }
+        }
         Analysis::empty()
     }
 
+    /// Report a binary operator which is always a runtime error on the kinds of
+    /// its operands, unless the left side overloads it.
+    fn check_operand_kinds(&mut self, location: Location, lhs: &Analysis<'o>, rhs: &Analysis<'o>, op: BinaryOp) {
+        if let StaticType::Type(ty) = lhs.static_ty {
+            if ty.get_proc(&format!("operator{}", op)).is_some() {
+                return;
+            }
+        }
+        if let (Some(left), Some(right)) = (operand_kinds::kind_of(lhs), operand_kinds::kind_of(rhs)) {
+            if operand_kinds::is_binary_error(op, left, right) {
+                error(location, format!("`{}` on {} and {} is always a runtime error", op, left.name(), right.name()))
+                    .with_errortype("operand_type_error")
+                    .register(self.context);
+            }
+        }
+    }
+
     fn check_filter_flag(&mut self, expr: &'o Expression, can_be_zero: bool, location: Location, typevalue: &str, valid_flags: &[&str], flagfieldname: &str, exclusive: bool) {
         match expr {
             Expression::BinaryOp{ op: BinaryOp::BitOr, lhs, rhs } => {
@@ -2134,6 +3146,17 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
 
     fn visit_call(&mut self, location: Location, src: TypeRef<'o>, proc: ProcRef<'o>, args: &'o [Expression], is_exact: bool, local_vars: &mut HashMap<String, LocalVar<'o>, RandomState>) -> Analysis<'o> {
         self.env.call_tree.entry(self.proc_ref).or_default().push((proc, location, self.inside_newcontext != 0));
+        if let Some(call_sites) = self.env.call_sites.as_mut() {
+            call_sites.push(lens::CallSite { location, src, proc, is_exact });
+        }
+        if self.discarded_call != Some(location) {
+            self.env.used_results.push((proc, location, is_exact));
+        }
+        if let Some(probe) = self.signature.as_mut() {
+            probe.record(location, proc, args);
+        }
+        colors::check_color_args(self.context, location, proc, args);
+        random_args::check_random_args(self.context, location, proc, args);
         if let Some((privateproc, true, decllocation)) = self.env.private.get_self_or_parent(proc) {
             if self.ty != privateproc.ty() {
                 error(location, format!("{} attempting to call private proc {}, types do not match", self.proc_ref, privateproc))
@@ -2151,6 +3174,10 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
         let mut param_idx_map = HashMap::with_hasher(RandomState::default());
         let mut param_idx = 0;
         let mut arglist_used = false;
+        let mut arguments = Vec::with_capacity(args.len());
+        let mut any_arglist = false;
+        let mut constant_args = Some(Vec::with_capacity(args.len()));
+        let mut given_args = Vec::with_capacity(args.len());
 
         for arg in args {
             let mut argument_value = arg;
@@ -2159,7 +3186,8 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
                 Expression::AssignOp { op: AssignOp::Assign, lhs, rhs } => {
                     match lhs.as_term() {
                         Some(Term::Ident(name)) |
-                        Some(Term::String(name)) => {
+                        Some(Term::String(name)) |
+                        Some(Term::RawString(name)) => {
                             // Don't visit_expression the kwarg key.
                             any_kwargs_yet = true;
                             this_kwarg = Some(name);
@@ -2203,6 +3231,7 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
                 },
                 expr => {
                     if let Some(Term::Call(callname, _)) = expr.as_term() {
+                        any_arglist |= callname.as_str() == "arglist";
                         // only interested in the first expression being arglist
                         if callname.as_str() == "arglist" && param_name_map.len() == 0 && param_idx == 0 {
                             arglist_used = true;
@@ -2218,6 +3247,24 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
             }
 
             let analysis = self.visit_expression(location, argument_value, None, local_vars);
+            if let Some(path) = analysis.typepath() {
+                let param = match this_kwarg {
+                    Some(kw) => proc.parameters.iter().find(|param| param.name == *kw),
+                    None => proc.parameters.get(param_idx),
+                };
+                if let Some(param) = param {
+                    self.check_typepath_argument(location, proc, param, path);
+                }
+            }
+            arguments.push(unsupplied_params::Argument {
+                name: this_kwarg.map(|kw| kw.as_str()),
+                supplied: !matches!(analysis.value, Some(Constant::Null(_))) && !analysis.aset.set.contains(&Assumption::IsNull(true)),
+            });
+            given_args.push((this_kwarg.map(|kw| kw.as_str()), analysis.value.clone()));
+            match (this_kwarg, analysis.value.as_ref(), constant_args.as_mut()) {
+                (None, Some(value), Some(values)) => values.push(value.clone()),
+                _ => constant_args = None,
+            }
             if let Some(kw) = this_kwarg {
                 param_name_map.insert(kw.as_str(), analysis);
                 param_expr_map.insert(kw.as_str(), argument_value);
@@ -2227,6 +3274,13 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
             }
         }
 
+        // `..()` and `.()` without arguments pass on the ones this proc was
+        // given, which are already counted.
+        let forwards = is_exact && args.is_empty() && proc.name() == self.proc_ref.name();
+        if let (Some(supplied_args), false) = (self.env.supplied_args.as_mut(), forwards) {
+            supplied_args.record_call(location, proc, &arguments, any_arglist);
+        }
+
         // filter call checking
         // TODO: some filters have limits for their numerical params
         //  eg "rays" type "threshold" param defaults to 0.5, can be 0 to 1
@@ -2262,18 +3316,51 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
             }
         }
 
+        // icon() and matrix() calls, with whichever arguments are constant
+        let constructor = match proc.name() {
+            "icon" => Some(ConstFn::Icon),
+            "matrix" => Some(ConstFn::Matrix),
+            _ => None,
+        };
+        if let (Some(constructor), true, false) = (constructor, proc.ty().is_root(), any_arglist) {
+            let given: Vec<_> = given_args.iter().map(|(keyword, value)| (*keyword, value.as_ref())).collect();
+            if let Err(message) = constructor.check_arguments(&given) {
+                error(location, message)
+                    .with_errortype("constructor_arguments")
+                    .register(self.context);
+            }
+        }
+
+        if proc.ty().is_root() && proc.is_builtin() && !any_arglist && matches!(proc.name(), "istype" | "ispath") {
+            self.check_type_test(location, proc.name(), args, &param_idx_map, local_vars);
+        }
+
         if proc.ty().is_root() && proc.is_builtin() {
-            Analysis::from(self.global_builtin_returntype(proc))
+            match constant_args.and_then(|values| builtin_eval::evaluate(proc.name(), &values)) {
+                Some(value) => Analysis::from_value(self.objtree, value, None),
+                None => Analysis::from(self.global_builtin_returntype(proc)),
+            }
         } else if let Some(return_type) = self.env.return_type.get(&proc) {
+            // Omitted arguments take their parameter's default, if it's constant.
+            if !any_arglist {
+                for (index, param) in proc.get().parameters.iter().enumerate() {
+                    if param_idx_map.contains_key(&index) || param_name_map.contains_key(param.name.as_str()) {
+                        continue;
+                    }
+                    if let Some(value) = param_defaults::folded_default(param) {
+                        param_idx_map.insert(index, Analysis::from_value(self.objtree, value, None));
+                    }
+                }
+            }
             let ec = type_expr::TypeExprContext {
                 objtree: self.objtree,
                 param_name_map,
                 param_idx_map,
             };
-            match return_type.evaluate(location, &ec) {
-                Ok(st) => {
-                    let hint = format!("return type evaluated to {:?}", st);
-                    Analysis::from(st).with_fix_hint(location, hint)
+            match return_type.evaluate_analysis(location, &ec) {
+                Ok(analysis) => {
+                    let hint = format!("return type evaluated to {:?}", analysis.static_ty);
+                    analysis.with_fix_hint(location, hint)
                 },
                 Err(err) => {
                     err.with_component(dm::Component::DreamChecker)
@@ -2287,13 +3374,73 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
         }
     }
 
+    /// Check the type given to `istype()` or `ispath()`. Text and numbers
+    /// never match, and neither do instances given to `ispath()`, although
+    /// `istype()` uses the type of an instance. The one-argument form of
+    /// `istype()` tests against the declared type of its var.
+    fn check_type_test(&mut self, location: Location, name: &str, args: &'o [Expression], analyses: &HashMap<usize, Analysis<'o>, RandomState>, local_vars: &HashMap<String, LocalVar<'o>, RandomState>) {
+        if name == "istype" && args.len() == 1 {
+            if self.declared_type(&args[0], local_vars).is_none() {
+                error(location, "istype() with one argument tests against the declared type of a var, and this has none")
+                    .set_severity(Severity::Warning)
+                    .with_errortype("type_test_argument")
+                    .with_note(location, "give the type to test against as the second argument")
+                    .register(self.context);
+            }
+            return;
+        }
+        let given = match analyses.get(&1) {
+            Some(given) => given,
+            None => return,
+        };
+        let kind = match operand_kinds::kind_of(given) {
+            Some(kind @ operand_kinds::Kind::Text) | Some(kind @ operand_kinds::Kind::Number) => kind.name(),
+            _ if name == "ispath" && given.aset.set.iter().any(|each| matches!(each, Assumption::IsType(true, _))) => "an instance",
+            _ => return,
+        };
+        error(location, format!("{}() second argument is {}, not a typepath, so it never matches", name, kind))
+            .set_severity(Severity::Warning)
+            .with_errortype("type_test_argument")
+            .register(self.context);
+    }
+
+    /// The declared type of a var, or of a chain of fields on one, regardless
+    /// of what's known about its current value.
+    fn declared_type(&mut self, expr: &Expression, local_vars: &HashMap<String, LocalVar<'o>, RandomState>) -> Option<TypeRef<'o>> {
+        let (term, follow) = match expr {
+            Expression::Base { term, follow } => (term, follow),
+            _ => return None,
+        };
+        let mut ty = match term.elem {
+            Term::Ident(ref name) => match local_vars.get(name) {
+                Some(var) => var.analysis.static_ty.basic_type()?,
+                None => {
+                    let decl = self.ty.get_var_declaration(name)?;
+                    self.env.static_type(decl.location, &decl.var_type.type_path).basic_type()?
+                }
+            },
+            Term::Expr(ref inner) if follow.is_empty() => return self.declared_type(inner, local_vars),
+            _ => return None,
+        };
+        for each in follow.iter() {
+            let name = match each.elem {
+                Follow::Field(_, ref name) => name,
+                _ => return None,
+            };
+            let decl = ty.get_var_declaration(name)?;
+            ty = self.env.static_type(decl.location, &decl.var_type.type_path).basic_type()?;
+        }
+        Some(ty)
+    }
+
     fn visit_arguments(&mut self, location: Location, args: &'o [Expression], local_vars: &mut HashMap<String, LocalVar<'o>, RandomState>) {
         for arg in args {
             let mut argument_value = arg;
             if let Expression::AssignOp { op: AssignOp::Assign, lhs, rhs } = arg {
                 match lhs.as_term() {
                     Some(Term::Ident(_name)) |
-                    Some(Term::String(_name)) => {
+                    Some(Term::String(_name)) |
+                    Some(Term::RawString(_name)) => {
                         // Don't visit_expression the kwarg key.
                         argument_value = rhs;
                     }
@@ -2305,6 +3452,32 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
         }
     }
 
+    /// Visit the entries of a `list()` literal and check their keys.
+    fn visit_list(&mut self, location: Location, args: &'o [Expression], local_vars: &mut HashMap<String, LocalVar<'o>, RandomState>) {
+        let mut keys = Vec::with_capacity(args.len());
+        for arg in args {
+            match arg {
+                Expression::AssignOp { op: AssignOp::Assign, lhs, rhs } => {
+                    let key = match assoc_lists::named_key(lhs) {
+                        Some(key) => key,
+                        None => {
+                            let key = self.visit_expression(location, lhs, None, local_vars);
+                            let is_null = key.aset.set.contains(&Assumption::IsNull(true));
+                            assoc_lists::ListKey::from_value(key.value.as_ref(), is_null)
+                        }
+                    };
+                    keys.push(key);
+                    self.visit_expression(location, rhs, None, local_vars);
+                }
+                _ => {
+                    keys.push(assoc_lists::ListKey::Plain);
+                    self.visit_expression(location, arg, None, local_vars);
+                }
+            }
+        }
+        assoc_lists::check_list_keys(self.context, location, &keys);
+    }
+
     fn static_type(&mut self, location: Location, of: &[String]) -> Analysis<'o> {
         Analysis::from(self.env.static_type(location, of))
     }
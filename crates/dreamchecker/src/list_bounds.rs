@@ -0,0 +1,203 @@
+//! Detection of constant list indexes which are always out of bounds.
+//!
+//! The length of a local list is known if it is initialized to a `list(...)`
+//! literal or a `new /list(N)` of constant size, as `var/L[N]` is, and the
+//! local is only ever indexed, has fields read, or is iterated over. Anything
+//! else, including reassigning it, calling a method on it, assigning a key
+//! which may not be there yet, or passing it anywhere, forgets the length.
+//! The lengths of the lists nested in `new /list(N, M)` are known likewise,
+//! until one of them is used other than by indexing into it.
+
+use std::collections::{HashMap, HashSet};
+
+use ahash::RandomState;
+
+use dm::{Context, DMError, Location, Severity};
+use dm::ast::*;
+use dm::constants::Constant;
+use dm::objtree::ProcRef;
+
+use crate::cfg::{declared_once, local_declarations, ControlFlowGraph, LocalKind, Step};
+use crate::visit::visit;
+
+pub fn check_list_bounds(context: &Context, proc: ProcRef, graph: &ControlFlowGraph) {
+    let lengths = known_lengths(proc, graph);
+
+    let mut found = Vec::new();
+    for step in graph.blocks.iter().flat_map(|block| block.steps.iter()) {
+        for expr in step.expressions() {
+            visit(expr, &mut |expr| {
+                if let Expression::Base { term, follow } = expr {
+                    let lens = match &term.elem {
+                        Term::Ident(name) => lengths.get(name).cloned(),
+                        Term::List(args) => Some(vec![args.len()]),
+                        _ => None,
+                    };
+                    let mut indexing = true;
+                    for (i, each) in follow.iter().enumerate() {
+                        // Only leading indexes are into lists of known length.
+                        indexing &= matches!(each.elem, Follow::Index(..));
+                        if let Follow::Index(_, index) = &each.elem {
+                            match constant_int(index) {
+                                Some(value) if value < 1 => {
+                                    found.push((each.location, format!("list index {} is out of bounds", value)));
+                                }
+                                Some(value) => match lens.as_ref().and_then(|lens| lens.get(i)) {
+                                    Some(&len) if indexing && value as usize > len => {
+                                        found.push((each.location, format!("list index {} is out of bounds for a list of length {}", value, len)));
+                                    }
+                                    _ => {}
+                                },
+                                None => {}
+                            }
+                        }
+                    }
+                }
+            });
+        }
+    }
+
+    found.sort();
+    for (location, message) in found {
+        DMError::new(location, message)
+            .with_component(dm::Component::DreamChecker)
+            .set_severity(Severity::Error)
+            .with_errortype("list_index_out_of_bounds")
+            .register(context);
+    }
+}
+
+/// The lengths of the locals which always hold a list of known length, and of
+/// the lists nested in them, outermost first.
+fn known_lengths(proc: ProcRef, graph: &ControlFlowGraph) -> HashMap<String, Vec<usize>, RandomState> {
+    let declarations = local_declarations(proc, graph);
+    let mut lengths: HashMap<String, Vec<usize>, RandomState> = Default::default();
+    for declaration in declared_once(&declarations) {
+        if let LocalKind::Var(_, var) = declaration.kind {
+            if var.var_type.flags.is_static() {
+                continue;
+            }
+            if let Some(len) = var.value.as_ref().and_then(list_length) {
+                lengths.insert(var.name.clone(), len);
+            }
+        }
+    }
+    if lengths.is_empty() {
+        return lengths;
+    }
+
+    // Forget the length of any list used in a way which may change it.
+    let mut forgotten: HashSet<String, RandomState> = Default::default();
+    // How deep the lists nested in each local stay as they were.
+    let mut depths: HashMap<String, usize, RandomState> = Default::default();
+    for step in graph.blocks.iter().flat_map(|block| block.steps.iter()) {
+        for expr in step.expressions() {
+            // Iterating over the list doesn't change it.
+            let mut allowed: HashSet<usize, RandomState> = Default::default();
+            if let Step::Header(_, Statement::ForList(_)) = step {
+                allowed.insert(expr as *const Expression as usize);
+            }
+            visit(expr, &mut |expr| {
+                match expr {
+                    Expression::BinaryOp { op: BinaryOp::In, rhs, .. } => {
+                        allowed.insert(&**rhs as *const Expression as usize);
+                    }
+                    Expression::AssignOp { lhs, .. } => {
+                        if let Expression::Base { term, follow } = &**lhs {
+                            if let Term::Ident(name) = &term.elem {
+                                // Assigning `L[1] = x` may replace a nested list.
+                                limit_depth(&mut depths, name, follow);
+                                let keeps_length = match follow.first().map(|each| &each.elem) {
+                                    Some(Follow::Index(_, index)) => constant_int(index).is_some(),
+                                    Some(Follow::Field(_, field)) => field.as_str() != "len",
+                                    _ => false,
+                                };
+                                if !keeps_length {
+                                    forgotten.insert(name.clone());
+                                }
+                            }
+                        }
+                    }
+                    Expression::Base { term, follow } => {
+                        if let Term::Ident(name) = &term.elem {
+                            // `L[1]` may be a nested list, which may be changed.
+                            limit_depth(&mut depths, name, follow);
+                            let keeps_length = match follow.first().map(|each| &each.elem) {
+                                None => allowed.contains(&(expr as *const Expression as usize)),
+                                Some(Follow::Index(..)) => true,
+                                Some(Follow::Field(_, field)) => {
+                                    field.as_str() != "len" || !follow[1..].iter().any(|each| matches!(each.elem,
+                                        Follow::Unary(UnaryOp::PreIncr) |
+                                        Follow::Unary(UnaryOp::PostIncr) |
+                                        Follow::Unary(UnaryOp::PreDecr) |
+                                        Follow::Unary(UnaryOp::PostDecr)))
+                                }
+                                Some(_) => false,
+                            };
+                            if !keeps_length {
+                                forgotten.insert(name.clone());
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            });
+        }
+    }
+    lengths.retain(|name, _| !forgotten.contains(name));
+    for (name, lens) in lengths.iter_mut() {
+        if let Some(&depth) = depths.get(name) {
+            lens.truncate(depth);
+        }
+    }
+    lengths
+}
+
+/// Limit how deep a local's nested lists are known by a use which reaches
+/// into it by `follow`.
+fn limit_depth(depths: &mut HashMap<String, usize, RandomState>, name: &str, follow: &[Spanned<Follow>]) {
+    let indexes = follow.iter().take_while(|each| matches!(each.elem, Follow::Index(..))).count();
+    if indexes > 0 {
+        let depth = depths.entry(name.to_owned()).or_insert(usize::MAX);
+        *depth = (*depth).min(indexes);
+    }
+}
+
+/// The lengths of the list an initializer creates and the lists nested in
+/// it, if they're known.
+fn list_length(expr: &Expression) -> Option<Vec<usize>> {
+    match expr.as_term()? {
+        Term::List(args) => Some(vec![args.len()]),
+        Term::NewPrefab { prefab, args } if prefab.vars.is_empty() && is_list_path(&prefab.path) => {
+            match args.as_deref() {
+                None | Some([]) => Some(vec![0]),
+                Some(sizes) => sizes.iter().map(constant_size).collect(),
+            }
+        }
+        _ => None,
+    }
+}
+
+/// The value of a list size, if it folds to a whole number of 0 or more.
+fn constant_size(expr: &Expression) -> Option<usize> {
+    match expr.clone().simple_evaluate(Location::default()).ok()? {
+        Constant::Float(size) if size >= 0. && size.fract() == 0. => Some(size as usize),
+        _ => None,
+    }
+}
+
+fn is_list_path(path: &TypePath) -> bool {
+    matches!(&path[..], [(PathOp::Slash, name)] if name == "list")
+}
+
+/// The value of an integer literal, possibly negated.
+fn constant_int(expr: &Expression) -> Option<i32> {
+    match expr {
+        Expression::Base { term, follow } => match (&term.elem, &follow[..]) {
+            (Term::Int(value), []) => Some(*value),
+            (Term::Int(value), [neg]) if neg.elem == Follow::Unary(UnaryOp::Neg) => Some(-*value),
+            _ => None,
+        },
+        _ => None,
+    }
+}
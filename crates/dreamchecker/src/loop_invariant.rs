@@ -0,0 +1,392 @@
+//! Detection of conditions which can't change between iterations of a loop.
+//!
+//! A condition is invariant in a loop if nothing it reads is assigned,
+//! incremented, passed to a proc, or has a proc called on it within the loop.
+//! Conditions which call procs are skipped, as are conditions which read
+//! through fields, indexes, or non-local vars while the loop calls anything,
+//! since the callee may change what they read. Non-local vars are fields of
+//! `src`, and two paths may name the same object, so a condition which reads
+//! through fields, indexes, or non-local vars is also skipped when the loop
+//! writes to any of those.
+
+use std::collections::HashSet;
+
+use ahash::RandomState;
+
+use dm::{Context, DMError, Location, Severity};
+use dm::ast::*;
+use dm::objtree::ProcRef;
+
+use crate::cfg::{local_declarations, ControlFlowGraph, Step};
+use crate::visit::{self, visit};
+
+pub fn check_loop_invariants(context: &Context, proc: ProcRef, code: &[Spanned<Statement>], graph: &ControlFlowGraph) {
+    let reachable = graph.reachable(true);
+    let mut checker = Checker {
+        context,
+        locals: local_declarations(proc, graph).iter().map(|each| each.name.to_owned()).collect(),
+        reachable: HashSet::default(),
+    };
+    for (id, block) in graph.blocks.iter().enumerate() {
+        if !reachable[id] {
+            continue;
+        }
+        for step in block.steps.iter() {
+            if let Step::Expression(_, expr) = *step {
+                checker.reachable.insert(expr as *const Expression as usize);
+            }
+        }
+    }
+    checker.block(code, None);
+}
+
+/// What a loop may change.
+#[derive(Default)]
+struct Effects {
+    modified: HashSet<String, RandomState>,
+    /// Whether anything is assigned or incremented through a field or index.
+    writes_through: bool,
+    calls: bool,
+}
+
+struct Checker<'c> {
+    context: &'c Context,
+    locals: HashSet<String, RandomState>,
+    /// The addresses of conditions which may be evaluated.
+    reachable: HashSet<usize, RandomState>,
+}
+
+impl Checker<'_> {
+    fn block(&self, code: &[Spanned<Statement>], innermost: Option<(Location, &Effects)>) {
+        for statement in code.iter() {
+            self.statement(statement, innermost);
+        }
+    }
+
+    fn statement(&self, statement: &Spanned<Statement>, innermost: Option<(Location, &Effects)>) {
+        let location = statement.location;
+        match &statement.elem {
+            Statement::If { arms, else_arm } => {
+                for (condition, block) in arms.iter() {
+                    if let Some((loop_location, effects)) = innermost {
+                        self.check(condition.location, &condition.elem, effects, || {
+                            DMError::new(condition.location, "if condition does not change between iterations of the loop")
+                                .with_note(loop_location, "loop is here")
+                        });
+                    }
+                    self.block(block, innermost);
+                }
+                if let Some(block) = else_arm {
+                    self.block(block, innermost);
+                }
+            }
+            Statement::While { condition, block } => {
+                let effects = loop_effects(&[condition], &[], block);
+                self.check_loop_condition(location, condition, &effects);
+                self.block(block, Some((location, &effects)));
+            }
+            Statement::DoWhile { block, condition } => {
+                let effects = loop_effects(&[&condition.elem], &[], block);
+                self.check_loop_condition(condition.location, &condition.elem, &effects);
+                self.block(block, Some((location, &effects)));
+            }
+            Statement::ForLoop { test, inc, block, .. } => {
+                let conditions: Vec<&Expression> = test.iter().map(|test| &**test).collect();
+                let inc: Vec<&Statement> = inc.iter().map(|inc| &**inc).collect();
+                let effects = loop_effects(&conditions, &inc, block);
+                if let Some(test) = test {
+                    self.check_loop_condition(location, test, &effects);
+                }
+                self.block(block, Some((location, &effects)));
+            }
+            Statement::ForList(for_list) => {
+                let mut effects = loop_effects(&[], &[], &for_list.block);
+                effects.modified.insert(for_list.name.to_string());
+                self.block(&for_list.block, Some((location, &effects)));
+            }
+            Statement::ForRange(for_range) => {
+                let mut effects = loop_effects(&[], &[], &for_range.block);
+                effects.modified.insert(for_range.name.to_string());
+                self.block(&for_range.block, Some((location, &effects)));
+            }
+            Statement::ForInfinite { block } => {
+                let effects = loop_effects(&[], &[], block);
+                self.block(block, Some((location, &effects)));
+            }
+            // Spawned code runs outside the loop.
+            Statement::Spawn { block, .. } => self.block(block, None),
+            Statement::Switch { cases, default, .. } => {
+                for (_, block) in cases.iter() {
+                    self.block(block, innermost);
+                }
+                if let Some(block) = default {
+                    self.block(block, innermost);
+                }
+            }
+            Statement::TryCatch { try_block, catch_block, .. } => {
+                self.block(try_block, innermost);
+                self.block(catch_block, innermost);
+            }
+            Statement::Label { block, .. } => self.block(block, innermost),
+            _ => {}
+        }
+    }
+
+    fn check_loop_condition(&self, location: Location, condition: &Expression, effects: &Effects) {
+        self.check(location, condition, effects, || {
+            DMError::new(location, "loop condition does not change between iterations")
+        });
+    }
+
+    fn check(&self, location: Location, condition: &Expression, effects: &Effects, make_error: impl FnOnce() -> DMError) {
+        if !self.reachable.contains(&(condition as *const Expression as usize)) {
+            return;
+        }
+        let mut names = Vec::new();
+        let mut reads_state = false;
+        let mut calls = false;
+        visit(condition, &mut |expr| match expr {
+            Expression::Base { term, follow } => {
+                if let Term::Ident(name) = &term.elem {
+                    if !self.locals.contains(name) {
+                        reads_state = true;
+                    }
+                    if !names.contains(name) {
+                        names.push(name.clone());
+                    }
+                }
+                if follow.iter().any(|each| matches!(each.elem, Follow::Field(..) | Follow::Index(..))) {
+                    reads_state = true;
+                }
+                calls |= visit::calls(&term.elem, follow);
+            }
+            Expression::AssignOp { .. } => calls = true,
+            _ => {}
+        });
+        if names.is_empty() || calls || (reads_state && (effects.calls || self.writes_state(effects))) {
+            return;
+        }
+        if names.iter().any(|name| effects.modified.contains(name)) {
+            return;
+        }
+        let names: Vec<_> = names.iter().map(|name| name.as_str()).collect();
+        let verb = if names.len() == 1 { "is" } else { "are" };
+        make_error()
+            .with_note(location, format!("{} {} not modified by the loop", names.join(", "), verb))
+            .with_component(dm::Component::DreamChecker)
+            .set_severity(Severity::Info)
+            .with_errortype("loop_invariant_condition")
+            .register(self.context);
+    }
+}
+
+impl Checker<'_> {
+    /// Whether a loop writes to anything other than a local, which may be
+    /// what a condition reads through a field, index, or non-local var.
+    fn writes_state(&self, effects: &Effects) -> bool {
+        // A bare non-local var is a field of `src`.
+        effects.writes_through || effects.modified.iter().any(|name| !self.locals.contains(name))
+    }
+}
+
+/// The names a loop with the given conditions, increment, and body may
+/// change, so that what was known about them before the loop may not hold on
+/// later iterations.
+pub(crate) fn modified_in_loop(conditions: &[&Expression], inc: &[&Statement], block: &[Spanned<Statement>]) -> HashSet<String, RandomState> {
+    loop_effects(conditions, inc, block).modified
+}
+
+/// Find everything a loop with the given conditions, increment, and body may
+/// change.
+fn loop_effects(conditions: &[&Expression], inc: &[&Statement], block: &[Spanned<Statement>]) -> Effects {
+    let mut effects = Effects::default();
+    for condition in conditions {
+        expression_effects(condition, &mut effects);
+    }
+    for statement in inc {
+        statement_effects(statement, &mut effects);
+    }
+    block_effects(block, &mut effects);
+    effects
+}
+
+fn block_effects(block: &[Spanned<Statement>], effects: &mut Effects) {
+    for statement in block.iter() {
+        statement_effects(&statement.elem, effects);
+    }
+}
+
+fn statement_effects(statement: &Statement, effects: &mut Effects) {
+    match statement {
+        Statement::Expr(expr) |
+        Statement::Return(Some(expr)) |
+        Statement::Throw(expr) |
+        Statement::Crash(Some(expr)) => expression_effects(expr, effects),
+        Statement::Del(expr) => {
+            effects.calls = true;
+            if let Some(name) = root(expr) {
+                effects.modified.insert(name.to_owned());
+            }
+            expression_effects(expr, effects);
+        }
+        Statement::Var(var) => var_effects(var, effects),
+        Statement::Vars(vars) => {
+            for var in vars.iter() {
+                var_effects(var, effects);
+            }
+        }
+        Statement::While { condition, block } => {
+            expression_effects(condition, effects);
+            block_effects(block, effects);
+        }
+        Statement::DoWhile { block, condition } => {
+            block_effects(block, effects);
+            expression_effects(&condition.elem, effects);
+        }
+        Statement::If { arms, else_arm } => {
+            for (condition, block) in arms.iter() {
+                expression_effects(&condition.elem, effects);
+                block_effects(block, effects);
+            }
+            if let Some(block) = else_arm {
+                block_effects(block, effects);
+            }
+        }
+        Statement::ForInfinite { block } => block_effects(block, effects),
+        Statement::ForLoop { init, test, inc, block } => {
+            for statement in init.iter().chain(inc.iter()) {
+                statement_effects(statement, effects);
+            }
+            if let Some(test) = test {
+                expression_effects(test, effects);
+            }
+            block_effects(block, effects);
+        }
+        Statement::ForList(for_list) => {
+            effects.modified.insert(for_list.name.to_string());
+            if let Some(in_list) = &for_list.in_list {
+                expression_effects(in_list, effects);
+            }
+            block_effects(&for_list.block, effects);
+        }
+        Statement::ForRange(for_range) => {
+            effects.modified.insert(for_range.name.to_string());
+            expression_effects(&for_range.start, effects);
+            expression_effects(&for_range.end, effects);
+            if let Some(step) = &for_range.step {
+                expression_effects(step, effects);
+            }
+            block_effects(&for_range.block, effects);
+        }
+        Statement::Spawn { delay, block } => {
+            // Spawned code may run whenever the loop sleeps.
+            effects.calls = true;
+            if let Some(delay) = delay {
+                expression_effects(delay, effects);
+            }
+            block_effects(block, effects);
+        }
+        Statement::Switch { input, cases, default } => {
+            expression_effects(input, effects);
+            for (_, block) in cases.iter() {
+                block_effects(block, effects);
+            }
+            if let Some(block) = default {
+                block_effects(block, effects);
+            }
+        }
+        Statement::TryCatch { try_block, catch_params, catch_block } => {
+            effects.modified.extend(catch_params.iter().filter_map(|param| param.last().cloned()));
+            block_effects(try_block, effects);
+            block_effects(catch_block, effects);
+        }
+        Statement::Label { block, .. } => block_effects(block, effects),
+        _ => {}
+    }
+}
+
+fn var_effects(var: &VarStatement, effects: &mut Effects) {
+    // Declarations in a loop body run again each iteration.
+    effects.modified.insert(var.name.clone());
+    if let Some(value) = &var.value {
+        expression_effects(value, effects);
+    }
+}
+
+fn expression_effects(expr: &Expression, effects: &mut Effects) {
+    visit(expr, &mut |expr| match expr {
+        Expression::AssignOp { lhs, .. } => {
+            if let Some(name) = root(lhs) {
+                effects.modified.insert(name.to_owned());
+            }
+            effects.writes_through |= is_through(lhs);
+        }
+        Expression::Base { term, follow } => {
+            let increments = follow.iter().any(|each| matches!(each.elem,
+                Follow::Unary(UnaryOp::PreIncr) |
+                Follow::Unary(UnaryOp::PostIncr) |
+                Follow::Unary(UnaryOp::PreDecr) |
+                Follow::Unary(UnaryOp::PostDecr)
+            ));
+            let through = follow.iter().any(|each| matches!(each.elem, Follow::Field(..) | Follow::Index(..)));
+            if increments || follow.iter().any(|each| matches!(each.elem, Follow::Call(..))) {
+                if let Term::Ident(name) = &term.elem {
+                    effects.modified.insert(name.clone());
+                }
+            }
+            effects.writes_through |= increments && through;
+            if visit::calls(&term.elem, follow) {
+                effects.calls = true;
+            }
+            // Anything passed to a proc may be changed by it.
+            for each in follow.iter() {
+                if let Follow::Call(_, _, args) = &each.elem {
+                    effects.modified.extend(args.iter().filter_map(root).map(ToOwned::to_owned));
+                }
+            }
+            if let Some(args) = call_args(&term.elem) {
+                effects.modified.extend(args.iter().filter_map(root).map(ToOwned::to_owned));
+            }
+        }
+        _ => {}
+    });
+}
+
+/// The variable an expression reads through, if any.
+fn root(expr: &Expression) -> Option<&str> {
+    match expr {
+        Expression::Base { term, .. } => match &term.elem {
+            Term::Ident(name) => Some(name),
+            Term::Expr(expr) => root(expr),
+            _ => None,
+        },
+        // The value of `x = y` is `x`.
+        Expression::AssignOp { lhs, .. } => root(lhs),
+        _ => None,
+    }
+}
+
+/// Whether an assigned expression goes through a field or index.
+fn is_through(expr: &Expression) -> bool {
+    match expr {
+        Expression::Base { term, follow } => {
+            follow.iter().any(|each| matches!(each.elem, Follow::Field(..) | Follow::Index(..))) ||
+                matches!(&term.elem, Term::Expr(expr) if is_through(expr))
+        }
+        Expression::AssignOp { lhs, .. } => is_through(lhs),
+        _ => false,
+    }
+}
+
+fn call_args(term: &Term) -> Option<&[Expression]> {
+    match term {
+        Term::Call(_, args) |
+        Term::SelfCall(args) |
+        Term::ParentCall(args) |
+        Term::DynamicCall(_, args) => Some(args),
+        Term::NewImplicit { args } |
+        Term::NewPrefab { args, .. } |
+        Term::NewMiniExpr { args, .. } => args.as_deref(),
+        _ => None,
+    }
+}
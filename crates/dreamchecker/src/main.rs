@@ -15,6 +15,25 @@ fn main() {
     let mut config_file = None;
     let mut json = false;
     let mut parse_only = false;
+    let mut cache_file = None;
+    let mut dump_defines = None;
+    let mut complexity_report = None;
+    let mut type_coverage = false;
+    let mut coverage_threshold = None;
+    let mut hover = None;
+    let mut complete_members = None;
+    let mut complete_at = None;
+    let mut signature = None;
+    let mut symbols = None;
+    let mut semantic_tokens = None;
+    let mut definitions = None;
+    let mut definitions_file = None;
+    let mut references = None;
+    let mut lens = None;
+    let mut reference_options = dreamchecker::references::ReferenceOptions::default();
+    let mut rename = None;
+    let mut rename_options = RenameOptions::default();
+    let mut lsp_diagnostics = false;
 
     let mut args = std::env::args();
     let _ = args.next();  // skip executable name
@@ -37,6 +56,53 @@ fn main() {
             json = true;
         } else if arg == "--parse-only" {
             parse_only = true;
+        } else if arg == "--cache" {
+            cache_file = Some(std::path::PathBuf::from(args.next().expect("must specify a file for --cache")));
+        } else if arg == "--dump-defines" {
+            dump_defines = Some(std::path::PathBuf::from(args.next().expect("must specify a file for --dump-defines")));
+        } else if arg == "--complexity-report" {
+            let count = args.next().expect("must specify a count for --complexity-report");
+            complexity_report = Some(count.parse::<usize>().expect("--complexity-report count must be a number"));
+        } else if arg == "--type-coverage" {
+            type_coverage = true;
+        } else if arg == "--coverage-threshold" {
+            let threshold = args.next().expect("must specify a percentage for --coverage-threshold");
+            coverage_threshold = Some(threshold.parse::<f64>().expect("--coverage-threshold must be a number"));
+        } else if arg == "--hover" {
+            hover = Some(args.next().expect("must specify a file:line:column for --hover"));
+        } else if arg == "--complete-members" {
+            complete_members = Some(args.next().expect("must specify a type path for --complete-members"));
+        } else if arg == "--complete-at" {
+            complete_at = Some(args.next().expect("must specify a file:line:column for --complete-at"));
+        } else if arg == "--signature" {
+            signature = Some(args.next().expect("must specify a file:line:column for --signature"));
+        } else if arg == "--symbols" {
+            symbols = Some(args.next().expect("must specify a file for --symbols"));
+        } else if arg == "--semantic-tokens" {
+            semantic_tokens = Some(args.next().expect("must specify a file for --semantic-tokens"));
+        } else if arg == "--definitions" {
+            definitions = Some(std::path::PathBuf::from(args.next().expect("must specify a file for --definitions")));
+        } else if arg == "--definitions-file" {
+            definitions_file = Some(args.next().expect("must specify a file for --definitions-file"));
+        } else if arg == "--references" {
+            references = Some(args.next().expect("must specify a path for --references"));
+        } else if arg == "--lens" {
+            lens = Some(args.next().expect("must specify a file for --lens"));
+        } else if arg == "--include-overrides" {
+            reference_options.overrides = true;
+        } else if arg == "--include-colon" {
+            reference_options.unresolved = true;
+        } else if arg == "--rename" {
+            let path = args.next().expect("must specify a path for --rename");
+            rename = Some((path, args.next().expect("must specify a new name for --rename")));
+        } else if arg == "--dry-run" {
+            rename_options.dry_run = true;
+        } else if arg == "--allow-unsafe" {
+            rename_options.allow_unsafe = true;
+        } else if arg == "--allow-missing" {
+            rename_options.allow_missing = true;
+        } else if arg == "--lsp-diagnostics" {
+            lsp_diagnostics = true;
         } else {
             eprintln!("unknown argument: {}", arg);
             return;
@@ -49,6 +115,12 @@ fn main() {
             .expect("error detecting .dme")
             .expect("no .dme found"));
 
+    if lsp_diagnostics {
+        let config = config_file.map(std::path::PathBuf::from);
+        serve_diagnostics(dreamchecker::session::Session::new(dme, config));
+        return;
+    }
+
     let mut context = dm::Context::default();
     if let Some(filepath) = config_file {
         context.force_config(filepath.as_ref());
@@ -57,31 +129,721 @@ fn main() {
     }
     context.set_print_severity(Some(dm::Severity::Info));
 
-    println!("============================================================");
-    println!("Parsing {}...\n", dme.display());
-    let pp = dm::preprocessor::Preprocessor::new(&context, dme)
-        .expect("i/o error opening .dme");
-    let indents = dm::indents::IndentProcessor::new(&context, pp);
-    let mut parser = dm::parser::Parser::new(&context, indents);
-    parser.enable_procs();
-    let (fatal_errored, tree) = parser.parse_object_tree_2();
+    // Switches which print JSON keep stdout for it alone.
+    let json_output = symbols.is_some() || semantic_tokens.is_some() || (rename.is_some() && rename_options.dry_run);
+    macro_rules! progress {
+        ($($rest:tt)*) => {
+            if json_output { eprintln!($($rest)*) } else { println!($($rest)*) }
+        }
+    }
+
+    progress!("============================================================");
+    let version = format!(
+        "{} {}",
+        env!("CARGO_PKG_VERSION"),
+        include_str!(concat!(env!("OUT_DIR"), "/build-info.txt")),
+    );
+    let start = std::time::Instant::now();
+    // Macro usage and annotations are only known after actually parsing.
+    let mut annotations = dm::annotation::AnnotationTree::default();
+    let annotate = signature.is_some() || symbols.is_some() || semantic_tokens.is_some();
+    let cached = cache_file.as_ref()
+        .filter(|_| dump_defines.is_none() && !annotate)
+        .and_then(|path| dm::cache::read(&context, &dme, &version, path));
+    let (fatal_errored, tree) = if let Some(tree) = cached {
+        progress!("Loaded {} from cache", dme.display());
+        (false, tree)
+    } else {
+        progress!("Parsing {}...\n", dme.display());
+        let mut pp = dm::preprocessor::Preprocessor::new(&context, dme.clone())
+            .expect("i/o error opening .dme");
+        if dump_defines.is_some() {
+            pp.enable_define_usage();
+        }
+        if semantic_tokens.is_some() {
+            pp.enable_annotations();
+        }
+        let indents = dm::indents::IndentProcessor::new(&context, &mut pp);
+        let mut parser = dm::parser::Parser::new(&context, indents);
+        parser.enable_procs();
+        if annotate {
+            parser.annotate_to(&mut annotations);
+        }
+        let (fatal_errored, tree) = parser.parse_object_tree_2();
+        if let Some(macros) = pp.take_annotations() {
+            annotations.merge(macros);
+        }
+        if let (Some(path), Some(usage)) = (dump_defines.as_ref(), pp.take_define_usage()) {
+            if let Err(e) = write_define_usage(&context, &usage, path) {
+                eprintln!("error writing defines {}: {}", path.display(), e);
+            }
+        }
+        // Checked before caching so that the cache keeps the diagnostics.
+        dreamchecker::check_macro_names(&context, &pp.finalize());
+        if let Some(path) = cache_file.as_ref() {
+            if !fatal_errored {
+                if let Err(e) = dm::cache::write(&context, &tree, &dme, &version, path) {
+                    eprintln!("error writing cache {}: {}", path.display(), e);
+                }
+            }
+        }
+        (fatal_errored, tree)
+    };
+    if cache_file.is_some() {
+        progress!("Object tree ready in {:.3}s", start.elapsed().as_secs_f32());
+    }
+
+    if let Some(position) = hover {
+        print_hover(&context, &tree, &dme, &position);
+        return;
+    }
+
+    if let Some(path) = complete_members {
+        print_members(&context, &tree, &path);
+        return;
+    }
+
+    if let Some(position) = complete_at {
+        let location = parse_position(&context, &dme, &position, "--complete-at");
+        println!("============================================================");
+        match dreamchecker::completion::members_at(&tree, location) {
+            Some(members) => print_member_list(&context, &members),
+            None => println!("No proc code at {}", position),
+        }
+        return;
+    }
 
+    if let Some(position) = signature {
+        let location = parse_position(&context, &dme, &position, "--signature");
+        print_signature(&context, &tree, &annotations, location, &position);
+        return;
+    }
+
+    if let Some(file) = symbols {
+        let file = find_file(&context, &dme, &file);
+        print_symbols(&annotations, file);
+        return;
+    }
+
+    if let Some(file) = semantic_tokens {
+        let file = find_file(&context, &dme, &file);
+        print_semantic_tokens(&context, &tree, &annotations, &dme, file);
+        return;
+    }
+
+    if let Some(path) = definitions {
+        let file = definitions_file.map(|file| find_file(&context, &dme, &file));
+        if let Err(e) = write_definitions(&context, &tree, file, &path) {
+            eprintln!("error writing definitions {}: {}", path.display(), e);
+        }
+        return;
+    }
+
+    if let Some(path) = references {
+        print_references(&context, &tree, &path, reference_options);
+        return;
+    }
+
+    if let Some(file) = lens {
+        let file = find_file(&context, &dme, &file);
+        print_lenses(&context, &tree, file);
+        return;
+    }
+
+    if let Some((path, new_name)) = rename {
+        run_rename(&context, &tree, &dme, &path, &new_name, &rename_options);
+        return;
+    }
+
+    let mut coverage = None;
     if !parse_only && !fatal_errored {
-        dreamchecker::run_cli(&context, &tree);
+        if type_coverage || coverage_threshold.is_some() {
+            coverage = Some(dreamchecker::run_cli_with_coverage(&context, &tree));
+        } else {
+            dreamchecker::run_cli(&context, &tree);
+        }
+    }
+
+    if let Some(count) = complexity_report {
+        print_complexity_report(&context, &tree, count);
     }
 
+    let coverage = coverage.map(|coverage| {
+        print_type_coverage(&context, &coverage, type_coverage);
+        coverage.overall
+    });
+
     println!("============================================================");
     let errors = context.errors().iter().filter(|each| each.severity() <= dm::Severity::Info).count();
     println!("Found {} diagnostics", errors);
 
+    let mut below_threshold = false;
+    if let (Some(threshold), Some(coverage)) = (coverage_threshold, coverage) {
+        if coverage.percent() < threshold {
+            println!("Type coverage of {:.1}% is below the threshold of {}%", coverage.percent(), threshold);
+            below_threshold = true;
+        }
+    }
+
     if json {
-        serde_json::to_writer(std::io::stdout().lock(), &json! {{
+        let mut summary = json! {{
             "hint": context.errors().iter().filter(|each| each.severity() == dm::Severity::Hint).count(),
             "info": context.errors().iter().filter(|each| each.severity() == dm::Severity::Info).count(),
             "warning": context.errors().iter().filter(|each| each.severity() == dm::Severity::Warning).count(),
             "error": context.errors().iter().filter(|each| each.severity() == dm::Severity::Error).count(),
-        }}).unwrap();
+        }};
+        if let Some(coverage) = coverage {
+            summary["type_coverage"] = json! {{
+                "typed": coverage.typed,
+                "total": coverage.total,
+                "percent": coverage.percent(),
+            }};
+        }
+        serde_json::to_writer(std::io::stdout().lock(), &summary).unwrap();
     }
 
-    std::process::exit(if errors > 0 { 1 } else { 0 });
+    std::process::exit(if errors > 0 || below_threshold { 1 } else { 0 });
+}
+
+/// Answer line-delimited JSON requests for diagnostics on stdin until
+/// shutdown, writing one JSON response line to stdout for each.
+///
+/// Requests look like `{"id": 1, "method": "check", "params": {"files": [...]}}`,
+/// with the methods `check`, `recheck`, and `shutdown`.
+fn serve_diagnostics(mut session: dreamchecker::session::Session) {
+    use std::io::{BufRead, Write};
+
+    let stdin = std::io::stdin();
+    for line in stdin.lock().lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(e) => {
+                eprintln!("error reading stdin: {}", e);
+                return;
+            }
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let mut shutdown = false;
+        let response = match serde_json::from_str::<serde_json::Value>(&line) {
+            Err(e) => json! {{ "id": null, "error": format!("invalid request: {}", e) }},
+            Ok(request) => {
+                let id = request["id"].clone();
+                let result = match request["method"].as_str() {
+                    Some("check") => {
+                        let files: Vec<std::path::PathBuf> = request["params"]["files"].as_array()
+                            .map(|files| files.iter().filter_map(|file| file.as_str()).map(Into::into).collect())
+                            .unwrap_or_default();
+                        session.check(&files).map(|report| report_json(&report)).map_err(|e| e.to_string())
+                    }
+                    Some("recheck") => session.recheck().map(|report| report_json(&report)).map_err(|e| e.to_string()),
+                    Some("shutdown") => {
+                        shutdown = true;
+                        Ok(serde_json::Value::Null)
+                    }
+                    Some(method) => Err(format!("unknown method: {}", method)),
+                    None => Err("missing method".to_owned()),
+                };
+                match result {
+                    Ok(result) => json! {{ "id": id, "result": result }},
+                    Err(error) => json! {{ "id": id, "error": error }},
+                }
+            }
+        };
+        let stdout = std::io::stdout();
+        let mut stdout = stdout.lock();
+        let written = serde_json::to_writer(&mut stdout, &response).map_err(std::io::Error::from)
+            .and_then(|_| writeln!(stdout))
+            .and_then(|_| stdout.flush());
+        if written.is_err() {
+            return;
+        }
+        if shutdown {
+            return;
+        }
+    }
+}
+
+/// The diagnostics of a report, shaped like the parameters of LSP's
+/// `publishDiagnostics` with lines and characters counted from zero.
+fn report_json(report: &dreamchecker::session::Report) -> serde_json::Value {
+    fn range(line: u32, column: u16) -> serde_json::Value {
+        let position = json! {{
+            "line": line.saturating_sub(1),
+            "character": column.saturating_sub(1),
+        }};
+        json! {{ "start": position, "end": position }}
+    }
+    let files: Vec<_> = report.files.iter().map(|file| json! {{
+        "file": file.path,
+        "diagnostics": file.diagnostics.iter().map(|each| json! {{
+            "range": range(each.line, each.column),
+            "severity": each.severity as u8,
+            "code": each.code,
+            "source": each.source,
+            "message": each.message,
+            "relatedInformation": each.related.iter().map(|related| json! {{
+                "location": { "file": related.path, "range": range(related.line, related.column) },
+                "message": related.message,
+            }}).collect::<Vec<_>>(),
+        }}).collect::<Vec<_>>(),
+    }}).collect();
+    json! {{ "reanalyzed": report.reanalyzed, "files": files }}
+}
+
+/// Print the most complex procs, most complex first.
+fn print_complexity_report(context: &dm::Context, tree: &dm::objtree::ObjectTree, count: usize) {
+    let mut scores = Vec::new();
+    tree.root().recurse(&mut |ty| {
+        for proc in ty.iter_self_procs() {
+            if let Some(ref code) = proc.get().code {
+                let graph = dreamchecker::cfg::ControlFlowGraph::new(code);
+                scores.push((dreamchecker::complexity::cyclomatic_complexity(&graph), proc));
+            }
+        }
+    });
+    scores.sort_by_key(|&(score, _)| std::cmp::Reverse(score));
+
+    println!("============================================================");
+    println!("Most complex procs:\n");
+    for (score, proc) in scores.iter().take(count) {
+        let location = proc.get().location;
+        println!("{:>6}  {}  {}:{}", score, proc, context.file_path(location.file).display(), location.line);
+    }
+}
+
+/// Print the overall type coverage, and if `full`, the procs, files, and type
+/// subtrees with the most untyped expressions.
+fn print_type_coverage(context: &dm::Context, coverage: &dreamchecker::type_coverage::Coverage, full: bool) {
+    const WORST: usize = 10;
+    fn line(count: dreamchecker::type_coverage::Count) -> String {
+        format!("{:>6} untyped  {:>5.1}%", count.untyped(), count.percent())
+    }
+
+    println!("============================================================");
+    if full {
+        println!("Procs with the most untyped expressions:\n");
+        for (path, location, count) in coverage.procs.iter().take(WORST) {
+            println!("{}  {}  {}:{}", line(*count), path, context.file_path(location.file).display(), location.line);
+        }
+        println!("\nFiles with the most untyped expressions:\n");
+        for (file, count) in coverage.files.iter().take(WORST) {
+            println!("{}  {}", line(*count), context.file_path(*file).display());
+        }
+        println!("\nType subtrees with the most untyped expressions:\n");
+        for (path, count) in coverage.subtrees.iter().take(WORST) {
+            println!("{}  {}", line(*count), path);
+        }
+        println!();
+    }
+    println!(
+        "Type coverage: {} of {} expressions typed ({:.1}%)",
+        coverage.overall.typed,
+        coverage.overall.total,
+        coverage.overall.percent(),
+    );
+}
+
+/// Look up a file of the environment, by a path relative to either the
+/// current directory or the `.dme`.
+fn find_file(context: &dm::Context, dme: &std::path::Path, file: &str) -> dm::FileId {
+    let path = std::path::Path::new(file);
+    context.get_file(path)
+        .or_else(|| context.get_file(path.strip_prefix(dme.parent()?).ok()?))
+        .unwrap_or_else(|| panic!("{} is not part of the environment", path.display()))
+}
+
+/// Parse a `file:line:column` position given to a switch.
+fn parse_position(context: &dm::Context, dme: &std::path::Path, position: &str, switch: &str) -> dm::Location {
+    let mut parts = position.rsplitn(3, ':');
+    let (column, line, file) = match (parts.next(), parts.next(), parts.next()) {
+        (Some(column), Some(line), Some(file)) => (column, line, file),
+        _ => panic!("{} position must be file:line:column", switch),
+    };
+    dm::Location {
+        file: find_file(context, dme, file),
+        line: line.parse().unwrap_or_else(|_| panic!("{} line must be a number", switch)),
+        column: column.parse().unwrap_or_else(|_| panic!("{} column must be a number", switch)),
+    }
+}
+
+/// Print what is known about the expression at a `file:line:column`.
+fn print_hover(context: &dm::Context, tree: &dm::objtree::ObjectTree, dme: &std::path::Path, position: &str) {
+    let location = parse_position(context, dme, position, "--hover");
+
+    println!("============================================================");
+    let info = match dreamchecker::hover::hover(tree, location) {
+        Some(info) => info,
+        None => {
+            println!("Nothing known at {}", position);
+            return;
+        }
+    };
+    println!("Expression at {}:{}:{}\n", context.file_path(info.location.file).display(), info.location.line, info.location.column);
+    println!("type: {}", info.type_path.as_deref().unwrap_or("unknown"));
+    println!("null: {}", match info.null {
+        Some(true) => "always",
+        Some(false) => "never",
+        None => "unknown",
+    });
+    if let Some(value) = info.value {
+        println!("value: {}", value);
+    }
+    if let Some(declaration) = info.declaration {
+        println!("declared: {}:{}:{}", context.file_path(declaration.file).display(), declaration.line, declaration.column);
+    }
+}
+
+/// Print the signature of the call whose arguments contain a position.
+fn print_signature(context: &dm::Context, tree: &dm::objtree::ObjectTree, annotations: &dm::annotation::AnnotationTree, location: dm::Location, position: &str) {
+    use dreamchecker::signature::Callee;
+
+    println!("============================================================");
+    let help = match dreamchecker::signature::signature_help(tree, annotations, location) {
+        Some(help) => help,
+        None => {
+            println!("No call at {}", position);
+            return;
+        }
+    };
+    match help.callee {
+        Callee::Proc { path, location, parameters } => {
+            println!("{} at {}:{}:{}\n", path, context.file_path(location.file).display(), location.line, location.column);
+            for (index, param) in parameters.iter().enumerate() {
+                let mut line = format!("{}{}", if help.parameter == Some(index) { "> " } else { "  " }, param.name);
+                if let Some(ref type_path) = param.type_path {
+                    line.push_str(&format!(": {}", type_path));
+                }
+                if let Some(ref default) = param.default {
+                    line.push_str(&format!(" = {}", default));
+                }
+                if let Some(ref input_type) = param.input_type {
+                    line.push_str(&format!(" as {}", input_type));
+                }
+                println!("{}", line);
+            }
+            if help.parameter.is_none() {
+                println!("\nargument {} matches no parameter", help.argument + 1);
+            }
+        }
+        Callee::Unknown { name } => {
+            println!("{}() could not be resolved; at argument {}", name, help.argument + 1);
+        }
+    }
+}
+
+/// Print the outline of a file as JSON, shaped like LSP document symbols.
+fn print_symbols(annotations: &dm::annotation::AnnotationTree, file: dm::FileId) {
+    use dm::annotation::SymbolKind;
+    use dreamchecker::symbols::DocumentSymbol;
+
+    // LSP positions count from zero.
+    fn position(loc: dm::Location) -> serde_json::Value {
+        json! {{
+            "line": loc.line.saturating_sub(1),
+            "character": loc.column.saturating_sub(1),
+        }}
+    }
+    fn symbol(each: &DocumentSymbol) -> serde_json::Value {
+        let global = each.parent_path.is_empty();
+        // Class, Field, Variable, Method, and Function.
+        let kind = match each.kind {
+            SymbolKind::Type => 5,
+            SymbolKind::Var if global => 13,
+            SymbolKind::Var => 8,
+            SymbolKind::Proc | SymbolKind::Verb if global => 12,
+            SymbolKind::Proc | SymbolKind::Verb => 6,
+        };
+        json! {{
+            "name": each.name,
+            "detail": each.path(),
+            "kind": kind,
+            "range": {
+                "start": position(each.range.start),
+                "end": position(each.range.end),
+            },
+            "selectionRange": {
+                "start": position(each.range.start),
+                "end": position(each.range.start),
+            },
+            "children": each.children.iter().map(symbol).collect::<Vec<_>>(),
+        }}
+    }
+
+    let symbols: Vec<_> = dreamchecker::symbols::document_symbols(annotations, file).iter().map(symbol).collect();
+    match serde_json::to_string_pretty(&symbols) {
+        Ok(text) => println!("{}", text),
+        Err(e) => eprintln!("error writing symbols: {}", e),
+    }
+}
+
+/// Print the classified tokens of a file as JSON, also encoded the way LSP's
+/// semantic tokens are.
+fn print_semantic_tokens(context: &dm::Context, tree: &dm::objtree::ObjectTree, annotations: &dm::annotation::AnnotationTree, dme: &std::path::Path, file: dm::FileId) {
+    use dreamchecker::semantic_tokens::{encode_lsp, semantic_tokens, TokenKind};
+
+    let path = dme.parent().unwrap_or_else(|| std::path::Path::new(".")).join(context.file_path(file));
+    let text = match std::fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) => {
+            eprintln!("error reading {}: {}", path.display(), e);
+            return;
+        }
+    };
+    let tokens = semantic_tokens(tree, annotations, file, &text);
+    let output = json! {{
+        "legend": TokenKind::ALL.iter().map(|kind| kind.name()).collect::<Vec<_>>(),
+        "tokens": tokens.iter().map(|token| json! {{
+            "line": token.location.line,
+            "column": token.location.column,
+            "length": token.length,
+            "kind": token.kind.name(),
+        }}).collect::<Vec<_>>(),
+        "data": encode_lsp(&tokens),
+    }};
+    match serde_json::to_string_pretty(&output) {
+        Ok(text) => println!("{}", text),
+        Err(e) => eprintln!("error writing semantic tokens: {}", e),
+    }
+}
+
+/// Print every var and proc which can be used on a type.
+fn print_members(context: &dm::Context, tree: &dm::objtree::ObjectTree, path: &str) {
+    println!("============================================================");
+    match tree.find(path) {
+        Some(ty) => print_member_list(context, &dreamchecker::completion::members(tree, ty)),
+        None => println!("No type {}", path),
+    }
+}
+
+fn print_member_list(context: &dm::Context, members: &[dreamchecker::completion::Member]) {
+    for member in members.iter() {
+        let mut line = format!("{} {}", member.kind.name(), member.name);
+        if let Some(ref type_path) = member.type_path {
+            line.push_str(&format!(": {}", type_path));
+        }
+        if let Some(ref declared_on) = member.declared_on {
+            line.push_str(&format!(" (on {})", if declared_on.is_empty() { "/" } else { declared_on }));
+        }
+        if let Some(null) = member.analysis.as_ref().and_then(|info| info.null) {
+            line.push_str(if null { " (null)" } else { " (not null)" });
+        }
+        if let Some(location) = member.location.filter(|location| !location.is_builtins()) {
+            line.push_str(&format!(" at {}:{}:{}", context.file_path(location.file).display(), location.line, location.column));
+        }
+        println!("{}", line);
+        if let Some(docs) = member.docs.as_ref().and_then(|docs| docs.lines().next()) {
+            println!("    {}", docs);
+        }
+    }
+    println!("\n{} members", members.len());
+}
+
+/// Print every reference to the declaration at a path.
+fn print_references(context: &dm::Context, tree: &dm::objtree::ObjectTree, path: &str, options: dreamchecker::references::ReferenceOptions) {
+    use dreamchecker::references::{find_references, Declaration};
+
+    println!("============================================================");
+    let declaration = match Declaration::find(tree, path) {
+        Some(declaration) => declaration,
+        None => {
+            println!("Nothing declared at {}", path);
+            return;
+        }
+    };
+    let index = dreamchecker::definitions::index_definitions(tree, None);
+    let references = find_references(tree, &index, &declaration, options);
+    let declared = declaration.location();
+    println!("References to {}, declared at {}:{}:{}\n", path, context.file_path(declared.file).display(), declared.line, declared.column);
+    for reference in references.iter() {
+        let location = reference.location;
+        if reference.is_potential() {
+            println!("{}:{}:{} (potential: {})", context.file_path(location.file).display(), location.line, location.column, reference.kind.name());
+        } else {
+            println!("{}:{}:{}", context.file_path(location.file).display(), location.line, location.column);
+        }
+    }
+    println!("\n{} references", references.len());
+}
+
+/// Print the override and reference counts of each proc defined in a file.
+fn print_lenses(context: &dm::Context, tree: &dm::objtree::ObjectTree, file: dm::FileId) {
+    let show = |location: dm::Location| format!("{}:{}:{}", context.file_path(location.file).display(), location.line, location.column);
+
+    println!("============================================================");
+    let lenses = dreamchecker::lens::proc_lenses(tree, file);
+    for lens in lenses.iter() {
+        println!("{}  {}: {} overrides | {} references", show(lens.location), lens.path, lens.overrides.len(), lens.references.len());
+        for location in lens.overrides.iter() {
+            println!("    override at {}", show(*location));
+        }
+        for location in lens.references.iter() {
+            println!("    referenced at {}", show(*location));
+        }
+    }
+    println!("\n{} procs", lenses.len());
+}
+
+/// How `--rename` treats edits it can't be sure of.
+#[derive(Default)]
+struct RenameOptions {
+    /// Print the edits as JSON instead of making them.
+    dry_run: bool,
+    /// Make edits which might not refer to the renamed declaration.
+    allow_unsafe: bool,
+    /// Rename even though some uses can't be found in the source text.
+    allow_missing: bool,
+}
+
+/// Rename a var or proc, or with `dry_run` print the edits it would make as
+/// JSON.
+fn run_rename(context: &dm::Context, tree: &dm::objtree::ObjectTree, dme: &std::path::Path, path: &str, new_name: &str, options: &RenameOptions) {
+    use dreamchecker::references::Declaration;
+    use dreamchecker::rename::{apply_edits, plan_rename};
+
+    let root = dme.parent().unwrap_or_else(|| std::path::Path::new("."));
+    let file_path = |file| root.join(context.file_path(file));
+    if !options.dry_run {
+        println!("============================================================");
+    }
+    let declaration = match Declaration::find(tree, path) {
+        Some(declaration) => declaration,
+        None => {
+            eprintln!("Nothing declared at {}", path);
+            std::process::exit(1);
+        }
+    };
+    let plan = match plan_rename(tree, &declaration, new_name, &mut |file| std::fs::read_to_string(file_path(file)).ok()) {
+        Ok(plan) => plan,
+        Err(e) => {
+            eprintln!("Can't rename {}: {}", path, e);
+            if let dreamchecker::rename::RenameError::Collision { location, .. } = e {
+                eprintln!("    declared at {}:{}:{}", context.file_path(location.file).display(), location.line, location.column);
+            }
+            std::process::exit(1);
+        }
+    };
+    for location in plan.missing.iter() {
+        eprintln!("Not in the source text: {}:{}:{}", context.file_path(location.file).display(), location.line, location.column);
+    }
+
+    if options.dry_run {
+        let edits: Vec<_> = plan.edits.iter().map(|edit| json! {{
+            "file": context.file_path(edit.location.file),
+            "line": edit.location.line,
+            "column": edit.location.column,
+            "start": edit.range.start,
+            "end": edit.range.end,
+            "replacement": edit.replacement,
+            "kind": edit.kind.name(),
+            "unsafe": edit.kind.is_unsafe(),
+        }}).collect();
+        match serde_json::to_string_pretty(&edits) {
+            Ok(text) => println!("{}", text),
+            Err(e) => eprintln!("error writing edits: {}", e),
+        }
+        return;
+    }
+
+    if plan.has_unsafe() && !options.allow_unsafe {
+        println!("Refusing to rename {}, since these might not refer to it:\n", path);
+        for edit in plan.edits.iter().filter(|edit| edit.kind.is_unsafe()) {
+            let location = edit.location;
+            println!("{}:{}:{} ({})", context.file_path(location.file).display(), location.line, location.column, edit.kind.name());
+        }
+        println!("\nReview them with --dry-run, and pass --allow-unsafe to rename them too.");
+        std::process::exit(1);
+    }
+    if !plan.missing.is_empty() && !options.allow_missing {
+        println!("Refusing to rename {}, since the uses above are not in the source text, such as those in macro expansions.", path);
+        println!("Pass --allow-missing to rename the rest and fix those by hand.");
+        std::process::exit(1);
+    }
+
+    // Nothing is written unless every file's edits still apply cleanly.
+    let mut rewritten = Vec::new();
+    let mut conflicted = false;
+    let mut start = 0;
+    while start < plan.edits.len() {
+        let file = plan.edits[start].location.file;
+        let count = plan.edits[start..].iter().take_while(|edit| edit.location.file == file).count();
+        let edits: Vec<_> = plan.edits[start..start + count].iter().collect();
+        start += count;
+
+        let result = std::fs::read_to_string(file_path(file))
+            .map_err(|e| e.to_string())
+            .and_then(|text| apply_edits(&text, declaration.name(), &edits).map_err(|location| {
+                format!("conflicting edit at {}:{}", location.line, location.column)
+            }));
+        match result {
+            Ok(text) => rewritten.push((file, text, edits.len())),
+            Err(e) => {
+                println!("{}: {}", context.file_path(file).display(), e);
+                conflicted = true;
+            }
+        }
+    }
+    if conflicted {
+        println!("\nNo files were changed.");
+        std::process::exit(1);
+    }
+
+    for (file, text, count) in rewritten {
+        if let Err(e) = std::fs::write(file_path(file), text) {
+            println!("{}: error writing, {}", context.file_path(file).display(), e);
+            std::process::exit(1);
+        }
+        println!("{}: {} edits", context.file_path(file).display(), count);
+    }
+}
+
+/// Write what each name used in proc code resolved to as JSON.
+fn write_definitions(context: &dm::Context, tree: &dm::objtree::ObjectTree, file: Option<dm::FileId>, path: &std::path::Path) -> std::io::Result<()> {
+    let location = |loc: dm::Location| json! {{
+        "file": context.file_path(loc.file),
+        "line": loc.line,
+        "column": loc.column,
+    }};
+    let index = dreamchecker::definitions::index_definitions(tree, file);
+    let occurrences: Vec<_> = index.occurrences.iter().map(|each| json! {{
+        "location": location(each.location),
+        "name": each.name,
+        "kind": each.kind.name(),
+        "builtin": each.definition.is_some_and(|def| def.is_builtins()),
+        "definition": each.definition.filter(|def| !def.is_builtins()).map(location),
+    }}).collect();
+    let output = std::io::BufWriter::new(std::fs::File::create(path)?);
+    serde_json::to_writer_pretty(output, &occurrences)?;
+    Ok(())
+}
+
+/// Write every macro definition and its uses as JSON, keyed by name.
+fn write_define_usage(context: &dm::Context, usage: &dm::preprocessor::DefineUsageMap, path: &std::path::Path) -> std::io::Result<()> {
+    let location = |loc: dm::Location| json! {{
+        "file": context.file_path(loc.file),
+        "line": loc.line,
+        "column": loc.column,
+    }};
+    let mut map = serde_json::Map::new();
+    for each in usage.iter() {
+        let (params, variadic) = match each.define {
+            dm::preprocessor::Define::Function { ref params, variadic, .. } => (Some(params), variadic),
+            dm::preprocessor::Define::Constant { .. } => (None, false),
+        };
+        let entry = map.entry(each.name.clone()).or_insert_with(|| serde_json::Value::Array(Vec::new()));
+        if let serde_json::Value::Array(list) = entry {
+            list.push(json! {{
+                "location": location(each.location),
+                "params": params,
+                "variadic": variadic,
+                "body": each.body(),
+                "ended": each.ended.map(location),
+                "uses": each.uses.iter().cloned().map(location).collect::<Vec<_>>(),
+            }});
+        }
+    }
+    let output = std::io::BufWriter::new(std::fs::File::create(path)?);
+    serde_json::to_writer_pretty(output, &map)?;
+    Ok(())
 }
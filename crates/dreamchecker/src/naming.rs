@@ -0,0 +1,130 @@
+//! Checking declaration names against the patterns in the
+//! `[dreamchecker.naming]` config section.
+//!
+//! Names are checked where they're declared: vars and procs on the type which
+//! first declares them, and types by the last segment of their path. Overrides
+//! are skipped, since their names are inherited, as are the names BYOND calls
+//! procs by. Macros are checked once parsing is done, at the first definition of
+//! each name.
+
+use regex::Regex;
+
+use std::collections::BTreeMap;
+
+use dm::{Context, DMError, Location, Severity};
+use dm::config::Naming;
+use dm::objtree::{ObjectTree, TypeRef};
+use dm::preprocessor::DefineHistory;
+
+/// Procs BYOND looks up by name, which may be declared on types that don't
+/// inherit a declaration of them.
+pub(crate) const MANDATED_PROCS: &[&str] = &[
+    "New", "Del", "Topic", "Login", "Logout", "Stat", "Read", "Write",
+    "Click", "DblClick", "MouseDown", "MouseDrag", "MouseDrop", "MouseEntered",
+    "MouseExited", "MouseMove", "MouseUp", "MouseWheel",
+    "Enter", "Entered", "Exit", "Exited", "Cross", "Crossed", "Uncross",
+    "Uncrossed", "Bump", "Move",
+];
+
+/// The patterns which apply to one type.
+struct Patterns<'a> {
+    vars: &'a Regex,
+    procs: &'a Regex,
+    types: &'a Regex,
+}
+
+impl<'a> Patterns<'a> {
+    /// The patterns for a type, from the paths enclosing it, closest first,
+    /// then the defaults.
+    fn for_type(naming: &'a Naming, path: &str) -> Patterns<'a> {
+        let mut enclosing: Vec<_> = naming.paths.iter()
+            .filter(|each| encloses(&each.path, path))
+            .collect();
+        enclosing.sort_by_key(|each| each.path.trim_end_matches('/').len());
+
+        let mut patterns = Patterns {
+            vars: &naming.vars,
+            procs: &naming.procs,
+            types: &naming.types,
+        };
+        for each in enclosing {
+            patterns.vars = each.vars.as_ref().unwrap_or(patterns.vars);
+            patterns.procs = each.procs.as_ref().unwrap_or(patterns.procs);
+            patterns.types = each.types.as_ref().unwrap_or(patterns.types);
+        }
+        patterns
+    }
+}
+
+/// Whether a configured path is a type path or one of its parents.
+fn encloses(prefix: &str, path: &str) -> bool {
+    let prefix = prefix.trim_end_matches('/');
+    match path.strip_prefix(prefix) {
+        Some(rest) => rest.is_empty() || rest.starts_with('/'),
+        None => false,
+    }
+}
+
+/// Check the names of every type and the vars and procs they declare.
+pub fn check_naming(context: &Context, objtree: &ObjectTree) {
+    let naming = &context.config().dreamchecker.naming;
+    if !naming.enabled {
+        return;
+    }
+    for ty in objtree.iter_types() {
+        let patterns = Patterns::for_type(naming, &ty.get().path);
+        if !ty.is_root() && !ty.get().location.is_builtins() {
+            check_name(context, ty.get().location, "type", ty.get().name(), patterns.types);
+        }
+        for (name, var) in ty.get().vars.iter() {
+            if let Some(ref decl) = var.declaration {
+                if !decl.location.is_builtins() && !inherits(ty, |parent| parent.get_var_declaration(name).is_some()) {
+                    check_name(context, decl.location, "var", name, patterns.vars);
+                }
+            }
+        }
+        for (name, proc) in ty.get().procs.iter() {
+            if let Some(ref decl) = proc.declaration {
+                if !decl.location.is_builtins()
+                    && !MANDATED_PROCS.contains(&name.as_str())
+                    && !inherits(ty, |parent| parent.get_proc_declaration(name).is_some())
+                {
+                    check_name(context, decl.location, decl.kind.name(), name, patterns.procs);
+                }
+            }
+        }
+    }
+}
+
+/// Check the names of the macros defined while preprocessing the environment.
+pub fn check_macro_names(context: &Context, defines: &DefineHistory) {
+    let naming = &context.config().dreamchecker.naming;
+    if !naming.enabled {
+        return;
+    }
+    let mut first: BTreeMap<&str, Location> = BTreeMap::new();
+    for (range, (name, _)) in defines.iter() {
+        if range.start.is_builtins() {
+            continue;
+        }
+        first.entry(name.as_str()).or_insert(range.start);
+    }
+    for (name, location) in first {
+        check_name(context, location, "macro", name, &naming.macros);
+    }
+}
+
+/// Whether a parent type already declares a name.
+fn inherits<'o>(ty: TypeRef<'o>, declares: impl Fn(TypeRef<'o>) -> bool) -> bool {
+    ty.parent_type().is_some_and(declares)
+}
+
+fn check_name(context: &Context, location: Location, kind: &str, name: &str, pattern: &Regex) {
+    if !pattern.is_match(name) {
+        DMError::new(location, format!("{} name {} does not match /{}/", kind, name, pattern))
+            .with_component(dm::Component::DreamChecker)
+            .set_severity(Severity::Info)
+            .with_errortype("naming_convention")
+            .register(context);
+    }
+}
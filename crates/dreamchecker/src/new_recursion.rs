@@ -0,0 +1,98 @@
+//! Detection of `New()` procs which create another instance of their own
+//! type.
+//!
+//! Creating the instance calls the same `New()` again, so unless something
+//! stops it, this recurses until the stack runs out. A `new` is only reported
+//! if it runs every time the proc does: anything past an `if`, `switch`, or
+//! loop condition, including an early `return`, counts as a guard.
+
+use dm::{Context, DMError, Location, Severity};
+use dm::ast::*;
+use dm::objtree::{ProcRef, TypeRef};
+
+use crate::cfg::{ControlFlowGraph, EdgeKind, Step};
+use crate::visit::visit;
+
+pub fn check_new_recursion(context: &Context, proc: ProcRef, graph: &ControlFlowGraph) {
+    if !matches!(proc.name(), "New" | "Initialize") {
+        return;
+    }
+
+    // The blocks which run whenever the proc does, up to its first branch.
+    // Spawned code runs whenever the spawn does, so it counts too.
+    let mut unconditional = vec![false; graph.blocks.len()];
+    let mut stack = vec![graph.entry];
+    while let Some(id) = stack.pop() {
+        if std::mem::replace(&mut unconditional[id], true) {
+            continue;
+        }
+        for &(kind, next) in graph.blocks[id].successors.iter() {
+            if matches!(kind, EdgeKind::Jump | EdgeKind::Spawn) {
+                stack.push(next);
+            }
+        }
+    }
+
+    let self_type = proc.ty();
+    let mut found = Vec::new();
+    for (id, block) in graph.blocks.iter().enumerate() {
+        if !unconditional[id] {
+            continue;
+        }
+        for step in block.steps.iter() {
+            if let Step::Statement(_, Statement::Var(var)) = step {
+                if let Some(Expression::Base { term, follow }) = &var.value {
+                    if let (Term::NewImplicit { .. }, []) = (&term.elem, &follow[..]) {
+                        if let Some(ty) = proc.tree().type_by_path(&var.var_type.type_path) {
+                            found.push((term.location, ty));
+                        }
+                    }
+                }
+            }
+            for expr in step.expressions() {
+                visit(expr, &mut |expr| {
+                    if let Expression::Base { term, .. } = expr {
+                        if let Some(ty) = created_type(self_type, &term.elem) {
+                            found.push((term.location, ty));
+                        }
+                    }
+                });
+            }
+        }
+    }
+
+    found.sort_by_key(|&(location, _)| location);
+    for (location, ty) in found {
+        if ty.is_subtype_of(&self_type) {
+            report(context, location, proc, ty);
+        }
+    }
+}
+
+/// The type a `new` term creates, if it's known.
+fn created_type<'o>(self_type: TypeRef<'o>, term: &Term) -> Option<TypeRef<'o>> {
+    match term {
+        Term::NewPrefab { prefab, .. } => Some(self_type.navigate_path(&prefab.path)?.ty()),
+        // `new type()` and `new src.type()` create at least this type.
+        Term::NewMiniExpr { expr, .. } => match (expr.ident.as_str(), &expr.fields[..]) {
+            ("type", []) => Some(self_type),
+            ("src", [field]) if field.ident.as_str() == "type" => Some(self_type),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn report(context: &Context, location: Location, proc: ProcRef, ty: TypeRef) {
+    let message = if ty == proc.ty() {
+        format!("creating {} unconditionally in its own {}() recurses forever", ty, proc.name())
+    } else {
+        format!("{} is a subtype of {}, so creating it unconditionally in {}() recurses forever", ty, proc.ty(), proc.name())
+    };
+    DMError::new(location, message)
+        .with_component(dm::Component::DreamChecker)
+        .set_severity(Severity::Warning)
+        .with_note(proc.get().location, format!("{} is here", proc))
+        .with_errortype("new_recursion")
+        .register(context);
+}
@@ -0,0 +1,51 @@
+//! Detection of calls whose result is used although the proc never returns a
+//! value.
+//!
+//! A proc never returns a value if it has no `return` with a value, never
+//! mentions `.`, and never calls `..()`, since the parent may return one.
+//! Procs which can stop by `CRASH()`, `throw`, or a call to a proc listed in
+//! `never_returns` are left alone, as are empty procs, which are usually
+//! stubs, and calls to procs with an override which may return a value, since
+//! any of them may run. A `..()` runs only the parent, so it is reported if
+//! the parent never returns a value, whatever its other overrides do, unless
+//! it is `. = ..()`, which passes on whatever the parent may come to return.
+
+use dm::ast::*;
+use dm::objtree::ProcRef;
+
+use crate::cfg::{ControlFlowGraph, Step};
+use crate::visit::visit;
+
+/// Whether a proc may return something other than null, or may not return
+/// normally at all.
+pub fn may_return_value(graph: &ControlFlowGraph, never_returns: &[String]) -> bool {
+    let mut found = false;
+    for step in graph.blocks.iter().flat_map(|block| block.steps.iter()) {
+        if let Step::Statement(_, Statement::Return(Some(_)) | Statement::Crash(_) | Statement::Throw(_)) = step {
+            return true;
+        }
+        for expr in step.expressions() {
+            visit(expr, &mut |expr| {
+                if let Expression::Base { term, .. } = expr {
+                    found |= match &term.elem {
+                        Term::Ident(name) => name == ".",
+                        Term::ParentCall(_) => true,
+                        Term::Call(name, _) => never_returns.iter().any(|each| each == name.as_str()),
+                        _ => false,
+                    };
+                }
+            });
+        }
+    }
+    found
+}
+
+/// The definitions which a call resolving to a proc may run: the proc itself
+/// and every definition of it on the types below.
+pub fn definitions_run_by(proc: ProcRef<'_>) -> Vec<ProcRef<'_>> {
+    let mut definitions = Vec::new();
+    proc.ty().recurse(&mut |ty| {
+        definitions.extend(ty.iter_self_procs().filter(|each| each.name() == proc.name()));
+    });
+    definitions
+}
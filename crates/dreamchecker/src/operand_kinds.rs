@@ -0,0 +1,91 @@
+//! Operators which are always runtime errors for the kinds of their operands.
+//!
+//! BYOND does arithmetic only on numbers, except that `+` joins text, and
+//! lists only support `+`, `-`, and the bitwise operators. An operator is
+//! classified only when every operand is known to be text, a number, or a
+//! list, so datums, which may overload operators, and values which may be
+//! null are never reported.
+
+use dm::ast::{BinaryOp, UnaryOp};
+
+use super::{Analysis, Assumption};
+
+use self::Kind::*;
+
+/// The kinds of operand which are classified.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Text,
+    Number,
+    List,
+}
+
+impl Kind {
+    pub fn name(self) -> &'static str {
+        match self {
+            Text => "text",
+            Number => "a number",
+            List => "a list",
+        }
+    }
+}
+
+/// The kind of an operand, if it's known to be exactly one of them.
+pub fn kind_of(analysis: &Analysis) -> Option<Kind> {
+    let set = &analysis.aset.set;
+    if set.contains(&Assumption::IsNull(true)) {
+        return None;
+    }
+    let kinds = [
+        (Text, set.contains(&Assumption::IsText(true))),
+        (Number, set.contains(&Assumption::IsNum(true))),
+        (List, analysis.static_ty.is_list()),
+    ];
+    let mut found = kinds.iter().filter(|&&(_, is)| is).map(|&(kind, _)| kind);
+    match (found.next(), found.next()) {
+        (Some(kind), None) => Some(kind),
+        _ => None,
+    }
+}
+
+/// Operand kinds, left then right.
+type Operands = (Kind, Kind);
+
+/// Arithmetic which only works on numbers.
+const ARITHMETIC: &[Operands] = &[
+    (Text, Number), (Number, Text), (Text, Text),
+    (Number, List), (Text, List),
+    (List, Number), (List, Text), (List, List),
+];
+
+/// The operand kinds which make each binary operator a runtime error.
+const BINARY_ERRORS: &[(&[BinaryOp], &[Operands])] = &[
+    // `+` of text and a number is `text_number_concat`.
+    (&[BinaryOp::Add], &[(Number, List), (Text, List)]),
+    // Subtracting from a list removes from it.
+    (&[BinaryOp::Sub], &[(Text, Number), (Number, Text), (Text, Text), (Number, List), (Text, List)]),
+    (&[BinaryOp::Mul, BinaryOp::Div, BinaryOp::Mod, BinaryOp::Pow], ARITHMETIC),
+    (
+        &[BinaryOp::Less, BinaryOp::Greater, BinaryOp::LessEq, BinaryOp::GreaterEq],
+        &[(Number, List), (List, Number), (Text, List), (List, Text)],
+    ),
+];
+
+/// The operand kinds which make each unary operator a runtime error. `++`
+/// and `--` on lists are reported with other types without overloads.
+const UNARY_ERRORS: &[(&[UnaryOp], &[Kind])] = &[
+    (&[UnaryOp::Neg, UnaryOp::BitNot], &[Text, List]),
+    (&[UnaryOp::PreIncr, UnaryOp::PostIncr, UnaryOp::PreDecr, UnaryOp::PostDecr], &[Text]),
+];
+
+/// Whether a binary operator is always a runtime error on these operands.
+pub fn is_binary_error(op: BinaryOp, lhs: Kind, rhs: Kind) -> bool {
+    BINARY_ERRORS.iter()
+        .any(|&(ops, kinds)| ops.contains(&op) && kinds.contains(&(lhs, rhs)))
+}
+
+/// Whether a unary operator is always a runtime error on this operand.
+pub fn is_unary_error(op: UnaryOp, operand: Kind) -> bool {
+    UNARY_ERRORS.iter()
+        .any(|&(ops, kinds)| ops.contains(&op) && kinds.contains(&operand))
+}
@@ -0,0 +1,117 @@
+//! Operands of `<<`, which is both a bit shift and output.
+//!
+//! A number on the left makes `<<` a shift, which needs a number on the
+//! right. A mob, client, savefile, `world`, `world.log`, or a list of targets
+//! on the left makes it output, which takes anything except the result of
+//! another output. Text, or a datum which isn't an atom and has no
+//! `operator<<`, is neither. Outputting a list anywhere but a savefile prints
+//! a reference to it rather than its contents, except for entries of lists,
+//! which `var/list/L[8]` declares as lists although they start out null.
+//! Operands whose type isn't known are never reported.
+
+use dm::{Context, DMError, Location, Severity};
+use dm::ast::*;
+use dm::objtree::ObjectTree;
+
+use crate::operand_kinds::{kind_of, Kind};
+use crate::same_ast::unparenthesize;
+use crate::{Analysis, StaticType};
+
+/// What a `<<` does, by its left side.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Meaning {
+    Shift,
+    /// Output, and whether it's to a savefile.
+    Output { savefile: bool },
+    Neither,
+    Unknown,
+}
+
+/// What a `<<` with this left side does.
+pub fn meaning(objtree: &ObjectTree, lhs_expr: &Expression, lhs: &Analysis) -> Meaning {
+    if is_world_log(lhs_expr) {
+        return Meaning::Output { savefile: false };
+    }
+    match kind_of(lhs) {
+        Some(Kind::Number) => return Meaning::Shift,
+        Some(Kind::Text) => return Meaning::Neither,
+        Some(Kind::List) => return Meaning::Output { savefile: false },
+        None => {}
+    }
+    let ty = match lhs.static_ty {
+        StaticType::Type(ty) => ty,
+        _ => return Meaning::Unknown,
+    };
+    if ty.get_proc("operator<<").is_some() {
+        return Meaning::Unknown;
+    }
+    if ty.is_subtype_of(&objtree.expect("/savefile")) {
+        return Meaning::Output { savefile: true };
+    }
+    let targets = ["/mob", "/client", "/world"];
+    if targets.iter().any(|path| ty.is_subtype_of(&objtree.expect(path))) {
+        return Meaning::Output { savefile: false };
+    }
+    if ty.is_subtype_of(&objtree.expect("/datum")) && !ty.is_subtype_of(&objtree.expect("/atom")) {
+        return Meaning::Neither;
+    }
+    Meaning::Unknown
+}
+
+/// Check the operands of a `<<` which does `meaning`. `rhs_is_output` is
+/// whether the right side is itself an output.
+pub fn check_lshift(context: &Context, location: Location, meaning: Meaning, lhs: &Analysis, rhs_expr: &Expression, rhs: &Analysis, rhs_is_output: bool) {
+    let message = match meaning {
+        Meaning::Shift => match kind_of(rhs) {
+            Some(kind @ Kind::Text) | Some(kind @ Kind::List) => {
+                format!("`<<` shifts a number here, so it needs a number on the right, not {}", kind.name())
+            }
+            _ => return,
+        },
+        Meaning::Output { .. } if rhs_is_output => {
+            "`<<` outputs the result of another output, which has no value".to_owned()
+        }
+        Meaning::Output { savefile: false } if kind_of(rhs) == Some(Kind::List) && !is_index(rhs_expr) => {
+            "outputting a list prints a reference to it, not its contents".to_owned()
+        }
+        Meaning::Neither => match lhs.static_ty {
+            StaticType::Type(ty) => format!("`<<` on {} is neither a bit shift nor output, and it has no operator<<", ty),
+            _ => "`<<` on text is neither a bit shift nor output".to_owned(),
+        },
+        _ => return,
+    };
+    DMError::new(location, message)
+        .with_component(dm::Component::DreamChecker)
+        .set_severity(Severity::Warning)
+        .with_errortype("output_operand")
+        .register(context);
+}
+
+/// Report output to `world`, which goes to every connected player.
+pub fn check_world_output(context: &Context, location: Location, lhs_expr: &Expression) {
+    if matches!(lhs_expr.as_term(), Some(Term::Ident(name)) if name == "world") {
+        DMError::new(location, "`world <<` outputs to every connected player")
+            .with_component(dm::Component::DreamChecker)
+            .set_severity(Severity::Warning)
+            .with_errortype("world_output")
+            .register(context);
+    }
+}
+
+fn is_index(expr: &Expression) -> bool {
+    match unparenthesize(expr) {
+        Expression::Base { follow, .. } => matches!(follow.last(), Some(last) if matches!(last.elem, Follow::Index(..))),
+        _ => false,
+    }
+}
+
+fn is_world_log(expr: &Expression) -> bool {
+    match unparenthesize(expr) {
+        Expression::Base { term, follow } => {
+            matches!(term.elem, Term::Ident(ref name) if name == "world")
+                && follow.len() == 1
+                && matches!(follow[0].elem, Follow::Field(_, ref name) if name == "log")
+        }
+        _ => false,
+    }
+}
@@ -0,0 +1,77 @@
+//! Checking and folding the default values of proc parameters.
+//!
+//! A default is evaluated when the proc is called without that argument, and
+//! may only rely on what's known by then. Defaults which refer to another
+//! parameter, to `src`, or to a var of `src` are reported, since those depend
+//! on the order in which the arguments are filled in and on the object the
+//! proc is called on. Globals and `const` or `static` vars are fine. Defaults
+//! are otherwise checked like the rest of the proc, so an undefined name in
+//! one is reported too. Defaults which fold to a constant are used at call
+//! sites which omit the argument.
+
+use dm::{Context, DMError, Severity};
+use dm::ast::*;
+use dm::constants::Constant;
+use dm::objtree::ProcRef;
+
+use crate::visit::visit;
+
+/// Report parameter defaults which refer to other parameters or to `src`.
+pub fn check_param_defaults(context: &Context, proc: ProcRef) {
+    let parameters = &proc.get().parameters;
+    for param in parameters.iter() {
+        let default = match param.default {
+            Some(ref default) => default,
+            None => continue,
+        };
+        let mut found = Vec::new();
+        visit(default, &mut |expr| {
+            if let Expression::Base { term, .. } = expr {
+                let name = match term.elem {
+                    Term::Ident(ref name) => name,
+                    _ => return,
+                };
+                let problem = if parameters.iter().any(|each| each.name == *name) {
+                    format!("parameter {}", name)
+                } else if name == "src" {
+                    "src".to_owned()
+                } else if is_src_var(proc, name) {
+                    format!("src var {}", name)
+                } else {
+                    return;
+                };
+                if !found.contains(&problem) {
+                    found.push(problem);
+                }
+            }
+        });
+        for problem in found {
+            DMError::new(param.location, format!("default of {} refers to {}", param.name, problem))
+                .with_component(dm::Component::DreamChecker)
+                .set_severity(Severity::Warning)
+                .with_errortype("param_default_reference")
+                .register(context);
+        }
+    }
+}
+
+/// Whether a name is a var of the object a proc runs on, rather than a global
+/// or a `const` or `static` var.
+fn is_src_var(proc: ProcRef, name: &str) -> bool {
+    if proc.tree().root().get_var_declaration(name).is_some() {
+        return false;
+    }
+    match proc.ty().get_var_declaration(name) {
+        Some(decl) => !decl.var_type.flags.is_const() && !decl.var_type.flags.is_static(),
+        None => false,
+    }
+}
+
+/// The value of a parameter's default, if it folds to a constant other than
+/// null.
+pub fn folded_default(param: &Parameter) -> Option<Constant> {
+    match param.default.clone()?.simple_evaluate(param.location) {
+        Ok(Constant::Null(_)) | Err(_) => None,
+        Ok(value) => Some(value),
+    }
+}
@@ -0,0 +1,53 @@
+//! Calls to `prob()` and `rand()` whose result is known in advance.
+//!
+//! `prob()` with a constant chance of 0 or less never succeeds, and one of 100
+//! or more always does. `rand()` with constant bounds is reported if the
+//! bounds are reversed, and comparing its result to a constant number outside
+//! the range it returns is always true or always false. Arguments are
+//! constant-folded first. The same results are used by the constant
+//! condition checks, so code under `if (prob(0))` is unreachable.
+
+use dm::{Context, DMError, Location, Severity};
+use dm::ast::*;
+use dm::constants::{constant_numbers, prob_outcome, rand_call_range};
+use dm::objtree::ProcRef;
+
+/// Check the arguments of a call to the builtin `prob()` or `rand()`.
+pub fn check_random_args(context: &Context, location: Location, proc: ProcRef, args: &[Expression]) {
+    if !proc.ty().is_root() || !proc.is_builtin() {
+        return;
+    }
+    let message = match (proc.name(), constant_numbers(args).as_deref()) {
+        ("prob", Some(&[chance])) => match prob_outcome(chance) {
+            Some(true) => format!("prob({}) always succeeds", chance),
+            Some(false) => format!("prob({}) never succeeds", chance),
+            None => return,
+        },
+        ("rand", Some(&[low, high])) if low > high => {
+            format!("rand({}, {}) has its lower bound above its upper bound", low, high)
+        }
+        _ => return,
+    };
+    report(context, location, message);
+}
+
+/// Check a comparison which may involve a call to `rand()`.
+pub fn check_random_comparison(context: &Context, location: Location, op: BinaryOp, lhs: &Expression, rhs: &Expression) {
+    let outcome = match dm::constants::rand_comparison(op, lhs, rhs) {
+        Some(outcome) => outcome,
+        None => return,
+    };
+    let (low, high) = match rand_call_range(lhs).or_else(|| rand_call_range(rhs)) {
+        Some(range) => range,
+        None => return,
+    };
+    report(context, location, format!("`{}` comparison is always {}, as rand() here returns from {} to {}", op, outcome, low, high));
+}
+
+fn report(context: &Context, location: Location, message: String) {
+    DMError::new(location, message)
+        .with_component(dm::Component::DreamChecker)
+        .set_severity(Severity::Warning)
+        .with_errortype("random_determinate")
+        .register(context);
+}
@@ -0,0 +1,184 @@
+//! Finding every reference to a type, var, or proc.
+//!
+//! This is the inverse of the definition index: the occurrences which
+//! resolved to the declaration are its references. Overrides and names which
+//! couldn't be resolved, such as colon accesses on untyped values, may also
+//! be included as potential references.
+
+use dm::Location;
+use dm::objtree::{ObjectTree, TypeRef};
+
+use crate::definitions::{DefinitionIndex, DefinitionKind};
+
+/// A type, or a var or proc declared on a type.
+#[derive(Debug, Clone)]
+pub enum Declaration<'o> {
+    Type(TypeRef<'o>),
+    Var(TypeRef<'o>, String),
+    Proc(TypeRef<'o>, String),
+}
+
+impl<'o> Declaration<'o> {
+    /// Look up a declaration by a path such as `/obj/item`,
+    /// `/obj/item/var/force`, or `/obj/item/proc/attack`. Global vars and
+    /// procs are `/var/name` and `/proc/name`.
+    ///
+    /// A var or proc may be named on any type which has it, not only the one
+    /// which declares it.
+    pub fn find(objtree: &'o ObjectTree, path: &str) -> Option<Declaration<'o>> {
+        if let Some((prefix, name)) = path.rsplit_once('/') {
+            let type_path = |kind: &str| prefix.strip_suffix(kind)?.strip_suffix('/');
+            if let Some(type_path) = type_path("var") {
+                let ty = objtree.find(type_path)?;
+                ty.get_var_declaration(name)?;
+                return Some(Declaration::Var(ty, name.to_owned()));
+            }
+            if let Some(type_path) = type_path("proc").or_else(|| type_path("verb")) {
+                let ty = objtree.find(type_path)?;
+                ty.get_proc_declaration(name)?;
+                return Some(Declaration::Proc(ty, name.to_owned()));
+            }
+        }
+        objtree.find(path).filter(|ty| !ty.is_root()).map(Declaration::Type)
+    }
+
+    /// The name references use.
+    pub fn name(&self) -> &str {
+        match self {
+            Declaration::Type(ty) => &ty.get().path,
+            Declaration::Var(_, name) | Declaration::Proc(_, name) => name,
+        }
+    }
+
+    /// Where it's declared.
+    pub fn location(&self) -> Location {
+        match self {
+            Declaration::Type(ty) => ty.get().location,
+            Declaration::Var(ty, name) => ty.get_var_declaration(name).map_or(ty.get().location, |decl| decl.location),
+            Declaration::Proc(ty, name) => ty.get_proc_declaration(name).map_or(ty.get().location, |decl| decl.location),
+        }
+    }
+
+    fn kind(&self) -> DefinitionKind {
+        match self {
+            Declaration::Type(_) => DefinitionKind::Type,
+            Declaration::Var(..) => DefinitionKind::Var,
+            Declaration::Proc(..) => DefinitionKind::Proc,
+        }
+    }
+}
+
+/// Which potential references to include.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ReferenceOptions {
+    /// Overrides of a var or proc on other types, and `..()` calls which
+    /// reach the declaration through them.
+    pub overrides: bool,
+    /// Field accesses and calls on values of unknown type, such as colon
+    /// accesses, which use the same name.
+    pub unresolved: bool,
+}
+
+/// How a reference was found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReferenceKind {
+    /// A name which resolved to the declaration.
+    Resolved,
+    /// An override of the var or proc, or a use which resolved to one.
+    Override,
+    /// A field or call whose name matches, on a value of unknown type.
+    Unresolved,
+}
+
+impl ReferenceKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            ReferenceKind::Resolved => "resolved",
+            ReferenceKind::Override => "override",
+            ReferenceKind::Unresolved => "unresolved",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reference {
+    pub location: Location,
+    pub kind: ReferenceKind,
+}
+
+impl Reference {
+    /// Whether this might not actually refer to the declaration.
+    pub fn is_potential(&self) -> bool {
+        self.kind != ReferenceKind::Resolved
+    }
+}
+
+/// Find the references to a declaration among the occurrences of an index,
+/// in order of location.
+pub fn find_references(objtree: &ObjectTree, index: &DefinitionIndex, declaration: &Declaration, options: ReferenceOptions) -> Vec<Reference> {
+    let declared_at = declaration.location();
+    let mut targets = vec![declared_at];
+    let mut overrides = Vec::new();
+
+    match declaration {
+        Declaration::Type(_) => {}
+        Declaration::Var(_, name) => {
+            for ty in objtree.iter_types() {
+                let var = match ty.get().vars.get(name) {
+                    Some(var) => var,
+                    None => continue,
+                };
+                if var.declaration.is_none() && ty.get_var_declaration(name).map(|decl| decl.location) == Some(declared_at) {
+                    overrides.push(var.value.location);
+                }
+            }
+        }
+        Declaration::Proc(_, name) => {
+            for ty in objtree.iter_types() {
+                let proc = match ty.get().procs.get(name) {
+                    Some(proc) => proc,
+                    None => continue,
+                };
+                if ty.get_proc_declaration(name).map(|decl| decl.location) != Some(declared_at) {
+                    continue;
+                }
+                // `..()` resolves to the specific proc it calls.
+                if proc.declaration.is_some() {
+                    targets.extend(proc.value.iter().map(|value| value.location));
+                } else {
+                    overrides.extend(proc.value.iter().map(|value| value.location));
+                }
+            }
+        }
+    }
+
+    let kind = declaration.kind();
+    let mut references = Vec::new();
+    for occurrence in index.occurrences.iter() {
+        if occurrence.kind == kind {
+            let definition = match occurrence.definition {
+                Some(definition) => definition,
+                None => continue,
+            };
+            if targets.contains(&definition) {
+                references.push(Reference { location: occurrence.location, kind: ReferenceKind::Resolved });
+            } else if options.overrides && overrides.contains(&definition) {
+                references.push(Reference { location: occurrence.location, kind: ReferenceKind::Override });
+            }
+        } else if options.unresolved
+            && kind != DefinitionKind::Type
+            && occurrence.kind == DefinitionKind::Unresolved
+            && occurrence.is_field
+            && occurrence.name == declaration.name()
+        {
+            references.push(Reference { location: occurrence.location, kind: ReferenceKind::Unresolved });
+        }
+    }
+    if options.overrides {
+        references.extend(overrides.into_iter()
+            .filter(|location| *location != Location::default())
+            .map(|location| Reference { location, kind: ReferenceKind::Override }));
+        references.sort_by_key(|each| each.location);
+    }
+    references
+}
@@ -0,0 +1,296 @@
+//! Collecting the text edits needed to rename a var or proc.
+//!
+//! The edits cover the declaration, every override, and every reference the
+//! analysis resolved to them, including `.proc/name` references. Colon
+//! accesses and string literals which use the same name might be related but
+//! can't be proven to be, so their edits are marked unsafe.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Range;
+
+use dm::{FileId, Location};
+use dm::ast::{Expression, Term};
+use dm::objtree::{ObjectTree, TypeRef};
+
+use crate::cfg::ControlFlowGraph;
+use crate::declaring_type;
+use crate::definitions::index_definitions;
+use crate::references::{find_references, Declaration, ReferenceKind, ReferenceOptions};
+use crate::visit::visit;
+
+/// Why an occurrence of the name is being renamed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum EditKind {
+    Declaration,
+    Override,
+    Reference,
+    /// A field access or call with the same name on a value of unknown type.
+    Colon,
+    /// A string literal which is exactly the name, such as in `call()()`.
+    String,
+}
+
+impl EditKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            EditKind::Declaration => "declaration",
+            EditKind::Override => "override",
+            EditKind::Reference => "reference",
+            EditKind::Colon => "colon",
+            EditKind::String => "string",
+        }
+    }
+
+    /// Whether the occurrence might not actually refer to what's renamed.
+    pub fn is_unsafe(self) -> bool {
+        matches!(self, EditKind::Colon | EditKind::String)
+    }
+}
+
+/// A replacement of the old name in a file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Edit {
+    /// Where the old name starts.
+    pub location: Location,
+    /// The bytes of the file holding the old name.
+    pub range: Range<usize>,
+    pub replacement: String,
+    pub kind: EditKind,
+}
+
+/// The edits needed for a rename.
+#[derive(Debug, Clone, Default)]
+pub struct RenamePlan {
+    /// The edits, in order of file and position.
+    pub edits: Vec<Edit>,
+    /// Occurrences whose name isn't in the source text where they are, such
+    /// as those from macro expansions.
+    pub missing: Vec<Location>,
+}
+
+impl RenamePlan {
+    pub fn has_unsafe(&self) -> bool {
+        self.edits.iter().any(|edit| edit.kind.is_unsafe())
+    }
+}
+
+/// Why a rename can't be done.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RenameError {
+    /// Only vars and procs can be renamed.
+    NotVarOrProc,
+    /// Builtin vars and procs can't be renamed.
+    Builtin,
+    InvalidName(String),
+    /// A var or proc with the new name is already visible where the renamed
+    /// one is.
+    Collision { path: String, location: Location },
+}
+
+impl fmt::Display for RenameError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RenameError::NotVarOrProc => f.write_str("only vars and procs can be renamed"),
+            RenameError::Builtin => f.write_str("builtin vars and procs can't be renamed"),
+            RenameError::InvalidName(name) => write!(f, "{:?} is not a valid name", name),
+            RenameError::Collision { path, .. } => write!(f, "{} already exists", path),
+        }
+    }
+}
+
+/// Collect the edits to rename a var or proc, reading the source of each file
+/// which has an occurrence.
+pub fn plan_rename(
+    objtree: &ObjectTree,
+    declaration: &Declaration,
+    new_name: &str,
+    source: &mut dyn FnMut(FileId) -> Option<String>,
+) -> Result<RenamePlan, RenameError> {
+    let (ty, proc) = match declaration {
+        Declaration::Var(ty, _) => (*ty, false),
+        Declaration::Proc(ty, _) => (*ty, true),
+        Declaration::Type(_) => return Err(RenameError::NotVarOrProc),
+    };
+    let old_name = declaration.name();
+    let declared_at = declaration.location();
+    if declared_at.is_builtins() {
+        return Err(RenameError::Builtin);
+    }
+    if !is_identifier(new_name) {
+        return Err(RenameError::InvalidName(new_name.to_owned()));
+    }
+    if let Some(error) = collision(objtree, declaring_type(ty, old_name, proc).unwrap_or(ty), new_name, proc) {
+        return Err(error);
+    }
+
+    let index = index_definitions(objtree, None);
+    let options = ReferenceOptions { overrides: true, unresolved: true };
+    // Declarations and overrides are found from where their name ends.
+    let mut found = vec![(declared_at, EditKind::Declaration, true)];
+    for reference in find_references(objtree, &index, declaration, options) {
+        let occurrence = index.at(reference.location);
+        if occurrence.is_some_and(|each| each.name == "." || each.name == "..") {
+            // `.()` and `..()` don't name the proc.
+            continue;
+        }
+        found.push(match reference.kind {
+            ReferenceKind::Resolved => (reference.location, EditKind::Reference, false),
+            ReferenceKind::Override if occurrence.is_some() => (reference.location, EditKind::Reference, false),
+            ReferenceKind::Override => (reference.location, EditKind::Override, true),
+            ReferenceKind::Unresolved => (reference.location, EditKind::Colon, false),
+        });
+    }
+    for location in string_literals(objtree, old_name) {
+        found.push((location, EditKind::String, false));
+    }
+
+    let mut sources = HashMap::new();
+    let mut plan = RenamePlan::default();
+    for (location, kind, backward) in found {
+        let text = sources.entry(location.file).or_insert_with(|| source(location.file));
+        match text.as_deref().and_then(|text| find_name(text, location, old_name, backward)) {
+            Some(range) => plan.edits.push(Edit {
+                location,
+                range,
+                replacement: new_name.to_owned(),
+                kind,
+            }),
+            None => plan.missing.push(location),
+        }
+    }
+    // The same name may be found from more than one occurrence.
+    plan.edits.sort_by(|a, b| a.location.file.cmp(&b.location.file)
+        .then(a.range.start.cmp(&b.range.start))
+        .then(a.kind.cmp(&b.kind)));
+    plan.edits.dedup_by(|a, b| a.location.file == b.location.file && a.range == b.range);
+    for edit in plan.edits.iter_mut() {
+        edit.location = location_of(sources[&edit.location.file].as_deref().unwrap_or(""), edit.location.file, edit.range.start);
+    }
+    plan.missing.sort();
+    Ok(plan)
+}
+
+/// Apply the edits for one file to its text, which must still have the old
+/// name where each edit is.
+///
+/// Returns the location of the first edit which overlaps another or whose
+/// text has changed, if any, in which case nothing should be written.
+pub fn apply_edits(text: &str, old_name: &str, edits: &[&Edit]) -> Result<String, Location> {
+    let mut result = String::with_capacity(text.len());
+    let mut copied = 0;
+    for edit in edits {
+        if edit.range.start < copied || text.get(edit.range.clone()) != Some(old_name) {
+            return Err(edit.location);
+        }
+        result.push_str(&text[copied..edit.range.start]);
+        result.push_str(&edit.replacement);
+        copied = edit.range.end;
+    }
+    result.push_str(&text[copied..]);
+    Ok(result)
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|first| first.is_ascii_alphabetic() || first == '_')
+        && chars.all(|each| each.is_ascii_alphanumeric() || each == '_')
+}
+
+/// A var or proc with the new name which the declaring type, its ancestors,
+/// or its subtypes already have.
+fn collision(objtree: &ObjectTree, declaring: TypeRef, new_name: &str, proc: bool) -> Option<RenameError> {
+    let kind = if proc { "proc" } else { "var" };
+    let existing = |ty: TypeRef| if proc {
+        ty.get().procs.get(new_name).map(|proc| proc.main_value().location)
+    } else {
+        ty.get().vars.get(new_name).map(|var| var.value.location)
+    };
+    let mut current = Some(declaring);
+    while let Some(ty) = current {
+        if let Some(location) = existing(ty) {
+            return Some(RenameError::Collision { path: format!("{}/{}/{}", ty.get().path, kind, new_name), location });
+        }
+        current = ty.parent_type();
+    }
+    objtree.iter_types()
+        .filter(|ty| ty.is_subtype_of(&declaring))
+        .find_map(|ty| existing(ty).map(|location| RenameError::Collision {
+            path: format!("{}/{}/{}", ty.get().path, kind, new_name),
+            location,
+        }))
+}
+
+/// Every string literal in proc code and var values which is exactly a name.
+fn string_literals(objtree: &ObjectTree, name: &str) -> Vec<Location> {
+    let mut found = Vec::new();
+    let mut check = |expr: &Expression| {
+        visit(expr, &mut |expr| {
+            if let Expression::Base { term, .. } = expr {
+                if matches!(&term.elem, Term::String(value) | Term::RawString(value) if value == name) {
+                    found.push(term.location);
+                }
+            }
+        });
+    };
+    for ty in objtree.iter_types() {
+        for var in ty.get().vars.values() {
+            if let Some(ref expr) = var.value.expression {
+                check(expr);
+            }
+        }
+        for proc in ty.iter_self_procs() {
+            if let Some(ref code) = proc.get().code {
+                let graph = ControlFlowGraph::new(code);
+                for step in graph.blocks.iter().flat_map(|block| block.steps.iter()) {
+                    for expr in step.expressions() {
+                        check(expr);
+                    }
+                }
+            }
+        }
+    }
+    found
+}
+
+/// Find the name as a whole word on the line of a location: the last one
+/// ending by it if `backward`, or the first one starting from it otherwise.
+fn find_name(text: &str, location: Location, name: &str, backward: bool) -> Option<Range<usize>> {
+    let line_start = line_start(text, location.line)?;
+    let line = &text[line_start..];
+    let line = &line[..line.find('\n').unwrap_or(line.len())];
+    let column = (location.column as usize).saturating_sub(1).min(line.len());
+    let is_word_byte = |byte: u8| byte.is_ascii_alphanumeric() || byte == b'_';
+    let bytes = line.as_bytes();
+    let mut matches = line.match_indices(name)
+        .map(|(start, _)| start..start + name.len())
+        .filter(|range| {
+            (range.start == 0 || !is_word_byte(bytes[range.start - 1]))
+                && (range.end == bytes.len() || !is_word_byte(bytes[range.end]))
+        });
+    let range = if backward {
+        matches.filter(|range| range.end <= column).last()?
+    } else {
+        matches.find(|range| range.start >= column)?
+    };
+    Some(line_start + range.start..line_start + range.end)
+}
+
+/// The offset where a line, counting from one, starts.
+fn line_start(text: &str, line: u32) -> Option<usize> {
+    if line <= 1 {
+        return Some(0);
+    }
+    text.match_indices('\n').nth(line as usize - 2).map(|(index, _)| index + 1)
+}
+
+/// The location of an offset into a file.
+fn location_of(text: &str, file: FileId, offset: usize) -> Location {
+    let before = &text[..offset.min(text.len())];
+    let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+    Location {
+        file,
+        line: before.matches('\n').count() as u32 + 1,
+        column: (offset - line_start) as u16 + 1,
+    }
+}
@@ -0,0 +1,88 @@
+//! Verification that a proc returns a value on every path.
+
+use dm::Location;
+use dm::ast::*;
+
+use crate::cfg::{BlockId, ControlFlowGraph, EdgeKind, Step};
+
+/// Find a path from the entry of a proc to its exit which neither returns a
+/// value, assigns to `.`, nor diverges.
+///
+/// Diverging means throwing, deleting `src`, or calling one of the named
+/// procs. Spawned code and runtime errors caught by a `try` are not
+/// considered. The path is returned as the edges taken, in order.
+pub fn find_missing_return(graph: &ControlFlowGraph, never_returns: &[String]) -> Option<Vec<(BlockId, EdgeKind, BlockId)>> {
+    let mut came_from: Vec<Option<(BlockId, EdgeKind)>> = vec![None; graph.blocks.len()];
+    let mut seen = vec![false; graph.blocks.len()];
+    let mut queue = std::collections::VecDeque::new();
+    seen[graph.entry] = true;
+    queue.push_back(graph.entry);
+
+    while let Some(id) = queue.pop_front() {
+        if id == graph.exit {
+            let mut path = Vec::new();
+            let mut current = id;
+            while let Some((from, kind)) = came_from[current] {
+                path.push((from, kind, current));
+                current = from;
+            }
+            path.reverse();
+            return Some(path);
+        }
+        if graph.blocks[id].steps.iter().any(|step| settles(step, never_returns)) {
+            continue;
+        }
+        for &(kind, next) in graph.blocks[id].successors.iter() {
+            let follow = match kind {
+                EdgeKind::Spawn | EdgeKind::Exception => false,
+                // Throwing out of the proc diverges, but a catch may not.
+                EdgeKind::Throw => next != graph.exit,
+                _ => true,
+            };
+            if follow && !seen[next] {
+                seen[next] = true;
+                came_from[next] = Some((id, kind));
+                queue.push_back(next);
+            }
+        }
+    }
+    None
+}
+
+/// The last condition on a path and whether it held, for explaining how the
+/// path is taken.
+pub fn last_condition(graph: &ControlFlowGraph, path: &[(BlockId, EdgeKind, BlockId)]) -> Option<(Location, bool)> {
+    path.iter().rev().find_map(|&(from, kind, _)| {
+        let truthy = match kind {
+            EdgeKind::True => true,
+            EdgeKind::False => false,
+            _ => return None,
+        };
+        match graph.blocks[from].steps.last() {
+            Some(Step::Expression(location, _)) => Some((*location, truthy)),
+            _ => None,
+        }
+    })
+}
+
+/// Whether a step returns a value or never finishes.
+fn settles(step: &Step, never_returns: &[String]) -> bool {
+    let statement = match step {
+        Step::Statement(_, statement) => statement,
+        _ => return false,
+    };
+    match statement {
+        Statement::Return(Some(_)) => true,
+        Statement::Expr(Expression::AssignOp { lhs, .. }) => is_ident(lhs, "."),
+        Statement::Del(expr) => is_ident(expr, "src"),
+        Statement::Expr(expr) => match expr.as_term() {
+            Some(Term::Call(name, _)) => never_returns.iter().any(|each| each == name.as_str()),
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+fn is_ident(expr: &Expression, name: &str) -> bool {
+    matches!(expr.as_term(), Some(Term::Ident(ident)) if ident == name)
+}
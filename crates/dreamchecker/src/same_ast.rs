@@ -0,0 +1,184 @@
+//! Structural equality of syntax trees.
+//!
+//! Two pieces of code are the same if they are written the same way, apart
+//! from where they are and from parentheses around expressions. Locations are
+//! already skipped by `Spanned`'s equality, but parentheses are kept by the
+//! parser, so `if (a)` and `else if ((a))` would otherwise differ. Statements
+//! are compared through the blocks nested in them; the parts of statements
+//! which aren't expressions or blocks, such as the declared type of a `var`,
+//! are compared exactly.
+
+use dm::ast::*;
+
+/// The expression inside any parentheses.
+pub fn unparenthesize(mut expr: &Expression) -> &Expression {
+    while let Some(Term::Expr(inner)) = expr.as_term() {
+        expr = inner;
+    }
+    expr
+}
+
+/// Whether two expressions are the same, apart from parentheses.
+pub fn same_expression(a: &Expression, b: &Expression) -> bool {
+    match (unparenthesize(a), unparenthesize(b)) {
+        (Expression::Base { term: term_a, follow: follow_a }, Expression::Base { term: term_b, follow: follow_b }) => {
+            same_term(&term_a.elem, &term_b.elem)
+                && follow_a.len() == follow_b.len()
+                && follow_a.iter().zip(follow_b.iter()).all(|(a, b)| same_follow(&a.elem, &b.elem))
+        }
+        (Expression::BinaryOp { op: op_a, lhs: lhs_a, rhs: rhs_a }, Expression::BinaryOp { op: op_b, lhs: lhs_b, rhs: rhs_b }) => {
+            op_a == op_b && same_expression(lhs_a, lhs_b) && same_expression(rhs_a, rhs_b)
+        }
+        (Expression::AssignOp { op: op_a, lhs: lhs_a, rhs: rhs_a }, Expression::AssignOp { op: op_b, lhs: lhs_b, rhs: rhs_b }) => {
+            op_a == op_b && same_expression(lhs_a, lhs_b) && same_expression(rhs_a, rhs_b)
+        }
+        (Expression::TernaryOp { cond: cond_a, if_: if_a, else_: else_a }, Expression::TernaryOp { cond: cond_b, if_: if_b, else_: else_b }) => {
+            same_expression(cond_a, cond_b) && same_expression(if_a, if_b) && same_expression(else_a, else_b)
+        }
+        _ => false,
+    }
+}
+
+/// Whether two blocks have the same statements.
+pub fn same_block(a: &[Spanned<Statement>], b: &[Spanned<Statement>]) -> bool {
+    a.len() == b.len() && a.iter().zip(b.iter()).all(|(a, b)| same_statement(&a.elem, &b.elem))
+}
+
+/// Whether two statements are the same, apart from parentheses in their
+/// expressions.
+pub fn same_statement(a: &Statement, b: &Statement) -> bool {
+    match (a, b) {
+        (Statement::Expr(a), Statement::Expr(b)) |
+        (Statement::Throw(a), Statement::Throw(b)) |
+        (Statement::Del(a), Statement::Del(b)) => same_expression(a, b),
+        (Statement::Return(a), Statement::Return(b)) |
+        (Statement::Crash(a), Statement::Crash(b)) => same_optional(a.as_ref(), b.as_ref()),
+        (Statement::While { condition: cond_a, block: block_a }, Statement::While { condition: cond_b, block: block_b }) => {
+            same_expression(cond_a, cond_b) && same_block(block_a, block_b)
+        }
+        (Statement::DoWhile { block: block_a, condition: cond_a }, Statement::DoWhile { block: block_b, condition: cond_b }) => {
+            same_block(block_a, block_b) && same_expression(&cond_a.elem, &cond_b.elem)
+        }
+        (Statement::If { arms: arms_a, else_arm: else_a }, Statement::If { arms: arms_b, else_arm: else_b }) => {
+            arms_a.len() == arms_b.len()
+                && arms_a.iter().zip(arms_b.iter()).all(|((cond_a, block_a), (cond_b, block_b))| {
+                    same_expression(&cond_a.elem, &cond_b.elem) && same_block(block_a, block_b)
+                })
+                && same_optional_block(else_a.as_deref(), else_b.as_deref())
+        }
+        (Statement::ForInfinite { block: a }, Statement::ForInfinite { block: b }) => same_block(a, b),
+        (Statement::ForList(a), Statement::ForList(b)) => {
+            a.var_type == b.var_type
+                && a.name == b.name
+                && a.input_type == b.input_type
+                && same_optional(a.in_list.as_ref(), b.in_list.as_ref())
+                && same_block(&a.block, &b.block)
+        }
+        (Statement::ForRange(a), Statement::ForRange(b)) => {
+            a.var_type == b.var_type
+                && a.name == b.name
+                && same_expression(&a.start, &b.start)
+                && same_expression(&a.end, &b.end)
+                && same_optional(a.step.as_ref(), b.step.as_ref())
+                && same_block(&a.block, &b.block)
+        }
+        (Statement::Spawn { delay: delay_a, block: block_a }, Statement::Spawn { delay: delay_b, block: block_b }) => {
+            same_optional(delay_a.as_ref(), delay_b.as_ref()) && same_block(block_a, block_b)
+        }
+        (Statement::Switch { input: input_a, cases: cases_a, default: default_a }, Statement::Switch { input: input_b, cases: cases_b, default: default_b }) => {
+            same_expression(input_a, input_b)
+                && cases_a.len() == cases_b.len()
+                && cases_a.iter().zip(cases_b.iter()).all(|((case_a, block_a), (case_b, block_b))| {
+                    case_a.elem == case_b.elem && same_block(block_a, block_b)
+                })
+                && same_optional_block(default_a.as_deref(), default_b.as_deref())
+        }
+        (
+            Statement::TryCatch { try_block: try_a, catch_params: params_a, catch_block: catch_a },
+            Statement::TryCatch { try_block: try_b, catch_params: params_b, catch_block: catch_b },
+        ) => same_block(try_a, try_b) && params_a == params_b && same_block(catch_a, catch_b),
+        (Statement::Label { name: name_a, block: block_a }, Statement::Label { name: name_b, block: block_b }) => {
+            name_a == name_b && same_block(block_a, block_b)
+        }
+        _ => a == b,
+    }
+}
+
+fn same_term(a: &Term, b: &Term) -> bool {
+    match (a, b) {
+        (Term::Expr(a), Term::Expr(b)) => same_expression(a, b),
+        (Term::InterpString(first_a, parts_a), Term::InterpString(first_b, parts_b)) => {
+            first_a == first_b
+                && parts_a.len() == parts_b.len()
+                && parts_a.iter().zip(parts_b.iter()).all(|((expr_a, text_a), (expr_b, text_b))| {
+                    text_a == text_b && same_optional(expr_a.as_ref(), expr_b.as_ref())
+                })
+        }
+        (Term::Call(name_a, args_a), Term::Call(name_b, args_b)) => name_a == name_b && same_all(args_a, args_b),
+        (Term::SelfCall(a), Term::SelfCall(b)) |
+        (Term::ParentCall(a), Term::ParentCall(b)) |
+        (Term::List(a), Term::List(b)) => same_all(a, b),
+        (Term::NewImplicit { args: a }, Term::NewImplicit { args: b }) => same_optional_all(a.as_deref(), b.as_deref()),
+        (Term::NewPrefab { prefab: prefab_a, args: args_a }, Term::NewPrefab { prefab: prefab_b, args: args_b }) => {
+            prefab_a == prefab_b && same_optional_all(args_a.as_deref(), args_b.as_deref())
+        }
+        (Term::NewMiniExpr { expr: expr_a, args: args_a }, Term::NewMiniExpr { expr: expr_b, args: args_b }) => {
+            expr_a == expr_b && same_optional_all(args_a.as_deref(), args_b.as_deref())
+        }
+        (
+            Term::Input { args: args_a, input_type: type_a, in_list: list_a },
+            Term::Input { args: args_b, input_type: type_b, in_list: list_b },
+        ) => same_all(args_a, args_b) && type_a == type_b && same_optional(list_a.as_deref(), list_b.as_deref()),
+        (Term::Locate { args: args_a, in_list: list_a }, Term::Locate { args: args_b, in_list: list_b }) => {
+            same_all(args_a, args_b) && same_optional(list_a.as_deref(), list_b.as_deref())
+        }
+        (Term::Pick(a), Term::Pick(b)) => {
+            a.len() == b.len()
+                && a.iter().zip(b.iter()).all(|((weight_a, value_a), (weight_b, value_b))| {
+                    same_optional(weight_a.as_ref(), weight_b.as_ref()) && same_expression(value_a, value_b)
+                })
+        }
+        (Term::DynamicCall(first_a, second_a), Term::DynamicCall(first_b, second_b)) => {
+            same_all(first_a, first_b) && same_all(second_a, second_b)
+        }
+        _ => a == b,
+    }
+}
+
+fn same_follow(a: &Follow, b: &Follow) -> bool {
+    match (a, b) {
+        (Follow::Index(kind_a, a), Follow::Index(kind_b, b)) => kind_a == kind_b && same_expression(a, b),
+        (Follow::Call(kind_a, name_a, args_a), Follow::Call(kind_b, name_b, args_b)) => {
+            kind_a == kind_b && name_a == name_b && same_all(args_a, args_b)
+        }
+        _ => a == b,
+    }
+}
+
+fn same_all(a: &[Expression], b: &[Expression]) -> bool {
+    a.len() == b.len() && a.iter().zip(b.iter()).all(|(a, b)| same_expression(a, b))
+}
+
+fn same_optional(a: Option<&Expression>, b: Option<&Expression>) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => same_expression(a, b),
+        (None, None) => true,
+        _ => false,
+    }
+}
+
+fn same_optional_all(a: Option<&[Expression]>, b: Option<&[Expression]>) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => same_all(a, b),
+        (None, None) => true,
+        _ => false,
+    }
+}
+
+fn same_optional_block(a: Option<&[Spanned<Statement>]>, b: Option<&[Spanned<Statement>]>) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => same_block(a, b),
+        (None, None) => true,
+        _ => false,
+    }
+}
@@ -0,0 +1,414 @@
+//! Classifying the tokens of a file by what they name, such as for editor
+//! semantic highlighting.
+//!
+//! The file is lexed again to find each token's span. Names are classified
+//! by the parser's annotations, the declarations in the object tree, and what
+//! the analysis of the file's procs resolved them to, so annotations must be
+//! enabled when parsing, including the preprocessor's for macro uses.
+
+use std::collections::HashMap;
+
+use dm::{Context, FileId, Location};
+use dm::annotation::{Annotation, AnnotationTree};
+use dm::ast::{Expression, Follow, Statement, Term, UnaryOp, VarTypeFlags};
+use dm::lexer::{Lexer, Punctuation, Token};
+use dm::objtree::ObjectTree;
+
+use crate::cfg::{ControlFlowGraph, Step};
+use crate::definitions::{index_definitions, DefinitionKind};
+use crate::visit::visit;
+
+/// What a token is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TokenKind {
+    /// A part of a type path, as in `/obj/item` or `var/mob/M`.
+    TypePath,
+    /// The name of a proc where it's defined or overridden.
+    ProcDefinition,
+    /// The name of a proc being called or referred to.
+    ProcCall,
+    /// The name of a var where it's declared, including locals and
+    /// parameters.
+    VarDeclaration,
+    LocalRead,
+    /// A local being assigned to, incremented, or decremented.
+    LocalWrite,
+    /// A var of a value or of `src`, including overridden values.
+    Field,
+    /// The name of a macro where it's expanded.
+    Macro,
+    /// The expression embedded in an interpolated string, between its
+    /// brackets. Its tokens are classified too.
+    Interpolation,
+    /// A number, string, resource, or `null`.
+    Constant,
+}
+
+impl TokenKind {
+    /// Every kind, in the order they are numbered by the LSP encoding.
+    pub const ALL: [TokenKind; 10] = [
+        TokenKind::TypePath,
+        TokenKind::ProcDefinition,
+        TokenKind::ProcCall,
+        TokenKind::VarDeclaration,
+        TokenKind::LocalRead,
+        TokenKind::LocalWrite,
+        TokenKind::Field,
+        TokenKind::Macro,
+        TokenKind::Interpolation,
+        TokenKind::Constant,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            TokenKind::TypePath => "type_path",
+            TokenKind::ProcDefinition => "proc_definition",
+            TokenKind::ProcCall => "proc_call",
+            TokenKind::VarDeclaration => "var_declaration",
+            TokenKind::LocalRead => "local_read",
+            TokenKind::LocalWrite => "local_write",
+            TokenKind::Field => "field",
+            TokenKind::Macro => "macro",
+            TokenKind::Interpolation => "interpolation",
+            TokenKind::Constant => "constant",
+        }
+    }
+}
+
+/// A classified span of a file, on one line.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SemanticToken {
+    pub location: Location,
+    /// How many bytes long it is.
+    pub length: usize,
+    pub kind: TokenKind,
+}
+
+/// Classify the tokens of a file, given its text and annotations, in order of
+/// location.
+///
+/// Diagnostics raised while lexing and analyzing the file are discarded.
+pub fn semantic_tokens(objtree: &ObjectTree, annotations: &AnnotationTree, file: FileId, text: &str) -> Vec<SemanticToken> {
+    let scratch = Context::default();
+    let tokens: Vec<(Location, Token)> = Lexer::new(&scratch, file, text.as_bytes())
+        .filter(|each| !matches!(each.token,
+            Token::Punct(Punctuation::Tab) |
+            Token::Punct(Punctuation::Space) |
+            Token::Punct(Punctuation::Newline) |
+            Token::DocComment(_)))
+        .map(|each| (each.location, each.token))
+        .collect();
+    let lines = Lines::new(text);
+
+    let mut macro_uses = Vec::new();
+    let mut in_sequence = Vec::new();
+    for (range, annotation) in annotations.iter() {
+        if range.start.file == file {
+            match annotation {
+                Annotation::MacroUse(..) => macro_uses.push(range.start),
+                Annotation::InSequence(_) => in_sequence.push(range.start),
+                _ => {}
+            }
+        }
+    }
+
+    // Declarations are found by name near where they're recorded: before for
+    // those in the object tree, which are recorded after their name, and
+    // usually after for those in proc code.
+    let mut classified: HashMap<usize, TokenKind> = HashMap::new();
+    let mut classify = |location: Location, name: &str, after: bool, kind: TokenKind| {
+        let found = find_ident(&tokens, location, name, after).or_else(|| find_ident(&tokens, location, name, !after));
+        if let Some(index) = found {
+            classified.entry(index).or_insert(kind);
+        }
+    };
+    let mut writes = Vec::new();
+    for ty in objtree.iter_types() {
+        for (name, var) in ty.get().vars.iter() {
+            if var.value.location.file == file {
+                let declared = var.declaration.as_ref().is_some_and(|decl| decl.location == var.value.location);
+                let kind = if declared { TokenKind::VarDeclaration } else { TokenKind::Field };
+                classify(var.value.location, name, false, kind);
+            }
+        }
+        for (name, proc) in ty.get().procs.iter() {
+            for value in proc.value.iter().filter(|value| value.location.file == file) {
+                classify(value.location, name, false, TokenKind::ProcDefinition);
+                for param in value.parameters.iter() {
+                    classify(param.location, &param.name, true, TokenKind::VarDeclaration);
+                }
+                let code = match value.code {
+                    Some(ref code) => code,
+                    None => continue,
+                };
+                for step in ControlFlowGraph::new(code).blocks.iter().flat_map(|block| block.steps.iter()) {
+                    for (name, declares) in step_locals(step) {
+                        let kind = if declares { TokenKind::VarDeclaration } else { TokenKind::LocalWrite };
+                        classify(step.location(), name, true, kind);
+                    }
+                    for expr in step.expressions() {
+                        visit(expr, &mut |expr| if let Some(location) = written_local(expr) {
+                            writes.push(location);
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    let index = index_definitions(objtree, Some(file));
+    let mut result = Vec::new();
+    let mut interpolations = Vec::new();
+    let mut string_blocks = Vec::new();
+    for (i, (location, token)) in tokens.iter().enumerate() {
+        let location = *location;
+        let offset = match lines.offset(location) {
+            Some(offset) => offset,
+            None => continue,
+        };
+        let punct = |index: Option<usize>| match index.and_then(|index| tokens.get(index)) {
+            Some((_, Token::Punct(punct))) => Some(*punct),
+            _ => None,
+        };
+        let (end, kind) = match token {
+            Token::Ident(name, _) => {
+                let previous = punct(i.checked_sub(1));
+                let after = punct(Some(i + 1));
+                let kind = if macro_uses.contains(&location) {
+                    Some(TokenKind::Macro)
+                } else if let Some(&kind) = classified.get(&i) {
+                    Some(kind)
+                } else if is_follow(previous) {
+                    if (name == "proc" || name == "verb") && after == Some(Punctuation::Slash) {
+                        None
+                    } else if after == Some(Punctuation::LParen) {
+                        Some(TokenKind::ProcCall)
+                    } else {
+                        Some(TokenKind::Field)
+                    }
+                } else if previous == Some(Punctuation::Slash) && is_proc_reference(&tokens, i) {
+                    Some(TokenKind::ProcCall)
+                } else if let Some(occurrence) = index.at(location).filter(|each| !each.is_field && each.name == *name) {
+                    match occurrence.kind {
+                        DefinitionKind::Local if writes.contains(&location) => Some(TokenKind::LocalWrite),
+                        DefinitionKind::Local => Some(TokenKind::LocalRead),
+                        DefinitionKind::Var => Some(TokenKind::Field),
+                        DefinitionKind::Proc => Some(TokenKind::ProcCall),
+                        DefinitionKind::Type => Some(TokenKind::TypePath),
+                        DefinitionKind::Unresolved if after == Some(Punctuation::LParen) => Some(TokenKind::ProcCall),
+                        DefinitionKind::Unresolved => None,
+                    }
+                } else if in_sequence.contains(&location) && !is_path_keyword(name) {
+                    Some(TokenKind::TypePath)
+                } else if name == "null" {
+                    Some(TokenKind::Constant)
+                } else {
+                    None
+                };
+                (offset + name.len(), kind)
+            }
+            Token::Int(_) | Token::Float(_) => (number_end(text, offset), Some(TokenKind::Constant)),
+            Token::String(_) => (string_end(text, offset, &mut string_blocks).0, Some(TokenKind::Constant)),
+            Token::RawString(value) => (raw_string_end(text, offset, value), Some(TokenKind::Constant)),
+            Token::Resource(_) => (text[offset + 1..].find('\'').map_or(text.len(), |end| offset + end + 2), Some(TokenKind::Constant)),
+            Token::InterpStringBegin(_) | Token::InterpStringPart(_) | Token::InterpStringEnd(_) => {
+                if !matches!(token, Token::InterpStringBegin(_)) {
+                    if let Some(start) = interpolations.pop() {
+                        if let (Some(start_location), true) = (lines.location(file, start), start < offset) {
+                            result.push(SemanticToken { location: start_location, length: offset - start, kind: TokenKind::Interpolation });
+                        }
+                    }
+                }
+                let (end, interpolates) = string_end(text, offset, &mut string_blocks);
+                if interpolates {
+                    interpolations.push(end);
+                }
+                (end, Some(TokenKind::Constant))
+            }
+            _ => continue,
+        };
+        if let Some(kind) = kind {
+            // Tokens don't span lines.
+            let length = end.min(lines.end_of_line(offset)).saturating_sub(offset);
+            result.push(SemanticToken { location, length, kind });
+        }
+    }
+    result.sort_by_key(|token| token.location);
+    result
+}
+
+/// Encode tokens the way LSP's semantic tokens are, as five integers per
+/// token relative to the one before it, numbering kinds as in
+/// `TokenKind::ALL`.
+///
+/// Interpolations are left out, since they contain other tokens.
+pub fn encode_lsp(tokens: &[SemanticToken]) -> Vec<u32> {
+    let mut data = Vec::new();
+    let (mut line, mut column) = (1, 1);
+    for token in tokens.iter().filter(|token| token.kind != TokenKind::Interpolation) {
+        let delta_line = token.location.line - line;
+        let delta_column = if delta_line == 0 { token.location.column - column } else { token.location.column - 1 };
+        let kind = TokenKind::ALL.iter().position(|&kind| kind == token.kind).unwrap_or_default();
+        data.extend_from_slice(&[delta_line, delta_column as u32, token.length as u32, kind as u32, 0]);
+        line = token.location.line;
+        column = token.location.column;
+    }
+    data
+}
+
+/// The offsets where lines start in a file.
+struct Lines {
+    starts: Vec<usize>,
+    len: usize,
+}
+
+impl Lines {
+    fn new(text: &str) -> Lines {
+        let mut starts = vec![0];
+        starts.extend(text.match_indices('\n').map(|(index, _)| index + 1));
+        Lines { starts, len: text.len() }
+    }
+
+    fn offset(&self, location: Location) -> Option<usize> {
+        let start = *self.starts.get((location.line as usize).checked_sub(1)?)?;
+        Some(start + (location.column as usize).saturating_sub(1))
+    }
+
+    fn location(&self, file: FileId, offset: usize) -> Option<Location> {
+        let line = self.starts.partition_point(|&start| start <= offset);
+        Some(Location {
+            file,
+            line: line as u32,
+            column: (offset - self.starts.get(line.checked_sub(1)?)?) as u16 + 1,
+        })
+    }
+
+    fn end_of_line(&self, offset: usize) -> usize {
+        let line = self.starts.partition_point(|&start| start <= offset);
+        self.starts.get(line).map_or(self.len, |next| next - 1)
+    }
+}
+
+/// The identifier token with a name nearest a location on its line: the
+/// first one at or after it if `after`, or the last one before it otherwise.
+fn find_ident(tokens: &[(Location, Token)], location: Location, name: &str, after: bool) -> Option<usize> {
+    let is_name = |index: &usize| {
+        let (each, ref token) = tokens[*index];
+        each.file == location.file && each.line == location.line && matches!(token, Token::Ident(ident, _) if ident == name)
+    };
+    let split = tokens.partition_point(|(each, _)| *each < location);
+    if after {
+        (split..tokens.len()).take_while(|&index| tokens[index].0.line == location.line).find(is_name)
+    } else {
+        (0..split).rev().take_while(|&index| tokens[index].0.line == location.line).find(is_name)
+    }
+}
+
+/// The locals a step names without them being an expression: those it
+/// declares, and the loop var of a `for` loop which reuses a local.
+fn step_locals<'a>(step: &Step<'a>) -> Vec<(&'a str, bool)> {
+    match *step {
+        Step::Statement(_, Statement::Var(var)) => vec![(&var.name, true)],
+        Step::Statement(_, Statement::Vars(vars)) => vars.iter().map(|var| (var.name.as_str(), true)).collect(),
+        Step::Header(_, Statement::ForList(for_list)) => vec![(&for_list.name, for_list.var_type.is_some())],
+        Step::Header(_, Statement::ForRange(for_range)) => vec![(&for_range.name, for_range.var_type.is_some())],
+        _ => Vec::new(),
+    }
+}
+
+/// The location of the bare name an expression assigns to, increments, or
+/// decrements, if any.
+fn written_local(expr: &Expression) -> Option<Location> {
+    let (term, follow) = match expr {
+        Expression::AssignOp { lhs, .. } => match &**lhs {
+            Expression::Base { term, follow } if follow.is_empty() => (term, None),
+            _ => return None,
+        },
+        // Increments and decrements are follows of their operand.
+        Expression::Base { term, follow } if follow.len() == 1 => (term, Some(&follow[0].elem)),
+        _ => return None,
+    };
+    let writes = match follow {
+        None => true,
+        Some(Follow::Unary(op)) => matches!(op, UnaryOp::PreIncr | UnaryOp::PostIncr | UnaryOp::PreDecr | UnaryOp::PostDecr),
+        Some(_) => false,
+    };
+    match term.elem {
+        Term::Ident(_) if writes => Some(term.location),
+        _ => None,
+    }
+}
+
+fn is_follow(punct: Option<Punctuation>) -> bool {
+    matches!(punct, Some(Punctuation::Dot) | Some(Punctuation::Colon) | Some(Punctuation::CloseColon) | Some(Punctuation::SafeDot) | Some(Punctuation::SafeColon))
+}
+
+/// Whether an identifier after a slash is the name in `.proc/name`.
+fn is_proc_reference(tokens: &[(Location, Token)], index: usize) -> bool {
+    index >= 3
+        && matches!(&tokens[index - 2].1, Token::Ident(name, _) if name == "proc" || name == "verb")
+        && matches!(tokens[index - 3].1, Token::Punct(Punctuation::Dot))
+}
+
+/// Words in tree paths which aren't types.
+fn is_path_keyword(name: &str) -> bool {
+    matches!(name, "var" | "proc" | "verb") || VarTypeFlags::from_name(name).is_some()
+}
+
+fn number_end(text: &str, offset: usize) -> usize {
+    let bytes = text.as_bytes();
+    let mut end = offset;
+    while end < bytes.len() {
+        let byte = bytes[end];
+        let exponent = (byte == b'+' || byte == b'-') && end > offset && matches!(bytes[end - 1], b'e' | b'E');
+        if !(byte.is_ascii_alphanumeric() || byte == b'.' || byte == b'#' || exponent) {
+            break;
+        }
+        end += 1;
+    }
+    end
+}
+
+/// Where a string literal, or the piece of one which starts at a `]` ending
+/// an interpolation, ends, and whether it ends by starting an interpolation.
+///
+/// Whether each string being interpolated into is a `{"` block string is
+/// kept in `blocks`.
+fn string_end(text: &str, offset: usize, blocks: &mut Vec<bool>) -> (usize, bool) {
+    let bytes = text.as_bytes();
+    let (block, mut index) = match bytes.get(offset) {
+        Some(b'{') => (true, offset + 2),
+        Some(b']') => (blocks.pop().unwrap_or(false), offset + 1),
+        _ => (false, offset + 1),
+    };
+    while index < bytes.len() {
+        match bytes[index] {
+            b'\\' => index += 1,
+            b'[' => {
+                blocks.push(block);
+                return (index + 1, true);
+            }
+            b'"' if !block => return (index + 1, false),
+            b'"' if bytes.get(index + 1) == Some(&b'}') => return (index + 2, false),
+            b'\n' if !block => return (index, false),
+            _ => {}
+        }
+        index += 1;
+    }
+    (bytes.len(), false)
+}
+
+fn raw_string_end(text: &str, offset: usize, value: &str) -> usize {
+    let rest = &text[offset..];
+    let end = if rest.starts_with("@{\"") {
+        3 + value.len() + 2
+    } else if rest.starts_with("@(") {
+        match rest.find(')') {
+            Some(close) => close + 1 + value.len() + (close - 2),
+            None => rest.len(),
+        }
+    } else {
+        2 + value.len() + 1
+    };
+    (offset + end).min(text.len())
+}
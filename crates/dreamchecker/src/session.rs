@@ -0,0 +1,210 @@
+//! A resident checking session, which keeps the results of checking an
+//! environment and reports them per file as they change.
+//!
+//! The environment is parsed and checked again only when one of its files has
+//! changed on disk since the last check, so asking about files while nothing
+//! has changed is cheap. Each report covers the files asked about, along with
+//! any other file whose diagnostics differ from what was last reported for it,
+//! such as when a change to a header affects other files.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use dm::{Context, DMError, Severity};
+
+/// A diagnostic, with its location numbered from one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub line: u32,
+    pub column: u16,
+    pub severity: Severity,
+    /// The errortype the diagnostic can be configured by, if any.
+    pub code: Option<&'static str>,
+    /// What raised the diagnostic, as in `dreamchecker`.
+    pub source: &'static str,
+    pub message: String,
+    pub related: Vec<Related>,
+}
+
+/// A note attached to a diagnostic, which may be in another file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Related {
+    pub path: PathBuf,
+    pub line: u32,
+    pub column: u16,
+    pub message: String,
+}
+
+/// The diagnostics of one file, relative to the `.dme`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileDiagnostics {
+    pub path: PathBuf,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// The result of a check.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Report {
+    /// Whether the environment was parsed and checked again.
+    pub reanalyzed: bool,
+    pub files: Vec<FileDiagnostics>,
+}
+
+/// The size and modification time of a file, or `None` if it's missing.
+type Stamp = Option<(SystemTime, u64)>;
+
+/// The diagnostics of an environment as of when its files had some stamps.
+struct Checked {
+    /// The stamp of every file that was read, by its path on disk.
+    stamps: Vec<(PathBuf, Stamp)>,
+    diagnostics: BTreeMap<PathBuf, Vec<Diagnostic>>,
+}
+
+/// A resident session checking one environment.
+pub struct Session {
+    dme: PathBuf,
+    config: Option<PathBuf>,
+    checked: Option<Checked>,
+    /// What was last reported for each file.
+    reported: HashMap<PathBuf, Vec<Diagnostic>>,
+}
+
+impl Session {
+    /// Start a session for a `.dme`, with a configuration file or the one
+    /// next to it. Nothing is parsed until the first check.
+    pub fn new(dme: PathBuf, config: Option<PathBuf>) -> Session {
+        Session {
+            dme,
+            config,
+            checked: None,
+            reported: Default::default(),
+        }
+    }
+
+    /// Report the diagnostics of some files, checking the environment again
+    /// first if any of its files have changed.
+    ///
+    /// Files may be given relative to the `.dme` or the current directory.
+    /// Files which aren't part of the environment are reported as having no
+    /// diagnostics.
+    pub fn check(&mut self, files: &[PathBuf]) -> Result<Report, DMError> {
+        let reanalyzed = match self.checked {
+            Some(ref checked) if !checked.is_stale() => false,
+            _ => {
+                self.checked = Some(self.analyze()?);
+                true
+            }
+        };
+        let requested: Vec<_> = files.iter().map(|file| self.relative(file)).collect();
+        Ok(self.report(reanalyzed, requested))
+    }
+
+    /// Check the whole environment again, whether or not anything changed,
+    /// and report every file whose diagnostics differ from what was last
+    /// reported for it.
+    pub fn recheck(&mut self) -> Result<Report, DMError> {
+        self.checked = Some(self.analyze()?);
+        Ok(self.report(true, Vec::new()))
+    }
+
+    fn root(&self) -> &Path {
+        self.dme.parent().unwrap_or_else(|| Path::new(""))
+    }
+
+    fn relative(&self, file: &Path) -> PathBuf {
+        file.strip_prefix(self.root()).unwrap_or(file).to_owned()
+    }
+
+    /// Parse and check the environment from scratch.
+    fn analyze(&self) -> Result<Checked, DMError> {
+        let context = Context::default();
+        match self.config {
+            Some(ref config) => context.force_config(config),
+            None => context.autodetect_config(&self.dme),
+        }
+        let mut pp = dm::preprocessor::Preprocessor::new(&context, self.dme.clone())?;
+        let indents = dm::indents::IndentProcessor::new(&context, &mut pp);
+        let mut parser = dm::parser::Parser::new(&context, indents);
+        parser.enable_procs();
+        let (fatal_errored, tree) = parser.parse_object_tree_2();
+        crate::check_macro_names(&context, &pp.finalize());
+        if !fatal_errored {
+            crate::run(&context, &tree);
+        }
+
+        // Included files are registered relative to the `.dme`, but it isn't.
+        let mut stamps = Vec::new();
+        context.file_list().for_each(|path| {
+            let path = if path == self.dme { path.to_owned() } else { self.root().join(path) };
+            let stamp = stamp(&path);
+            stamps.push((path, stamp));
+        });
+        let mut diagnostics: BTreeMap<PathBuf, Vec<Diagnostic>> = BTreeMap::new();
+        for error in context.errors().iter() {
+            diagnostics.entry(context.file_path(error.location().file))
+                .or_default()
+                .push(diagnostic(&context, error));
+        }
+        Ok(Checked { stamps, diagnostics })
+    }
+
+    /// Report the requested files and every other file whose diagnostics
+    /// changed, remembering what was reported.
+    fn report(&mut self, reanalyzed: bool, mut paths: Vec<PathBuf>) -> Report {
+        let current = match self.checked {
+            Some(ref checked) => &checked.diagnostics,
+            None => return Report { reanalyzed, files: Vec::new() },
+        };
+        for (path, diagnostics) in current.iter() {
+            if self.reported.get(path) != Some(diagnostics) {
+                paths.push(path.clone());
+            }
+        }
+        // Files which had diagnostics and no longer do must be cleared.
+        for (path, diagnostics) in self.reported.iter() {
+            if !diagnostics.is_empty() && !current.contains_key(path) {
+                paths.push(path.clone());
+            }
+        }
+        paths.sort();
+        paths.dedup();
+
+        let mut files = Vec::new();
+        for path in paths {
+            let diagnostics = current.get(&path).cloned().unwrap_or_default();
+            self.reported.insert(path.clone(), diagnostics.clone());
+            files.push(FileDiagnostics { path, diagnostics });
+        }
+        Report { reanalyzed, files }
+    }
+}
+
+impl Checked {
+    fn is_stale(&self) -> bool {
+        self.stamps.iter().any(|(path, old)| stamp(path) != *old)
+    }
+}
+
+fn stamp(path: &Path) -> Stamp {
+    let metadata = std::fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+fn diagnostic(context: &Context, error: &DMError) -> Diagnostic {
+    let location = error.location();
+    Diagnostic {
+        line: location.line,
+        column: location.column,
+        severity: error.severity(),
+        code: error.errortype(),
+        source: error.component().name().unwrap_or("dreammaker"),
+        message: error.description().to_owned(),
+        related: error.notes().iter().map(|note| Related {
+            path: context.file_path(note.location().file),
+            line: note.location().line,
+            column: note.location().column,
+            message: note.description().to_owned(),
+        }).collect(),
+    }
+}
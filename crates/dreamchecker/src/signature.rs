@@ -0,0 +1,165 @@
+//! Queries for the signature of the proc being called at a position, such as
+//! for editor signature help.
+//!
+//! Which argument list contains the position is found from the parser's
+//! annotations. The proc containing it is analyzed again on demand to resolve
+//! the callee the same way the argument checks do.
+
+use dm::{Context, Location};
+use dm::annotation::{Annotation, AnnotationTree};
+use dm::ast::{AssignOp, Expression, FormatTreePath, ProcDeclKind, Term};
+use dm::constants::Constant;
+use dm::objtree::{ObjectTree, ProcRef};
+
+use crate::{AnalyzeObjectTree, AnalyzeProc};
+use crate::hover::proc_at;
+
+/// A parameter of a proc.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParameterInfo {
+    pub name: String,
+    /// The declared type, as in `mob/user`.
+    pub type_path: Option<String>,
+    /// The input type, as in `as mob|obj`.
+    pub input_type: Option<String>,
+    /// The default value, if it has one and it's constant.
+    pub default: Option<Constant>,
+}
+
+/// The proc an argument list is passed to.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Callee {
+    /// A proc the call resolved to, with the signature of the declaration it
+    /// overrides, if any.
+    Proc {
+        /// The path of the declaring proc, as in `/obj/item/proc/attack`.
+        path: String,
+        location: Location,
+        parameters: Vec<ParameterInfo>,
+    },
+    /// A call which couldn't be resolved, such as a colon call or `call()()`.
+    Unknown {
+        name: String,
+    },
+}
+
+/// The signature of a call at a position and which parameter it's at.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SignatureHelp {
+    pub callee: Callee,
+    /// Which argument the position is in, counting from zero.
+    pub argument: usize,
+    /// Which parameter that argument is passed as, if it's known. A named
+    /// argument is passed as the parameter with its name.
+    pub parameter: Option<usize>,
+}
+
+/// Find the signature of the innermost call whose argument list contains a
+/// position, given the annotations of the file it's in.
+///
+/// Diagnostics raised while analyzing the proc again are discarded.
+pub fn signature_help(objtree: &ObjectTree, annotations: &AnnotationTree, location: Location) -> Option<SignatureHelp> {
+    // The innermost argument list is the one starting last.
+    let mut list: Option<(Location, &str, usize)> = None;
+    for (range, annotation) in annotations.get_location(location) {
+        if let Annotation::ProcArguments(_, name, count) = annotation {
+            if list.is_none_or(|(start, ..)| start < range.start) {
+                list = Some((range.start, name, *count));
+            }
+        }
+    }
+    let (start, name, count) = list?;
+    let argument = annotations.get_location(location)
+        .filter(|(range, _)| range.start >= start)
+        .find_map(|(_, annotation)| match annotation {
+            Annotation::ProcArgument(index) => Some(*index),
+            _ => None,
+        })
+        .unwrap_or(count);
+
+    let unknown = SignatureHelp {
+        callee: Callee::Unknown { name: name.to_owned() },
+        argument,
+        parameter: None,
+    };
+    let proc = match proc_at(objtree, location) {
+        Some(proc) => proc,
+        None => return Some(unknown),
+    };
+    let code = match proc.get().code.as_ref() {
+        Some(code) => code,
+        None => return Some(unknown),
+    };
+
+    let scratch = Context::default();
+    let mut env = AnalyzeObjectTree::new(&scratch, objtree);
+    env.gather_all_settings();
+    let mut analyzer = AnalyzeProc::new(&mut env, &scratch, objtree, proc);
+    analyzer.signature = Some(Probe { open_paren: start, name: name.to_owned(), found: None });
+    analyzer.run(code);
+    let (_, callee, args) = match analyzer.signature.take().and_then(|probe| probe.found) {
+        Some(found) => found,
+        None => return Some(unknown),
+    };
+
+    // The signature of an override is the one it has to accept.
+    let mut base = callee;
+    while let Some(parent) = base.parent_proc() {
+        base = parent;
+    }
+    let parameters: Vec<_> = base.get().parameters.iter().map(|param| ParameterInfo {
+        name: param.name.clone(),
+        type_path: Some(FormatTreePath(&param.var_type.type_path).to_string()).filter(|path| !path.is_empty()),
+        input_type: param.input_type.map(|input_type| input_type.to_string()),
+        default: param.default.clone().and_then(|default| default.simple_evaluate(param.location).ok()),
+    }).collect();
+
+    let parameter = match args.get(argument) {
+        Some(Expression::AssignOp { op: AssignOp::Assign, lhs, .. }) => match lhs.as_term() {
+            Some(Term::Ident(name)) |
+            Some(Term::String(name)) |
+            Some(Term::RawString(name)) => parameters.iter().position(|param| param.name == *name),
+            _ => None,
+        },
+        _ => Some(argument).filter(|&index| index < parameters.len()),
+    };
+    let kind = base.ty().get_proc_declaration(base.name()).map_or(ProcDeclKind::Proc, |decl| decl.kind);
+    Some(SignatureHelp {
+        callee: Callee::Proc {
+            path: format!("{}/{}/{}", base.ty().get().path, kind, base.name()),
+            location: base.get().location,
+            parameters,
+        },
+        argument,
+        parameter,
+    })
+}
+
+/// The argument list being queried, and the call it's most likely part of so
+/// far.
+pub(crate) struct Probe<'o> {
+    open_paren: Location,
+    name: String,
+    found: Option<(Location, ProcRef<'o>, &'o [Expression])>,
+}
+
+impl<'o> Probe<'o> {
+    /// Note a call which resolved to a proc.
+    ///
+    /// The call an argument list belongs to is the last one which starts
+    /// before it on the same line, as long as it has the same name.
+    pub(crate) fn record(&mut self, location: Location, proc: ProcRef<'o>, args: &'o [Expression]) {
+        let same_name = match self.name.as_str() {
+            "." | ".." => true,
+            name => proc.name() == name,
+        };
+        if same_name
+            && location.file == self.open_paren.file
+            && location.line == self.open_paren.line
+            && location < self.open_paren
+            && self.found.is_none_or(|(best, ..)| best <= location)
+        {
+            self.found = Some((location, proc, args));
+        }
+    }
+}
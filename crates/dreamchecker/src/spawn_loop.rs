@@ -0,0 +1,104 @@
+//! Detection of `spawn` inside loops which may run many times.
+
+use dm::{Context, DMError, Location, Severity};
+use dm::ast::*;
+
+/// The most iterations a constant `for (var/i in 1 to N)` can make without
+/// its spawns being reported.
+const SMALL_LOOP_BOUND: i32 = 10;
+
+pub fn check_spawn_in_loops(context: &Context, code: &[Spanned<Statement>]) {
+    block(context, code, None);
+}
+
+fn block(context: &Context, code: &[Spanned<Statement>], enclosing: Option<Location>) {
+    for statement in code.iter() {
+        self::statement(context, statement, enclosing);
+    }
+}
+
+fn statement(context: &Context, statement: &Spanned<Statement>, enclosing: Option<Location>) {
+    let location = statement.location;
+    match &statement.elem {
+        Statement::Spawn { delay, block: spawned } => {
+            if let Some(loop_location) = enclosing {
+                let immediate = match delay.as_ref().map(|delay| delay.as_term()) {
+                    None | Some(Some(Term::Null)) => true,
+                    Some(Some(Term::Int(ticks))) => *ticks <= 0,
+                    Some(_) => false,
+                };
+                let message = if immediate {
+                    "spawn inside a loop schedules a separate proc on every iteration, all of which run as soon as this proc sleeps or returns"
+                } else {
+                    "spawn inside a loop schedules a separate proc on every iteration, all of which the scheduler must hold until their delay passes"
+                };
+                DMError::new(location, message)
+                    .with_component(dm::Component::DreamChecker)
+                    .set_severity(Severity::Warning)
+                    .with_note(loop_location, "loop is here")
+                    .with_errortype("spawn_in_loop")
+                    .register(context);
+            }
+            // Spawned code runs once per spawn, whatever loop it's in.
+            block(context, spawned, None);
+        }
+        Statement::While { block: body, .. } |
+        Statement::DoWhile { block: body, .. } |
+        Statement::ForLoop { block: body, .. } |
+        Statement::ForInfinite { block: body } => block(context, body, Some(location)),
+        Statement::ForList(for_list) => block(context, &for_list.block, Some(location)),
+        Statement::ForRange(for_range) => {
+            if is_small_range(for_range) {
+                block(context, &for_range.block, enclosing);
+            } else {
+                block(context, &for_range.block, Some(location));
+            }
+        }
+        Statement::If { arms, else_arm } => {
+            for (_, body) in arms.iter() {
+                block(context, body, enclosing);
+            }
+            if let Some(body) = else_arm {
+                block(context, body, enclosing);
+            }
+        }
+        Statement::Switch { cases, default, .. } => {
+            for (_, body) in cases.iter() {
+                block(context, body, enclosing);
+            }
+            if let Some(body) = default {
+                block(context, body, enclosing);
+            }
+        }
+        Statement::TryCatch { try_block, catch_block, .. } => {
+            block(context, try_block, enclosing);
+            block(context, catch_block, enclosing);
+        }
+        Statement::Label { block: body, .. } => block(context, body, enclosing),
+        _ => {}
+    }
+}
+
+/// Whether a `for (var/i in A to B step C)` loop has literal bounds and runs
+/// only a few times.
+fn is_small_range(for_range: &ForRangeStatement) -> bool {
+    let int = |expr: &Expression| match expr {
+        Expression::Base { term, follow } => match (&term.elem, &follow[..]) {
+            (Term::Int(value), []) => Some(*value),
+            (Term::Int(value), [neg]) if neg.elem == Follow::Unary(UnaryOp::Neg) => Some(-*value),
+            _ => None,
+        },
+        _ => None,
+    };
+    let step = match &for_range.step {
+        Some(step) => int(step),
+        None => Some(1),
+    };
+    match (int(&for_range.start), int(&for_range.end), step) {
+        (Some(start), Some(end), Some(step)) if step != 0 => {
+            let iterations = (i64::from(end) - i64::from(start)) / i64::from(step) + 1;
+            iterations <= i64::from(SMALL_LOOP_BOUND)
+        }
+        _ => false,
+    }
+}
@@ -0,0 +1,146 @@
+//! Switch cases which earlier cases already match.
+//!
+//! Cases are constant-folded and checked in order against the values matched
+//! by earlier branches of a switch. A case is unreachable if earlier cases
+//! match all of it, and partially shadowed if they match only some of a
+//! range. An exact value which comes before a range covering it is the usual
+//! way to special-case that value, so it doesn't count as shadowing the
+//! range. Cases which don't fold, such as ranges with a bound which isn't a
+//! constant, are skipped.
+//!
+//! The same folding decides which branch a switch on a constant input takes.
+
+use dm::ast::*;
+use dm::constants::Constant;
+use dm::{Context, DMError, Location, Severity};
+
+/// The values a folded case matches.
+enum Matched {
+    Range(f32, f32),
+    Value(Constant),
+}
+
+/// The branch a switch takes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Taken {
+    /// The case branch at this index.
+    Case(usize),
+    /// The `else` branch, or nothing if there isn't one.
+    Default,
+}
+
+/// The branch a switch on a constant number, text, or null takes, if every
+/// case before the one which matches can be folded.
+pub fn taken_case(cases: &[(Spanned<Vec<Case>>, Block)], input: &Constant) -> Option<Taken> {
+    if !matches!(input, Constant::Float(_) | Constant::String(_) | Constant::Null(_)) {
+        return None;
+    }
+    for (index, (case, _)) in cases.iter().enumerate() {
+        for each in case.elem.iter() {
+            let matches = match fold_case(each, case.location)? {
+                Matched::Value(value) => value == *input,
+                Matched::Range(low, high) => input.to_float().is_some_and(|number| low <= number && number <= high),
+            };
+            if matches {
+                return Some(Taken::Case(index));
+            }
+        }
+    }
+    Some(Taken::Default)
+}
+
+pub fn check_switch_overlap(cases: &[(Spanned<Vec<Case>>, Block)], context: &Context) {
+    let mut earlier: Vec<(Matched, Location)> = Vec::new();
+    for (case, _) in cases.iter() {
+        let location = case.location;
+        let folded: Vec<Matched> = case.elem.iter().filter_map(|each| fold_case(each, location)).collect();
+        for matched in folded.iter() {
+            check_case(context, location, matched, &earlier);
+        }
+        earlier.extend(folded.into_iter().map(|matched| (matched, location)));
+    }
+}
+
+fn check_case(context: &Context, location: Location, matched: &Matched, earlier: &[(Matched, Location)]) {
+    let (message, notes) = match *matched {
+        Matched::Value(ref value) => {
+            let number = value.to_float();
+            let first = earlier.iter().find(|(other, _)| match *other {
+                Matched::Value(ref other) => other == value,
+                Matched::Range(low, high) => number.is_some_and(|number| low <= number && number <= high),
+            });
+            match first {
+                Some(&(_, first)) => (format!("unreachable case {}, earlier cases already match it", value), vec![first]),
+                None => return,
+            }
+        }
+        Matched::Range(low, high) => {
+            let mut overlaps: Vec<(f32, f32, Location)> = earlier.iter().filter_map(|&(ref other, location)| match *other {
+                Matched::Range(other_low, other_high) if other_low <= high && low <= other_high => {
+                    Some((other_low, other_high, location))
+                }
+                _ => None,
+            }).collect();
+            if overlaps.is_empty() {
+                return;
+            }
+            overlaps.sort_by(|a, b| a.0.total_cmp(&b.0));
+            let mut notes: Vec<Location> = Vec::new();
+            for &(_, _, location) in overlaps.iter() {
+                if !notes.contains(&location) {
+                    notes.push(location);
+                }
+            }
+            let message = if covers(&overlaps, low, high) {
+                format!("unreachable case {} to {}, earlier cases already match all of it", low, high)
+            } else {
+                format!("case {} to {} is partially shadowed by earlier cases", low, high)
+            };
+            (message, notes)
+        }
+    };
+
+    let mut error = DMError::new(location, message)
+        .with_component(dm::Component::DreamChecker)
+        .set_severity(Severity::Warning)
+        .with_errortype("switch_case_overlap");
+    for note in notes {
+        error.add_note(note, "matched by this earlier case");
+    }
+    error.register(context);
+}
+
+/// Whether ranges sorted by their low end, which all overlap the range from
+/// `low` to `high`, match every value in it.
+fn covers(ranges: &[(f32, f32, Location)], low: f32, high: f32) -> bool {
+    let mut reach = None;
+    for &(start, end, _) in ranges {
+        let joined = match reach {
+            None => start <= low,
+            Some(reach) => start <= reach,
+        };
+        if !joined {
+            return false;
+        }
+        let end = reach.map_or(end, |reach: f32| reach.max(end));
+        if end >= high {
+            return true;
+        }
+        reach = Some(end);
+    }
+    false
+}
+
+fn fold_case(case: &Case, location: Location) -> Option<Matched> {
+    match case {
+        Case::Exact(value) => match value.clone().simple_evaluate(location).ok()? {
+            value @ Constant::Float(_) | value @ Constant::String(_) | value @ Constant::Null(_) => Some(Matched::Value(value)),
+            _ => None,
+        },
+        Case::Range(low, high) => {
+            let low = low.clone().simple_evaluate(location).ok()?.to_float()?;
+            let high = high.clone().simple_evaluate(location).ok()?.to_float()?;
+            Some(Matched::Range(low, high))
+        }
+    }
+}
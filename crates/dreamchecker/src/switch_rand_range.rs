@@ -1,5 +1,3 @@
-use std::borrow::Borrow;
-
 use dm::ast::*;
 use dm::{Context, DMError, Location, Severity};
 
@@ -88,30 +86,6 @@ fn get_case_range(case: &Case, location: Location) -> Option<(f32, f32)> {
 }
 
 fn get_rand_range(maybe_rand: &Expression) -> Option<(i32, i32)> {
-    let (term, location) = match maybe_rand {
-        &Expression::Base {
-            ref term,
-            ref follow,
-        } if follow.is_empty() => (&term.elem, term.location),
-        _ => return None,
-    };
-
-    let rand_args: &[Expression] = match term {
-        Term::Call(proc_name, args) if proc_name.as_str() == "rand" => args.borrow(),
-        _ => return None,
-    };
-
-    let (min, max) = match rand_args {
-        [min, max] => (
-            min.to_owned().simple_evaluate(location).ok()?.to_float()?,
-            max.to_owned().simple_evaluate(location).ok()?.to_float()?,
-        ),
-        [max] => (
-            0.,
-            max.to_owned().simple_evaluate(location).ok()?.to_float()?,
-        ),
-        _ => return None,
-    };
-
-    Some((min.ceil() as i32, max.floor() as i32))
+    let (min, max) = dm::constants::rand_call_range(maybe_rand)?;
+    Some((min as i32, max as i32))
 }
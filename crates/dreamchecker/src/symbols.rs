@@ -0,0 +1,89 @@
+//! An outline of the types, vars, and procs defined in a file, such as for
+//! editor document symbols.
+//!
+//! The extent of each definition is recorded by the parser in its
+//! annotations, so they must be enabled when parsing.
+
+use std::ops::Range;
+
+use dm::{FileId, Location};
+use dm::annotation::{Annotation, AnnotationTree, SymbolKind};
+
+/// A type block, var, or proc definition.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DocumentSymbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    /// The path of the type it's on, or for a type, its parent.
+    pub parent_path: String,
+    /// The whole definition, including its body or value.
+    pub range: Range<Location>,
+    /// The definitions nested within it, in order.
+    pub children: Vec<DocumentSymbol>,
+}
+
+impl DocumentSymbol {
+    /// The full path, as in `/obj/item`, `/obj/item/var/force`, or
+    /// `/obj/item/proc/attack`.
+    pub fn path(&self) -> String {
+        match self.kind {
+            SymbolKind::Type => format!("{}/{}", self.parent_path, self.name),
+            SymbolKind::Var => format!("{}/var/{}", self.parent_path, self.name),
+            SymbolKind::Proc => format!("{}/proc/{}", self.parent_path, self.name),
+            SymbolKind::Verb => format!("{}/verb/{}", self.parent_path, self.name),
+        }
+    }
+}
+
+/// List the definitions in a file, nested the way the file nests them.
+pub fn document_symbols(annotations: &AnnotationTree, file: FileId) -> Vec<DocumentSymbol> {
+    let mut flat: Vec<DocumentSymbol> = annotations.iter()
+        .filter(|(range, _)| range.start.file == file)
+        .filter_map(|(range, annotation)| match annotation {
+            Annotation::Symbol(kind, parent_path, name) => Some(DocumentSymbol {
+                name: name.clone(),
+                kind: *kind,
+                parent_path: parent_path.clone(),
+                range: range.start..succ(range.end),
+                children: Vec::new(),
+            }),
+            _ => None,
+        })
+        .collect();
+    // Outer definitions first, so each one's children follow it.
+    flat.sort_by(|a, b| a.range.start.cmp(&b.range.start).then(b.range.end.cmp(&a.range.end)));
+
+    let mut roots = Vec::new();
+    let mut stack: Vec<DocumentSymbol> = Vec::new();
+    for symbol in flat {
+        while stack.last().is_some_and(|open| open.range.end <= symbol.range.start) {
+            close(&mut stack, &mut roots);
+        }
+        stack.push(symbol);
+    }
+    while !stack.is_empty() {
+        close(&mut stack, &mut roots);
+    }
+    roots
+}
+
+/// Finish the innermost open definition, adding it to its parent.
+fn close(stack: &mut Vec<DocumentSymbol>, roots: &mut Vec<DocumentSymbol>) {
+    if let Some(done) = stack.pop() {
+        match stack.last_mut() {
+            Some(parent) => parent.children.push(done),
+            None => roots.push(done),
+        }
+    }
+}
+
+/// The location after the last one in an annotation's range.
+fn succ(mut location: Location) -> Location {
+    if location.column == !0 {
+        location.line += 1;
+        location.column = 0;
+    } else {
+        location.column += 1;
+    }
+    location
+}
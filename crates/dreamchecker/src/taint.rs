@@ -0,0 +1,243 @@
+//! Tracking of values which come from the client, such as the contents of
+//! `href_list` in `Topic()`, into places which must not trust them.
+//!
+//! The analysis is intraprocedural: locals are tracked by name through the
+//! control flow graph, and a local is tainted at a point if it may hold a
+//! tainted value on any path reaching that point. Values which pass through a
+//! configured sanitizer, a method call, or a comparison are considered clean.
+
+use std::collections::HashSet;
+
+use ahash::RandomState;
+
+use dm::{Context, DMError, Location, Severity};
+use dm::ast::*;
+use dm::config::Taint;
+
+use crate::cfg::{ControlFlowGraph, Step};
+use crate::visit::visit;
+
+type State = HashSet<String, RandomState>;
+
+pub fn check_taint(context: &Context, graph: &ControlFlowGraph) {
+    let config = &context.config().dreamchecker.taint;
+
+    // Forward may-taint to a fixed point. Spawned code sees the locals as
+    // they were when it was spawned, so spawn edges are followed too.
+    let mut state_in: Vec<State> = vec![State::default(); graph.blocks.len()];
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for id in 0..graph.blocks.len() {
+            let mut state = state_in[id].clone();
+            for step in graph.blocks[id].steps.iter() {
+                transfer(config, &mut state, step);
+            }
+            for &(_, next) in graph.blocks[id].successors.iter() {
+                for name in state.iter() {
+                    if !state_in[next].contains(name) {
+                        state_in[next].insert(name.clone());
+                        changed = true;
+                    }
+                }
+            }
+        }
+    }
+
+    let reachable = graph.reachable(true);
+    let mut found: Vec<(Location, String)> = Vec::new();
+    for (id, block) in graph.blocks.iter().enumerate() {
+        if !reachable[id] {
+            continue;
+        }
+        let mut state = state_in[id].clone();
+        for step in block.steps.iter() {
+            let checker = Checker { config, state: &state };
+            for expr in step.expressions() {
+                checker.sinks(step.location(), expr, &mut found);
+            }
+            transfer(config, &mut state, step);
+        }
+    }
+
+    found.sort();
+    found.dedup();
+    for (location, message) in found {
+        DMError::new(location, message)
+            .with_component(dm::Component::DreamChecker)
+            .set_severity(Severity::Warning)
+            .with_errortype("tainted_value")
+            .register(context);
+    }
+}
+
+fn transfer(config: &Taint, state: &mut State, step: &Step) {
+    match *step {
+        Step::Statement(_, Statement::Var(var)) => declare(config, state, var),
+        Step::Statement(_, Statement::Vars(vars)) => {
+            for var in vars.iter() {
+                declare(config, state, var);
+            }
+        }
+        Step::Statement(_, Statement::Expr(expr)) |
+        Step::Expression(_, expr) => assign(config, state, expr),
+        Step::Header(_, Statement::ForList(for_list)) => {
+            let tainted = for_list.in_list.as_ref().is_some_and(|in_list| Checker { config, state }.tainted(in_list));
+            set(state, &for_list.name, tainted);
+        }
+        Step::Header(_, Statement::ForRange(for_range)) => set(state, &for_range.name, false),
+        Step::Header(_, Statement::TryCatch { catch_params, .. }) => {
+            for param in catch_params.iter() {
+                if let Some(name) = param.last() {
+                    set(state, name, false);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+fn declare(config: &Taint, state: &mut State, var: &VarStatement) {
+    let tainted = var.value.as_ref().is_some_and(|value| Checker { config, state }.tainted(value));
+    set(state, &var.name, tainted);
+}
+
+fn assign(config: &Taint, state: &mut State, expr: &Expression) {
+    if let Expression::AssignOp { op, lhs, rhs } = expr {
+        if let Some(Term::Ident(name)) = lhs.as_term() {
+            let tainted = Checker { config, state }.tainted(rhs);
+            if *op == AssignOp::Assign {
+                set(state, name, tainted);
+            } else if tainted {
+                set(state, name, true);
+            }
+        }
+    }
+}
+
+fn set(state: &mut State, name: &str, tainted: bool) {
+    if tainted {
+        state.insert(name.to_owned());
+    } else {
+        state.remove(name);
+    }
+}
+
+struct Checker<'c, 's> {
+    config: &'c Taint,
+    state: &'s State,
+}
+
+impl Checker<'_, '_> {
+    fn is_sink(&self, name: &str) -> bool {
+        name == "text2path" || self.config.sinks.iter().any(|sink| sink == name)
+    }
+
+    /// Find sinks reached by tainted values anywhere within an expression.
+    fn sinks(&self, location: Location, expr: &Expression, found: &mut Vec<(Location, String)>) {
+        visit(expr, &mut |expr| {
+            match expr {
+                Expression::Base { term, follow } => {
+                    let reported = match &term.elem {
+                        Term::Call(name, args) if self.is_sink(name) && self.any_tainted(args) => Some(name.as_str()),
+                        Term::Locate { args, in_list: None } if args.len() == 1 && self.tainted(&args[0]) => Some("locate"),
+                        Term::DynamicCall(first, _) if self.any_tainted(first) => Some("call"),
+                        _ => None,
+                    };
+                    if let Some(name) = reported {
+                        found.push((term.location, format!("unsanitized user input passed to {}()", name)));
+                    }
+                    for each in follow.iter() {
+                        if let Follow::Call(_, name, args) = &each.elem {
+                            if self.is_sink(name) && self.any_tainted(args) {
+                                found.push((each.location, format!("unsanitized user input passed to {}()", name)));
+                            }
+                        }
+                    }
+                }
+                Expression::AssignOp { lhs, rhs, .. } => {
+                    if let Some(name) = assigned_var(lhs) {
+                        if self.config.sink_vars.iter().any(|var| var == name) && self.tainted(rhs) {
+                            found.push((location, format!("unsanitized user input assigned to {}", name)));
+                        }
+                    }
+                }
+                _ => {}
+            }
+        });
+    }
+
+    fn any_tainted(&self, exprs: &[Expression]) -> bool {
+        exprs.iter().any(|expr| self.tainted(expr))
+    }
+
+    /// Whether an expression's value may come from the client.
+    fn tainted(&self, expr: &Expression) -> bool {
+        match expr {
+            Expression::Base { term, follow } => {
+                let mut tainted = self.term(&term.elem);
+                for each in follow.iter() {
+                    match &each.elem {
+                        // Indexing or reading a field of a tainted value
+                        // gives a tainted value.
+                        Follow::Index(..) | Follow::Field(..) | Follow::Unary(_) => {}
+                        Follow::Call(..) => tainted = false,
+                    }
+                }
+                tainted
+            }
+            Expression::BinaryOp { op, lhs, rhs } => match op {
+                BinaryOp::Eq |
+                BinaryOp::NotEq |
+                BinaryOp::Less |
+                BinaryOp::Greater |
+                BinaryOp::LessEq |
+                BinaryOp::GreaterEq |
+                BinaryOp::Equiv |
+                BinaryOp::NotEquiv |
+                BinaryOp::In => false,
+                _ => self.tainted(lhs) || self.tainted(rhs),
+            },
+            Expression::AssignOp { rhs, .. } => self.tainted(rhs),
+            Expression::TernaryOp { if_, else_, .. } => self.tainted(if_) || self.tainted(else_),
+        }
+    }
+
+    fn term(&self, term: &Term) -> bool {
+        match term {
+            Term::Ident(name) => self.config.sources.contains(name) || self.state.contains(name),
+            Term::Expr(expr) => self.tainted(expr),
+            Term::InterpString(_, parts) => parts.iter().filter_map(|(expr, _)| expr.as_ref()).any(|expr| self.tainted(expr)),
+            Term::Call(name, args) => {
+                if self.config.sanitizers.iter().any(|sanitizer| sanitizer == name.as_str()) {
+                    false
+                } else if self.config.source_procs.iter().any(|source| source == name.as_str()) {
+                    true
+                } else {
+                    self.any_tainted(args)
+                }
+            }
+            Term::List(args) => self.any_tainted(args),
+            Term::Pick(choices) => choices.iter().any(|(_, value)| self.tainted(value)),
+            Term::Input { in_list: None, .. } => self.config.input,
+            _ => false,
+        }
+    }
+}
+
+/// The name of the var an assignment target refers to, if any.
+fn assigned_var(lhs: &Expression) -> Option<&str> {
+    match lhs {
+        Expression::Base { term, follow } => match follow.last() {
+            None => match &term.elem {
+                Term::Ident(name) => Some(name),
+                _ => None,
+            },
+            Some(last) => match &last.elem {
+                Follow::Field(_, name) => Some(name),
+                _ => None,
+            },
+        },
+        _ => None,
+    }
+}
@@ -1,13 +1,75 @@
 use dm::Context;
 use std::borrow::Cow;
+use std::path::{Path, PathBuf};
 
 use crate::{run_inner};
+use crate::type_coverage::Coverage;
 
 pub const NO_ERRORS: &[(u32, u16, &str)] = &[];
 
 pub fn parse_a_file_for_test<S: Into<Cow<'static, str>>>(buffer: S) -> Context {
+    parse_a_file_for_test_in(Context::default(), buffer)
+}
+
+/// Create a context using a configuration file with the given contents.
+pub fn context_with_config(name: &str, toml: &str) -> Context {
+    let path = std::env::temp_dir().join(format!("dreamchecker-{}-{}.toml", name, std::process::id()));
+    std::fs::write(&path, toml).unwrap();
     let context = Context::default();
+    context.force_config(&path);
+    std::fs::remove_file(&path).unwrap();
+    context
+}
 
+/// Parse a file without checking it, returning its tree and file ID.
+pub fn parse_tree_for_test<S: Into<Cow<'static, str>>>(buffer: S) -> (Context, dm::objtree::ObjectTree, dm::FileId) {
+    let context = Context::default();
+    let pp = dm::preprocessor::Preprocessor::from_buffer(&context, "unit_tests.rs".into(), buffer.into());
+    let indents = dm::indents::IndentProcessor::new(&context, pp);
+    let mut parser = dm::parser::Parser::new(&context, indents);
+    parser.enable_procs();
+    let tree = parser.parse_object_tree();
+    context.assert_success();
+    let file = context.get_file("unit_tests.rs".as_ref()).unwrap();
+    (context, tree, file)
+}
+
+/// Parse a file without checking it, also returning the parser's and
+/// preprocessor's annotations.
+pub fn parse_annotated_tree_for_test<S: Into<Cow<'static, str>>>(buffer: S) -> (Context, dm::objtree::ObjectTree, dm::annotation::AnnotationTree, dm::FileId) {
+    let context = Context::default();
+    let mut annotations = dm::annotation::AnnotationTree::default();
+    let mut pp = dm::preprocessor::Preprocessor::from_buffer(&context, "unit_tests.rs".into(), buffer.into());
+    pp.enable_annotations();
+    let indents = dm::indents::IndentProcessor::new(&context, &mut pp);
+    let mut parser = dm::parser::Parser::new(&context, indents);
+    parser.annotate_to(&mut annotations);
+    let tree = parser.parse_object_tree();
+    context.assert_success();
+    if let Some(macros) = pp.take_annotations() {
+        annotations.merge(macros);
+    }
+    let file = context.get_file("unit_tests.rs".as_ref()).unwrap();
+    (context, tree, annotations, file)
+}
+
+/// Parse several files without checking them, as if included in order.
+pub fn parse_files_for_test(files: &[(&str, &str)]) -> (Context, dm::objtree::ObjectTree) {
+    let context = Context::default();
+    let mut pp = dm::preprocessor::Preprocessor::from_buffer(&context, "unit_tests.dme".into(), "");
+    // The last file pushed is read first.
+    for &(path, code) in files.iter().rev() {
+        pp.push_file(path.into(), std::io::Cursor::new(code.to_owned())).unwrap();
+    }
+    let indents = dm::indents::IndentProcessor::new(&context, pp);
+    let mut parser = dm::parser::Parser::new(&context, indents);
+    parser.enable_procs();
+    let tree = parser.parse_object_tree();
+    context.assert_success();
+    (context, tree)
+}
+
+pub fn parse_a_file_for_test_in<S: Into<Cow<'static, str>>>(context: Context, buffer: S) -> Context {
     let pp = dm::preprocessor::Preprocessor::from_buffer(&context, "unit_tests.rs".into(), buffer.into());
 
     let indents = dm::indents::IndentProcessor::new(&context, pp);
@@ -16,13 +78,22 @@ pub fn parse_a_file_for_test<S: Into<Cow<'static, str>>>(buffer: S) -> Context {
     parser.enable_procs();
     let tree = parser.parse_object_tree();
 
-    run_inner(&context, &tree, false);
+    run_inner(&context, &tree, false, false);
 
     context
 }
 
+/// Run DreamChecker on a tree without printing progress, and return the type
+/// coverage of its proc bodies.
+pub fn type_coverage_for_test(context: &Context, tree: &dm::objtree::ObjectTree) -> Coverage {
+    run_inner(context, tree, false, true).unwrap_or_default()
+}
+
 pub fn check_errors_match<S: Into<Cow<'static, str>>>(buffer: S, errorlist: &[(u32, u16, &str)]) {
-    let context = parse_a_file_for_test(buffer);
+    check_context_errors_match(&parse_a_file_for_test(buffer), errorlist)
+}
+
+pub fn check_context_errors_match(context: &Context, errorlist: &[(u32, u16, &str)]) {
     let errors = context.errors();
     let mut iter = errors.iter();
     for (line, column, desc) in errorlist {
@@ -46,3 +117,106 @@ pub fn check_errors_match<S: Into<Cow<'static, str>>>(buffer: S, errorlist: &[(u
         panic!("found more errors than was expected");
     }
 }
+
+/// Check a fixture against the expectations written in its comments.
+///
+/// A fixture is a `.dm` file, or a directory holding one `.dme` and the files
+/// it includes, along with a `SpacemanDMM.toml` if wanted. A comment of the
+/// form `// expect code: message` expects a diagnostic with exactly that
+/// message on the same line, or on the next line if the comment is alone on
+/// its line. The code is the diagnostic's errortype, or its severity if it has
+/// none. A fixture without expectations must raise no diagnostics.
+pub fn check_fixture(path: &Path) {
+    let context = Context::default();
+    let dme = if path.is_dir() {
+        let dme = find_dme(path);
+        context.autodetect_config(&dme);
+        dme
+    } else {
+        path.to_owned()
+    };
+    let mut pp = dm::preprocessor::Preprocessor::new(&context, dme.clone())
+        .unwrap_or_else(|error| panic!("{}: {}", path.display(), error));
+    let indents = dm::indents::IndentProcessor::new(&context, &mut pp);
+    let mut parser = dm::parser::Parser::new(&context, indents);
+    parser.enable_procs();
+    let (fatal_errored, tree) = parser.parse_object_tree_2();
+    crate::check_macro_names(&context, &pp.finalize());
+    if !fatal_errored {
+        run_inner(&context, &tree, false, false);
+    }
+
+    // Included files are registered relative to the `.dme`, but it isn't.
+    let root = dme.parent().unwrap();
+    let mut expected = Vec::new();
+    context.file_list().for_each(|file| {
+        let on_disk = if file == dme { file.to_owned() } else { root.join(file) };
+        if let Ok(text) = std::fs::read_to_string(&on_disk) {
+            expected.extend(expectations(&text).into_iter()
+                .map(|(line, code, message)| (file.to_owned(), line, code, message)));
+        }
+    });
+    let mut found: Vec<_> = context.errors().iter().map(|error| {
+        let code = match error.errortype() {
+            Some(errortype) => errortype.to_owned(),
+            None => error.severity().to_string(),
+        };
+        let location = error.location();
+        (context.file_path(location.file), location.line, code, error.description().to_owned())
+    }).collect();
+    expected.sort();
+    found.sort();
+
+    if expected != found {
+        let show = |list: &[(PathBuf, u32, String, String)], other: &[(PathBuf, u32, String, String)]| {
+            list.iter()
+                .filter(|each| !other.contains(each))
+                .map(|(file, line, code, message)| format!("\n    {}:{}: {}: {}", file.display(), line, code, message))
+                .collect::<String>()
+        };
+        panic!(
+            "fixture {} did not match\n  missing:{}\n  unexpected:{}",
+            path.display(),
+            show(&expected, &found),
+            show(&found, &expected),
+        );
+    }
+}
+
+fn find_dme(dir: &Path) -> PathBuf {
+    let mut dmes = std::fs::read_dir(dir)
+        .unwrap_or_else(|error| panic!("{}: {}", dir.display(), error))
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "dme"));
+    match (dmes.next(), dmes.next()) {
+        (Some(dme), None) => dme,
+        _ => panic!("fixture {} must hold exactly one .dme", dir.display()),
+    }
+}
+
+/// The line, code, and message of each expectation comment in a file.
+fn expectations(text: &str) -> Vec<(u32, String, String)> {
+    const MARKER: &str = "// expect ";
+    let mut found = Vec::new();
+    let mut pending = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let line_number = index as u32 + 1;
+        let (code, rest) = match line.find(MARKER) {
+            Some(start) => (&line[..start], &line[start + MARKER.len()..]),
+            None => (line, ""),
+        };
+        if !rest.is_empty() {
+            let (kind, message) = match rest.find(':') {
+                Some(colon) => (rest[..colon].trim(), rest[colon + 1..].trim()),
+                None => panic!("line {}: expectation {:?} has no message", line_number, rest),
+            };
+            pending.push((kind.to_owned(), message.to_owned()));
+            if code.trim().is_empty() {
+                continue;
+            }
+        }
+        found.extend(pending.drain(..).map(|(kind, message)| (line_number, kind, message)));
+    }
+    assert!(pending.is_empty(), "expectation at the end of the file has no line to apply to");
+    found
+}
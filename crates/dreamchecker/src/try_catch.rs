@@ -0,0 +1,88 @@
+//! Determining whether a `try` block can throw at all.
+//!
+//! This is conservative: anything which can raise a runtime error, such as a
+//! call, a field access, an index, or a division, is assumed to throw.
+
+use dm::ast::*;
+
+use crate::visit::visit;
+
+/// Whether running a block may throw, either explicitly or through a
+/// runtime error. Code in `spawn` blocks is not counted, since what it throws
+/// is not caught by an enclosing `try`.
+pub fn can_throw(block: &[Spanned<Statement>]) -> bool {
+    block.iter().any(|statement| statement_can_throw(&statement.elem))
+}
+
+fn statement_can_throw(statement: &Statement) -> bool {
+    match statement {
+        Statement::Throw(_) |
+        Statement::Crash(_) |
+        Statement::Del(_) => true,
+        Statement::Expr(expr) |
+        Statement::Return(Some(expr)) => expression_can_throw(expr),
+        Statement::Var(var) => var.value.as_ref().is_some_and(expression_can_throw),
+        Statement::Vars(vars) => vars.iter().any(|var| var.value.as_ref().is_some_and(expression_can_throw)),
+        Statement::While { condition, block } => expression_can_throw(condition) || can_throw(block),
+        Statement::DoWhile { block, condition } => can_throw(block) || expression_can_throw(&condition.elem),
+        Statement::If { arms, else_arm } => {
+            arms.iter().any(|(condition, block)| expression_can_throw(&condition.elem) || can_throw(block))
+                || else_arm.as_ref().is_some_and(|block| can_throw(block))
+        }
+        Statement::ForInfinite { block } => can_throw(block),
+        Statement::ForLoop { init, test, inc, block } => {
+            init.as_ref().is_some_and(|init| statement_can_throw(init))
+                || test.as_ref().is_some_and(|test| expression_can_throw(test))
+                || inc.as_ref().is_some_and(|inc| statement_can_throw(inc))
+                || can_throw(block)
+        }
+        Statement::ForList(for_list) => {
+            for_list.in_list.as_ref().is_some_and(expression_can_throw) || can_throw(&for_list.block)
+        }
+        Statement::ForRange(for_range) => {
+            expression_can_throw(&for_range.start)
+                || expression_can_throw(&for_range.end)
+                || for_range.step.as_ref().is_some_and(expression_can_throw)
+                || can_throw(&for_range.block)
+        }
+        Statement::Spawn { delay, .. } => delay.as_ref().is_some_and(expression_can_throw),
+        Statement::Switch { input, cases, default } => {
+            expression_can_throw(input)
+                || cases.iter().any(|(_, block)| can_throw(block))
+                || default.as_ref().is_some_and(|block| can_throw(block))
+        }
+        Statement::TryCatch { try_block, catch_block, .. } => can_throw(try_block) || can_throw(catch_block),
+        Statement::Label { block, .. } => can_throw(block),
+        _ => false,
+    }
+}
+
+fn expression_can_throw(expr: &Expression) -> bool {
+    let mut throws = false;
+    visit(expr, &mut |expr| {
+        throws |= match expr {
+            Expression::Base { term, follow } => {
+                // Everything else calls something.
+                let calls = !matches!(term.elem,
+                    Term::Null |
+                    Term::Int(_) |
+                    Term::Float(_) |
+                    Term::Ident(_) |
+                    Term::String(_) |
+                    Term::RawString(_) |
+                    Term::Resource(_) |
+                    Term::As(_) |
+                    Term::Expr(_) |
+                    Term::Prefab(_) |
+                    Term::ProcReference(_) |
+                    Term::InterpString(..) |
+                    Term::List(_));
+                calls || follow.iter().any(|each| !matches!(each.elem, Follow::Unary(_)))
+            }
+            Expression::BinaryOp { op, .. } => matches!(op, BinaryOp::Div | BinaryOp::Mod),
+            Expression::AssignOp { op, .. } => matches!(op, AssignOp::DivAssign | AssignOp::ModAssign),
+            Expression::TernaryOp { .. } => false,
+        };
+    });
+    throws
+}
@@ -0,0 +1,106 @@
+//! How many expressions in proc bodies have a known type.
+//!
+//! An expression is typed if its analysis has a static type, a constant
+//! value, or an assumption that it is text, a number, null, a typepath, or of
+//! some type. Anything else could be any value, and the checks which rely on
+//! types can't say anything about it. Counts are kept per proc definition and
+//! added up per file and per type subtree, which is the first two levels of a
+//! type's path, such as `/obj/item`, with global procs under `/`.
+
+use std::collections::HashMap;
+
+use dm::{FileId, Location};
+use dm::objtree::ProcRef;
+
+use crate::{Analysis, Assumption, StaticType};
+
+/// The number of expressions, and how many of them are typed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Count {
+    pub typed: u32,
+    pub total: u32,
+}
+
+impl Count {
+    pub(crate) fn record(&mut self, typed: bool) {
+        self.total += 1;
+        if typed {
+            self.typed += 1;
+        }
+    }
+
+    fn add(&mut self, other: Count) {
+        self.typed += other.typed;
+        self.total += other.total;
+    }
+
+    pub fn untyped(self) -> u32 {
+        self.total - self.typed
+    }
+
+    /// The percentage of expressions which are typed, or 100 if there are
+    /// none.
+    pub fn percent(self) -> f64 {
+        if self.total == 0 {
+            100.0
+        } else {
+            f64::from(self.typed) * 100.0 / f64::from(self.total)
+        }
+    }
+}
+
+/// The type coverage of a whole tree. Each list is sorted with the most
+/// untyped expressions first, and ties in order of path or location.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Coverage {
+    pub overall: Count,
+    /// Each proc definition, by path and location.
+    pub procs: Vec<(String, Location, Count)>,
+    pub files: Vec<(FileId, Count)>,
+    /// Each type subtree, by path.
+    pub subtrees: Vec<(String, Count)>,
+}
+
+/// Whether an analysis says anything about the type of its value.
+pub(crate) fn is_typed(analysis: &Analysis) -> bool {
+    analysis.static_ty != StaticType::None
+        || analysis.value.is_some()
+        || analysis.aset.set.iter().any(|each| matches!(each,
+            Assumption::IsText(true) |
+            Assumption::IsNum(true) |
+            Assumption::IsNull(true) |
+            Assumption::IsPath(true, _) |
+            Assumption::IsType(true, _)
+        ))
+}
+
+/// Add up the counts kept for each proc while analyzing proc bodies.
+pub(crate) fn summarize(counts: HashMap<ProcRef, Count>) -> Coverage {
+    let mut coverage = Coverage::default();
+    let mut files: HashMap<FileId, Count> = HashMap::new();
+    let mut subtrees: HashMap<String, Count> = HashMap::new();
+    for (proc, count) in counts {
+        let location = proc.get().location;
+        coverage.overall.add(count);
+        files.entry(location.file).or_default().add(count);
+        subtrees.entry(subtree(proc)).or_default().add(count);
+        coverage.procs.push((proc.to_string(), location, count));
+    }
+    coverage.files = files.into_iter().collect();
+    coverage.subtrees = subtrees.into_iter().collect();
+
+    coverage.procs.sort_by(|a, b| b.2.untyped().cmp(&a.2.untyped()).then_with(|| (&a.0, a.1).cmp(&(&b.0, b.1))));
+    coverage.files.sort_by(|a, b| b.1.untyped().cmp(&a.1.untyped()).then_with(|| a.0.cmp(&b.0)));
+    coverage.subtrees.sort_by(|a, b| b.1.untyped().cmp(&a.1.untyped()).then_with(|| a.0.cmp(&b.0)));
+    coverage
+}
+
+/// The path of the subtree a proc is counted under.
+fn subtree(proc: ProcRef) -> String {
+    let path = &proc.ty().get().path;
+    match path.match_indices('/').nth(2) {
+        Some((end, _)) => path[..end].to_owned(),
+        None if path.is_empty() => "/".to_owned(),
+        None => path.clone(),
+    }
+}
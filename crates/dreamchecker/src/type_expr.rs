@@ -51,6 +51,12 @@ pub enum TypeExpr<'o> {
         index_ct: usize,
     },
 
+    // The analysis of an argument, from `param(N)`.
+    ParamAnalysis {
+        name: String,
+        p_idx: usize,
+    },
+
     // from `&&`, `||`, and `?:`
     Condition {
         cond: Box<TypeExpr<'o>>,
@@ -115,6 +121,36 @@ impl<'o> TypeExpr<'o> {
                     Ok(StaticType::None)
                 }
             }
+
+            TypeExpr::ParamAnalysis { name, p_idx } => {
+                if let Some(analysis) = ec.get(name, *p_idx) {
+                    Ok(analysis.static_ty.clone())
+                } else {
+                    Ok(StaticType::None)
+                }
+            }
+        }
+    }
+
+    /// Evaluate this expression into the analysis of a call's result, which
+    /// is that of an argument for `param(N)`.
+    pub fn evaluate_analysis(
+        &self,
+        location: Location,
+        ec: &TypeExprContext<'o, '_>,
+    ) -> Result<Analysis<'o>, DMError> {
+        match self {
+            TypeExpr::ParamAnalysis { name, p_idx } => {
+                Ok(ec.get(name, *p_idx).cloned().unwrap_or_else(Analysis::empty))
+            }
+            TypeExpr::Condition { cond, if_, else_ } => {
+                if cond.evaluate(location, ec)?.is_truthy() {
+                    if_.evaluate_analysis(location, ec)
+                } else {
+                    else_.evaluate_analysis(location, ec)
+                }
+            }
+            _ => self.evaluate(location, ec).map(Analysis::from),
         }
     }
 }
@@ -206,6 +242,37 @@ impl<'o> TypeExprCompiler<'o> {
 
             Term::Expr(expr) => self.visit_expression(location, expr),
 
+            // param_type(N) => typepath given as parameter N
+            // param(N) => analysis of argument N
+            Term::Call(func, args) if func == "param_type" || func == "param" => {
+                let p_idx = match args.first().and_then(|arg| arg.as_term()) {
+                    Some(&Term::Int(number)) if args.len() == 1 => number,
+                    _ => return Err(DMError::new(
+                        location,
+                        format!("type expr: {}() takes one parameter number", func),
+                    )),
+                };
+                let param = if p_idx >= 1 {
+                    let index = p_idx as usize - 1;
+                    self.proc.parameters.get(index).map(|param| (index, param))
+                } else {
+                    None
+                };
+                let (p_idx, param) = match param {
+                    Some(param) => param,
+                    None => return Err(DMError::new(
+                        location,
+                        format!("type expr: {}({}) but {} has {} parameters", func, p_idx, self.proc, self.proc.parameters.len()),
+                    )),
+                };
+                let name = param.name.to_owned();
+                if func == "param" {
+                    Ok(TypeExpr::ParamAnalysis { name, p_idx })
+                } else {
+                    Ok(TypeExpr::ParamTypepath { name, p_idx, index_ct: 0 })
+                }
+            }
+
             Term::Prefab(fab) => {
                 let bits: Vec<_> = fab.path.iter().map(|(_, name)| name.to_owned()).collect();
                 let ty = crate::static_type(self.objtree, location, &bits)?;
@@ -0,0 +1,165 @@
+//! Finding parameters which no call ever gives a value.
+//!
+//! The arguments of every resolved call are recorded against the type which
+//! declares the proc, since dynamic dispatch may run any override of it. A
+//! parameter which a proc reads is reported if the proc is called somewhere,
+//! but no call passes anything other than null in its position or by its
+//! name. Procs whose arguments may come from elsewhere are skipped: overrides
+//! of builtin procs, procs BYOND calls by name, verbs, procs called with
+//! `arglist()` or through a reference such as `.proc/name`, and procs named by
+//! a call which couldn't be resolved.
+
+use std::collections::{HashMap, HashSet};
+
+use ahash::RandomState;
+
+use dm::{Context, DMError, Location, Severity};
+use dm::ast::*;
+use dm::objtree::{ObjectTree, ProcRef, TypeRef};
+
+use crate::cfg::ControlFlowGraph;
+use crate::declaring_type;
+use crate::naming::MANDATED_PROCS;
+use crate::param_defaults::folded_default;
+use crate::visit::visit;
+
+/// One argument of a call, as far as whether it supplies a parameter.
+pub struct Argument<'a> {
+    /// The keyword the argument is given by, if any.
+    pub name: Option<&'a str>,
+    /// Whether the value may be something other than null.
+    pub supplied: bool,
+}
+
+/// The arguments given to calls of the procs declared on one type.
+#[derive(Default)]
+struct Family {
+    first_call: Option<Location>,
+    calls: usize,
+    positions: HashSet<usize, RandomState>,
+    names: HashSet<String, RandomState>,
+    /// Whether a call passes arguments which can't be seen, as by `arglist()`.
+    unknown: bool,
+}
+
+/// The arguments passed to each proc, and which parameters each proc reads.
+#[derive(Default)]
+pub struct SuppliedArgs<'o> {
+    families: HashMap<(TypeRef<'o>, &'o str), Family, RandomState>,
+    /// The names of procs called where the proc can't be resolved.
+    unresolved: HashSet<&'o str, RandomState>,
+    /// The parameters each proc reads, by index.
+    reads: HashMap<ProcRef<'o>, HashSet<usize, RandomState>, RandomState>,
+}
+
+impl<'o> SuppliedArgs<'o> {
+    /// Record a call which resolved to a proc.
+    pub fn record_call(&mut self, location: Location, proc: ProcRef<'o>, args: &[Argument], unknown: bool) {
+        let family = self.families.entry(declaration_key(proc)).or_default();
+        family.first_call = Some(family.first_call.map_or(location, |first| first.min(location)));
+        family.calls += 1;
+        family.unknown |= unknown;
+        let mut index = 0;
+        for arg in args {
+            match arg.name {
+                Some(name) => if arg.supplied {
+                    family.names.insert(name.to_owned());
+                },
+                None => {
+                    if arg.supplied {
+                        family.positions.insert(index);
+                    }
+                    index += 1;
+                }
+            }
+        }
+    }
+
+    /// Record a call to a proc, by name, which couldn't be resolved.
+    pub fn record_unresolved(&mut self, name: &'o str) {
+        self.unresolved.insert(name);
+    }
+
+    /// Record which parameters a proc reads.
+    pub fn record_reads(&mut self, proc: ProcRef<'o>, graph: &ControlFlowGraph) {
+        let parameters = &proc.get().parameters;
+        let mut reads = HashSet::with_hasher(RandomState::default());
+        for step in graph.blocks.iter().flat_map(|block| block.steps.iter()) {
+            for expr in step.expressions() {
+                visit(expr, &mut |expr| {
+                    if let Some(Term::Ident(name)) = expr.as_term() {
+                        if let Some(index) = parameters.iter().position(|param| param.name == *name) {
+                            reads.insert(index);
+                        }
+                    }
+                });
+            }
+        }
+        self.reads.insert(proc, reads);
+    }
+
+    /// Report the parameters no call gives a value.
+    pub fn check(&self, context: &Context, objtree: &'o ObjectTree, referenced: &HashSet<ProcRef<'o>>) {
+        let mut procs: Vec<ProcRef<'o>> = objtree.iter_types().flat_map(|ty| ty.iter_self_procs()).collect();
+        procs.sort_by_key(|proc| proc.get().location);
+
+        let mut exempt: HashSet<(TypeRef<'o>, &'o str), RandomState> = Default::default();
+        for &proc in procs.iter() {
+            if referenced.contains(&proc) {
+                exempt.insert(declaration_key(proc));
+            }
+        }
+
+        for proc in procs {
+            let key = declaration_key(proc);
+            let family = match self.families.get(&key) {
+                Some(family) => family,
+                None => continue,
+            };
+            let decl = match key.0.get().procs.get(key.1).and_then(|type_proc| type_proc.declaration.as_ref()) {
+                Some(decl) => decl,
+                None => continue,
+            };
+            if family.unknown
+                || decl.location.is_builtins()
+                || decl.kind.is_verb()
+                || MANDATED_PROCS.contains(&key.1)
+                || self.unresolved.contains(key.1)
+                || exempt.contains(&key)
+            {
+                continue;
+            }
+            let reads = match self.reads.get(&proc) {
+                Some(reads) => reads,
+                None => continue,
+            };
+
+            for (index, param) in proc.get().parameters.iter().enumerate() {
+                if !reads.contains(&index) || family.positions.contains(&index) || family.names.contains(param.name.as_str()) {
+                    continue;
+                }
+                let message = match folded_default(param) {
+                    Some(default) => format!("no call of {} gives a value for {}, so it's always its default of {}", proc, param.name, default),
+                    None => format!("no call of {} gives a value for {}", proc, param.name),
+                };
+                let mut error = DMError::new(param.location, message)
+                    .with_component(dm::Component::DreamChecker)
+                    .set_severity(Severity::Info)
+                    .with_errortype("unsupplied_parameter");
+                if let Some(first_call) = family.first_call {
+                    error = match family.calls {
+                        1 => error.with_note(first_call, "called without it here"),
+                        calls => error.with_note(first_call, format!("called without it here, and {} other places", calls - 1)),
+                    };
+                }
+                error.register(context);
+            }
+        }
+    }
+}
+
+/// The type which declares a proc, and its name.
+fn declaration_key(proc: ProcRef<'_>) -> (TypeRef<'_>, &str) {
+    let name = proc.name();
+    (declaring_type(proc.ty(), name, true).unwrap_or(proc.ty()), name)
+}
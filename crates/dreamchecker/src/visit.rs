@@ -0,0 +1,117 @@
+//! Traversal of nested expressions.
+
+use dm::ast::*;
+
+/// Call a function on an expression and each expression nested within it.
+pub fn visit<'e>(expr: &'e Expression, f: &mut dyn FnMut(&'e Expression)) {
+    f(expr);
+    match expr {
+        Expression::Base { term, follow } => {
+            visit_term(&term.elem, f);
+            for each in follow.iter() {
+                match &each.elem {
+                    Follow::Index(_, index) => visit(index, f),
+                    Follow::Call(_, _, args) => visit_all(args, f),
+                    Follow::Field(..) | Follow::Unary(_) => {}
+                }
+            }
+        }
+        Expression::BinaryOp { lhs, rhs, .. } |
+        Expression::AssignOp { lhs, rhs, .. } => {
+            visit(lhs, f);
+            visit(rhs, f);
+        }
+        Expression::TernaryOp { cond, if_, else_ } => {
+            visit(cond, f);
+            visit(if_, f);
+            visit(else_, f);
+        }
+    }
+}
+
+pub fn visit_all<'e>(exprs: &'e [Expression], f: &mut dyn FnMut(&'e Expression)) {
+    for expr in exprs.iter() {
+        visit(expr, f);
+    }
+}
+
+pub fn visit_term<'e>(term: &'e Term, f: &mut dyn FnMut(&'e Expression)) {
+    match term {
+        Term::Expr(expr) => visit(expr, f),
+        Term::Prefab(prefab) => {
+            for (_, value) in prefab.vars.iter() {
+                visit(value, f);
+            }
+        }
+        Term::InterpString(_, parts) => {
+            for expr in parts.iter().filter_map(|(expr, _)| expr.as_ref()) {
+                visit(expr, f);
+            }
+        }
+        Term::Call(_, args) |
+        Term::SelfCall(args) |
+        Term::ParentCall(args) |
+        Term::List(args) => visit_all(args, f),
+        Term::NewImplicit { args } |
+        Term::NewPrefab { args, .. } |
+        Term::NewMiniExpr { args, .. } => {
+            if let Some(args) = args {
+                visit_all(args, f);
+            }
+        }
+        Term::Input { args, in_list, .. } |
+        Term::Locate { args, in_list } => {
+            visit_all(args, f);
+            if let Some(in_list) = in_list {
+                visit(in_list, f);
+            }
+        }
+        Term::Pick(choices) => {
+            for (weight, value) in choices.iter() {
+                if let Some(weight) = weight {
+                    visit(weight, f);
+                }
+                visit(value, f);
+            }
+        }
+        Term::DynamicCall(first, second) => {
+            visit_all(first, f);
+            visit_all(second, f);
+        }
+        _ => {}
+    }
+}
+
+/// Whether evaluating an expression calls a proc.
+pub fn has_call(expr: &Expression) -> bool {
+    let mut found = false;
+    visit(expr, &mut |expr| {
+        if let Expression::Base { term, follow } = expr {
+            found |= calls(&term.elem, follow);
+        }
+    });
+    found
+}
+
+/// Whether a term or what follows it calls a proc, not counting what is
+/// nested within them.
+pub fn calls(term: &Term, follow: &[Spanned<Follow>]) -> bool {
+    is_call(term) || follow.iter().any(|each| matches!(each.elem, Follow::Call(..)))
+}
+
+/// Whether a term calls a proc, counting `new`, `input()`, `locate()`, and
+/// `pick()`.
+pub fn is_call(term: &Term) -> bool {
+    matches!(term,
+        Term::Call(..) |
+        Term::SelfCall(_) |
+        Term::ParentCall(_) |
+        Term::NewImplicit { .. } |
+        Term::NewPrefab { .. } |
+        Term::NewMiniExpr { .. } |
+        Term::Input { .. } |
+        Term::Locate { .. } |
+        Term::Pick(_) |
+        Term::DynamicCall(..)
+    )
+}
@@ -0,0 +1,112 @@
+extern crate dreamchecker as dc;
+
+use dc::test_helpers::*;
+
+pub const ALWAYS_NULL_ERRORS: &[(u32, u16, &str)] = &[
+    (5, 18, "O is always null here, as nothing but null is ever assigned to it"),
+    (9, 18, "cleared is always null here, as nothing but null is ever assigned to it"),
+];
+
+#[test]
+fn always_null() {
+    let code = r##"
+/obj/var/health = 10
+
+/proc/test(obj/param)
+    var/obj/O
+    world.log << O.health
+    O.health = 5
+    var/obj/cleared = param
+    cleared = null
+    world.log << cleared.health
+"##.trim();
+    check_errors_match(code, ALWAYS_NULL_ERRORS);
+}
+
+#[test]
+fn sometimes_assigned() {
+    let code = r##"
+/obj/var/health = 10
+
+/proc/test(list/L, cond)
+    var/obj/maybe
+    if (cond)
+        maybe = locate(/obj) in L
+    world.log << maybe.health
+    var/obj/looped
+    for (looped in L)
+        world.log << looped.health
+    var/obj/later
+    while (prob(50))
+        if (later)
+            world.log << later.health
+        later = new
+    var/obj/safe
+    world.log << safe?.health
+    var/obj/inline
+    if ((inline = locate(/obj) in L) && inline.health)
+        world.log << "found"
+"##.trim();
+    check_errors_match(code, NO_ERRORS);
+}
+
+#[test]
+fn assigned_after_spawn() {
+    let code = r##"
+/obj/var/health = 10
+
+/proc/test()
+    var/obj/O
+    spawn(10)
+        world.log << O.health
+    O = new
+"##.trim();
+    check_errors_match(code, NO_ERRORS);
+}
+
+#[test]
+fn unassigned_fields() {
+    let code = r##"
+/obj/var/obj/item/held
+/obj/var/obj/item/given
+/obj/var/obj/item/initialized
+/obj/var/obj/item/set_elsewhere
+/obj/var/untyped
+
+/obj/proc/setup(obj/item/I)
+    given = I
+    var/obj/other = new
+    other.set_elsewhere = I
+
+/obj/child/initialized = new /obj/item
+/obj/item
+"##.trim();
+    let config = "[dreamchecker]\nunassigned_fields = true\n";
+    let context = context_with_config("unassigned-fields", config);
+    check_context_errors_match(&parse_a_file_for_test_in(context, code), &[
+        (1, 23, "/obj/var/obj/item/held is never assigned a value"),
+    ]);
+    check_errors_match(code, NO_ERRORS);
+}
+
+#[test]
+fn unassigned_fields_by_type() {
+    let code = r##"
+/obj/var/obj/item/held
+/mob/var/obj/item/held
+/mob/var/obj/item/carried
+/turf/var/obj/item/carried
+
+/obj/proc/setup(obj/item/I, mob/M, turf/T)
+    held = I
+    M.carried = I
+    T:carried = I
+
+/obj/item
+"##.trim();
+    let config = "[dreamchecker]\nunassigned_fields = true\n";
+    let context = context_with_config("unassigned-fields-by-type", config);
+    check_context_errors_match(&parse_a_file_for_test_in(context, code), &[
+        (2, 23, "/mob/var/obj/item/held is never assigned a value"),
+    ]);
+}
@@ -20,6 +20,7 @@ fn const_eval() {
 }
 
 pub const IF_ELSE_ERRORS: &[(u32, u16, &str)] = &[
+    (2, 5, "both branches of this if are the same, so its condition makes no difference"),
     (6, 5, "possible unreachable code here"),
 ];
 
@@ -0,0 +1,99 @@
+extern crate dreamchecker as dc;
+extern crate dreammaker as dm;
+
+use dc::builtin_eval::evaluate;
+use dm::constants::Constant;
+
+fn text(value: &str) -> Constant {
+    Constant::string(value)
+}
+
+fn num(value: f32) -> Constant {
+    Constant::Float(value)
+}
+
+#[test]
+fn text_length_and_case() {
+    assert_eq!(evaluate("length", &[text("hello")]), Some(num(5.)));
+    assert_eq!(evaluate("length", &[Constant::Null(None)]), Some(num(0.)));
+    assert_eq!(evaluate("length", &[text("né")]), Some(num(3.)));
+    assert_eq!(evaluate("length_char", &[text("né")]), Some(num(2.)));
+    assert_eq!(evaluate("uppertext", &[text("abc")]), Some(text("ABC")));
+    assert_eq!(evaluate("lowertext", &[text("AbC")]), Some(text("abc")));
+}
+
+#[test]
+fn text_search() {
+    assert_eq!(evaluate("findtext", &[text("Hello"), text("l")]), Some(num(3.)));
+    assert_eq!(evaluate("findtext", &[text("ABC"), text("b")]), Some(num(2.)));
+    assert_eq!(evaluate("findtextEx", &[text("ABC"), text("b")]), Some(num(0.)));
+    assert_eq!(evaluate("findtext", &[text("Hello"), text("l"), num(4.)]), Some(num(4.)));
+    assert_eq!(evaluate("findtext", &[text("Hello"), text("lo"), num(1.), num(5.)]), Some(num(0.)));
+    assert_eq!(evaluate("replacetext", &[text("Hello hello"), text("HELLO"), text("bye")]), Some(text("bye bye")));
+    assert_eq!(evaluate("replacetextEx", &[text("Hello hello"), text("hello"), text("bye")]), Some(text("Hello bye")));
+    assert_eq!(evaluate("cmptext", &[text("abc"), text("ABC")]), Some(num(1.)));
+    assert_eq!(evaluate("cmptextEx", &[text("abc"), text("ABC")]), Some(num(0.)));
+}
+
+#[test]
+fn text_slicing() {
+    assert_eq!(evaluate("copytext", &[text("Hello"), num(2.), num(4.)]), Some(text("el")));
+    assert_eq!(evaluate("copytext", &[text("Hello"), num(3.)]), Some(text("llo")));
+    assert_eq!(evaluate("copytext", &[text("Hello"), num(1.), num(0.)]), Some(text("Hello")));
+    assert_eq!(evaluate("copytext", &[text("Hello"), num(6.)]), Some(text("")));
+    assert_eq!(evaluate("text2ascii", &[text("A")]), Some(num(65.)));
+    assert_eq!(evaluate("text2ascii", &[text("AB"), num(3.)]), Some(num(0.)));
+    assert_eq!(evaluate("ascii2text", &[num(65.)]), Some(text("A")));
+}
+
+#[test]
+fn text_conversion() {
+    assert_eq!(evaluate("text2num", &[text("12.5")]), Some(num(12.5)));
+    assert_eq!(evaluate("text2num", &[text("-3")]), Some(num(-3.)));
+    assert_eq!(evaluate("text2num", &[num(7.)]), Some(num(7.)));
+    assert_eq!(evaluate("num2text", &[num(42.)]), Some(text("42")));
+    assert_eq!(evaluate("istext", &[text("")]), Some(num(1.)));
+    assert_eq!(evaluate("isnum", &[text("1")]), Some(num(0.)));
+    assert_eq!(evaluate("isnull", &[Constant::Null(None)]), Some(num(1.)));
+}
+
+#[test]
+fn rounding() {
+    assert_eq!(evaluate("round", &[num(2.7)]), Some(num(2.)));
+    assert_eq!(evaluate("round", &[num(-2.5)]), Some(num(-3.)));
+    assert_eq!(evaluate("round", &[num(2.5), num(1.)]), Some(num(3.)));
+    assert_eq!(evaluate("round", &[num(17.), num(5.)]), Some(num(15.)));
+    assert_eq!(evaluate("floor", &[num(-1.5)]), Some(num(-2.)));
+    assert_eq!(evaluate("ceil", &[num(1.2)]), Some(num(2.)));
+    assert_eq!(evaluate("trunc", &[num(-1.5)]), Some(num(-1.)));
+    assert_eq!(evaluate("fract", &[num(-1.5)]), Some(num(-0.5)));
+}
+
+#[test]
+fn math() {
+    assert_eq!(evaluate("abs", &[num(-4.)]), Some(num(4.)));
+    assert_eq!(evaluate("sqrt", &[num(16.)]), Some(num(4.)));
+    assert_eq!(evaluate("min", &[num(3.), num(1.), num(2.)]), Some(num(1.)));
+    assert_eq!(evaluate("max", &[num(3.), num(1.), num(2.)]), Some(num(3.)));
+    assert_eq!(evaluate("clamp", &[num(15.), num(1.), num(10.)]), Some(num(10.)));
+    assert_eq!(evaluate("clamp", &[num(-5.), num(1.), num(10.)]), Some(num(1.)));
+    assert_eq!(evaluate("sin", &[num(30.)]), Some(num(0.5)));
+    assert_eq!(evaluate("cos", &[num(60.)]), Some(num(0.5)));
+    assert_eq!(evaluate("arcsin", &[num(1.)]), Some(num(90.)));
+    assert_eq!(evaluate("arctan", &[num(1.)]), Some(num(45.)));
+    assert_eq!(evaluate("arctan", &[num(0.), num(1.)]), Some(num(90.)));
+    assert_eq!(evaluate("log", &[num(10.), num(100.)]), Some(num(2.)));
+}
+
+#[test]
+fn uncertain_calls_are_not_evaluated() {
+    assert_eq!(evaluate("copytext", &[text("Hello"), num(-2.)]), None);
+    assert_eq!(evaluate("copytext", &[text("Hello"), num(1.5)]), None);
+    assert_eq!(evaluate("findtext", &[text("Hello"), text("")]), None);
+    assert_eq!(evaluate("length", &[text("\\red")]), None);
+    assert_eq!(evaluate("text2num", &[text("12abc")]), None);
+    assert_eq!(evaluate("sqrt", &[num(-1.)]), None);
+    assert_eq!(evaluate("round", &[num(1.), num(0.)]), None);
+    assert_eq!(evaluate("uppertext", &[num(1.)]), None);
+    assert_eq!(evaluate("view", &[]), None);
+}
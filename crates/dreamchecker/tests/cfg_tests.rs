@@ -0,0 +1,330 @@
+extern crate dreamchecker as dc;
+extern crate dreammaker as dm;
+
+use dc::cfg::{BlockId, ControlFlowGraph, EdgeKind};
+use dm::Context;
+use dm::ast::*;
+use dm::indents::IndentProcessor;
+use dm::lexer::Lexer;
+use dm::parser::Parser;
+
+use EdgeKind::{Jump, True, False, Case, Return, Throw, Exception, Spawn};
+
+fn parse_proc(code: &str) -> Block {
+    let context = Context::default();
+    let lexer = Lexer::new(&context, Default::default(), code.trim().as_bytes());
+    let mut parser = Parser::new(&context, IndentProcessor::new(&context, lexer));
+    parser.enable_procs();
+    let tree = parser.parse_object_tree();
+    context.assert_success();
+    let code = tree.root().get_proc("test").expect("no /proc/test").get().code.clone();
+    code.expect("no code")
+}
+
+/// Every block with a step on the given line, in creation order.
+fn blocks_at(graph: &ControlFlowGraph, line: u32) -> Vec<BlockId> {
+    (0..graph.blocks.len())
+        .filter(|&id| graph.blocks[id].steps.iter().any(|step| step.location().line == line))
+        .collect()
+}
+
+/// The only block with a step on the given line.
+fn at(graph: &ControlFlowGraph, line: u32) -> BlockId {
+    let blocks = blocks_at(graph, line);
+    assert_eq!(blocks.len(), 1, "line {} is in blocks {:?}", line, blocks);
+    blocks[0]
+}
+
+fn successors(graph: &ControlFlowGraph, id: BlockId) -> Vec<(EdgeKind, BlockId)> {
+    graph.blocks[id].successors.clone()
+}
+
+/// The single successor of a block which holds no steps.
+fn through(graph: &ControlFlowGraph, id: BlockId) -> BlockId {
+    match successors(graph, id)[..] {
+        [(Jump, next)] => next,
+        ref other => panic!("block {} has successors {:?}", id, other),
+    }
+}
+
+#[test]
+fn if_else() {
+    let code = parse_proc(r#"
+/proc/test(x)
+    if (x)
+        x = 1
+    else if (x > 1)
+        x = 2
+    else
+        x = 3
+    return x
+"#);
+    let graph = ControlFlowGraph::new(&code);
+    assert_eq!(graph.entry, at(&graph, 2));
+    assert_eq!(successors(&graph, at(&graph, 2)), vec![(True, at(&graph, 3)), (False, at(&graph, 4))]);
+    assert_eq!(successors(&graph, at(&graph, 4)), vec![(True, at(&graph, 5)), (False, at(&graph, 7))]);
+    // Every arm of the chain rejoins at the same block.
+    for line in [3, 5, 7] {
+        assert_eq!(through(&graph, at(&graph, line)), at(&graph, 8));
+    }
+    assert_eq!(successors(&graph, at(&graph, 8)), vec![(Return, graph.exit)]);
+    assert!(graph.blocks[graph.exit].steps.is_empty());
+}
+
+#[test]
+fn while_loop() {
+    let code = parse_proc(r#"
+/proc/test(x)
+    while (x)
+        if (x > 5)
+            break
+        x++
+        continue
+    while (1)
+        x--
+    return
+"#);
+    let graph = ControlFlowGraph::new(&code);
+    let header = at(&graph, 2);
+    let after = graph.blocks[header].successors[1].1;
+    assert_eq!(successors(&graph, header), vec![(True, at(&graph, 3)), (False, after)]);
+    assert_eq!(successors(&graph, at(&graph, 4)), vec![(Jump, after)]);
+    assert_eq!(successors(&graph, at(&graph, 6)), vec![(Jump, header)]);
+    // A constant condition never exits the loop.
+    assert_eq!(through(&graph, after), at(&graph, 7));
+    assert_eq!(successors(&graph, at(&graph, 7)), vec![(True, at(&graph, 8))]);
+    assert_eq!(successors(&graph, at(&graph, 8)), vec![(Jump, at(&graph, 7))]);
+    let reachable = graph.reachable(false);
+    assert!(!reachable[at(&graph, 9)]);
+    assert!(!reachable[graph.exit]);
+}
+
+#[test]
+fn other_loops() {
+    let code = parse_proc(r#"
+/proc/test(x)
+    do
+        x--
+    while (x)
+    for (var/i = 1; i < 10; i++)
+        x += i
+    for ()
+        return
+    x = 1
+"#);
+    let graph = ControlFlowGraph::new(&code);
+    assert_eq!(successors(&graph, at(&graph, 3)), vec![(Jump, at(&graph, 4))]);
+    let after_do = graph.blocks[at(&graph, 4)].successors[1].1;
+    assert_eq!(successors(&graph, at(&graph, 4)), vec![(True, at(&graph, 3)), (False, after_do)]);
+
+    let for_blocks = blocks_at(&graph, 5);
+    let (init, header, increment) = (for_blocks[0], for_blocks[1], for_blocks[2]);
+    assert_eq!(init, after_do);
+    assert_eq!(successors(&graph, init), vec![(Jump, header)]);
+    let after_for = graph.blocks[header].successors[1].1;
+    assert_eq!(successors(&graph, header), vec![(True, at(&graph, 6)), (False, after_for)]);
+    assert_eq!(successors(&graph, at(&graph, 6)), vec![(Jump, increment)]);
+    assert_eq!(successors(&graph, increment), vec![(Jump, header)]);
+
+    assert_eq!(through(&graph, after_for), at(&graph, 8));
+    assert_eq!(successors(&graph, at(&graph, 8)), vec![(Return, graph.exit)]);
+    // Nothing leaves an infinite loop without a break.
+    assert!(graph.blocks[at(&graph, 9)].predecessors.is_empty());
+    assert!(!graph.reachable(true)[at(&graph, 9)]);
+}
+
+#[test]
+fn labelled_loops() {
+    let code = parse_proc(r#"
+/proc/test(list/L)
+    outer:
+        for (var/a in L)
+            for (var/b in L)
+                if (b)
+                    continue outer
+                break outer
+    return
+"#);
+    let graph = ControlFlowGraph::new(&code);
+    let outer = through(&graph, at(&graph, 3));
+    let done = graph.blocks[outer].successors[1].1;
+    assert_eq!(successors(&graph, outer), vec![(True, at(&graph, 4)), (False, done)]);
+    let inner = through(&graph, at(&graph, 4));
+    assert_eq!(successors(&graph, inner)[0], (True, at(&graph, 5)));
+    assert_eq!(successors(&graph, at(&graph, 6)), vec![(Jump, outer)]);
+    assert_eq!(successors(&graph, at(&graph, 7)), vec![(Jump, done)]);
+    // The end of the outer loop is also the end of the labelled block.
+    assert_eq!(through(&graph, done), at(&graph, 8));
+}
+
+#[test]
+fn switch() {
+    let code = parse_proc(r#"
+/proc/test(x)
+    switch (x)
+        if (1)
+            x = 1
+        if (2 to 3)
+            x = 2
+    switch (x)
+        if (1, 2)
+            x = 3
+        else
+            x = 4
+    return
+"#);
+    let graph = ControlFlowGraph::new(&code);
+    let first_join = through(&graph, at(&graph, 4));
+    assert_eq!(successors(&graph, at(&graph, 2)), vec![
+        (Case(0), at(&graph, 4)),
+        (Case(1), at(&graph, 6)),
+        (EdgeKind::Default, first_join),
+    ]);
+    assert_eq!(successors(&graph, at(&graph, 6)), vec![(Jump, first_join)]);
+    assert_eq!(successors(&graph, first_join), vec![(Case(0), at(&graph, 9)), (EdgeKind::Default, at(&graph, 11))]);
+    assert_eq!(through(&graph, at(&graph, 11)), at(&graph, 12));
+}
+
+#[test]
+fn switch_on_constant_var() {
+    let (_, tree, _) = dc::test_helpers::parse_tree_for_test(r#"
+/obj/var/const/MODE = 2
+
+/obj/proc/test()
+    var/const/LEVEL = 1
+    switch (LEVEL)
+        if (1)
+            world.log << 1
+        if (2)
+            world.log << 2
+    switch (MODE)
+        if (1)
+            world.log << 3
+        else
+            world.log << 4
+    return
+"#.trim());
+    let proc = tree.find("/obj").unwrap().get_proc("test").unwrap();
+    let code = proc.get().code.as_ref().unwrap();
+    // Without the type, only the local is known.
+    let graph = ControlFlowGraph::new(code);
+    assert_eq!(successors(&graph, at(&graph, 5)), vec![(Case(0), at(&graph, 7))]);
+    assert_eq!(successors(&graph, at(&graph, 10)).len(), 2);
+
+    let graph = ControlFlowGraph::for_proc(proc, code);
+    assert_eq!(successors(&graph, at(&graph, 5)), vec![(Case(0), at(&graph, 7))]);
+    assert_eq!(successors(&graph, at(&graph, 10)), vec![(EdgeKind::Default, at(&graph, 14))]);
+    assert!(!graph.reachable(false)[at(&graph, 12)]);
+}
+
+#[test]
+fn switch_on_shadowed_constant_var() {
+    let (_, tree, _) = dc::test_helpers::parse_tree_for_test(r#"
+/obj/var/const/MODE = 2
+
+/obj/proc/test()
+    var/MODE = rand(1, 2)
+    switch (MODE)
+        if (1)
+            world.log << 1
+        if (2)
+            world.log << 2
+    return
+"#.trim());
+    let proc = tree.find("/obj").unwrap().get_proc("test").unwrap();
+    let graph = ControlFlowGraph::for_proc(proc, proc.get().code.as_ref().unwrap());
+    assert_eq!(successors(&graph, at(&graph, 5)).len(), 3);
+}
+
+#[test]
+fn switch_on_parameter_shadowing_constant_var() {
+    let (_, tree, _) = dc::test_helpers::parse_tree_for_test(r#"
+/obj/var/const/MODE = 2
+
+/obj/proc/test(MODE)
+    switch (MODE)
+        if (1)
+            world.log << 1
+        if (2)
+            world.log << 2
+    return
+"#.trim());
+    let proc = tree.find("/obj").unwrap().get_proc("test").unwrap();
+    let graph = ControlFlowGraph::for_proc(proc, proc.get().code.as_ref().unwrap());
+    assert_eq!(successors(&graph, at(&graph, 4)).len(), 3);
+}
+
+#[test]
+fn try_catch() {
+    let code = parse_proc(r#"
+/proc/test()
+    try
+        world.log << 1
+        throw EXCEPTION("x")
+    catch (var/exception/e)
+        world.log << e
+    return
+"#);
+    let graph = ControlFlowGraph::new(&code);
+    let catch = at(&graph, 6);
+    let body = at(&graph, 3);
+    assert_eq!(body, at(&graph, 4));
+    assert_eq!(successors(&graph, body), vec![(Exception, catch), (Throw, catch)]);
+    assert!(graph.blocks[catch].predecessors.contains(&(Exception, body)));
+    assert_eq!(through(&graph, catch), at(&graph, 7));
+    // The catch block itself isn't covered by its own try.
+    assert!(!graph.blocks[catch].successors.iter().any(|&(kind, _)| kind == Exception));
+}
+
+#[test]
+fn spawn() {
+    let code = parse_proc(r#"
+/proc/test()
+    spawn (10)
+        return
+    world.log << 1
+"#);
+    let graph = ControlFlowGraph::new(&code);
+    let spawned = at(&graph, 3);
+    assert_eq!(successors(&graph, graph.entry), vec![(Spawn, spawned), (Jump, at(&graph, 4))]);
+    // Returning from spawned code doesn't return from the proc.
+    assert!(successors(&graph, spawned).is_empty());
+    assert!(!graph.reachable(false)[spawned]);
+    assert!(graph.reachable(true)[spawned]);
+    assert_eq!(graph.blocks[graph.exit].predecessors, vec![(Jump, at(&graph, 4))]);
+}
+
+#[test]
+fn goto() {
+    let code = parse_proc(r#"
+/proc/test(x)
+    start:
+        x++
+    if (x < 10)
+        goto start
+    goto nowhere
+    return
+"#);
+    let graph = ControlFlowGraph::new(&code);
+    assert_eq!(at(&graph, 2), at(&graph, 3));
+    assert_eq!(successors(&graph, at(&graph, 5)), vec![(Jump, at(&graph, 2))]);
+    assert!(successors(&graph, at(&graph, 6)).is_empty());
+    assert!(!graph.reachable(false)[at(&graph, 7)]);
+}
+
+#[test]
+fn dot_export() {
+    let code = parse_proc(r#"
+/proc/test(x)
+    if (x == "a")
+        x = 1
+    return x
+"#);
+    let graph = ControlFlowGraph::new(&code);
+    let dot = graph.dot().to_string();
+    assert!(dot.starts_with("digraph cfg {\n"));
+    assert!(dot.ends_with("}\n"));
+    assert!(dot.contains(r#"b0 [label="entry\lx == \"a\""];"#), "{}", dot);
+    assert!(dot.contains("b1 [label=\"exit\"];"));
+    assert!(dot.contains("b0 -> b2 [label=\"True\"];"));
+}
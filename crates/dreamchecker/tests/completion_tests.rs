@@ -0,0 +1,100 @@
+extern crate dreamchecker as dc;
+extern crate dreammaker as dm;
+
+use dc::completion::{members, members_at, Member, MemberKind};
+use dc::test_helpers::*;
+use dm::Location;
+
+const CODE: &str = r##"
+/obj/item
+    /// How hard it hits.
+    var/force = 5
+    var/obj/item/parent
+
+/obj/item/proc/hit(mob/target)
+    set SpacemanDMM_return_type = /mob
+    return target
+
+/obj/item/sword
+    force = 10
+
+/obj/item/sword/hit(mob/target)
+    var/obj/item/I = new /obj/item
+    if (I)
+        var/inner = 1
+        return inner
+    var/after = 2
+    return ..()
+
+/obj/item/sword/verb/swing()
+"##;
+
+fn find<'a>(members: &'a [Member], kind: MemberKind, name: &str) -> &'a Member {
+    members.iter().find(|each| each.kind == kind && each.name == name).unwrap()
+}
+
+#[test]
+fn type_members() {
+    let (_context, tree, _file) = parse_tree_for_test(CODE.trim());
+    let found = members(&tree, tree.expect("/obj/item/sword"));
+
+    let force = find(&found, MemberKind::Var, "force");
+    assert_eq!(force.declared_on.as_deref(), Some("/obj/item"));
+    assert_eq!(force.docs.as_deref().map(str::trim), Some("How hard it hits."));
+    assert_eq!(force.location.map(|each| each.line), Some(3));
+    assert_eq!(found.iter().filter(|each| each.name == "force").count(), 1);
+
+    let parent = find(&found, MemberKind::Var, "parent");
+    assert_eq!(parent.type_path.as_deref(), Some("/obj/item"));
+
+    let hit = find(&found, MemberKind::Proc, "hit");
+    assert_eq!(hit.declared_on.as_deref(), Some("/obj/item"));
+    assert_eq!(found.iter().filter(|each| each.name == "hit").count(), 1);
+
+    let swing = find(&found, MemberKind::Verb, "swing");
+    assert_eq!(swing.declared_on.as_deref(), Some("/obj/item/sword"));
+
+    // Builtin vars and procs are visible too.
+    assert!(found.iter().any(|each| each.kind == MemberKind::Var && each.name == "name"));
+    assert!(found.iter().any(|each| each.kind == MemberKind::Proc && each.name == "Del"));
+}
+
+#[test]
+fn return_type_member() {
+    let (_context, tree, _file) = parse_tree_for_test(CODE.trim());
+    let found = members(&tree, tree.expect("/obj/item"));
+    assert_eq!(find(&found, MemberKind::Proc, "hit").type_path.as_deref(), Some("/mob"));
+}
+
+fn locals_at(line: u32, column: u16) -> Vec<(String, Option<String>)> {
+    let (_context, tree, file) = parse_tree_for_test(CODE.trim());
+    members_at(&tree, Location { file, line, column }).unwrap().into_iter()
+        .filter(|each| each.kind == MemberKind::Local)
+        .map(|each| (each.name, each.type_path))
+        .collect()
+}
+
+#[test]
+fn locals_in_scope() {
+    let names = |locals: Vec<(String, Option<String>)>| locals.into_iter().map(|(name, _)| name).collect::<Vec<_>>();
+
+    let locals = locals_at(15, 9);
+    assert!(locals.contains(&("I".to_owned(), Some("/obj/item".to_owned()))));
+    assert!(locals.contains(&("target".to_owned(), Some("/mob".to_owned()))));
+    assert!(!names(locals).contains(&"inner".to_owned()));
+
+    let locals = names(locals_at(17, 16));
+    assert!(locals.contains(&"inner".to_owned()));
+
+    let locals = names(locals_at(19, 9));
+    assert!(locals.contains(&"after".to_owned()));
+    assert!(!locals.contains(&"inner".to_owned()));
+}
+
+#[test]
+fn members_at_includes_type() {
+    let (_context, tree, file) = parse_tree_for_test(CODE.trim());
+    let found = members_at(&tree, Location { file, line: 14, column: 5 }).unwrap();
+    assert!(found.iter().any(|each| each.kind == MemberKind::Var && each.name == "force"));
+    assert!(members_at(&tree, Location { file, line: 2, column: 5 }).is_none());
+}
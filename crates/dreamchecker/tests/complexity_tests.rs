@@ -0,0 +1,123 @@
+extern crate dreamchecker as dc;
+extern crate dreammaker as dm;
+
+use dc::cfg::ControlFlowGraph;
+use dc::complexity::cyclomatic_complexity;
+use dc::test_helpers::*;
+use dm::Context;
+use dm::indents::IndentProcessor;
+use dm::lexer::Lexer;
+use dm::parser::Parser;
+
+fn complexity_of(code: &str) -> u32 {
+    let context = Context::default();
+    let lexer = Lexer::new(&context, Default::default(), code.trim().as_bytes());
+    let mut parser = Parser::new(&context, IndentProcessor::new(&context, lexer));
+    parser.enable_procs();
+    let tree = parser.parse_object_tree();
+    context.assert_success();
+    let proc = tree.root().get_proc("test").expect("no /proc/test");
+    let code = proc.get().code.as_ref().expect("no code");
+    cyclomatic_complexity(&ControlFlowGraph::new(code))
+}
+
+#[test]
+fn straight_line() {
+    assert_eq!(complexity_of(r##"
+/proc/test(a)
+    a += 1
+    return a
+"##), 1);
+}
+
+#[test]
+fn if_arms() {
+    assert_eq!(complexity_of(r##"
+/proc/test(a)
+    if (a > 1)
+        return 1
+    else if (a)
+        return 2
+    else
+        return 3
+"##), 3);
+}
+
+#[test]
+fn loops() {
+    assert_eq!(complexity_of(r##"
+/proc/test(a, list/L)
+    while (a)
+        a--
+    do
+        a++
+    while (a < 10)
+    for (var/i = 0; i < a; i++)
+        world.log << i
+    for (var/x in L)
+        world.log << x
+    for (var/j in 1 to a)
+        world.log << j
+    while (TRUE)
+        break
+"##), 7);
+}
+
+#[test]
+fn switch_cases() {
+    assert_eq!(complexity_of(r##"
+/proc/test(a)
+    switch (a)
+        if (1)
+            return "one"
+        if (2, 3)
+            return "few"
+        if (4 to 10)
+            return "some"
+        else
+            return "many"
+"##), 4);
+}
+
+#[test]
+fn boolean_operators() {
+    assert_eq!(complexity_of(r##"
+/proc/test(a, b, c)
+    if (a && (b || !c))
+        return 1
+    var/d = a && b
+    return d
+"##), 4);
+}
+
+#[test]
+fn spawned_code() {
+    assert_eq!(complexity_of(r##"
+/proc/test(a)
+    spawn (10)
+        if (a)
+            return
+"##), 2);
+}
+
+#[test]
+fn complexity_threshold() {
+    let code = r##"
+/proc/simple(a)
+    if (a)
+        return 1
+
+/proc/branchy(a, b)
+    if (a)
+        return 1
+    if (b)
+        return 2
+    if (a && b)
+        return 3
+"##.trim();
+    let context = context_with_config("complexity", "[dreamchecker]\ncomplexity_threshold = 4\n");
+    check_context_errors_match(&parse_a_file_for_test_in(context, code), &[
+        (5, 14, "/proc/branchy has a cyclomatic complexity of 5, more than the limit of 4"),
+    ]);
+    check_errors_match(code, NO_ERRORS);
+}
@@ -0,0 +1,126 @@
+extern crate dreamchecker as dc;
+
+use dc::test_helpers::*;
+
+const CONFIG: &str = "[dreamchecker]\ndead_stores = true\n";
+
+fn check_dead_stores(name: &str, code: &'static str, errors: &[(u32, u16, &str)]) {
+    let context = context_with_config(name, CONFIG);
+    check_context_errors_match(&parse_a_file_for_test_in(context, code.trim()), errors);
+}
+
+#[test]
+fn dead_stores_are_opt_in() {
+    check_errors_match(r##"
+/proc/test()
+    var/x = 1
+    x = 2
+"##.trim(), NO_ERRORS);
+}
+
+pub const OVERWRITTEN_ERRORS: &[(u32, u16, &str)] = &[
+    (6, 5, "value assigned to x is never read, but the call still runs for its side effects"),
+    (9, 5, "initial value of y is never read"),
+    (12, 5, "initial value of z is never read"),
+    (13, 5, "value assigned to z is never read"),
+];
+
+#[test]
+fn overwritten_stores() {
+    check_dead_stores("overwritten", r##"
+/proc/compute()
+    return 1
+
+/proc/test()
+    var/x
+    x = compute()
+    x = 2
+    world.log << x
+    var/y = 5
+    y = 6
+    world.log << y
+    var/z = 0
+    z = 1
+"##, OVERWRITTEN_ERRORS);
+}
+
+#[test]
+fn stores_read_on_some_path() {
+    check_dead_stores("some-path", r##"
+/proc/test(cond)
+    var/x = 1
+    if (cond)
+        x = 2
+    world.log << x
+    var/y = 0
+    while (cond)
+        y += 1
+        cond = y < 10
+    var/total = 0
+    for (var/i in 1 to 3)
+        total = total + i
+    return total
+"##, NO_ERRORS);
+}
+
+#[test]
+fn stores_which_escape() {
+    check_dead_stores("escape", r##"
+/proc/callback(thing, proc_ref, arg)
+
+/datum/proc/test(a, b)
+
+/datum/child/test(a, b)
+    var/x = 1
+    spawn (10)
+        world.log << x
+    var/y = 2
+    callback(null, .proc/test, y)
+    a = 3
+    ..()
+    b = 4
+    world.log << args[2]
+    try
+        var/z = 5
+        z = "[b]"
+        throw EXCEPTION("z")
+    catch (var/exception/e)
+        world.log << "[e]"
+"##, &[
+        // The store to `a` is passed on by `..()`, but the argument is not.
+        (5, 20, "parameter a is overwritten before being read"),
+        (16, 9, "initial value of z is never read"),
+        (17, 9, "value assigned to z is never read"),
+    ]);
+}
+
+#[test]
+fn overwritten_parameters() {
+    check_dead_stores("params", r##"
+/proc/test(a, b, c)
+    a = 1
+    world.log << a
+    if (b)
+        b = 2
+    world.log << b
+    c = 3
+"##, &[
+        (1, 13, "parameter a is overwritten before being read"),
+        (7, 5, "value assigned to c is never read"),
+    ]);
+}
+
+#[test]
+fn shadowed_and_static_locals() {
+    check_dead_stores("shadowed", r##"
+/proc/test(cond)
+    if (cond)
+        var/x = 1
+        x = 2
+    else
+        var/x = 3
+        world.log << x
+    var/static/counter = 0
+    counter = 1
+"##, NO_ERRORS);
+}
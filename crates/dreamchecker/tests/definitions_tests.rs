@@ -0,0 +1,40 @@
+extern crate dreamchecker as dc;
+
+use dc::definitions::{index_definitions, DefinitionKind};
+use dc::test_helpers::*;
+
+#[test]
+fn definitions() {
+    let code = r##"
+/obj/item
+    var/force = 5
+
+/obj/item/proc/hit(mob/target)
+    return force
+
+/obj/item/sword/hit(mob/target)
+    var/obj/item/I = new /obj/item
+    var/damage = I.hit(target) + ..()
+    target.nonexistent()
+    undefined_proc()
+    return damage
+"##.trim();
+    let (_context, tree, file) = parse_tree_for_test(code);
+    let index = index_definitions(&tree, Some(file));
+    let found: Vec<_> = index.occurrences.iter()
+        .map(|each| (each.location.line, each.location.column, each.name.as_str(), each.kind, each.definition.map(|def| def.line)))
+        .collect();
+    assert_eq!(found, [
+        (5, 12, "force", DefinitionKind::Var, Some(2)),
+        (8, 22, "/obj/item", DefinitionKind::Type, Some(1)),
+        (9, 18, "I", DefinitionKind::Local, Some(8)),
+        (9, 19, "hit", DefinitionKind::Proc, Some(4)),
+        (9, 24, "target", DefinitionKind::Local, Some(7)),
+        (9, 34, "..", DefinitionKind::Proc, Some(4)),
+        (10, 5, "target", DefinitionKind::Local, Some(7)),
+        (10, 11, "nonexistent", DefinitionKind::Unresolved, None),
+        (11, 5, "undefined_proc", DefinitionKind::Unresolved, None),
+        (12, 12, "damage", DefinitionKind::Local, Some(9)),
+    ]);
+    assert_eq!(index.at(index.occurrences[3].location).map(|each| each.name.as_str()), Some("hit"));
+}
@@ -0,0 +1,22 @@
+extern crate dreamchecker as dc;
+
+use std::path::Path;
+
+use dc::test_helpers::check_fixture;
+
+/// Check every fixture in `tests/fixtures`; see `check_fixture` for how they
+/// express what they expect.
+#[test]
+fn fixtures() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("fixtures");
+    let mut paths: Vec<_> = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.is_dir() || path.extension().is_some_and(|ext| ext == "dm"))
+        .collect();
+    paths.sort();
+    assert!(!paths.is_empty());
+    for path in paths {
+        check_fixture(&path);
+    }
+}
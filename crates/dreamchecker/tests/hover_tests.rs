@@ -0,0 +1,102 @@
+extern crate dreamchecker as dc;
+extern crate dreammaker as dm;
+
+use dc::hover::{hover, HoverInfo};
+use dc::test_helpers::*;
+use dm::Location;
+use dm::constants::Constant;
+
+const CODE: &str = r##"
+/obj/item
+    var/obj/item/parent
+    var/force = 5
+
+/obj/item/proc/describe()
+    return "item"
+
+/obj/item/proc/test(obj/item/other)
+    var/obj/item/I = new /obj/item
+    var/text = I.describe()
+    var/x = null
+    world.log << I.parent.force
+    world.log << x
+    world.log << other
+"##;
+
+fn hover_at(line: u32, column: u16) -> Option<HoverInfo> {
+    let (_context, tree, file) = parse_tree_for_test(CODE.trim());
+    hover(&tree, Location { file, line, column })
+}
+
+#[test]
+fn hover_local() {
+    let info = hover_at(10, 16).unwrap();
+    assert_eq!((info.location.line, info.location.column), (10, 16));
+    assert_eq!(info.type_path.as_deref(), Some("/obj/item"));
+    assert_eq!(info.declaration.map(|each| each.line), Some(9));
+}
+
+#[test]
+fn hover_call() {
+    let info = hover_at(10, 20).unwrap();
+    assert_eq!((info.location.line, info.location.column), (10, 17));
+    assert_eq!(info.declaration.map(|each| each.line), Some(5));
+}
+
+#[test]
+fn hover_field_chain() {
+    let info = hover_at(12, 21).unwrap();
+    assert_eq!(info.type_path.as_deref(), Some("/obj/item"));
+    assert_eq!(info.declaration.map(|each| each.line), Some(2));
+
+    let info = hover_at(12, 28).unwrap();
+    assert_eq!(info.declaration.map(|each| each.line), Some(3));
+}
+
+#[test]
+fn hover_constant() {
+    let info = hover_at(13, 18).unwrap();
+    assert_eq!(info.null, Some(true));
+    assert_eq!(info.value, Some(Constant::Null(None)));
+    assert_eq!(info.declaration.map(|each| each.line), Some(11));
+}
+
+#[test]
+fn hover_parameter() {
+    let info = hover_at(14, 20).unwrap();
+    assert_eq!(info.type_path.as_deref(), Some("/obj/item"));
+    assert_eq!(info.null, None);
+}
+
+#[test]
+fn hover_outside_procs() {
+    assert_eq!(hover_at(2, 10), None);
+}
+
+#[test]
+fn hover_between_terms() {
+    // The space before `<<`, the operator itself, and past the end of the line.
+    assert_eq!(hover_at(12, 14), None);
+    assert_eq!(hover_at(12, 15), None);
+    assert_eq!(hover_at(10, 28), None);
+}
+
+#[test]
+fn hover_call_argument() {
+    let code = r##"
+/proc/pair(a, b)
+    return a
+
+/proc/test()
+    var/x = null
+    world.log << pair(x, 1)
+"##.trim();
+    let (_context, tree, file) = parse_tree_for_test(code);
+    let info = hover(&tree, Location { file, line: 6, column: 23 }).unwrap();
+    assert_eq!((info.location.line, info.location.column), (6, 23));
+    assert_eq!(info.declaration.map(|each| each.line), Some(5));
+
+    let info = hover(&tree, Location { file, line: 6, column: 19 }).unwrap();
+    assert_eq!((info.location.line, info.location.column), (6, 18));
+    assert_eq!(info.declaration.map(|each| each.line), Some(1));
+}
@@ -0,0 +1,37 @@
+extern crate dreamchecker as dc;
+
+use dc::test_helpers::*;
+
+pub const LABEL_ERRORS: &[(u32, u16, &str)] = &[
+    (8, 5, "goto retyr: no such label in this proc"),
+    (10, 9, "goto later: cannot jump into or out of a spawn block"),
+    (20, 5, "goto inner: cannot jump into or out of a spawn block"),
+    (4, 5, "label leftover is never used"),
+];
+
+#[test]
+fn labels() {
+    let code = r##"
+/proc/test(list/L)
+    retry:
+        world.log << "trying"
+    leftover:
+        world.log << "done"
+    if (prob(50))
+        goto retry
+    goto retyr
+    spawn
+        goto later
+    later:
+        world.log << "later"
+    outer:
+        for (var/x in L)
+            for (var/y in x)
+                break outer
+    spawn
+        inner:
+            world.log << "spawned"
+    goto inner
+"##.trim();
+    check_errors_match(code, LABEL_ERRORS);
+}
@@ -0,0 +1,52 @@
+extern crate dreamchecker as dc;
+
+use dc::lens::proc_lenses;
+use dc::test_helpers::*;
+
+const CODE: &str = r##"
+/obj/item/proc/attack()
+    return
+
+/obj/item/attack()
+    ..()
+
+/obj/item/sword/attack()
+    return ..()
+
+/obj/item/sword/long/attack()
+    return
+
+/obj/item/axe/attack()
+    return
+
+/proc/use(obj/item/I, obj/item/sword/S, obj/item/axe/A)
+    I.attack()
+    S.attack()
+    A.attack()
+"##;
+
+fn lenses() -> Vec<(String, u32, Vec<u32>, Vec<u32>)> {
+    let (_context, tree, file) = parse_tree_for_test(CODE.trim());
+    proc_lenses(&tree, file).into_iter()
+        .map(|lens| (
+            lens.path,
+            lens.location.line,
+            lens.overrides.iter().map(|each| each.line).collect(),
+            lens.references.iter().map(|each| each.line).collect(),
+        ))
+        .collect()
+}
+
+#[test]
+fn lens_counts() {
+    assert_eq!(lenses(), [
+        // Only reachable through the redefinition's `..()`.
+        ("/obj/item/proc/attack".to_owned(), 1, vec![4, 7, 10, 13], vec![5]),
+        // Any call on an item may run it, as well as `..()` from a sword.
+        ("/obj/item/proc/attack".to_owned(), 4, vec![7, 10, 13], vec![8, 17]),
+        ("/obj/item/sword/proc/attack".to_owned(), 7, vec![10], vec![17, 18]),
+        ("/obj/item/sword/long/proc/attack".to_owned(), 10, vec![], vec![17, 18]),
+        ("/obj/item/axe/proc/attack".to_owned(), 13, vec![], vec![17, 19]),
+        ("/proc/use".to_owned(), 16, vec![], vec![]),
+    ]);
+}
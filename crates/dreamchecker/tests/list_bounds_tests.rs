@@ -0,0 +1,105 @@
+extern crate dreamchecker as dc;
+
+use dc::test_helpers::*;
+
+pub const LIST_BOUNDS_ERRORS: &[(u32, u16, &str)] = &[
+    (3, 19, "list index 4 is out of bounds for a list of length 3"),
+    (5, 23, "list index 9 is out of bounds for a list of length 8"),
+    (6, 19, "list index 0 is out of bounds"),
+    (7, 23, "list index -1 is out of bounds"),
+    (11, 23, "list index 2 is out of bounds for a list of length 0"),
+    (12, 32, "list index 3 is out of bounds for a list of length 2"),
+];
+
+#[test]
+fn list_bounds() {
+    let code = r##"
+/proc/test(list/other)
+    var/list/L = list(1, 2, 3)
+    world.log << L[4]
+    var/list/sized[8]
+    world.log << sized[9]
+    world.log << L[0]
+    world.log << other[-1]
+    for (var/x in L)
+        world.log << L[3] + x
+    var/list/empty = new /list()
+    world.log << empty[2]
+    world.log << list("a", "b")[3]
+    if (2 in L)
+        world.log << L.len
+"##.trim();
+    check_errors_match(code, LIST_BOUNDS_ERRORS);
+}
+
+#[test]
+fn list_length_forgotten() {
+    let code = r##"
+/proc/helper(list/L)
+    L += 1
+
+/proc/test(key)
+    var/list/added = list(1)
+    added += 2
+    world.log << added[2]
+    var/list/cut = list(1, 2, 3)
+    cut.Cut(1, 2)
+    world.log << cut[3]
+    var/list/resized = list()
+    resized.len = 5
+    world.log << resized[5]
+    var/list/grown = list()
+    grown.len++
+    world.log << grown[1]
+    var/list/passed = list()
+    helper(passed)
+    world.log << passed[1]
+    var/list/keyed = list()
+    keyed[key] = 1
+    world.log << keyed[1]
+    var/list/alias = list()
+    var/list/other = alias
+    other.Add(1)
+    world.log << alias[1]
+    var/list/reassigned = list()
+    reassigned = list(1, 2)
+    world.log << reassigned[2]
+"##.trim();
+    check_errors_match(code, NO_ERRORS);
+}
+
+pub const SIZED_LIST_ERRORS: &[(u32, u16, &str)] = &[
+    (9, 17, "undefined var: \"missing\""),
+    (10, 18, "undefined var: \"absent\""),
+    (17, 5, "outputting a list prints a reference to it, not its contents"),
+    (18, 5, "outputting a list prints a reference to it, not its contents"),
+    (3, 24, "list index 9 is out of bounds for a list of length 8"),
+    (5, 22, "list index 3 is out of bounds for a list of length 2"),
+    (6, 25, "list index 4 is out of bounds for a list of length 3"),
+    (8, 23, "list index 3 is out of bounds for a list of length 2"),
+];
+
+#[test]
+fn sized_lists() {
+    let code = r##"
+/proc/test(count)
+    var/list/folded[2 * 4]
+    world.log << folded[9]
+    var/grid[2][3]
+    world.log << grid[3][1]
+    world.log << grid[1][4]
+    var/given[10] = list(1, 2)
+    world.log << given[3]
+    var/unknown[missing]
+    var/explicit[absent] = list()
+    var/list/rows[2][3]
+    var/list/row = rows[1]
+    row.Add(1)
+    world.log << rows[1][4]
+    var/dynamic[count]
+    world.log << dynamic[20]
+    world.log << unknown
+    world.log << explicit
+"##.trim();
+    check_errors_match(code, SIZED_LIST_ERRORS);
+}
@@ -7,6 +7,7 @@ pub const LOCAL_SCOPE_ERRORS: &[(u32, u16, &str)] = &[
     (7, 9, "undefined var: \"bar\""),
     (9, 5, "undefined var: \"bar\""),
     (13, 5, "undefined var: \"bar\""),
+    (11, 5, "label alabel is never used"),
 ];
 
 #[test]
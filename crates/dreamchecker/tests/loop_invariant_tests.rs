@@ -0,0 +1,81 @@
+extern crate dreamchecker as dc;
+extern crate dreammaker as dm;
+
+use dc::test_helpers::*;
+
+pub const LOOP_INVARIANT_ERRORS: &[(u32, u16, &str)] = &[
+    (4, 5, "loop condition does not change between iterations"),
+    (8, 12, "if condition does not change between iterations of the loop"),
+    (11, 5, "loop condition does not change between iterations"),
+];
+
+#[test]
+fn loop_invariant_conditions() {
+    let code = r##"
+/mob/var/health = 100
+
+/proc/test(mob/target, mob/victim, verbose, list/L)
+    while (target.health > 0)
+        world.log << victim
+    for (var/x in L)
+        world.log << x
+        if (verbose)
+            world.log << "verbose"
+    var/i = 0
+    for (var/j = 0; j < 10;)
+        i++
+"##.trim();
+    check_errors_match(code, LOOP_INVARIANT_ERRORS);
+}
+
+#[test]
+fn loop_variant_conditions() {
+    let code = r##"
+/mob/var/health = 100
+/mob/proc/hurt()
+    health -= 5
+
+/proc/test(mob/target, list/L, cond)
+    while (target.health > 0)
+        target.hurt()
+    while (target.health > 0)
+        sleep(1)
+    var/i = 0
+    while (i < 10)
+        i++
+    for (var/x in L)
+        if (x > 5)
+            break
+        if (prob(50))
+            break
+        if (cond)
+            cond = FALSE
+        spawn
+            if (cond)
+                world.log << "later"
+    do
+        L.Cut(1, 2)
+    while (L.len)
+    while (TRUE)
+        if (!L)
+            return
+        L = null
+"##.trim();
+    check_errors_match(code, &[
+        (20, 9, "spawn inside a loop schedules a separate proc on every iteration, all of which run as soon as this proc sleeps or returns"),
+    ]);
+}
+
+#[test]
+fn loop_invariant_note() {
+    let code = r##"
+/proc/test(a, b)
+    while (a < b)
+        world.log << a
+"##.trim();
+    let context = parse_a_file_for_test(code);
+    let errors = context.errors();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].severity(), dm::Severity::Info);
+    assert_eq!(errors[0].notes()[0].description(), "a, b are not modified by the loop");
+}
@@ -0,0 +1,112 @@
+extern crate dreamchecker as dc;
+
+use dc::test_helpers::*;
+
+pub const MUST_RETURN_ERRORS: &[(u32, u16, &str)] = &[
+    (25, 18, "/proc/global_proc can reach the end without returning a value"),
+    (1, 20, "/obj/proc/falls_off can reach the end without returning a value"),
+    (6, 22, "/obj/proc/bare_return can reach the end without returning a value"),
+];
+
+#[test]
+fn must_return() {
+    let code = r##"
+/obj/proc/falls_off(x)
+    set SpacemanDMM_return_type = /obj
+    if (x)
+        return src
+
+/obj/proc/bare_return(x)
+    set SpacemanDMM_must_return = 1
+    if (x)
+        return
+    . = 1
+
+/obj/proc/assigns_dot(x)
+    set SpacemanDMM_must_return = 1
+    if (x)
+        . = 2
+    else
+        return 3
+
+/obj/proc/diverges(x)
+    set SpacemanDMM_must_return = 1
+    if (x)
+        return 1
+    CRASH("bad")
+
+/proc/global_proc()
+    set SpacemanDMM_must_return = 1
+    spawn
+        . = 1
+
+/obj/proc/loops()
+    set SpacemanDMM_return_type = /obj
+    while (TRUE)
+        if (prob(50))
+            return src
+
+/obj/proc/deletes()
+    set SpacemanDMM_return_type = /obj
+    del src
+
+/obj/proc/unannotated()
+    return
+"##.trim();
+    check_errors_match(code, MUST_RETURN_ERRORS);
+}
+
+#[test]
+fn must_return_inherited() {
+    let code = r##"
+/obj/proc/base()
+    set SpacemanDMM_must_return = 1
+    return 1
+
+/obj/child/base()
+    ..()
+
+/obj/other/base()
+    set SpacemanDMM_must_return = 0
+    ..()
+"##.trim();
+    check_errors_match(code, &[
+        (5, 16, "/obj/child/proc/base can reach the end without returning a value"),
+    ]);
+}
+
+#[test]
+fn must_return_path_note() {
+    let code = r##"
+/proc/pick_one(x)
+    set SpacemanDMM_must_return = 1
+    if (x > 1)
+        return 1
+    else if (x)
+        return 2
+"##.trim();
+    let context = parse_a_file_for_test(code);
+    let errors = context.errors();
+    assert_eq!(errors.len(), 1);
+    let notes: Vec<_> = errors[0].notes().iter().map(|note| (note.location().line, note.description().to_owned())).collect();
+    assert_eq!(notes, vec![(5, "when the condition at line 5 is false".to_owned())]);
+}
+
+#[test]
+fn never_returns_config() {
+    let code = r##"
+/proc/stack_trace_and_die(msg)
+    CRASH(msg)
+
+/proc/checked(x)
+    set SpacemanDMM_must_return = 1
+    if (x)
+        return x
+    stack_trace_and_die("no x")
+"##.trim();
+    let context = context_with_config("never-returns", "[dreamchecker]\nnever_returns = [\"stack_trace_and_die\"]\n");
+    check_context_errors_match(&parse_a_file_for_test_in(context, code), NO_ERRORS);
+    check_errors_match(code, &[
+        (4, 14, "/proc/checked can reach the end without returning a value"),
+    ]);
+}
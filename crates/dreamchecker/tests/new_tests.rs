@@ -42,3 +42,43 @@ fn new_precedence() {
 "##.trim();
     check_errors_match(code, NEW_PRECEDENCE_ERRORS);
 }
+
+#[test]
+fn new_recursion() {
+    let code = r##"
+/obj/item/box/New()
+    ..()
+    new /obj/item/box/large(src)
+    spawn(1)
+        new type(loc)
+/obj/item/box/large
+/obj/item/crate/New(loc, prefill)
+    var/obj/item/crate/C = new
+    new /obj/item/box(src)
+"##.trim();
+    check_errors_match(code, &[
+        (3, 5, "/obj/item/box/large is a subtype of /obj/item/box, so creating it unconditionally in New() recurses forever"),
+        (5, 9, "creating /obj/item/box unconditionally in its own New() recurses forever"),
+        (8, 28, "creating /obj/item/crate unconditionally in its own New() recurses forever"),
+    ]);
+}
+
+#[test]
+fn new_recursion_guarded() {
+    let code = r##"
+/obj/item/box/New(loc, depth = 1)
+    ..()
+    if (depth < 3)
+        new type(src, depth + 1)
+/obj/item/crate/var/static/prefilling = FALSE
+/obj/item/crate/New()
+    if (prefilling)
+        return
+    prefilling = TRUE
+    new /obj/item/crate(src)
+    prefilling = FALSE
+/obj/item/crate/proc/Duplicate()
+    return new type(loc)
+"##.trim();
+    check_errors_match(code, NO_ERRORS);
+}
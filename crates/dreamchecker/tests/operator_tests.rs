@@ -1,6 +1,6 @@
 extern crate dreamchecker as dc;
 
-use dc::test_helpers::check_errors_match;
+use dc::test_helpers::{check_errors_match, parse_a_file_for_test};
 
 pub const IN_AMBIG_ERRORS: &[(u32, u16, &str)] = &[
     (2, 7, "ambiguous `!` on left side of an `in`"),
@@ -90,3 +90,38 @@ fn ambigous_not_bitwise() {
 "##.trim();
     check_errors_match(code, NOT_AMBIG_BITWISE_ERRORS);
 }
+
+pub const LOGICAL_ASSIGN_ERRORS: &[(u32, u16, &str)] = &[
+    (7, 18, "Attempting operator++ on a /mob which does not overload operator++"),
+];
+
+#[test]
+fn logical_assign() {
+    let code = r##"
+/mob/test/operator++()
+    return
+
+/proc/test()
+    var/mob/M = new
+    var/mob/other = new
+    (M ||= other)++
+    var/mob/test/T = new
+    (T &&= new /mob/test)++
+    (T ?= null)++
+    (M ||= T)++
+"##.trim();
+    check_errors_match(code, LOGICAL_ASSIGN_ERRORS);
+}
+
+#[test]
+fn text_number_concat_suggestion() {
+    let code = r##"
+/proc/test(name)
+    var/points = 5
+    world << {"Say "hi", "} + points + " \[" + name
+"##.trim();
+    let context = parse_a_file_for_test(code);
+    let errors = context.errors();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].notes()[0].description(), r#"use an embedded expression instead: {"Say "hi", [points] \[[name]"}"#);
+}
@@ -0,0 +1,43 @@
+
+extern crate dreamchecker as dc;
+
+use dc::test_helpers::check_errors_match;
+
+pub const PICK_WEIGHT_ERRORS: &[(u32, u16, &str)] = &[
+    (5, 5, "pick() weights sum to zero"),
+    (6, 5, "pick() always chooses entry 2 of 3"),
+    (7, 5, "pick() always chooses entry 1 of 2"),
+];
+
+#[test]
+fn pick_weights() {
+    let code = r##"
+/proc/test(x)
+    pick(30; "a", 70; "b")
+    pick(prob(30); "a", prob(70); "b", "c")
+    pick(x; "a", 0; "b")
+    pick(0; "a", 0; "b")
+    pick(0; "a", 5 + 5; "b", 0; "c")
+    pick("a", prob(0); "b")
+    pick(0; "a")
+"##.trim();
+    check_errors_match(code, PICK_WEIGHT_ERRORS);
+}
+
+pub const PICK_TYPE_ERRORS: &[(u32, u16, &str)] = &[
+    (5, 18, "undefined field: \"nonexistent\" on /obj/thing"),
+    (6, 15, "field access requires static type: \"nonexistent\""),
+];
+
+#[test]
+fn pick_result_type() {
+    let code = r##"
+/obj/thing/proc/test()
+    var/obj/thing/a = src
+    var/obj/thing/b = src
+    var/mob/c
+    pick(a, 3; b).nonexistent
+    pick(a, c).nonexistent
+"##.trim();
+    check_errors_match(code, PICK_TYPE_ERRORS);
+}
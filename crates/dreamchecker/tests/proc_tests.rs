@@ -16,3 +16,33 @@ fn no_parent() {
 "##.trim();
     check_errors_match(code, NO_PARENT_ERRORS);
 }
+
+pub const PROC_REFERENCE_ERRORS: &[(u32, u16, &str)] = &[
+    (9, 19, "undefined proc: \"missing\" on /obj/thing"),
+    (10, 19, "undefined proc: \"gone\" on /mob"),
+    (11, 19, "failed to resolve path /nowhere/proc/foo"),
+    (14, 19, "\"update\" on /obj/thing is a proc, not a verb"),
+    (15, 19, "\"act\" on /mob is a verb, not a proc"),
+];
+
+#[test]
+fn proc_references() {
+    let code = r##"
+/proc/callback(thing, procpath)
+
+/mob/verb/act()
+
+/obj/thing/proc/update()
+
+/obj/thing/proc/test()
+    callback(src, .proc/update)
+    callback(src, .proc/missing)
+    callback(src, /mob/proc/gone)
+    callback(src, /nowhere/proc/foo)
+    callback(src, /mob/verb/act)
+    callback(src, /proc/callback)
+    callback(src, .verb/update)
+    callback(src, /mob/proc/act)
+"##.trim();
+    check_errors_match(code, PROC_REFERENCE_ERRORS);
+}
@@ -0,0 +1,86 @@
+extern crate dreamchecker as dc;
+
+use dc::definitions::index_definitions;
+use dc::references::{find_references, Declaration, ReferenceKind, ReferenceOptions};
+use dc::test_helpers::*;
+
+const CODE: &str = r##"
+/obj/item
+    var/force = 5
+
+/obj/item/proc/hit(mob/target)
+    return force
+
+/obj/item/sword
+    force = 10
+
+/obj/item/sword/hit(mob/target)
+    var/obj/item/I = new /obj/item/sword
+    var/untyped = I
+    untyped:force = 0
+    return I.hit(target) + ..() + I.force
+
+/obj/item/sword/long/hit(mob/target)
+    return ..()
+"##;
+
+fn references(path: &str, options: ReferenceOptions) -> Vec<(u32, u16, ReferenceKind)> {
+    let (_context, tree, file) = parse_tree_for_test(CODE.trim());
+    let declaration = Declaration::find(&tree, path).unwrap();
+    let index = index_definitions(&tree, Some(file));
+    find_references(&tree, &index, &declaration, options).iter()
+        .map(|each| (each.location.line, each.location.column, each.kind))
+        .collect()
+}
+
+#[test]
+fn var_references() {
+    assert_eq!(references("/obj/item/var/force", ReferenceOptions::default()), [
+        (5, 12, ReferenceKind::Resolved),
+        (14, 36, ReferenceKind::Resolved),
+    ]);
+    // Naming the var on a subtype finds the same declaration.
+    assert_eq!(references("/obj/item/sword/var/force", ReferenceOptions::default()).len(), 2);
+}
+
+#[test]
+fn var_potential_references() {
+    let options = ReferenceOptions { overrides: true, unresolved: true };
+    assert_eq!(references("/obj/item/var/force", options), [
+        (5, 12, ReferenceKind::Resolved),
+        (8, 11, ReferenceKind::Override),
+        (13, 12, ReferenceKind::Unresolved),
+        (14, 36, ReferenceKind::Resolved),
+    ]);
+}
+
+#[test]
+fn proc_references() {
+    assert_eq!(references("/obj/item/proc/hit", ReferenceOptions::default()), [
+        (14, 13, ReferenceKind::Resolved),
+        (14, 28, ReferenceKind::Resolved),
+    ]);
+    let options = ReferenceOptions { overrides: true, ..Default::default() };
+    assert_eq!(references("/obj/item/proc/hit", options), [
+        (10, 20, ReferenceKind::Override),
+        (14, 13, ReferenceKind::Resolved),
+        (14, 28, ReferenceKind::Resolved),
+        (16, 25, ReferenceKind::Override),
+        (17, 12, ReferenceKind::Override),
+    ]);
+}
+
+#[test]
+fn type_references() {
+    assert_eq!(references("/obj/item/sword", ReferenceOptions::default()), [
+        (11, 22, ReferenceKind::Resolved),
+    ]);
+}
+
+#[test]
+fn missing_declarations() {
+    let (_context, tree, _file) = parse_tree_for_test(CODE.trim());
+    assert!(Declaration::find(&tree, "/obj/item/var/nonexistent").is_none());
+    assert!(Declaration::find(&tree, "/obj/item/proc/force").is_none());
+    assert!(Declaration::find(&tree, "/obj/nonexistent").is_none());
+}
@@ -0,0 +1,140 @@
+extern crate dreamchecker as dc;
+
+use dc::references::Declaration;
+use dc::rename::{apply_edits, plan_rename, EditKind, RenameError, RenamePlan};
+use dc::test_helpers::*;
+
+const ITEM: &str = r##"
+/obj/item/proc/attack_self(mob/user)
+    return
+
+/obj/item/proc/use(mob/user)
+    attack_self(user)
+    spawn(1)
+        call(src, "attack_self")(user)
+"##;
+
+const SWORD: &str = r##"
+/obj/item/sword/attack_self(mob/user)
+    ..()
+    var/callback = .proc/attack_self
+    return callback
+
+/obj/item/sword/proc/activate(mob/user, thing)
+    var/obj/item/sword/S = src
+    S.attack_self(user)
+    thing:attack_self(user)
+"##;
+
+fn files() -> [(&'static str, &'static str); 2] {
+    [("item.dm", ITEM.trim_start()), ("sword.dm", SWORD.trim_start())]
+}
+
+fn plan(path: &str, new_name: &str) -> Result<RenamePlan, RenameError> {
+    let files = files();
+    let (context, tree) = parse_files_for_test(&files);
+    let declaration = Declaration::find(&tree, path).unwrap();
+    plan_rename(&tree, &declaration, new_name, &mut |file| {
+        let path = context.file_path(file);
+        files.iter().find(|(name, _)| path.ends_with(name)).map(|(_, code)| code.to_string())
+    })
+}
+
+fn edits(plan: &RenamePlan) -> Vec<(u32, u16, EditKind)> {
+    plan.edits.iter().map(|edit| (edit.location.line, edit.location.column, edit.kind)).collect()
+}
+
+#[test]
+fn rename_overrides_across_files() {
+    let plan = plan("/obj/item/proc/attack_self", "interact").unwrap();
+    // The files are registered last first, so sword.dm's edits come first.
+    assert_eq!(edits(&plan)[..], [
+        (1, 17, EditKind::Override),
+        (3, 26, EditKind::Reference),
+        (8, 7, EditKind::Reference),
+        (9, 11, EditKind::Colon),
+        (1, 16, EditKind::Declaration),
+        (5, 5, EditKind::Reference),
+        (7, 20, EditKind::String),
+    ]);
+    assert!(plan.missing.is_empty());
+    assert!(plan.has_unsafe());
+
+    // Applying the edits to each file replaces only the name.
+    let (sword, item) = plan.edits.split_at(4);
+    for (code, edits) in [(SWORD, sword), (ITEM, item)].iter() {
+        let edits: Vec<_> = edits.iter().collect();
+        let renamed = apply_edits(code.trim_start(), "attack_self", &edits).unwrap();
+        assert_eq!(renamed, code.trim_start().replace("attack_self", "interact"));
+    }
+}
+
+#[test]
+fn rename_collision() {
+    let code = r##"
+/obj/item/proc/attack_self()
+    return
+
+/obj/item/sword/proc/interact()
+    return
+
+/obj/item/sword/var/force
+"##;
+    let files = [("collide.dm", code.trim_start())];
+    let (_context, tree) = parse_files_for_test(&files);
+    let declaration = Declaration::find(&tree, "/obj/item/proc/attack_self").unwrap();
+    let result = plan_rename(&tree, &declaration, "interact", &mut |_| Some(files[0].1.to_owned()));
+    match result {
+        Err(RenameError::Collision { path, location }) => {
+            assert_eq!(path, "/obj/item/sword/proc/interact");
+            assert_eq!(location.line, 4);
+        }
+        other => panic!("unexpected result {:?}", other),
+    }
+
+    // A var of the same name isn't a collision for a proc.
+    assert!(plan_rename(&tree, &declaration, "force", &mut |_| Some(files[0].1.to_owned())).is_ok());
+}
+
+#[test]
+fn rename_invalid() {
+    assert_eq!(plan("/obj/item/proc/attack_self", "1st").unwrap_err(), RenameError::InvalidName("1st".to_owned()));
+    assert_eq!(plan("/obj/item", "thing").unwrap_err(), RenameError::NotVarOrProc);
+}
+
+#[test]
+fn apply_conflicts() {
+    let plan = plan("/obj/item/proc/attack_self", "interact").unwrap();
+    let here: Vec<_> = plan.edits[4..].iter().collect();
+
+    // The file changed since it was parsed.
+    let changed = ITEM.trim_start().replacen("attack_self", "attack", 1);
+    assert_eq!(apply_edits(&changed, "attack_self", &here), Err(here[0].location));
+
+    // Two edits overlap.
+    let overlapping = [here[0], here[0]];
+    assert!(apply_edits(ITEM.trim_start(), "attack_self", &overlapping).is_err());
+}
+
+#[test]
+fn rename_raw_strings() {
+    let code = r##"
+/obj/item/proc/attack_self(mob/user)
+    return
+
+/obj/item/proc/use(mob/user)
+    call(src, @"attack_self")(user)
+    call(src, @{"attack_self"})(user)
+"##.trim_start();
+    let files = [("item.dm", code)];
+    let (_, tree) = parse_files_for_test(&files);
+    let declaration = Declaration::find(&tree, "/obj/item/proc/attack_self").unwrap();
+    let plan = plan_rename(&tree, &declaration, "interact", &mut |_| Some(code.to_owned())).unwrap();
+    assert_eq!(edits(&plan)[..], [
+        (1, 16, EditKind::Declaration),
+        (5, 17, EditKind::String),
+        (6, 18, EditKind::String),
+    ]);
+    let edits: Vec<_> = plan.edits.iter().collect();
+    assert_eq!(apply_edits(code, "attack_self", &edits).unwrap(), code.replace("attack_self", "interact"));
+}
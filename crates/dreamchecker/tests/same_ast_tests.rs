@@ -0,0 +1,97 @@
+extern crate dreamchecker as dc;
+extern crate dreammaker as dm;
+
+use dc::same_ast::{same_block, same_expression};
+use dm::Context;
+use dm::ast::*;
+use dm::indents::IndentProcessor;
+use dm::lexer::{Lexer, LocationTracker};
+use dm::parser::Parser;
+
+fn expr(code: &str) -> Expression {
+    let context = Context::default();
+    let mut lexer = Lexer::from_input(&context, LocationTracker::from_location(Default::default(), code.as_bytes().into()));
+    dm::parser::parse_expression(&context, Default::default(), &mut lexer).unwrap()
+}
+
+fn same(a: &str, b: &str) -> bool {
+    same_expression(&expr(a), &expr(b))
+}
+
+/// The bodies of `/proc/a` and `/proc/b`.
+fn bodies(code: &str) -> (Block, Block) {
+    let context = Context::default();
+    let lexer = Lexer::new(&context, Default::default(), code.trim().as_bytes());
+    let mut parser = Parser::new(&context, IndentProcessor::new(&context, lexer));
+    parser.enable_procs();
+    let tree = parser.parse_object_tree();
+    context.assert_success();
+    let body = |name| tree.root().get_proc(name).unwrap().get().code.clone().unwrap();
+    (body("a"), body("b"))
+}
+
+#[test]
+fn expressions() {
+    assert!(same("a + b * 2", "a + b * 2"));
+    assert!(same("(a) + (b * 2)", "a + b * 2"));
+    assert!(same("((x.y))[1]", "(x.y)[1]"));
+    assert!(same("foo(a, (b))", "foo(a, b)"));
+    assert!(same("x ? (1) : 2", "x ? 1 : 2"));
+    assert!(!same("a + b", "b + a"));
+    assert!(!same("a - b", "a + b"));
+    assert!(!same("foo(a)", "foo(a, b)"));
+    assert!(!same("x.y", "x:y"));
+    assert!(!same("1", "1.5"));
+}
+
+#[test]
+fn interpolated_strings() {
+    assert!(same(r#""[a] and [b]""#, r#""[(a)] and [b]""#));
+    assert!(same(r#""\the [src]""#, r#""\the [src]""#));
+    assert!(!same(r#""[a] and [b]""#, r#""[a] or [b]""#));
+    assert!(!same(r#""[a] and [b]""#, r#""[b] and [a]""#));
+    assert!(!same(r#""[a]""#, r#""[a][]""#));
+}
+
+#[test]
+fn nested_blocks() {
+    let (a, b) = bodies(r#"
+/proc/a(x)
+    if (x)
+        for (var/i in 1 to 3)
+            world.log << "[i]"
+    else
+        while ((x > 1))
+            x--
+/proc/b(x)
+    if ((x))
+        for (var/i in 1 to (3))
+            world.log << "[(i)]"
+    else
+        while (x > 1)
+            x--
+"#);
+    assert!(same_block(&a, &b));
+
+    let (a, b) = bodies(r#"
+/proc/a(x)
+    if (x)
+        for (var/i in 1 to 3)
+            world.log << "[i]"
+/proc/b(x)
+    if (x)
+        for (var/i in 1 to 3)
+            world.log << "[i + 1]"
+"#);
+    assert!(!same_block(&a, &b));
+
+    let (a, b) = bodies(r#"
+/proc/a(x)
+    var/y = x
+    return y
+/proc/b(x)
+    var/z = x
+    return z
+"#);
+    assert!(!same_block(&a, &b));
+}
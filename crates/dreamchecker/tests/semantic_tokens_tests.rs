@@ -0,0 +1,90 @@
+extern crate dreamchecker as dc;
+
+use dc::semantic_tokens::{encode_lsp, semantic_tokens, SemanticToken, TokenKind};
+use dc::test_helpers::*;
+
+const CODE: &str = r##"
+#define DAMAGE 5
+
+/obj/item
+    var/force = DAMAGE
+    name = "item"
+
+/obj/item/proc/hit(mob/target, times = 2)
+    var/total = 0
+    for (var/i in 1 to times)
+        total += force
+    total++
+    target.take(total)
+    world << "hit [target.name] for [total]"
+    var/callback = .proc/hit
+    return new /obj/item
+"##;
+
+fn tokens() -> Vec<SemanticToken> {
+    let (_context, tree, annotations, file) = parse_annotated_tree_for_test(CODE.trim());
+    semantic_tokens(&tree, &annotations, file, CODE.trim())
+}
+
+#[test]
+fn classify_fixture() {
+    let text = CODE.trim();
+    let found: Vec<_> = tokens().iter().map(|token| {
+        let line = text.lines().nth(token.location.line as usize - 1).unwrap();
+        let start = token.location.column as usize - 1;
+        (token.location.line, token.location.column, &line[start..start + token.length], token.kind)
+    }).collect();
+    assert_eq!(found, [
+        (1, 16, "5", TokenKind::Constant),
+        (3, 2, "obj", TokenKind::TypePath),
+        (3, 6, "item", TokenKind::TypePath),
+        (4, 9, "force", TokenKind::VarDeclaration),
+        (4, 17, "DAMAGE", TokenKind::Macro),
+        (5, 5, "name", TokenKind::Field),
+        (5, 12, "\"item\"", TokenKind::Constant),
+        (7, 2, "obj", TokenKind::TypePath),
+        (7, 6, "item", TokenKind::TypePath),
+        (7, 16, "hit", TokenKind::ProcDefinition),
+        (7, 20, "mob", TokenKind::TypePath),
+        (7, 24, "target", TokenKind::VarDeclaration),
+        (7, 32, "times", TokenKind::VarDeclaration),
+        (7, 40, "2", TokenKind::Constant),
+        (8, 9, "total", TokenKind::VarDeclaration),
+        (8, 17, "0", TokenKind::Constant),
+        (9, 14, "i", TokenKind::VarDeclaration),
+        (9, 19, "1", TokenKind::Constant),
+        (9, 24, "times", TokenKind::LocalRead),
+        (10, 9, "total", TokenKind::LocalWrite),
+        (10, 18, "force", TokenKind::Field),
+        (11, 5, "total", TokenKind::LocalWrite),
+        (12, 5, "target", TokenKind::LocalRead),
+        (12, 12, "take", TokenKind::ProcCall),
+        (13, 5, "world", TokenKind::Field),
+        (13, 14, "\"hit [", TokenKind::Constant),
+        (13, 20, "target", TokenKind::LocalRead),
+        (13, 20, "target.name", TokenKind::Interpolation),
+        (13, 27, "name", TokenKind::Field),
+        (13, 31, "] for [", TokenKind::Constant),
+        (13, 38, "total", TokenKind::LocalRead),
+        (13, 38, "total", TokenKind::Interpolation),
+        (13, 43, "]\"", TokenKind::Constant),
+        (14, 9, "callback", TokenKind::VarDeclaration),
+        (14, 26, "hit", TokenKind::ProcCall),
+        (15, 17, "obj", TokenKind::TypePath),
+        (15, 21, "item", TokenKind::TypePath),
+    ]);
+}
+
+#[test]
+fn encode_fixture() {
+    let data = encode_lsp(&tokens());
+    // The `5` of the define, then `obj` and `item` two lines later.
+    assert_eq!(data[..15], [
+        0, 15, 1, 9, 0,
+        2, 1, 3, 0, 0,
+        0, 4, 4, 0, 0,
+    ]);
+    // Interpolations are left out.
+    let interpolation = TokenKind::ALL.iter().position(|&kind| kind == TokenKind::Interpolation).unwrap() as u32;
+    assert!(data.chunks(5).all(|token| token[3] != interpolation));
+}
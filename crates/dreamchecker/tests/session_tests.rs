@@ -0,0 +1,62 @@
+extern crate dreamchecker as dc;
+
+use std::path::{Path, PathBuf};
+
+use dc::session::{Report, Session};
+
+fn env(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("dreamchecker-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let mut dme = String::new();
+    for (path, code) in files {
+        dme.push_str(&format!("#include \"{}\"\n", path));
+        std::fs::write(dir.join(path), code).unwrap();
+    }
+    std::fs::write(dir.join("test.dme"), dme).unwrap();
+    dir
+}
+
+fn summary(report: &Report) -> Vec<(&Path, Vec<(u32, &str)>)> {
+    report.files.iter()
+        .map(|file| (&*file.path, file.diagnostics.iter().map(|each| (each.line, &*each.message)).collect()))
+        .collect()
+}
+
+#[test]
+fn session_reports_changes() {
+    let dir = env("session", &[
+        ("a.dm", "/proc/first()\n    missing()\n"),
+        ("b.dm", "/proc/second()\n    first()\n"),
+    ]);
+    let mut session = Session::new(dir.join("test.dme"), None);
+
+    let report = session.check(&["a.dm".into(), "b.dm".into()]).unwrap();
+    assert!(report.reanalyzed);
+    assert_eq!(summary(&report), [
+        (Path::new("a.dm"), vec![(2, "undefined proc: \"missing\" on (global)")]),
+        (Path::new("b.dm"), vec![]),
+    ]);
+
+    // Nothing changed, so nothing is checked again.
+    let report = session.check(&["b.dm".into()]).unwrap();
+    assert!(!report.reanalyzed);
+    assert_eq!(summary(&report), [(Path::new("b.dm"), vec![])]);
+
+    // Fixing a.dm clears it, and breaking b.dm is reported even when only a.dm
+    // is asked about.
+    std::fs::write(dir.join("a.dm"), "/proc/renamed()\n    return\n").unwrap();
+    let report = session.check(&["a.dm".into()]).unwrap();
+    assert!(report.reanalyzed);
+    assert_eq!(summary(&report), [
+        (Path::new("a.dm"), vec![]),
+        (Path::new("b.dm"), vec![(2, "undefined proc: \"first\" on (global)")]),
+    ]);
+
+    // A recheck only reports what changed since.
+    let report = session.recheck().unwrap();
+    assert!(report.reanalyzed);
+    assert!(report.files.is_empty());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,79 @@
+extern crate dreamchecker as dc;
+extern crate dreammaker as dm;
+
+use dc::signature::{signature_help, Callee, SignatureHelp};
+use dc::test_helpers::*;
+use dm::Location;
+use dm::constants::Constant;
+
+const CODE: &str = r##"
+/obj/item/proc/hit(mob/target, damage = 5, flags)
+    return
+
+/obj/item/sword/hit(mob/victim)
+    return
+
+/obj/item/proc/test(obj/item/sword/S, untyped)
+    S.hit(usr, 10, flags = 1)
+    hit(S.hit(usr), 2)
+    untyped:hit(usr)
+    S.hit(usr, @"flags" = 1)
+"##;
+
+fn help_at(line: u32, column: u16) -> Option<SignatureHelp> {
+    let (_context, tree, annotations, file) = parse_annotated_tree_for_test(CODE.trim());
+    signature_help(&tree, &annotations, Location { file, line, column })
+}
+
+#[test]
+fn signature_of_base_declaration() {
+    let help = help_at(8, 12).unwrap();
+    let (path, parameters) = match help.callee {
+        Callee::Proc { path, parameters, .. } => (path, parameters),
+        other => panic!("unexpected callee {:?}", other),
+    };
+    // The override on /obj/item/sword names its parameter differently, but
+    // callers have to follow the declaration.
+    assert_eq!(path, "/obj/item/proc/hit");
+    let names: Vec<_> = parameters.iter().map(|param| param.name.as_str()).collect();
+    assert_eq!(names, ["target", "damage", "flags"]);
+    assert_eq!(parameters[0].type_path.as_deref(), Some("/mob"));
+    assert_eq!(parameters[1].default, Some(Constant::Float(5.)));
+    assert_eq!((help.argument, help.parameter), (0, Some(0)));
+}
+
+#[test]
+fn signature_argument_index() {
+    let help = help_at(8, 16).unwrap();
+    assert_eq!((help.argument, help.parameter), (1, Some(1)));
+
+    // A named argument is passed as the parameter it names.
+    let help = help_at(8, 24).unwrap();
+    assert_eq!((help.argument, help.parameter), (2, Some(2)));
+
+    // So is one named by a raw string.
+    let help = help_at(11, 18).unwrap();
+    assert_eq!((help.argument, help.parameter), (1, Some(2)));
+}
+
+#[test]
+fn signature_nested_calls() {
+    let help = help_at(9, 16).unwrap();
+    assert!(matches!(help.callee, Callee::Proc { .. }));
+    assert_eq!((help.argument, help.parameter), (0, Some(0)));
+
+    let help = help_at(9, 21).unwrap();
+    assert_eq!((help.argument, help.parameter), (1, Some(1)));
+}
+
+#[test]
+fn signature_unknown_callee() {
+    let help = help_at(10, 18).unwrap();
+    assert_eq!(help.callee, Callee::Unknown { name: "hit".to_owned() });
+    assert_eq!(help.argument, 0);
+}
+
+#[test]
+fn signature_outside_calls() {
+    assert_eq!(help_at(7, 3), None);
+}
@@ -1,7 +1,7 @@
 
 extern crate dreamchecker as dc;
 
-use dc::test_helpers::check_errors_match;
+use dc::test_helpers::*;
 
 pub const SLEEP_ERRORS: &[(u32, u16, &str)] = &[
     (16, 16, "/mob/proc/test3 sets SpacemanDMM_should_not_sleep but calls blocking proc /proc/sleepingproc"),
@@ -149,7 +149,72 @@ fn sleep4() {
     check_errors_match(code, SLEEP_ERROR4);
 }
 
+#[test]
+fn never_sleep() {
+    let code = r##"
+/mob/Move()
+    sleep(1)
+    return ..()
+/turf/CanPass()
+    helper()
+/turf/proc/Entered2()
+    sleep(1)
+/proc/helper()
+    input()
+/atom/proc/CanPass()
+    return TRUE
+"##.trim();
+    check_errors_match(code, &[
+        (1, 10, "/mob/proc/Move overrides /atom/movable/proc/Move, which must not sleep, but calls blocking built-in(s)"),
+        (4, 14, "/turf/proc/CanPass overrides /atom/proc/CanPass, which must not sleep, but calls blocking proc /proc/helper"),
+    ]);
+
+    let config = "[dreamchecker]\nnever_sleep = [\"/turf/proc/Entered2\"]\n";
+    let context = context_with_config("never-sleep", config);
+    check_context_errors_match(&parse_a_file_for_test_in(context, code), &[
+        (6, 20, "/turf/proc/Entered2 overrides /turf/proc/Entered2, which must not sleep, but calls blocking built-in(s)"),
+    ]);
+}
+
+#[test]
+fn waitfor_result() {
+    let code = r##"
+/proc/detached()
+    set waitfor = 0
+    sleep(10)
+    return 5
+/proc/quick()
+    set waitfor = FALSE
+    return 1
+/proc/nested()
+    set waitfor = 0
+    detached()
+    return 2
+/proc/indirect()
+    set waitfor = 0
+    helper()
+    return 3
+/proc/helper()
+    sleep(1)
+/mob/proc/test()
+    detached()
+    var/x = detached()
+    if (detached())
+        x = indirect()
+    world.log << quick()
+    world.log << nested()
+    return detached()
+"##.trim();
+    check_errors_match(code, &[
+        (20, 13, "/proc/detached sets waitfor = 0, so it returns to its caller as soon as it sleeps, and the value used here is whatever . held at that point rather than what it finally returns"),
+        (21, 9, "/proc/detached sets waitfor = 0, so it returns to its caller as soon as it sleeps, and the value used here is whatever . held at that point rather than what it finally returns"),
+        (22, 13, "/proc/indirect sets waitfor = 0, so it returns to its caller as soon as it sleeps, and the value used here is whatever . held at that point rather than what it finally returns"),
+        (25, 12, "/proc/detached sets waitfor = 0, so it returns to its caller as soon as it sleeps, and the value used here is whatever . held at that point rather than what it finally returns"),
+    ]);
+}
+
 pub const PURE_ERRORS: &[(u32, u16, &str)] = &[
+    (11, 12, "/proc/foo never returns a value, so the result used here is always null"),
     (12, 16, "/mob/proc/test2 sets SpacemanDMM_should_be_pure but calls a /proc/impure that does impure operations"),
 ];
 
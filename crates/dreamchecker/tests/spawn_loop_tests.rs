@@ -0,0 +1,66 @@
+extern crate dreamchecker as dc;
+
+use dc::test_helpers::*;
+
+pub const SPAWN_LOOP_ERRORS: &[(u32, u16, &str)] = &[
+    (3, 9, "spawn inside a loop schedules a separate proc on every iteration, all of which run as soon as this proc sleeps or returns"),
+    (6, 9, "spawn inside a loop schedules a separate proc on every iteration, all of which the scheduler must hold until their delay passes"),
+    (12, 13, "spawn inside a loop schedules a separate proc on every iteration, all of which run as soon as this proc sleeps or returns"),
+    (16, 13, "spawn inside a loop schedules a separate proc on every iteration, all of which run as soon as this proc sleeps or returns"),
+];
+
+#[test]
+fn spawn_in_loop() {
+    let code = r##"
+/proc/test(list/L, count)
+    for (var/x in L)
+        spawn
+            world.log << x
+    while (count-- > 0)
+        spawn (10)
+            world.log << count
+    for (var/i in 1 to count)
+        world.log << i
+    for (var/x in world)
+        if (x)
+            spawn (0)
+                world.log << x
+    for (var/j in 1 to 3)
+        for (var/k = 0; k < j; k++)
+            spawn ()
+                world.log << k
+"##.trim();
+    check_errors_match(code, SPAWN_LOOP_ERRORS);
+}
+
+#[test]
+fn spawn_outside_loop() {
+    let code = r##"
+/proc/test(list/L)
+    spawn
+        for (var/x in L)
+            world.log << x
+    for (var/i in 1 to 5)
+        spawn (i * 10)
+            world.log << i
+    for (var/i in 10 to 1 step -2)
+        spawn
+            world.log << i
+"##.trim();
+    check_errors_match(code, NO_ERRORS);
+}
+
+#[test]
+fn spawn_in_loop_note() {
+    let code = r##"
+/proc/test(list/L)
+    for (var/x in L)
+        spawn
+            world.log << x
+"##.trim();
+    let context = parse_a_file_for_test(code);
+    let errors = context.errors();
+    assert_eq!(errors.len(), 1);
+    let notes: Vec<_> = errors[0].notes().iter().map(|note| (note.location().line, note.description().to_owned())).collect();
+    assert_eq!(notes, vec![(2, "loop is here".to_owned())]);
+}
@@ -6,6 +6,8 @@ use dc::test_helpers::*;
 pub const FIELD_ACCESS_ERRORS: &[(u32, u16, &str)] = &[
     (3, 9, "field access requires static type: \"name\""),
     (4, 10, "field access requires static type: \"name\""),
+    (3, 6, "list index 1 is out of bounds for a list of length 0"),
+    (4, 6, "list index 1 is out of bounds for a list of length 0"),
 ];
 
 #[test]
@@ -24,6 +26,8 @@ fn field_access() {
 pub const PROC_CALL_ERRORS: &[(u32, u16, &str)] = &[
     (3, 9, "proc call requires static type: \"foo\""),
     (4, 10, "proc call requires static type: \"foo\""),
+    (3, 6, "list index 1 is out of bounds for a list of length 0"),
+    (4, 6, "list index 1 is out of bounds for a list of length 0"),
 ];
 
 #[test]
@@ -52,3 +56,32 @@ fn return_type() {
 "##.trim();
     check_errors_match(code, RETURN_TYPE_ERRORS);
 }
+
+pub const PARAM_RETURN_TYPE_ERRORS: &[(u32, u16, &str)] = &[
+    (8, 35, "type expr: param(2) but /proc/wrong has 1 parameters"),
+    (13, 20, "undefined proc: \"bar\" on /mob"),
+    (14, 15, "undefined proc: \"bar\" on /obj"),
+];
+
+#[test]
+fn param_return_type() {
+    let code = r##"
+/proc/cast(thing)
+    set SpacemanDMM_return_type = param(1)
+    return thing
+/proc/make(path)
+    set SpacemanDMM_return_type = param_type(1)
+    return new path
+/proc/wrong(thing)
+    set SpacemanDMM_return_type = param(2)
+    return thing
+/proc/test()
+    var/mob/M = new
+    cast(M).foo()
+    cast(thing = M).bar()
+    make(/obj).bar()
+    make(/mob).foo()
+/mob/proc/foo()
+"##.trim();
+    check_errors_match(code, PARAM_RETURN_TYPE_ERRORS);
+}
@@ -0,0 +1,65 @@
+extern crate dreamchecker as dc;
+extern crate dreammaker as dm;
+
+use dc::symbols::{document_symbols, DocumentSymbol};
+use dc::test_helpers::*;
+use dm::annotation::SymbolKind;
+
+const CODE: &str = r##"
+/obj/item
+    var/force = 5
+    var/obj/item/parent
+
+    proc/hit(mob/target)
+        return force
+
+/obj/item/sword
+    force = 10
+
+/obj/item/sword/hit(mob/target)
+    if (target)
+        return ..()
+
+/obj/item/sword/verb/swing()
+
+/proc/helper()
+    return
+
+/obj/item/dagger
+"##;
+
+/// The path, kind, start, end, and number of children of each symbol.
+type Outline = Vec<(String, SymbolKind, (u32, u16), (u32, u16), usize)>;
+
+fn outline(symbols: &[DocumentSymbol]) -> Outline {
+    symbols.iter()
+        .map(|each| (each.path(), each.kind, (each.range.start.line, each.range.start.column), (each.range.end.line, each.range.end.column), each.children.len()))
+        .collect()
+}
+
+#[test]
+fn symbols_nested() {
+    let (_context, _tree, annotations, file) = parse_annotated_tree_for_test(CODE.trim());
+    let symbols = document_symbols(&annotations, file);
+    assert_eq!(outline(&symbols), [
+        ("/obj/item".to_owned(), SymbolKind::Type, (1, 1), (8, 1), 3),
+        ("/obj/item/sword".to_owned(), SymbolKind::Type, (8, 1), (11, 1), 1),
+        ("/obj/item/sword/proc/hit".to_owned(), SymbolKind::Proc, (11, 1), (15, 1), 0),
+        ("/obj/item/sword/verb/swing".to_owned(), SymbolKind::Verb, (15, 1), (15, 29), 0),
+        ("/proc/helper".to_owned(), SymbolKind::Proc, (17, 1), (20, 1), 0),
+        ("/obj/item/dagger".to_owned(), SymbolKind::Type, (20, 1), (20, 17), 0),
+    ]);
+    assert_eq!(outline(&symbols[0].children), [
+        ("/obj/item/var/force".to_owned(), SymbolKind::Var, (2, 5), (2, 18), 0),
+        ("/obj/item/var/parent".to_owned(), SymbolKind::Var, (3, 5), (3, 24), 0),
+        ("/obj/item/proc/hit".to_owned(), SymbolKind::Proc, (5, 5), (8, 1), 0),
+    ]);
+    assert_eq!(symbols[1].children[0].path(), "/obj/item/sword/var/force");
+}
+
+#[test]
+fn symbols_other_files() {
+    let (_context, _tree, annotations, file) = parse_annotated_tree_for_test(CODE.trim());
+    assert!(!document_symbols(&annotations, file).is_empty());
+    assert!(document_symbols(&annotations, dm::FileId::default()).is_empty());
+}
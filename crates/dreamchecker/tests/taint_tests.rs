@@ -0,0 +1,102 @@
+extern crate dreamchecker as dc;
+
+use dc::test_helpers::*;
+
+const CONFIG: &str = r#"
+[dreamchecker.taint]
+enabled = true
+source_procs = ["get_param"]
+sinks = ["give_item"]
+sink_vars = ["rights"]
+sanitizers = ["sanitize_ref"]
+"#;
+
+fn check_taint(name: &str, code: &'static str, errors: &[(u32, u16, &str)]) {
+    let context = context_with_config(name, CONFIG);
+    check_context_errors_match(&parse_a_file_for_test_in(context, code.trim()), errors);
+}
+
+#[test]
+fn taint_is_opt_in() {
+    check_errors_match(r##"
+/datum/Topic(href, href_list)
+    var/thing = locate(href_list["ref"])
+    world.log << thing
+"##.trim(), NO_ERRORS);
+}
+
+#[test]
+fn tainted_sinks() {
+    check_taint("sinks", r##"
+/proc/give_item(thing)
+/proc/get_param(name)
+/datum/var/rights = 0
+
+/datum/Topic(href, href_list)
+    var/ref = href_list["ref"]
+    var/thing = locate(ref)
+    var/path = text2path("/obj/[href_list["type"]]")
+    new path(thing)
+    give_item(get_param("item"))
+    rights = href_list["rights"]
+    call(src, href_list["proc"])()
+    var/typed = input("Which?")
+    src.give_item(typed)
+"##, &[
+        (7, 17, "unsanitized user input passed to locate()"),
+        (8, 16, "unsanitized user input passed to text2path()"),
+        (10, 5, "unsanitized user input passed to give_item()"),
+        (11, 5, "unsanitized user input assigned to rights"),
+        (12, 5, "unsanitized user input passed to call()"),
+        (14, 8, "unsanitized user input passed to give_item()"),
+    ]);
+}
+
+#[test]
+fn sanitized_and_clean_values() {
+    check_taint("clean", r##"
+/proc/give_item(thing)
+/proc/sanitize_ref(ref)
+    return ref
+/datum/proc/lookup(key)
+
+/datum/Topic(href, href_list)
+    var/ref = href_list["ref"]
+    ref = sanitize_ref(ref)
+    locate(ref)
+    var/other = href_list["other"]
+    if (other == "yes")
+        other = "yes"
+    else
+        other = "no"
+    give_item(other)
+    locate(href_list["x"]) in world
+    give_item(input("Which?") in list(1, 2))
+    give_item(src.lookup(href_list["key"]))
+    give_item(href_list["a"] == "b")
+"##, NO_ERRORS);
+}
+
+#[test]
+fn taint_flows_through_paths() {
+    check_taint("paths", r##"
+/proc/give_item(thing)
+
+/datum/Topic(href, href_list)
+    var/item = "default"
+    if (href_list["custom"])
+        item = href_list["custom"]
+    give_item(item)
+    var/total = ""
+    for (var/key in href_list)
+        total += key
+    give_item(total)
+    var/later = href_list["later"]
+    spawn (10)
+        give_item(later)
+"##, &[
+        (7, 5, "unsanitized user input passed to give_item()"),
+        (11, 5, "unsanitized user input passed to give_item()"),
+        (14, 9, "unsanitized user input passed to give_item()"),
+    ]);
+}
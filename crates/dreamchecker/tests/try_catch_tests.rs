@@ -0,0 +1,89 @@
+extern crate dreamchecker as dc;
+
+use dc::test_helpers::*;
+
+pub const THROWN_TYPE_ERRORS: &[(u32, u16, &str)] = &[
+    (3, 9, "throwing text, which is caught as /exception, will runtime error on field access"),
+    (7, 9, "throwing a number, which is caught as /exception, will runtime error on field access"),
+];
+
+#[test]
+fn thrown_non_exceptions() {
+    let code = r##"
+/proc/test(x)
+    try
+        throw "bad [x]"
+    catch (var/exception/e)
+        world.log << e.name
+    try
+        throw 5
+    catch (var/exception/e)
+        world.log << e.line
+    try
+        throw EXCEPTION("fine")
+    catch (var/exception/e)
+        world.log << e.file
+    try
+        throw "untyped is fine"
+    catch (e)
+        world.log << e
+    try
+        spawn
+            throw "not caught here"
+        world.log << x
+    catch (var/exception/e)
+        world.log << e.desc
+"##.trim();
+    check_errors_match(code, THROWN_TYPE_ERRORS);
+}
+
+#[test]
+fn thrown_non_exception_note() {
+    let code = r##"
+/proc/test()
+    try
+        throw "bad"
+    catch (var/exception/e)
+        world.log << e.name
+"##.trim();
+    let context = parse_a_file_for_test(code);
+    let errors = context.errors();
+    assert_eq!(errors.len(), 1);
+    let notes: Vec<_> = errors[0].notes().iter().map(|note| (note.location().line, note.description().to_owned())).collect();
+    assert_eq!(notes, vec![(2, "caught by this try".to_owned())]);
+}
+
+pub const UNREACHABLE_CATCH_ERRORS: &[(u32, u16, &str)] = &[
+    (3, 5, "try block cannot throw, so its catch block never runs"),
+    (11, 5, "try block cannot throw, so its catch block never runs"),
+];
+
+#[test]
+fn unreachable_catch() {
+    let code = r##"
+/proc/test(a, b)
+    var/total
+    try
+        total = a + b * 2
+    catch
+        world.log << "impossible"
+    try
+        total = a / b
+    catch
+        total = 0
+    try
+        spawn
+            CRASH("elsewhere")
+    catch
+        world.log << "also impossible"
+    try
+        world.log << total
+    catch
+    try
+        total = length(a)
+    catch
+        total = 0
+    return total
+"##.trim();
+    check_errors_match(code, UNREACHABLE_CATCH_ERRORS);
+}
@@ -0,0 +1,54 @@
+extern crate dreamchecker as dc;
+extern crate dreammaker as dm;
+
+use dc::test_helpers::{parse_tree_for_test, type_coverage_for_test};
+use dc::type_coverage::Count;
+
+#[test]
+fn counts_typed_expressions() {
+    let (context, tree, _) = parse_tree_for_test(r##"
+/proc/test(x)
+    var/n = 1
+    return x
+"##.trim());
+    let coverage = type_coverage_for_test(&context, &tree);
+    assert_eq!(coverage.overall, Count { typed: 1, total: 2 });
+    assert_eq!(coverage.procs.len(), 1);
+    assert_eq!(coverage.procs[0].0, "/proc/test");
+}
+
+#[test]
+fn worst_first() {
+    let (context, tree, file) = parse_tree_for_test(r##"
+/obj/item/weapon/proc/a(x, y)
+    return x + y
+
+/obj/item/proc/b(x)
+    return x
+
+/datum/proc/c()
+    return "text"
+
+/proc/d(x)
+    return x
+"##.trim());
+    let coverage = type_coverage_for_test(&context, &tree);
+    let procs: Vec<_> = coverage.procs.iter().map(|(path, _, count)| (path.as_str(), count.untyped())).collect();
+    assert_eq!(procs, [
+        ("/obj/item/weapon/proc/a", 3),
+        ("/obj/item/proc/b", 1),
+        ("/proc/d", 1),
+        ("/datum/proc/c", 0),
+    ]);
+    let subtrees: Vec<_> = coverage.subtrees.iter().map(|(path, count)| (path.as_str(), count.untyped())).collect();
+    assert_eq!(subtrees, [("/obj/item", 4), ("/", 1), ("/datum", 0)]);
+    assert_eq!(coverage.files, [(file, coverage.overall)]);
+}
+
+#[test]
+fn empty_is_fully_covered() {
+    let (context, tree, _) = parse_tree_for_test("/obj/item");
+    let coverage = type_coverage_for_test(&context, &tree);
+    assert_eq!(coverage.overall, Count::default());
+    assert_eq!(coverage.overall.percent(), 100.0);
+}
@@ -41,6 +41,18 @@ pub enum Annotation {
 
     ProcArguments(Vec<Ident>, String, usize),  // Vec empty for unscoped call
     ProcArgument(usize),  // where in the prog arguments we are
+
+    // a definition spanning its whole extent: kind, path of the type it's on, name
+    Symbol(SymbolKind, String, Ident),
+}
+
+/// The kind of thing a `Symbol` annotation defines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    Type,
+    Var,
+    Proc,
+    Verb,
 }
 
 #[derive(Debug)]
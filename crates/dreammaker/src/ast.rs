@@ -4,6 +4,7 @@
 use std::fmt;
 use std::iter::FromIterator;
 use phf::phf_map;
+use serde::{Serialize, Deserialize};
 
 use crate::error::Location;
 
@@ -11,7 +12,7 @@ use crate::error::Location;
 // Simple enums
 
 /// The unary operators, both prefix and postfix.
-#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub enum UnaryOp {
     Neg,
     Not,
@@ -66,7 +67,7 @@ impl UnaryOp {
 /// The DM path operators.
 ///
 /// Which path operator is used typically only matters at the start of a path.
-#[derive(Copy, Clone, Hash, PartialEq, Eq, Debug)]
+#[derive(Copy, Clone, Hash, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub enum PathOp {
     /// `/` for absolute pathing.
     Slash,
@@ -93,7 +94,7 @@ impl fmt::Display for PathOp {
 }
 
 /// The binary operators.
-#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub enum BinaryOp {
     Add,
     Sub,
@@ -152,7 +153,7 @@ impl fmt::Display for BinaryOp {
 }
 
 /// The assignment operators, including augmented assignment.
-#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub enum AssignOp {
     Assign,
     AddAssign,
@@ -165,6 +166,7 @@ pub enum AssignOp {
     AndAssign,
     BitOrAssign,
     OrAssign,
+    NullAssign,
     BitXorAssign,
     LShiftAssign,
     RShiftAssign,
@@ -186,6 +188,7 @@ impl fmt::Display for AssignOp {
             BitXorAssign => "^=",
             BitOrAssign => "|=",
             OrAssign => "||=",
+            NullAssign => "?=",
             LShiftAssign => "<<=",
             RShiftAssign => ">>=",
         })
@@ -231,13 +234,13 @@ augmented! {
 }
 
 /// The ternary operator, represented uniformly for convenience.
-#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub enum TernaryOp {
     Conditional,
 }
 
 /// The possible kinds of access operators for lists
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub enum ListAccessKind {
     /// `[]`
     Normal,
@@ -246,7 +249,7 @@ pub enum ListAccessKind {
 }
 
 /// The possible kinds of index operators, for both fields and methods.
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub enum PropertyAccessKind {
     /// `a.b`
     Dot,
@@ -280,7 +283,7 @@ impl fmt::Display for PropertyAccessKind {
 /// DM requires referencing proc paths to include whether the target is
 /// declared as a proc or verb, even though the two modes are functionally
 /// identical in many other respects.
-#[derive(Debug, Clone, PartialEq, Eq, Copy, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Copy, Hash, Serialize, Deserialize)]
 pub enum ProcDeclKind {
     Proc,
     Verb,
@@ -316,7 +319,7 @@ impl fmt::Display for ProcDeclKind {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum SettingMode {
     /// As in `set name = "Use"`.
     Assign,
@@ -512,6 +515,28 @@ impl fmt::Display for VarTypeFlags {
     }
 }
 
+/// Bitflags are serialized as their raw bits.
+macro_rules! serde_bits {
+    ($($name:ident: $repr:ty;)*) => {$(
+        impl Serialize for $name {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                self.bits().serialize(serializer)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                Ok($name::from_bits_truncate(<$repr>::deserialize(deserializer)?))
+            }
+        }
+    )*}
+}
+
+serde_bits! {
+    InputType: u32;
+    VarTypeFlags: u8;
+}
+
 // ----------------------------------------------------------------------------
 // Helper types
 
@@ -521,7 +546,7 @@ pub type Ident = String;
 // Ident2 is an opaque type which promises a limited interface.
 // It's a `Box<str>` for now (smaller than `Ident` by 8 bytes),
 // but could be replaced by interning later.
-#[derive(Clone, Eq, PartialEq)]
+#[derive(Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub struct Ident2 {
     inner: Box<str>,
 }
@@ -576,10 +601,13 @@ impl fmt::Debug for Ident2 {
 }
 
 /// An AST element with an additional location attached.
-#[derive(Copy, Clone, Eq, Debug)]
+#[derive(Copy, Clone, Eq, Debug, Serialize, Deserialize)]
 pub struct Spanned<T> {
     // TODO: add a Span type and use it here
     pub location: Location,
+    /// Where the element ends, which is only recorded for terms and follows
+    /// and is otherwise the same as `location`.
+    pub end: Location,
     pub elem: T,
 }
 
@@ -592,7 +620,11 @@ impl<T: PartialEq> PartialEq for Spanned<T> {
 
 impl<T> Spanned<T> {
     pub fn new(location: Location, elem: T) -> Spanned<T> {
-        Spanned { location, elem }
+        Spanned { location, end: location, elem }
+    }
+
+    pub fn with_end(location: Location, end: Location, elem: T) -> Spanned<T> {
+        Spanned { location, end, elem }
     }
 }
 
@@ -628,7 +660,7 @@ impl<'a> fmt::Display for FormatTypePath<'a> {
 // Terms and Expressions
 
 /// A typepath optionally followed by a set of variables.
-#[derive(Clone, PartialEq, Debug)]
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub struct Prefab {
     pub path: TypePath,
     pub vars: Box<[(Ident2, Expression)]>,
@@ -643,6 +675,57 @@ impl From<TypePath> for Prefab {
     }
 }
 
+/// A reference to a proc or verb by path, like `.proc/name` or
+/// `/type/proc/name`.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct ProcReference {
+    /// The type the proc is looked up on, or `None` for the current type as
+    /// in `.proc/name`. An empty path refers to global procs.
+    pub ty: Option<TypePath>,
+    pub kind: ProcDeclKind,
+    pub name: Ident,
+}
+
+impl ProcReference {
+    /// Split a path into a proc reference if it names a single proc.
+    pub fn from_path(path: &[(PathOp, Ident)]) -> Option<ProcReference> {
+        let (kind_idx, name_idx) = match path.len() {
+            len if len >= 2 => (len - 2, len - 1),
+            _ => return None,
+        };
+        let kind = ProcDeclKind::from_name(&path[kind_idx].1)?;
+        if path[name_idx].0 != PathOp::Slash {
+            return None;
+        }
+        let ty = if kind_idx == 0 && path[0].0 == PathOp::Dot {
+            None
+        } else {
+            Some(path[..kind_idx].to_vec())
+        };
+        Some(ProcReference { ty, kind, name: path[name_idx].1.clone() })
+    }
+
+    /// The path this reference was written as, in canonical form.
+    pub fn path(&self) -> TypePath {
+        let mut path = match self.ty {
+            Some(ref ty) => {
+                let mut path = ty.clone();
+                path.push((PathOp::Slash, self.kind.name().to_owned()));
+                path
+            }
+            None => vec![(PathOp::Dot, self.kind.name().to_owned())],
+        };
+        path.push((PathOp::Slash, self.name.clone()));
+        path
+    }
+}
+
+impl fmt::Display for ProcReference {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", FormatTypePath(&self.path()))
+    }
+}
+
 /// Formatting helper for variable arrays.
 pub struct FormatVars<'a, T>(pub &'a T);
 
@@ -666,7 +749,7 @@ where
 }
 
 /// The structure of an expression, a tree of terms and operators.
-#[derive(Clone, PartialEq, Debug)]
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub enum Expression {
     /// An expression containing a term directly. The term is evaluated first,
     /// then its follows, then its unary operators in reverse order.
@@ -774,6 +857,9 @@ impl Expression {
                 Some(truthy)
             },
             Expression::BinaryOp { op, lhs, rhs } => {
+                if let Some(truthy) = crate::constants::rand_comparison(*op, lhs, rhs) {
+                    return Some(truthy);
+                }
                 guard!(let Some(lhtruth) = lhs.is_truthy() else {
                     return None
                 });
@@ -823,7 +909,7 @@ impl From<Term> for Expression {
 }
 
 /// The structure of a term, the basic building block of the AST.
-#[derive(Clone, PartialEq, Debug)]
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub enum Term {
     // Terms with no recursive contents ---------------------------------------
     /// The literal `null`.
@@ -836,6 +922,8 @@ pub enum Term {
     Ident(Ident),
     /// A string literal.
     String(String),
+    /// A raw string literal, which is never interpolated and has no escapes.
+    RawString(String),
     /// A resource literal.
     Resource(String),
     /// An `as()` call, with an input type. Undocumented.
@@ -846,6 +934,8 @@ pub enum Term {
     Expr(Box<Expression>),
     /// A prefab literal (path + vars).
     Prefab(Box<Prefab>),
+    /// A proc reference literal, like `.proc/name`.
+    ProcReference(Box<ProcReference>),
     /// An interpolated string, alternating string/expr/string/expr.
     InterpString(Ident2, Box<[(Option<Expression>, Box<str>)]>),
 
@@ -901,7 +991,9 @@ impl Term {
             | Term::Int(_)
             | Term::Float(_)
             | Term::String(_)
+            | Term::RawString(_)
             | Term::Prefab(_)
+            | Term::ProcReference(_)
         )
     }
 
@@ -911,16 +1003,20 @@ impl Term {
             Term::Null => Some(false),
             Term::Int(i) => Some(*i != 0),
             Term::Float(i) => Some(*i != 0f32),
-            Term::String(s) => Some(!s.is_empty()),
+            Term::String(s) | Term::RawString(s) => Some(!s.is_empty()),
 
             // Paths/prefabs are truthy.
             Term::Prefab(_) => Some(true),
+            Term::ProcReference(_) => Some(true),
             // `new()` and `list()` return the newly-created reference.
             Term::NewImplicit { .. } => Some(true),
             Term::NewPrefab { .. } => Some(true),
             Term::NewMiniExpr { .. } => Some(true),
             Term::List(_) => Some(true),
 
+            // `prob()` of 0 or less never succeeds, and of 100 or more always does.
+            Term::Call(name, args) if name == "prob" => crate::constants::prob_call_outcome(args),
+
             // Truthy if any of the literal parts are non-empty.
             // Otherwise, don't try to determine the content of the parts.
             Term::InterpString(first, parts) => {
@@ -977,14 +1073,14 @@ impl From<Expression> for Term {
     }
 }
 
-#[derive(Clone, PartialEq, Debug)]
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub struct MiniExpr {
     pub ident: Ident2,
     pub fields: Box<[Field]>,
 }
 
 /// An expression part which is applied to a term or another follow.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Follow {
     /// Index the value by an expression.
     Index(ListAccessKind, Box<Expression>),
@@ -997,7 +1093,7 @@ pub enum Follow {
 }
 
 /// Like a `Follow` but only supports field accesses.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Field {
     pub kind: PropertyAccessKind,
     pub ident: Ident2,
@@ -1010,7 +1106,7 @@ impl From<Field> for Follow {
 }
 
 /// A parameter declaration in the header of a proc.
-#[derive(Debug, Clone, PartialEq, Default)]
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
 pub struct Parameter {
     pub var_type: VarType,
     pub name: Ident,
@@ -1031,7 +1127,7 @@ impl fmt::Display for Parameter {
 }
 
 /// A type which may be ascribed to a `var`.
-#[derive(Debug, Clone, PartialEq, Default)]
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
 pub struct VarType {
     pub flags: VarTypeFlags,
     pub type_path: TreePath,
@@ -1120,19 +1216,20 @@ impl VarSuffix {
     pub fn is_empty(&self) -> bool {
         self.list.is_empty()
     }
+}
 
-    pub fn into_initializer(self) -> Option<Expression> {
-        // `var/L[10]` is equivalent to `var/list/L = new /list(10)`
-        // `var/L[2][][3]` is equivalent to `var/list/list/list = new /list(2, 3)`
-        let args: Vec<_> = self.list.into_iter().filter_map(|x| x).collect();
-        if args.is_empty() {
-            None
-        } else {
-            Some(Expression::from(Term::NewPrefab {
-                prefab: Box::new(Prefab::from(vec![(PathOp::Slash, "list".to_owned())])),
-                args: Some(args.into_boxed_slice()),
-            }))
-        }
+/// The value implied by the sizes of a list declaration.
+pub fn list_initializer(sizes: &[Option<Expression>]) -> Option<Expression> {
+    // `var/L[10]` is equivalent to `var/list/L = new /list(10)`
+    // `var/L[2][][3]` is equivalent to `var/list/list/list = new /list(2, 3)`
+    let args: Vec<_> = sizes.iter().flatten().cloned().collect();
+    if args.is_empty() {
+        None
+    } else {
+        Some(Expression::from(Term::NewPrefab {
+            prefab: Box::new(Prefab::from(vec![(PathOp::Slash, "list".to_owned())])),
+            args: Some(args.into_boxed_slice()),
+        }))
     }
 }
 
@@ -1143,7 +1240,7 @@ impl VarSuffix {
 pub type Block = Box<[Spanned<Statement>]>;
 
 /// A statement in a proc body.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Statement {
     Expr(Expression),
     Return(Option<Expression>),
@@ -1203,20 +1300,30 @@ pub enum Statement {
     Crash(Option<Expression>),
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct VarStatement {
     pub var_type: VarType,
     pub name: Ident,
     pub value: Option<Expression>,
+    /// The sizes of a list declaration such as `var/L[2][3]`, one for each
+    /// `[]`, which are `None` where a `[]` is empty.
+    pub dimensions: Vec<Option<Expression>>,
+}
+
+impl VarStatement {
+    /// Whether the value is the one implied by the sizes, rather than given.
+    pub fn has_implied_value(&self) -> bool {
+        self.value.is_some() && self.value == list_initializer(&self.dimensions)
+    }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Case {
     Exact(Expression),
     Range(Expression, Expression),
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ForListStatement {
     pub var_type: Option<VarType>,
     pub name: Ident2,
@@ -1227,7 +1334,7 @@ pub struct ForListStatement {
     pub block: Block,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ForRangeStatement {
     pub var_type: Option<VarType>,
     pub name: Ident2,
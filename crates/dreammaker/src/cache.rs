@@ -0,0 +1,284 @@
+//! Binary cache of a fully-parsed object tree.
+//!
+//! The cache stores the tree's flat type graph alongside a fingerprint of
+//! everything that went into producing it: the tool version, the builtin
+//! define set, the configuration file, and the contents of every input file.
+//! It begins with magic bytes and a format number, so that other files and
+//! caches in an older layout are rejected before anything else is read. A
+//! cache is only trusted when every part of the fingerprint still matches,
+//! so callers should fall back to a full parse whenever `read` fails.
+
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+use serde::{Serialize, Deserialize};
+
+use crate::{Context, DMError, Location, Severity, Component};
+use crate::objtree::{ObjectTree, Type};
+use crate::preprocessor::DefineMap;
+use crate::resource::SavedResourceDirs;
+
+/// The bytes every cache file begins with.
+const MAGIC: &[u8; 8] = b"DMCACHE\0";
+
+/// The layout of what follows `MAGIC`, to be bumped whenever `Header` or
+/// `Body` change.
+const FORMAT: u32 = 3;
+
+/// Everything that must match for a cache to be considered valid.
+#[derive(Serialize, Deserialize, PartialEq)]
+struct Header {
+    version: String,
+    defines: u64,
+    config: Option<(PathBuf, u64)>,
+    /// Every registered file, in registration order, so that `FileId`s in
+    /// the body remain valid once the list is replayed.
+    files: Vec<(PathBuf, u64)>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Body {
+    graph: Vec<Type>,
+    diagnostics: Vec<Diagnostic>,
+    resources: SavedResourceDirs,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Diagnostic {
+    location: Location,
+    severity: Severity,
+    component: Component,
+    errortype: Option<String>,
+    description: String,
+    notes: Vec<(Location, String)>,
+}
+
+/// The errortypes which may be raised while parsing, so that cached
+/// diagnostics can be given back their `'static` names. A cache holding any
+/// other errortype is not trusted.
+const ERRORTYPES: &[&str] = &[
+    "as_local_var",
+    "duplicate_include",
+    "final_no_effect",
+    "global_init_cycle",
+    "global_init_order",
+    "in_precedes_as",
+    "integer_precision_loss",
+    "macro_redefined",
+    "macro_undefined_no_definition",
+    "naming_convention",
+    "override_precedes_definition",
+    "private_var",
+    "protected_var",
+    "semicolon_in_proc_parameter",
+    "static_in_proc_parameter",
+    "tmp_no_effect",
+    "var_in_proc_parameter",
+];
+
+/// Write an object tree parsed from `environment` and the diagnostics raised
+/// while parsing it to a cache file.
+pub fn write(context: &Context, tree: &ObjectTree, environment: &Path, version: &str, path: &Path) -> io::Result<()> {
+    let header = Header::current(context, environment, version)?;
+    let diagnostics: Vec<_> = context.errors().iter().map(|error| Diagnostic {
+        location: error.location(),
+        severity: error.severity(),
+        component: error.component(),
+        errortype: error.errortype().map(ToOwned::to_owned),
+        description: error.description().to_owned(),
+        notes: error.notes().iter().map(|note| (note.location(), note.description().to_owned())).collect(),
+    }).collect();
+    let body = BodyRef {
+        graph: tree.graph(),
+        diagnostics: &diagnostics,
+        resources: context.resources().save(),
+    };
+
+    let mut output = BufWriter::new(File::create(path)?);
+    output.write_all(MAGIC)?;
+    output.write_all(&FORMAT.to_le_bytes())?;
+    bincode::serialize_into(&mut output, &header).map_err(to_io_error)?;
+    bincode::serialize_into(&mut output, &body).map_err(to_io_error)?;
+    Ok(())
+}
+
+/// Borrowed form of `Body` for writing.
+#[derive(Serialize)]
+struct BodyRef<'a> {
+    graph: &'a [Type],
+    diagnostics: &'a [Diagnostic],
+    resources: SavedResourceDirs,
+}
+
+/// Attempt to load the object tree for `environment` from a cache file.
+///
+/// The context must not have any files registered yet. On success, the
+/// cached file list and diagnostics are replayed into it. Returns `None` if
+/// the cache is missing, corrupt, or stale.
+pub fn read(context: &Context, environment: &Path, version: &str, path: &Path) -> Option<ObjectTree> {
+    let mut files_registered = false;
+    context.file_list().for_each(|_| files_registered = true);
+    if files_registered {
+        return None;
+    }
+
+    let mut input = BufReader::new(File::open(path).ok()?);
+    let mut magic = [0; 8];
+    let mut format = [0; 4];
+    input.read_exact(&mut magic).ok()?;
+    input.read_exact(&mut format).ok()?;
+    if &magic != MAGIC || u32::from_le_bytes(format) != FORMAT {
+        return None;
+    }
+    let header: Header = bincode::deserialize_from(&mut input).ok()?;
+    if header.version != version
+        || header.defines != define_fingerprint()
+        || header.config != config_fingerprint(context).ok()?
+    {
+        return None;
+    }
+    let root = environment.parent()?;
+    for (file, hash) in header.files.iter() {
+        if hash_file(&root.join(file)).ok()? != *hash {
+            return None;
+        }
+    }
+    let body: Body = bincode::deserialize_from(&mut input).ok()?;
+    let mut errortypes = Vec::with_capacity(body.diagnostics.len());
+    for each in body.diagnostics.iter() {
+        errortypes.push(match each.errortype {
+            Some(ref name) => Some(*ERRORTYPES.iter().find(|known| **known == name)?),
+            None => None,
+        });
+    }
+
+    for (file, _) in header.files.iter() {
+        context.register_file(file);
+    }
+    for (each, errortype) in body.diagnostics.into_iter().zip(errortypes) {
+        let mut error = DMError::new(each.location, each.description)
+            .set_severity(each.severity)
+            .with_component(each.component);
+        if let Some(errortype) = errortype {
+            error = error.with_errortype(errortype);
+        }
+        for (location, note) in each.notes {
+            error.add_note(location, note);
+        }
+        context.register_error(error);
+    }
+    context.resources_mut().restore(body.resources);
+
+    let mut tree = ObjectTree::from_graph(body.graph);
+    restore_builtin_docs(&mut tree);
+    Some(tree)
+}
+
+impl Header {
+    fn current(context: &Context, environment: &Path, version: &str) -> io::Result<Header> {
+        // Included files are registered relative to the environment.
+        let root = environment.parent().unwrap_or_else(|| Path::new("."));
+        let mut paths = Vec::new();
+        context.file_list().for_each(|path| paths.push(path.to_owned()));
+        let mut files = Vec::with_capacity(paths.len());
+        for path in paths {
+            let hash = hash_file(&root.join(&path))?;
+            files.push((path, hash));
+        }
+        Ok(Header {
+            version: version.to_owned(),
+            defines: define_fingerprint(),
+            config: config_fingerprint(context)?,
+            files,
+        })
+    }
+}
+
+fn config_fingerprint(context: &Context) -> io::Result<Option<(PathBuf, u64)>> {
+    match context.config_path() {
+        Some(path) => {
+            // A missing config file is treated as the default config.
+            let hash = if path.exists() { hash_file(&path)? } else { 0 };
+            Ok(Some((path, hash)))
+        }
+        None => Ok(None),
+    }
+}
+
+fn define_fingerprint() -> u64 {
+    let defines = DefineMap::with_builtins();
+    let mut lines: Vec<String> = defines.iter()
+        .map(|(name, (_, define))| define.display_with_name(name).to_string())
+        .collect();
+    lines.sort();
+    let mut hash = Fnv::default();
+    for line in lines {
+        hash.write(line.as_bytes());
+        hash.write(b"\n");
+    }
+    hash.finish()
+}
+
+fn hash_file(path: &Path) -> io::Result<u64> {
+    let mut hash = Fnv::default();
+    hash.write(&std::fs::read(path)?);
+    Ok(hash.finish())
+}
+
+/// Builtin docs are `'static` references which can't be serialized, so copy
+/// them back from a freshly-built builtins tree.
+fn restore_builtin_docs(tree: &mut ObjectTree) {
+    let builtins = ObjectTree::with_builtins();
+    for idx in builtins.node_indices() {
+        let builtin = &builtins[idx];
+        let target = match tree.find(&builtin.path) {
+            Some(ty) => ty.index(),
+            None => continue,
+        };
+        let target = &mut tree[target];
+        target.docs.builtin_docs = builtin.docs.builtin_docs.clone();
+        for (name, var) in builtin.vars.iter() {
+            if let Some(target_var) = target.vars.get_mut(name) {
+                if target_var.value.location.is_builtins() {
+                    target_var.value.docs.builtin_docs = var.value.docs.builtin_docs.clone();
+                }
+            }
+        }
+        for (name, proc) in builtin.procs.iter() {
+            if let Some(target_proc) = target.procs.get_mut(name) {
+                for (target_value, value) in target_proc.value.iter_mut().zip(proc.value.iter()) {
+                    if target_value.location.is_builtins() {
+                        target_value.docs.builtin_docs = value.docs.builtin_docs.clone();
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn to_io_error(error: bincode::Error) -> io::Error {
+    io::Error::other(error)
+}
+
+/// 64-bit FNV-1a, chosen for being stable across toolchains.
+struct Fnv(u64);
+
+impl Default for Fnv {
+    fn default() -> Fnv {
+        Fnv(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Fnv {
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= u64::from(byte);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
@@ -6,7 +6,8 @@ use std::path::{Path, PathBuf};
 use std::collections::HashMap;
 
 use ahash::RandomState;
-use serde::Deserialize;
+use regex::Regex;
+use serde::{Deserialize, Deserializer};
 
 use crate::error::Severity;
 use crate::DMError;
@@ -23,6 +24,7 @@ pub struct Config {
     pub code_standards: CodeStandards,
 
     // tool-specific configuration
+    pub dreamchecker: DreamChecker,
     pub langserver: Langserver,
     pub dmdoc: DMDoc,
     pub debugger: Debugger,
@@ -36,6 +38,149 @@ pub struct WarningDisplay {
     error_level: WarningLevel,
 }
 
+/// DreamChecker config options
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct DreamChecker {
+    /// Names of procs which never return to their caller.
+    pub never_returns: Vec<String>,
+    /// Whether to report assignments to locals which are never read.
+    pub dead_stores: bool,
+    /// Whether to report typed fields which nothing ever assigns.
+    pub unassigned_fields: bool,
+    /// Whether to report parameters which no call gives a value.
+    pub unsupplied_params: bool,
+    /// Whether to check constant `icon_state` values against the states in
+    /// constant icons.
+    pub icon_states: bool,
+    /// Names of vars whose constant values must be valid colors.
+    pub color_vars: Vec<String>,
+    /// The cyclomatic complexity above which procs are reported.
+    pub complexity_threshold: Option<u32>,
+    /// Procs, such as `/atom/movable/proc/Move`, whose overrides are checked
+    /// as if they set `SpacemanDMM_should_not_sleep`.
+    pub never_sleep: Vec<String>,
+    /// Whether to report `world <<`, which outputs to every player.
+    pub world_output: bool,
+    pub taint: Taint,
+    pub naming: Naming,
+}
+
+impl Default for DreamChecker {
+    fn default() -> Self {
+        DreamChecker {
+            never_returns: Vec::new(),
+            dead_stores: false,
+            unassigned_fields: false,
+            unsupplied_params: false,
+            icon_states: false,
+            color_vars: vec!["color".to_owned()],
+            complexity_threshold: None,
+            never_sleep: vec![
+                "/atom/movable/proc/Move".to_owned(),
+                "/atom/proc/CanPass".to_owned(),
+                "/client/proc/Click".to_owned(),
+            ],
+            world_output: false,
+            taint: Default::default(),
+            naming: Default::default(),
+        }
+    }
+}
+
+/// Taint analysis config options
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct Taint {
+    pub enabled: bool,
+    /// Vars, such as `href_list`, whose contents come from the client.
+    pub sources: Vec<String>,
+    /// Procs whose return values come from the client.
+    pub source_procs: Vec<String>,
+    /// Whether `input()` without an `in` list is a source.
+    pub input: bool,
+    /// Procs whose arguments must not come from the client, in addition to
+    /// `locate()`, `text2path()`, and `call()`.
+    pub sinks: Vec<String>,
+    /// Vars which must not be assigned values from the client.
+    pub sink_vars: Vec<String>,
+    /// Procs whose return values are safe regardless of their arguments.
+    pub sanitizers: Vec<String>,
+}
+
+impl Default for Taint {
+    fn default() -> Self {
+        Taint {
+            enabled: false,
+            sources: vec!["href_list".to_owned(), "params".to_owned()],
+            source_procs: Vec::new(),
+            input: true,
+            sinks: Vec::new(),
+            sink_vars: Vec::new(),
+            sanitizers: Vec::new(),
+        }
+    }
+}
+
+/// Naming convention config options
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct Naming {
+    pub enabled: bool,
+    /// The pattern for vars declared on types.
+    #[serde(deserialize_with = "deserialize_regex")]
+    pub vars: Regex,
+    /// The pattern for procs and verbs declared on types.
+    #[serde(deserialize_with = "deserialize_regex")]
+    pub procs: Regex,
+    /// The pattern for the last segment of type paths.
+    #[serde(deserialize_with = "deserialize_regex")]
+    pub types: Regex,
+    /// The pattern for macros.
+    #[serde(deserialize_with = "deserialize_regex")]
+    pub macros: Regex,
+    /// Patterns for the types under particular paths, in place of the above.
+    pub paths: Vec<NamingPath>,
+}
+
+impl Default for Naming {
+    fn default() -> Self {
+        let snake_case = Regex::new("^[a-z][a-z0-9_]*$").unwrap();
+        Naming {
+            enabled: false,
+            vars: snake_case.clone(),
+            procs: snake_case.clone(),
+            types: snake_case,
+            macros: Regex::new("^[A-Z][A-Z0-9_]*$").unwrap(),
+            paths: Vec::new(),
+        }
+    }
+}
+
+/// Naming convention patterns for the types under one path. Patterns which
+/// aren't given are inherited from the closest enclosing path.
+#[derive(Deserialize, Debug, Clone)]
+pub struct NamingPath {
+    /// A type path, such as `/obj/legacy`, which applies to that type and
+    /// its subtypes.
+    pub path: String,
+    #[serde(default, deserialize_with = "deserialize_optional_regex")]
+    pub vars: Option<Regex>,
+    #[serde(default, deserialize_with = "deserialize_optional_regex")]
+    pub procs: Option<Regex>,
+    #[serde(default, deserialize_with = "deserialize_optional_regex")]
+    pub types: Option<Regex>,
+}
+
+fn deserialize_regex<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Regex, D::Error> {
+    let pattern = String::deserialize(deserializer)?;
+    Regex::new(&pattern).map_err(serde::de::Error::custom)
+}
+
+fn deserialize_optional_regex<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Regex>, D::Error> {
+    deserialize_regex(deserializer).map(Some)
+}
+
 /// Langserver config options
 #[derive(Deserialize, Default, Debug, Clone)]
 pub struct Langserver {
@@ -1,12 +1,14 @@
 //! The constant folder/evaluator, used by the preprocessor and object tree.
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 use std::ops;
 use std::path::Path;
 
-use indexmap::IndexMap;
+use indexmap::{IndexMap, IndexSet};
 use ahash::RandomState;
 use ordered_float::OrderedFloat;
 use color_space::{Hsl, Hsv, Lch, Rgb};
+use serde::{Serialize, Deserialize};
 
 use super::ast::*;
 use super::objtree::*;
@@ -16,7 +18,7 @@ use super::{Context, DMError, HasLocation, Location, Severity};
 /// An absolute typepath and optional variables.
 ///
 /// The path may involve `/proc` or `/verb` references.
-#[derive(Clone, Eq, PartialEq, Debug)]
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
 pub struct Pop {
     pub path: TreePath,
     pub vars: IndexMap<Ident, Constant, RandomState>,
@@ -49,7 +51,7 @@ impl fmt::Display for Pop {
 ///
 /// This is intended to represent the degree to which constants are evaluated
 /// before being displayed in DreamMaker.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Constant {
     /// The literal `null`.
     Null(Option<TreePath>),
@@ -119,7 +121,7 @@ impl std::cmp::PartialEq for Constant {
 impl std::cmp::Eq for Constant {}
 
 /// The constant functions which are represented as-is.
-#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq)]
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq, Serialize, Deserialize)]
 pub enum ConstFn {
     /// The `icon()` type constructor.
     Icon,
@@ -396,6 +398,244 @@ impl fmt::Display for ConstFn {
     }
 }
 
+/// The parameters of `icon()`, in order.
+const ICON_PARAMS: &[&str] = &["icon", "icon_state", "dir", "frame", "moving"];
+
+/// The directions `icon()` accepts, where 0 means every direction.
+const ICON_DIRS: &[i32] = &[0, 1, 2, 4, 8, 5, 6, 9, 10];
+
+/// The `matrix()` operations and how many arguments, counting the operation,
+/// each may be called with.
+const MATRIX_OPS: &[(i32, &str, &[usize])] = &[
+    (0, "MATRIX_COPY", &[2]),
+    (1, "MATRIX_MULTIPLY", &[3]),
+    (2, "MATRIX_ADD", &[3]),
+    (3, "MATRIX_SUBTRACT", &[3]),
+    (4, "MATRIX_INVERT", &[2]),
+    (5, "MATRIX_ROTATE", &[2, 3]),
+    (6, "MATRIX_SCALE", &[3, 4]),
+    (7, "MATRIX_TRANSLATE", &[3, 4]),
+    (8, "MATRIX_INTERPOLATE", &[4]),
+];
+
+/// The flag which makes a `matrix()` operation change its first argument.
+const MATRIX_MODIFY: i32 = 128;
+
+impl ConstFn {
+    /// Check the arguments of a call to this function, each given by its
+    /// keyword, if any, and its value, if it's constant.
+    ///
+    /// Only `icon()` and `matrix()` are checked. Arguments whose value isn't
+    /// known are assumed to be right.
+    pub fn check_arguments(self, args: &[(Option<&str>, Option<&Constant>)]) -> Result<(), String> {
+        match self {
+            ConstFn::Icon => check_icon_arguments(args),
+            ConstFn::Matrix => check_matrix_arguments(args),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn check_icon_arguments(args: &[(Option<&str>, Option<&Constant>)]) -> Result<(), String> {
+    if args.len() > ICON_PARAMS.len() {
+        return Err(format!("malformed icon() call, must have at most 5 arguments and instead has {}", args.len()));
+    }
+    for (position, &(keyword, value)) in args.iter().enumerate() {
+        let index = match keyword {
+            Some(keyword) => match ICON_PARAMS.iter().position(|&param| param == keyword) {
+                Some(index) => index,
+                None => continue,
+            },
+            None => position,
+        };
+        let value = match value {
+            Some(Constant::Null(_)) | None => continue,
+            Some(value) => value,
+        };
+        let whole = match value {
+            Constant::Float(float) if float.fract() == 0. => Some(*float as i32),
+            _ => None,
+        };
+        let expected = match (index, whole) {
+            (0, _) if matches!(value, Constant::Float(_)) => "an icon or file",
+            (2, Some(dir)) if ICON_DIRS.contains(&dir) => continue,
+            (2, _) => "a direction",
+            (3, Some(frame)) if frame >= 0 => continue,
+            (3, _) => "a whole number of 0 or more",
+            (4, Some(0 | 1)) => continue,
+            (4, _) => "0, 1, or null",
+            _ => continue,
+        };
+        return Err(format!(
+            "malformed icon() call, argument {} ({}) must be {} and instead is {}",
+            index + 1,
+            ICON_PARAMS[index],
+            expected,
+            value,
+        ));
+    }
+    Ok(())
+}
+
+fn check_matrix_arguments(args: &[(Option<&str>, Option<&Constant>)]) -> Result<(), String> {
+    // matrix() has no parameter names to check keywords against.
+    if args.iter().any(|&(keyword, _)| keyword.is_some()) {
+        return Ok(());
+    }
+    match args.len() {
+        0 => Ok(()),
+        1 => match args[0].1 {
+            Some(value @ (Constant::Float(_) | Constant::String(_))) => Err(format!(
+                "malformed matrix() call, argument 1 must be a matrix and instead is {}",
+                value,
+            )),
+            _ => Ok(()),
+        },
+        2..=4 => {
+            let op = match args[args.len() - 1].1 {
+                Some(op) => op,
+                None => return Ok(()),
+            };
+            let found = match op {
+                Constant::Float(float) if float.fract() == 0. => {
+                    let code = (*float as i32) & !MATRIX_MODIFY;
+                    MATRIX_OPS.iter().find(|&&(each, _, _)| each == code)
+                }
+                _ => None,
+            };
+            match found {
+                Some((_, _, arities)) if arities.contains(&args.len()) => Ok(()),
+                Some((_, name, arities)) => Err(format!(
+                    "malformed matrix() call, {} must have {} arguments and instead has {}",
+                    name,
+                    arities.iter().map(ToString::to_string).collect::<Vec<_>>().join(" or "),
+                    args.len(),
+                )),
+                None => Err(format!(
+                    "malformed matrix() call, argument {} must be a MATRIX_* operation and instead is {}",
+                    args.len(),
+                    op,
+                )),
+            }
+        }
+        6 => {
+            for (index, &(_, value)) in args.iter().enumerate() {
+                if let Some(value @ (Constant::String(_) | Constant::Resource(_) | Constant::List(_) | Constant::Prefab(_))) = value {
+                    return Err(format!(
+                        "malformed matrix() call, argument {} must be a number and instead is {}",
+                        index + 1,
+                        value,
+                    ));
+                }
+            }
+            Ok(())
+        }
+        other => Err(format!("malformed matrix() call, must have 0, 1, 2 to 4, or 6 arguments and instead has {}", other)),
+    }
+}
+
+// ----------------------------------------------------------------------------
+// Random builtins
+
+/// Whether `prob()` with a constant chance, in percent, always succeeds or
+/// never does.
+pub fn prob_outcome(chance: f32) -> Option<bool> {
+    if chance <= 0. {
+        Some(false)
+    } else if chance >= 100. {
+        Some(true)
+    } else {
+        None
+    }
+}
+
+/// The lowest and highest whole numbers `rand()` may return with constant
+/// arguments. `rand(H)` returns from 0 to H.
+///
+/// Calls with no arguments return a fraction, and bounds which are out of
+/// order are not classified.
+pub fn rand_range(args: &[f32]) -> Option<(f32, f32)> {
+    let (low, high) = match *args {
+        [high] => (0., high.floor()),
+        [low, high] => (low.ceil(), high.floor()),
+        _ => return None,
+    };
+    if low > high {
+        return None;
+    }
+    Some((low, high))
+}
+
+/// Whether `x op value` always has the same result for every whole number
+/// `x` in a range.
+pub fn compare_range((low, high): (f32, f32), op: BinaryOp, value: f32) -> Option<bool> {
+    let (always, never) = match op {
+        BinaryOp::Less => (high < value, low >= value),
+        BinaryOp::LessEq => (high <= value, low > value),
+        BinaryOp::Greater => (low > value, high <= value),
+        BinaryOp::GreaterEq => (low >= value, high < value),
+        BinaryOp::Eq => (low == high && low == value, value < low || value > high || value.fract() != 0.),
+        BinaryOp::NotEq => return compare_range((low, high), BinaryOp::Eq, value).map(|equal| !equal),
+        _ => return None,
+    };
+    if always {
+        Some(true)
+    } else if never {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+/// The values of arguments, if they're all positional and fold to numbers.
+pub fn constant_numbers(args: &[Expression]) -> Option<Vec<f32>> {
+    args.iter().map(|arg| match arg {
+        Expression::AssignOp { .. } => None,
+        _ => match arg.clone().simple_evaluate(Location::default()) {
+            Ok(Constant::Float(value)) => Some(value),
+            _ => None,
+        },
+    }).collect()
+}
+
+/// The range of a `rand()` call whose arguments fold to numbers.
+pub fn rand_call_range(expr: &Expression) -> Option<(f32, f32)> {
+    match expr.as_term()? {
+        Term::Call(name, args) if name == "rand" => rand_range(&constant_numbers(args)?),
+        Term::Expr(inner) => rand_call_range(inner),
+        _ => None,
+    }
+}
+
+/// Whether a `prob()` call with these arguments always succeeds or never
+/// does.
+pub fn prob_call_outcome(args: &[Expression]) -> Option<bool> {
+    match *constant_numbers(args)? {
+        [chance] => prob_outcome(chance),
+        _ => None,
+    }
+}
+
+/// Whether comparing a `rand()` call to a constant number, on either side,
+/// always has the same result.
+pub fn rand_comparison(op: BinaryOp, lhs: &Expression, rhs: &Expression) -> Option<bool> {
+    let number = |expr: &Expression| match *constant_numbers(std::slice::from_ref(expr))? {
+        [value] => Some(value),
+        _ => None,
+    };
+    if let Some(range) = rand_call_range(lhs) {
+        return compare_range(range, op, number(rhs)?);
+    }
+    let flipped = match op {
+        BinaryOp::Less => BinaryOp::Greater,
+        BinaryOp::Greater => BinaryOp::Less,
+        BinaryOp::LessEq => BinaryOp::GreaterEq,
+        BinaryOp::GreaterEq => BinaryOp::LessEq,
+        other => other,
+    };
+    compare_range(rand_call_range(rhs)?, flipped, number(lhs)?)
+}
+
 // ----------------------------------------------------------------------------
 // The constant evaluator
 
@@ -416,7 +656,7 @@ impl Expression {
     /// Evaluate this expression in the absence of any surrounding context.
     pub fn simple_evaluate(self, location: Location) -> Result<Constant, DMError> {
         ConstantFolder {
-            tree: None,
+            tree: FolderTree::None,
             location,
             ty: NodeIndex::new(0),
             defines: None,
@@ -427,13 +667,46 @@ impl Expression {
 /// Evaluate an expression in the preprocessor, with `defined()` available.
 pub fn preprocessor_evaluate(location: Location, expr: Expression, defines: &DefineMap) -> Result<Constant, DMError> {
     ConstantFolder {
-        tree: None,
+        tree: FolderTree::None,
         location,
         ty: NodeIndex::new(0),
         defines: Some(defines),
     }.expr(expr, None)
 }
 
+/// Evaluate source text as a constant expression in the context of a type.
+///
+/// Macros in `defines` are expanded first. Identifiers resolve to the
+/// already-evaluated vars of `ty` and its parents, and relative type paths
+/// are resolved against `ty`. Errors are located by line and column within
+/// `text`; their file is not meaningful.
+pub fn evaluate_const_expression(
+    tree: &ObjectTree,
+    ty: TypeRef,
+    text: &str,
+    defines: &DefineMap,
+) -> Result<Constant, DMError> {
+    use super::lexer::{Punctuation, Token};
+    use super::preprocessor::Preprocessor;
+
+    let ctx = Context::default();
+    let mut preprocessor = Preprocessor::from_buffer(&ctx, "<expression>".into(), text);
+    preprocessor.set_defines(defines.clone());
+    let location = Location { line: 1, column: 1, ..Location::default() };
+    let tokens = preprocessor.filter(|token| token.token != Token::Punct(Punctuation::Newline));
+    let expr = crate::parser::parse_whole_expression(&ctx, location, tokens);
+    if let Some(error) = ctx.errors().iter().find(|error| error.severity() == Severity::Error) {
+        return Err(error.clone());
+    }
+    let expr = expr?;
+    ConstantFolder {
+        tree: FolderTree::Finished(tree),
+        location,
+        ty: ty.index(),
+        defines: Some(defines),
+    }.expr(expr, None)
+}
+
 /// Evaluate all the type-level variables in an object tree into constants.
 pub(crate) fn evaluate_all(context: &Context, tree: &mut ObjectTree) {
     for ty in tree.node_indices() {
@@ -463,6 +736,7 @@ pub(crate) fn evaluate_all(context: &Context, tree: &mut ObjectTree) {
             }
         }
     }
+    check_global_init_order(context, tree);
 }
 
 enum ConstLookup {
@@ -525,7 +799,7 @@ fn constant_ident_lookup(
     };
     // evaluate full_value
     let value = ConstantFolder {
-        tree: Some(tree),
+        tree: FolderTree::Building(tree),
         defines: None,
         location,
         ty,
@@ -537,8 +811,288 @@ fn constant_ident_lookup(
     Ok(ConstLookup::Found(type_hint, value))
 }
 
+/// Read the value of a var in a tree which has already been evaluated.
+fn evaluated_ident_lookup(
+    tree: &ObjectTree,
+    ty: NodeIndex,
+    ident: &str,
+    must_be_const: bool,
+) -> Result<ConstLookup, DMError> {
+    let decl = match tree[ty].get_var_declaration(ident, tree) {
+        Some(decl) => decl,
+        None => return Ok(ConstLookup::Continue(None)),
+    };
+    let type_ = &tree[ty];
+    let var = match type_.vars.get(ident) {
+        Some(var) => var,
+        None => return Ok(ConstLookup::Continue(type_.parent_type_index())),
+    };
+    if !decl.var_type.is_const_evaluable() {
+        return Err(DMError::new(var.value.location, format!("non-const-evaluable variable: {}", ident)));
+    } else if !decl.var_type.flags.is_const() && must_be_const {
+        return Err(DMError::new(var.value.location, format!("non-const variable: {}", ident)));
+    }
+    match (&var.value.constant, &var.value.expression) {
+        (Some(constant), _) => Ok(ConstLookup::Found(decl.var_type.type_path.clone(), constant.clone())),
+        (None, None) => Ok(ConstLookup::Found(
+            decl.var_type.type_path.clone(),
+            Constant::Null(Some(decl.var_type.type_path.clone())),
+        )),
+        (None, Some(_)) => Err(DMError::new(var.value.location, format!("variable has no constant value: {}", ident))),
+    }
+}
+
+// ----------------------------------------------------------------------------
+// Global initialization order
+
+/// A global var, by the type which declares it and its name. Globals are the
+/// vars declared at the root and the `static` vars of types, except for
+/// `const` ones.
+type GlobalVar = (NodeIndex, String);
+
+/// Report globals whose initializers depend on each other in a cycle, and
+/// those which use a global declared later, which is still null when they run.
+///
+/// Initializers which fold to a constant other than a list are set by the
+/// compiler. The rest run when the world starts, in the order the globals are
+/// declared: by file in the order files are included, then by position in the
+/// file. Only uses of globals directly in initializers are followed, not
+/// those in procs which initializers call.
+fn check_global_init_order(context: &Context, tree: &ObjectTree) {
+    // Globals whose initializers run when the world starts.
+    let mut runtime: Vec<(GlobalVar, Location, &Expression)> = Vec::new();
+    for ty in tree.node_indices() {
+        for (name, var) in tree[ty].vars.iter() {
+            let decl = match var.declaration {
+                Some(ref decl) if is_global(ty, decl) && !decl.location.is_builtins() => decl,
+                _ => continue,
+            };
+            let expr = match var.value.expression {
+                Some(ref expr) => expr,
+                None => continue,
+            };
+            let folded = ConstantFolder {
+                tree: FolderTree::Finished(tree),
+                defines: None,
+                location: var.value.location,
+                ty,
+            }.expr(expr.clone(), None);
+            if !matches!(folded, Err(_) | Ok(Constant::List(_))) {
+                continue;
+            }
+            runtime.push(((ty, name.clone()), decl.location, expr));
+        }
+    }
+    runtime.sort_by_key(|&(_, location, _)| location);
+    let locations: HashMap<&GlobalVar, Location, RandomState> = runtime.iter().map(|(global, location, _)| (global, *location)).collect();
+    let location_of = |global: &GlobalVar| locations.get(global).copied();
+
+    let mut uses: IndexMap<GlobalVar, IndexSet<GlobalVar, RandomState>, RandomState> = Default::default();
+    for ((ty, name), _, expr) in runtime.iter() {
+        let mut found = IndexSet::default();
+        visit_idents(expr, &mut |ident, global_field| {
+            let target = match global_field {
+                Some(field) => resolve_global(tree, NodeIndex::new(0), field),
+                None => resolve_global(tree, *ty, ident),
+            };
+            if let Some(target) = target {
+                if locations.contains_key(&target) {
+                    found.insert(target);
+                }
+            }
+        });
+        uses.insert((*ty, name.clone()), found);
+    }
+
+    let mut in_cycle: HashSet<GlobalVar, RandomState> = Default::default();
+    for (global, location, _) in runtime.iter() {
+        if in_cycle.contains(global) {
+            continue;
+        }
+        let mut path = vec![global.clone()];
+        let mut visited = HashSet::default();
+        if !path_back(&uses, global, &mut path, &mut visited) {
+            continue;
+        }
+        let chain: Vec<String> = path.iter().map(|each| global_name(tree, each)).collect();
+        let mut error = DMError::new(*location, format!("global {} depends on its own value: {}", chain[0], chain.join(" -> ")))
+            .with_errortype("global_init_cycle");
+        for each in path[1..path.len() - 1].iter() {
+            if let Some(other) = location_of(each) {
+                error.add_note(other, format!("{} is initialized here", global_name(tree, each)));
+            }
+        }
+        context.register_error(error);
+        in_cycle.extend(path);
+    }
+
+    for (global, location, _) in runtime.iter() {
+        for target in uses[global].iter() {
+            let target_location = match location_of(target) {
+                Some(target_location) if target_location > *location => target_location,
+                _ => continue,
+            };
+            if in_cycle.contains(global) && in_cycle.contains(target) {
+                continue;
+            }
+            let target_name = global_name(tree, target);
+            context.register_error(DMError::new(*location, format!(
+                "global {} is initialized using {}, which is declared later and is still null then",
+                global_name(tree, global),
+                target_name,
+            ))
+                .set_severity(Severity::Warning)
+                .with_errortype("global_init_order")
+                .with_note(target_location, format!("{} is declared here", target_name)));
+        }
+    }
+}
+
+fn is_global(ty: NodeIndex, decl: &VarDeclaration) -> bool {
+    !decl.var_type.flags.is_const() && (ty == NodeIndex::new(0) || decl.var_type.flags.is_static())
+}
+
+/// The global which a name in an initializer on `ty` refers to, if any.
+fn resolve_global(tree: &ObjectTree, ty: NodeIndex, name: &str) -> Option<GlobalVar> {
+    let mut next = Some(ty);
+    while let Some(ty) = next {
+        if let Some(TypeVar { declaration: Some(decl), .. }) = tree[ty].vars.get(name) {
+            return is_global(ty, decl).then(|| (ty, name.to_owned()));
+        }
+        next = tree[ty].parent_type_index();
+    }
+    let root = NodeIndex::new(0);
+    match tree[root].vars.get(name) {
+        Some(TypeVar { declaration: Some(decl), .. }) if ty != root => is_global(root, decl).then(|| (root, name.to_owned())),
+        _ => None,
+    }
+}
+
+fn global_name(tree: &ObjectTree, &(ty, ref name): &GlobalVar) -> String {
+    if ty == NodeIndex::new(0) {
+        name.clone()
+    } else {
+        format!("{}/var/{}", tree[ty].path, name)
+    }
+}
+
+/// Whether a path leads from the end of `path` back to its start, which
+/// `path` is extended with if so.
+fn path_back(uses: &IndexMap<GlobalVar, IndexSet<GlobalVar, RandomState>, RandomState>, start: &GlobalVar, path: &mut Vec<GlobalVar>, visited: &mut HashSet<GlobalVar, RandomState>) -> bool {
+    let current = path.last().unwrap().clone();
+    for next in uses[&current].iter() {
+        if next == start {
+            path.push(next.clone());
+            return true;
+        }
+        if !visited.insert(next.clone()) {
+            continue;
+        }
+        path.push(next.clone());
+        if path_back(uses, start, path, visited) {
+            return true;
+        }
+        path.pop();
+    }
+    false
+}
+
+/// Call a function on each identifier in an expression, with the field named
+/// after it if it's `global`.
+fn visit_idents(expr: &Expression, f: &mut dyn FnMut(&str, Option<&str>)) {
+    match expr {
+        Expression::Base { term, follow } => {
+            if let Term::Ident(ref ident) = term.elem {
+                let global_field = match follow.first() {
+                    Some(Spanned { elem: Follow::Field(_, field), .. }) if ident == "global" => Some(field.as_str()),
+                    _ => None,
+                };
+                f(ident, global_field);
+            }
+            visit_term_idents(&term.elem, f);
+            for each in follow.iter() {
+                match each.elem {
+                    Follow::Index(_, ref index) => visit_idents(index, f),
+                    Follow::Call(_, _, ref args) => args.iter().for_each(|arg| visit_idents(arg, f)),
+                    Follow::Field(..) | Follow::Unary(_) => {}
+                }
+            }
+        }
+        Expression::BinaryOp { lhs, rhs, .. } |
+        Expression::AssignOp { lhs, rhs, .. } => {
+            visit_idents(lhs, f);
+            visit_idents(rhs, f);
+        }
+        Expression::TernaryOp { cond, if_, else_ } => {
+            visit_idents(cond, f);
+            visit_idents(if_, f);
+            visit_idents(else_, f);
+        }
+    }
+}
+
+fn visit_term_idents(term: &Term, f: &mut dyn FnMut(&str, Option<&str>)) {
+    fn all(exprs: &[Expression], f: &mut dyn FnMut(&str, Option<&str>)) {
+        exprs.iter().for_each(|expr| visit_idents(expr, f));
+    }
+    match term {
+        Term::Expr(expr) => visit_idents(expr, f),
+        Term::InterpString(_, parts) => {
+            for expr in parts.iter().filter_map(|(expr, _)| expr.as_ref()) {
+                visit_idents(expr, f);
+            }
+        }
+        Term::Call(_, args) |
+        Term::SelfCall(args) |
+        Term::ParentCall(args) |
+        Term::List(args) => all(args, f),
+        Term::NewImplicit { args: Some(args) } |
+        Term::NewPrefab { args: Some(args), .. } |
+        Term::NewMiniExpr { args: Some(args), .. } => all(args, f),
+        Term::Input { args, in_list, .. } |
+        Term::Locate { args, in_list } => {
+            all(args, f);
+            if let Some(in_list) = in_list {
+                visit_idents(in_list, f);
+            }
+        }
+        Term::Pick(choices) => {
+            for (weight, value) in choices.iter() {
+                if let Some(weight) = weight {
+                    visit_idents(weight, f);
+                }
+                visit_idents(value, f);
+            }
+        }
+        Term::DynamicCall(first, second) => {
+            all(first, f);
+            all(second, f);
+        }
+        _ => {}
+    }
+}
+
+/// The object tree, if any, that a constant folder resolves names against.
+enum FolderTree<'a> {
+    None,
+    /// A tree still being built, whose vars are evaluated on demand.
+    Building(&'a mut ObjectTree),
+    /// A finished tree, whose vars are only read.
+    Finished(&'a ObjectTree),
+}
+
+impl<'a> FolderTree<'a> {
+    fn get(&self) -> Option<&ObjectTree> {
+        match self {
+            FolderTree::None => None,
+            FolderTree::Building(tree) => Some(tree),
+            FolderTree::Finished(tree) => Some(tree),
+        }
+    }
+}
+
 struct ConstantFolder<'a> {
-    tree: Option<&'a mut ObjectTree>,
+    tree: FolderTree<'a>,
     defines: Option<&'a DefineMap>,
     location: Location,
     ty: NodeIndex,
@@ -554,6 +1108,9 @@ impl<'a> ConstantFolder<'a> {
     fn expr(&mut self, expression: Expression, type_hint: Option<&TreePath>) -> Result<Constant, DMError> {
         Ok(match expression {
             Expression::Base { term, follow } => {
+                if term.location != Location::default() {
+                    self.location = term.location;
+                }
                 let base_type_hint = if follow.is_empty() {
                     type_hint
                 } else {
@@ -613,6 +1170,23 @@ impl<'a> ConstantFolder<'a> {
         Ok(out.into())
     }
 
+    /// A constructor call, whose arguments are checked but otherwise kept
+    /// as they are.
+    fn checked_call(&mut self, func: ConstFn, args: Box<[Expression]>) -> Result<Constant, DMError> {
+        let args = self.arguments(args)?;
+        let given: Vec<_> = args.iter()
+            .map(|(key, value)| match (key, value) {
+                (Constant::String(keyword), Some(value)) => (Some(&**keyword), Some(value)),
+                (_, Some(value)) => (None, Some(value)),
+                (key, None) => (None, Some(key)),
+            })
+            .collect();
+        if let Err(message) = func.check_arguments(&given) {
+            return Err(self.error(message));
+        }
+        Ok(Constant::Call(func, args))
+    }
+
     fn follow(&mut self, term: Constant, follow: Follow) -> Result<Constant, DMError> {
         match (term, follow) {
             // Meant to handle the GLOB.SCI_FREQ case:
@@ -627,7 +1201,7 @@ impl<'a> ConstantFolder<'a> {
                     full_path.push('/');
                     full_path.push_str(each);
                 }
-                match self.tree.as_mut().and_then(|t| t.find(&full_path)).map(|t| t.index()) {
+                match self.tree.get().and_then(|t| t.find(&full_path)).map(|t| t.index()) {
                     Some(idx) => self.recursive_lookup(idx, &field_name, true),
                     None => Err(self.error(format!("unknown typepath {}", full_path))),
                 }
@@ -724,9 +1298,9 @@ impl<'a> ConstantFolder<'a> {
             Term::List(vec) => Constant::List(self.arguments(vec)?),
             Term::Call(ident, args) => match &*ident {
                 // constructors which remain as they are
-                "matrix" => Constant::Call(ConstFn::Matrix, self.arguments(args)?),
+                "matrix" => self.checked_call(ConstFn::Matrix, args)?,
                 "newlist" => Constant::Call(ConstFn::Newlist, self.arguments(args)?),
-                "icon" => Constant::Call(ConstFn::Icon, self.arguments(args)?),
+                "icon" => self.checked_call(ConstFn::Icon, args)?,
                 "sound" => Constant::Call(ConstFn::Sound, self.arguments(args)?),
                 "file" => Constant::Call(ConstFn::File, self.arguments(args)?),
                 "generator" => Constant::Call(ConstFn::Generator, self.arguments(args)?),
@@ -750,8 +1324,10 @@ impl<'a> ConstantFolder<'a> {
                 _ => return Err(self.error(format!("non-constant function call: {}", ident))),
             },
             Term::Prefab(prefab) => Constant::Prefab(Box::new(self.prefab(*prefab)?)),
+            Term::ProcReference(reference) => Constant::Prefab(Box::new(self.prefab(Prefab::from(reference.path()))?)),
             Term::Ident(ident) => self.ident(ident, false)?,
             Term::String(v) => Constant::String(v.into()),
+            Term::RawString(v) => Constant::String(v.into()),
             Term::Resource(v) => Constant::Resource(v.into()),
             Term::Int(v) => Constant::Float(v as f32),
             Term::Float(v) => Constant::from(v),
@@ -781,7 +1357,7 @@ impl<'a> ConstantFolder<'a> {
         }
 
         // Otherwise, resolve it against our object tree, then stringify it.
-        let tree = match self.tree.as_ref() {
+        let tree = match self.tree.get() {
             Some(tree) => tree,
             None => return Err(self.error(format!(
                 "cannot resolve relative type path without an object tree: {}",
@@ -818,13 +1394,12 @@ impl<'a> ConstantFolder<'a> {
         let mut idx = Some(ty);
         while let Some(ty) = idx {
             let location = self.location;
-            if self.tree.is_none() {
-                return Err(self.error(format!("cannot reference variable {:?} in this context", ident)));
-            }
-            let tree = self.tree.as_mut().unwrap();
-            match constant_ident_lookup(tree, ty, &ident, must_be_const)
-                .map_err(|e| e.with_location(location))?
-            {
+            let lookup = match self.tree {
+                FolderTree::None => return Err(self.error(format!("cannot reference variable {:?} in this context", ident))),
+                FolderTree::Building(ref mut tree) => constant_ident_lookup(tree, ty, ident, must_be_const),
+                FolderTree::Finished(tree) => evaluated_ident_lookup(tree, ty, ident, must_be_const),
+            };
+            match lookup.map_err(|e| e.with_location(location))? {
                 ConstLookup::Found(_, v) => return Ok(v),
                 ConstLookup::Continue(i) => idx = i,
             }
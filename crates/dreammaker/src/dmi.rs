@@ -255,9 +255,32 @@ impl Metadata {
     }
 
     /// Parse metadata from a `Description` string.
+    ///
+    /// Panics if the metadata is malformed.
     #[inline]
     pub fn meta_from_str(data: &str) -> Metadata {
-        parse_metadata(data)
+        parse_metadata(data).unwrap()
+    }
+
+    /// Read only the DMI metadata of a file, without decoding its pixels.
+    ///
+    /// Unlike `from_file`, malformed metadata is an error rather than a panic.
+    pub fn read_file(path: &Path) -> io::Result<Metadata> {
+        Metadata::from_png_bytes(&std::fs::read(crate::fix_case(path))?)
+    }
+
+    /// Read only the DMI metadata of the contents of a PNG file.
+    pub fn from_png_bytes(data: &[u8]) -> io::Result<Metadata> {
+        let ((width, height), description) = read_png_description(data)?;
+        match description {
+            Some(description) => parse_metadata(&description),
+            None => Ok(Metadata {
+                width,
+                height,
+                states: Default::default(),
+                state_names: Default::default(),
+            }),
+        }
     }
 
     pub fn rect_of(&self, bitmap_width: u32, icon_state: &str, dir: Dir, frame: u32) -> Option<(u32, u32, u32, u32)> {
@@ -341,7 +364,11 @@ impl Frames {
 // ----------------------------------------------------------------------------
 // Metadata parser
 
-fn parse_metadata(data: &str) -> Metadata {
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn parse_metadata(data: &str) -> io::Result<Metadata> {
     let mut metadata = Metadata {
         width: 32,
         height: 32,
@@ -349,12 +376,17 @@ fn parse_metadata(data: &str) -> Metadata {
         state_names: BTreeMap::new(),
     };
     if data.is_empty() {
-        return metadata;
+        return Ok(metadata);
     }
 
     let mut lines = data.lines();
-    assert_eq!(lines.next().unwrap(), "# BEGIN DMI");
-    assert_eq!(lines.next().unwrap(), &format!("version = {}", VERSION));
+    if lines.next() != Some("# BEGIN DMI") {
+        return Err(invalid("missing \"# BEGIN DMI\"".to_owned()));
+    }
+    let version = lines.next().unwrap_or("");
+    if version != format!("version = {}", VERSION) {
+        return Err(invalid(format!("unsupported {:?}", version)));
+    }
 
     let mut state: Option<State> = None;
     let mut frames_so_far = 0;
@@ -363,54 +395,58 @@ fn parse_metadata(data: &str) -> Metadata {
         if line.starts_with("# END DMI") {
             break;
         }
-        let mut split = line.trim().splitn(2, " = ");
-        let key = split.next().unwrap();
-        let value = split.next().unwrap();
-        match key {
-            "width" => metadata.width = value.parse().unwrap(),
-            "height" => metadata.height = value.parse().unwrap(),
-            "state" => {
-                if let Some(state) = state.take() {
-                    frames_so_far += state.frames.len() * state.dirs.len();
-                    metadata.states.push(state);
-                }
-                let unquoted = value[1..value.len() - 1].to_owned(); // TODO: unquote
-                assert!(!unquoted.contains('\\') && !unquoted.contains('"'));
-                if !metadata.state_names.contains_key(&unquoted) {
-                    metadata.state_names.insert(unquoted.clone(), metadata.states.len());
-                }
-
-                state = Some(State {
-                    offset: frames_so_far,
-                    name: unquoted,
-                    loop_: 0,
-                    rewind: false,
-                    movement: false,
-                    dirs: Dirs::One,
-                    frames: Frames::One,
-                });
+        let (key, value) = line.trim().split_once(" = ")
+            .ok_or_else(|| invalid(format!("malformed line {:?}", line)))?;
+        let bad_value = || invalid(format!("bad value for {}: {:?}", key, value));
+        if key == "width" || key == "height" {
+            let size = value.parse().map_err(|_| bad_value())?;
+            if key == "width" {
+                metadata.width = size;
+            } else {
+                metadata.height = size;
             }
+            continue;
+        }
+        if key == "state" {
+            if let Some(state) = state.take() {
+                frames_so_far += state.frames.len() * state.dirs.len();
+                metadata.states.push(state);
+            }
+            let unquoted = unquote(value).ok_or_else(bad_value)?;
+            if !metadata.state_names.contains_key(&unquoted) {
+                metadata.state_names.insert(unquoted.clone(), metadata.states.len());
+            }
+
+            state = Some(State {
+                offset: frames_so_far,
+                name: unquoted,
+                loop_: 0,
+                rewind: false,
+                movement: false,
+                dirs: Dirs::One,
+                frames: Frames::One,
+            });
+            continue;
+        }
+
+        let state = state.as_mut().ok_or_else(|| invalid(format!("{} before the first state", key)))?;
+        match key {
             "dirs" => {
-                let state = state.as_mut().unwrap();
-                let n: u8 = value.parse().unwrap();
-                state.dirs = match n {
-                    1 => Dirs::One,
-                    4 => Dirs::Four,
-                    8 => Dirs::Eight,
-                    _ => panic!(),
+                state.dirs = match value.parse::<u8>() {
+                    Ok(1) => Dirs::One,
+                    Ok(4) => Dirs::Four,
+                    Ok(8) => Dirs::Eight,
+                    _ => return Err(bad_value()),
                 };
             }
             "frames" => {
-                let state = state.as_mut().unwrap();
-                match state.frames {
-                    Frames::One => {},
-                    _ => panic!(),
+                if state.frames != Frames::One {
+                    return Err(invalid("frames after frames or delay".to_owned()));
                 }
-                state.frames = Frames::Count(value.parse().unwrap());
+                state.frames = Frames::Count(value.parse().map_err(|_| bad_value())?);
             }
             "delay" => {
-                let state = state.as_mut().unwrap();
-                let mut vector: Vec<f32> = value.split(',').map(str::parse).collect::<Result<Vec<_>, _>>().unwrap();
+                let mut vector: Vec<f32> = value.split(',').map(str::parse).collect::<Result<Vec<_>, _>>().map_err(|_| bad_value())?;
                 match state.frames {
                     Frames::One => if vector.iter().all(|&n| n == 1.) {
                         state.frames = Frames::Count(vector.len());
@@ -421,17 +457,110 @@ fn parse_metadata(data: &str) -> Metadata {
                         vector.truncate(n);
                         state.frames = Frames::Delays(vector);
                     },
-                    Frames::Delays(_) => panic!(),
+                    Frames::Delays(_) => return Err(invalid("delay after delay".to_owned())),
                 }
             }
-            "loop" => state.as_mut().unwrap().loop_ = value.parse().unwrap(),
-            "rewind" => state.as_mut().unwrap().rewind = value.parse::<u8>().unwrap() != 0,
+            "loop" => state.loop_ = value.parse().map_err(|_| bad_value())?,
+            "rewind" => state.rewind = value.parse::<u8>().map_err(|_| bad_value())? != 0,
             "hotspot" => { /* TODO */ }
-            "movement" => state.as_mut().unwrap().movement = value.parse::<u8>().unwrap() != 0,
-            _ => panic!(),
+            "movement" => state.movement = value.parse::<u8>().map_err(|_| bad_value())? != 0,
+            _ => return Err(invalid(format!("unknown key {:?}", key))),
         }
     }
     metadata.states.extend(state);
 
-    metadata
+    Ok(metadata)
+}
+
+/// Remove the quotes and escapes from a state name.
+fn unquote(value: &str) -> Option<String> {
+    let inner = value.strip_prefix('"')?.strip_suffix('"')?;
+    let mut out = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\\' => out.push(chars.next()?),
+            '"' => return None,
+            _ => out.push(ch),
+        }
+    }
+    Some(out)
+}
+
+// ----------------------------------------------------------------------------
+// PNG chunk reader
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// Find the size and the `Description` text of PNG data without decoding its
+/// pixels.
+fn read_png_description(data: &[u8]) -> io::Result<((u32, u32), Option<String>)> {
+    let mut rest = data.strip_prefix(PNG_SIGNATURE)
+        .ok_or_else(|| invalid("not a PNG file".to_owned()))?;
+    let truncated = || invalid("truncated PNG file".to_owned());
+    let be_u32 = |bytes: &[u8]| u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+
+    let mut size = (0, 0);
+    while rest.len() >= 8 {
+        let length = be_u32(&rest[0..4]) as usize;
+        let kind = &rest[4..8];
+        // The chunk's data is followed by a checksum, which isn't verified.
+        let chunk = rest.get(8..8 + length).ok_or_else(truncated)?;
+        let whole = rest.get(..12 + length).ok_or_else(truncated)?;
+        rest = &rest[12 + length..];
+
+        match kind {
+            b"IHDR" if chunk.len() >= 8 => size = (be_u32(&chunk[0..4]), be_u32(&chunk[4..8])),
+            b"tEXt" | b"zTXt" | b"iTXt" => {
+                let nul = chunk.iter().position(|&b| b == 0).ok_or_else(truncated)?;
+                if &chunk[..nul] != b"Description" {
+                    continue;
+                }
+                let text = match kind {
+                    b"tEXt" => chunk[nul + 1..].to_vec(),
+                    // Compression flag and method, then language and
+                    // translated keyword, each ending in a nul.
+                    b"iTXt" if *chunk.get(nul + 1).ok_or_else(truncated)? == 0 => {
+                        let mut fields = chunk.get(nul + 3..).ok_or_else(truncated)?.splitn(3, |&b| b == 0);
+                        fields.nth(2).ok_or_else(truncated)?.to_vec()
+                    }
+                    _ => return Ok((size, Some(decompress_description(whole)?))),
+                };
+                let text = String::from_utf8(text).map_err(|_| invalid("description is not UTF-8".to_owned()))?;
+                return Ok((size, Some(text)));
+            }
+            b"IDAT" | b"IEND" => break,
+            _ => {}
+        }
+    }
+    Ok((size, None))
+}
+
+/// The start of a one-pixel PNG, up to where its pixels would follow.
+const TINY_PNG_HEAD: &[u8] = b"\x89PNG\r\n\x1a\n\
+    \x00\x00\x00\x0d\x49\x48\x44\x52\x00\x00\x00\x01\x00\x00\x00\x01\x08\x00\x00\x00\x00\x3a\x7e\x9b\x55";
+/// The pixels and end of a one-pixel PNG.
+const TINY_PNG_TAIL: &[u8] = b"\x00\x00\x00\x0a\x49\x44\x41\x54\x78\x9c\x63\x60\x00\x00\x00\x02\x00\x01\x48\xaf\xa4\x71\
+    \x00\x00\x00\x00\x49\x45\x4e\x44\xae\x42\x60\x82";
+
+/// Decompress a `Description` held in a `zTXt` or compressed `iTXt` chunk,
+/// by having lodepng read the chunk as part of a one-pixel image rather than
+/// decoding the whole file.
+fn decompress_description(chunk: &[u8]) -> io::Result<String> {
+    let mut png = Vec::with_capacity(TINY_PNG_HEAD.len() + chunk.len() + TINY_PNG_TAIL.len());
+    png.extend_from_slice(TINY_PNG_HEAD);
+    png.extend_from_slice(chunk);
+    png.extend_from_slice(TINY_PNG_TAIL);
+
+    let mut decoder = Decoder::new();
+    decoder.read_text_chunks(true);
+    decoder.decode(&png).map_err(|e| invalid(e.to_string()))?;
+    let info = decoder.info_png();
+    if let Some((_, value)) = info.text_keys().find(|&(key, _)| key == b"Description") {
+        return String::from_utf8(value.to_vec()).map_err(|_| invalid("description is not UTF-8".to_owned()));
+    }
+    info.itext_keys()
+        .find(|&(key, ..)| key == "Description")
+        .map(|(.., value)| value.to_owned())
+        .ok_or_else(|| invalid("unreadable description".to_owned()))
 }
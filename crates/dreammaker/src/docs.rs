@@ -2,10 +2,13 @@
 
 use std::fmt;
 
+use serde::{Serialize, Deserialize};
+
 /// A collection of documentation comments targeting the same item.
-#[derive(Default, Clone, Debug, PartialEq)]
+#[derive(Default, Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct DocCollection {
     elems: Vec<DocComment>,
+    #[serde(skip)]
     pub builtin_docs: BuiltinDocs,
 }
 
@@ -60,7 +63,7 @@ impl DocCollection {
 }
 
 /// A documentation comment.
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct DocComment {
     pub kind: CommentKind,
     pub target: DocTarget,
@@ -95,7 +98,7 @@ impl fmt::Display for DocComment {
 }
 
 /// The possible documentation comment kinds.
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub enum CommentKind {
     /// A block `/** */` comment.
     Block,
@@ -197,7 +200,7 @@ fn is_empty(text: &str, ignore_char: char) -> bool {
 }
 
 /// The possible items that a documentation comment may target.
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub enum DocTarget {
     /// Starting with `*` or `/`, referring to the following item.
     FollowingItem,
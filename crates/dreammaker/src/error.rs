@@ -8,11 +8,13 @@ use std::collections::HashMap;
 use ahash::RandomState;
 
 use termcolor::{ColorSpec, Color};
+use serde::{Serialize, Deserialize};
 
 use crate::config::Config;
+use crate::resource::ResourceDirs;
 
 /// An identifier referring to a loaded file.
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
 pub struct FileId(u16);
 
 const FILEID_BUILTINS: FileId = FileId(0x0000);
@@ -43,6 +45,9 @@ pub struct Context {
     errors: RefCell<Vec<DMError>>,
     /// Warning config
     config: RefCell<Config>,
+    config_path: RefCell<Option<PathBuf>>,
+    /// Search path for resource literals.
+    resources: RefCell<ResourceDirs>,
     print_severity: Option<Severity>,
 
     io_time: std::cell::Cell<std::time::Duration>,
@@ -119,10 +124,35 @@ impl Context {
         &self.files
     }
 
+    // ------------------------------------------------------------------------
+    // Resources
+
+    /// Access the search path for resource literals.
+    pub fn resources(&self) -> Ref<'_, ResourceDirs> {
+        self.resources.borrow()
+    }
+
+    pub(crate) fn resources_mut(&self) -> RefMut<'_, ResourceDirs> {
+        self.resources.borrow_mut()
+    }
+
+    /// Find the file a `'resource'` literal refers to, using the environment's
+    /// `FILE_DIR` search order.
+    pub fn resolve_resource(&self, literal: &str) -> Option<PathBuf> {
+        self.resources.borrow().resolve(literal)
+    }
+
+    /// Resolve resources to their first candidate path without touching the
+    /// filesystem.
+    pub fn set_resource_dry_run(&self, dry: bool) {
+        self.resources.borrow_mut().set_dry(dry);
+    }
+
     // ------------------------------------------------------------------------
     // Configuration
 
     pub fn force_config(&self, toml: &Path) {
+        *self.config_path.borrow_mut() = Some(toml.to_owned());
         match Config::read_toml(toml) {
             Ok(config) => *self.config.borrow_mut() = config,
             Err(io_error) => {
@@ -146,6 +176,11 @@ impl Context {
         self.config.borrow()
     }
 
+    /// The path of the configuration file in use, if any.
+    pub fn config_path(&self) -> Option<PathBuf> {
+        self.config_path.borrow().clone()
+    }
+
     /// Set a severity at and above which errors will be printed immediately.
     pub fn set_print_severity(&mut self, print_severity: Option<Severity>) {
         self.print_severity = print_severity;
@@ -273,7 +308,7 @@ impl Context {
 // Location handling
 
 /// File, line, and column information for an error.
-#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Default)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Default, Serialize, Deserialize)]
 pub struct Location {
     /// The index into the file table.
     pub file: FileId,
@@ -340,7 +375,7 @@ pub(crate) trait HasLocation {
 // Error handling
 
 /// The possible diagnostic severities available.
-#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize)]
 pub enum Severity {
     Error = 1,
     Warning = 2,
@@ -379,7 +414,7 @@ impl fmt::Display for Severity {
 }
 
 /// A component which generated a diagnostic, when separation is desired.
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub enum Component {
     Unspecified,
     DreamChecker,
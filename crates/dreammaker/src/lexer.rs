@@ -116,6 +116,7 @@ table! {
     "?",   QuestionMark;
     "?.",  SafeDot;
     "?:",  SafeColon;
+    "?=",  NullAssign;
     "?[",  SafeLBracket;
     "[",   LBracket;
     "]",   RBracket;
@@ -152,15 +153,15 @@ static SPEEDY_TABLE: [(usize, usize); 127] = [
     (2, 3), (3, 5), (5, 6), (6, 8), (0, 0), (8, 10), (10, 14), (14, 15),
     (15, 16), (16, 17), (17, 20), (20, 23), (23, 24), (24, 27), (27, 30), (30, 34),
     (0, 0), (0, 0), (0, 0), (0, 0), (0, 0), (0, 0), (0, 0), (0, 0),
-    (0, 0), (0, 0), (34, 36), (36, 37), (37, 42), (42, 44), (44, 48), (48, 52),
+    (0, 0), (0, 0), (34, 36), (36, 37), (37, 42), (42, 44), (44, 48), (48, 53),
     (0, 0), (0, 0), (0, 0), (0, 0), (0, 0), (0, 0), (0, 0), (0, 0),
     (0, 0), (0, 0), (0, 0), (0, 0), (0, 0), (0, 0), (0, 0), (0, 0),
     (0, 0), (0, 0), (0, 0), (0, 0), (0, 0), (0, 0), (0, 0), (0, 0),
-    (0, 0), (0, 0), (0, 0), (52, 53), (0, 0), (53, 54), (54, 56), (0, 0),
+    (0, 0), (0, 0), (0, 0), (53, 54), (0, 0), (54, 55), (55, 57), (0, 0),
     (0, 0), (0, 0), (0, 0), (0, 0), (0, 0), (0, 0), (0, 0), (0, 0),
     (0, 0), (0, 0), (0, 0), (0, 0), (0, 0), (0, 0), (0, 0), (0, 0),
     (0, 0), (0, 0), (0, 0), (0, 0), (0, 0), (0, 0), (0, 0), (0, 0),
-    (0, 0), (0, 0), (0, 0), (56, 58), (58, 62), (62, 63), (63, 66)];
+    (0, 0), (0, 0), (0, 0), (57, 59), (59, 63), (63, 64), (64, 67)];
 
 #[test]
 fn make_speedy_table() {
@@ -245,6 +246,8 @@ pub enum Token {
     Ident(Ident, bool),
     /// A string literal with no interpolation.
     String(String),
+    /// A raw string literal, whose contents are exact and contain no escapes.
+    RawString(String),
     /// The opening portion of an interpolated string. Followed by an expression.
     InterpStringBegin(String),
     /// An internal portion of an interpolated string. Preceded and followed by an expression.
@@ -301,6 +304,7 @@ impl Token {
                 RShift |
                 RShiftAssign |
                 QuestionMark |
+                NullAssign |
                 BitXorAssign |
                 BitOrAssign |
                 OrAssign |
@@ -352,6 +356,7 @@ impl fmt::Display for Token {
             Punct(p) => write!(f, "{}", p),
             Ident(ref i, _) => f.write_str(i),
             String(ref i) => Quote(i).fmt(f),
+            RawString(ref i) => RawQuote(i).fmt(f),
             InterpStringBegin(ref i) => write!(f, "\"{}[", i),
             InterpStringPart(ref i) => write!(f, "]{}[", i),
             InterpStringEnd(ref i) => write!(f, "]{}\"", i),
@@ -381,6 +386,26 @@ impl<'a> fmt::Display for Quote<'a> {
     }
 }
 
+/// Formatting helper to quote a raw string according to DM's rules.
+pub struct RawQuote<'a>(pub &'a str);
+
+impl<'a> fmt::Display for RawQuote<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = self.0;
+        if !s.contains('"') && !s.contains('\n') {
+            return write!(f, "@\"{}\"", s);
+        } else if !s.contains("\"}") {
+            return write!(f, "@{{\"{}\"}}", s);
+        }
+        // find a terminator which does not appear in the string
+        let mut terminator = "END".to_owned();
+        while s.contains(&terminator[..]) {
+            terminator.push('_');
+        }
+        write!(f, "@({}){}{}", terminator, s, terminator)
+    }
+}
+
 /// Formatting helper to format a float according to DM's rules.
 pub struct FormatFloat(pub f32);
 
@@ -980,6 +1005,7 @@ impl<'ctx> Lexer<'ctx> {
                 // TODO: this is a hack to fix the '""}' situation
                 buf.extend_from_slice(&end[..idx]);
                 idx = 1;
+                continue;
             } else {
                 buf.extend_from_slice(&end[..idx]);
                 idx = 0;
@@ -1023,11 +1049,41 @@ impl<'ctx> Lexer<'ctx> {
         }
     }
 
-    fn read_raw_string_inner(&mut self, terminator: &[u8]) -> Token {
+    /// Read the remainder of a `#warn` or `#error` line verbatim.
+    fn read_directive_text(&mut self) -> Token {
+        let mut buf = Vec::new();
+        loop {
+            match self.next() {
+                None => break,
+                Some(b'\n') => {
+                    // a trailing backslash continues the message on the next line
+                    if buf.last() == Some(&b'\r') {
+                        buf.pop();
+                    }
+                    if buf.last() == Some(&b'\\') {
+                        buf.pop();
+                        continue;
+                    }
+                    break;
+                }
+                Some(ch) => buf.push(ch),
+            }
+        }
+        Token::String(from_utf8_or_latin1(buf))
+    }
+
+    fn read_raw_string_inner(&mut self, terminator: &[u8], multiline: bool) -> Token {
         let start_loc = self.location();
         let mut buf = Vec::new();
         loop {
             match self.next() {
+                Some(b'\n') if !multiline => {
+                    // leave the newline to end the statement
+                    self.put_back(Some(b'\n'));
+                    DMError::new(start_loc, "unterminated raw string")
+                        .register(self.context);
+                    break;
+                }
                 Some(ch) => buf.push(ch),
                 None => {
                     DMError::new(start_loc, "unterminated raw string")
@@ -1041,7 +1097,7 @@ impl<'ctx> Lexer<'ctx> {
                 break;
             }
         }
-        Token::String(from_utf8_or_latin1(buf))
+        Token::RawString(from_utf8_or_latin1(buf))
     }
 
     fn read_raw_string(&mut self) -> Token {
@@ -1051,9 +1107,9 @@ impl<'ctx> Lexer<'ctx> {
             Some(b'\n') |
             None => {
                 self.error("unterminated raw string").register(self.context);
-                Token::String(String::new())
+                Token::RawString(String::new())
             },
-            // @(<terminator string>)<string><terminator string> - no LF in contents
+            // @(<terminator string>)<string><terminator string> - LF allowed in contents
             Some(b'(') => {
                 // build terminator until ), then read until that terminator
                 let mut terminator = Vec::new();
@@ -1063,27 +1119,27 @@ impl<'ctx> Lexer<'ctx> {
                         Some(ch) => terminator.push(ch),
                         None => {
                             self.error("unterminated raw string terminator").register(self.context);
-                            return Token::String(String::new())
+                            return Token::RawString(String::new())
                         }
                     }
                 }
                 if terminator.is_empty() {
                     self.error("empty raw string terminator").register(self.context);
-                    return Token::String(String::new())
+                    return Token::RawString(String::new())
                 }
-                self.read_raw_string_inner(&terminator)
+                self.read_raw_string_inner(&terminator, true)
             },
             Some(b'{') => match self.next() {
                 // @{"<string>"} - LF allowed in contents
-                Some(b'"') => self.read_raw_string_inner(b"\"}"),
-                // @{<not ">{ -
+                Some(b'"') => self.read_raw_string_inner(b"\"}", true),
+                // @{<not ">{ - no LF in contents
                 other => {
                     self.put_back(other);
-                    self.read_raw_string_inner(b"{")
+                    self.read_raw_string_inner(b"{", false)
                 }
             },
-            // @<terminator char><string><terminator char>
-            Some(terminator) => self.read_raw_string_inner(&[terminator]),
+            // @<terminator char><string><terminator char> - no LF in contents
+            Some(terminator) => self.read_raw_string_inner(&[terminator], false),
         }
     }
 
@@ -1180,7 +1236,7 @@ impl<'ctx> Iterator for Lexer<'ctx> {
             if self.directive == Directive::Stringy {
                 self.directive = Directive::None;
                 self.put_back(Some(first));
-                return Some(locate(self.read_string(b"\n", false)));
+                return Some(locate(self.read_directive_text()));
             }
 
             let mut punct = self.read_punct(first);
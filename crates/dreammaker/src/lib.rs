@@ -11,6 +11,7 @@ extern crate ordered_float;
 extern crate serde;
 extern crate serde_derive;
 extern crate toml;
+extern crate bincode;
 
 use std::path::Path;
 use std::borrow::Cow;
@@ -36,11 +37,14 @@ pub mod indents;
 pub mod parser;
 pub mod annotation;
 pub mod ast;
+pub mod printer;
 pub mod objtree;
 mod builtins;
 pub mod constants;
 pub mod dmi;
 pub mod config;
+pub mod cache;
+pub mod resource;
 
 impl Context {
     /// Run the parsing suite on a given `.dme` file, producing an object tree.
@@ -5,8 +5,9 @@ use std::fmt;
 
 use indexmap::IndexMap;
 use ahash::RandomState;
+use serde::{Serialize, Deserialize};
 
-use super::ast::{Expression, VarType, VarTypeBuilder, VarSuffix, PathOp, Parameter, Block, ProcDeclKind, Ident};
+use super::ast::{Expression, VarType, VarTypeBuilder, VarSuffix, PathOp, Parameter, Block, ProcDeclKind, Ident, list_initializer};
 use super::constants::Constant;
 use super::docs::DocCollection;
 use super::{DMError, Location, Context, Severity};
@@ -15,7 +16,7 @@ use super::{DMError, Location, Context, Severity};
 // Symbol IDs
 
 /// An identifier referring to a symbol in the object tree.
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
 pub struct SymbolId(u32);
 
 #[derive(Debug)]
@@ -47,14 +48,17 @@ impl SymbolIdSource {
 
 pub type Vars = IndexMap<String, Constant, RandomState>;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VarDeclaration {
     pub var_type: VarType,
     pub location: Location,
     pub id: SymbolId,
+    /// The sizes of a list declaration such as `var/L[10]`, which are
+    /// evaluated when an instance is created.
+    pub dimensions: Vec<Option<Expression>>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VarValue {
     pub location: Location,
     /// Syntactic value, as specified in the source.
@@ -65,13 +69,13 @@ pub struct VarValue {
     pub docs: DocCollection,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TypeVar {
     pub value: VarValue,
     pub declaration: Option<VarDeclaration>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProcDeclaration {
     pub location: Location,
     pub kind: ProcDeclKind,
@@ -80,7 +84,7 @@ pub struct ProcDeclaration {
     pub is_protected: bool,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProcValue {
     pub location: Location,
     pub parameters: Box<[Parameter]>,
@@ -88,7 +92,7 @@ pub struct ProcValue {
     pub code: Option<Block>,
 }
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct TypeProc {
     pub value: Vec<ProcValue>,
     pub declaration: Option<ProcDeclaration>,
@@ -104,7 +108,7 @@ impl TypeProc {
 // ----------------------------------------------------------------------------
 // Types
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Type {
     pub path: String,
     path_last_slash: usize,
@@ -520,7 +524,7 @@ impl<'a> ProcRef<'a> {
         self.ty
     }
 
-    pub fn name(&self) -> &str {
+    pub fn name(self) -> &'a str {
         self.name
     }
 
@@ -715,6 +719,21 @@ impl ObjectTree {
         }
     }
 
+    /// Every type in the tree, in index order.
+    pub(crate) fn graph(&self) -> &[Type] {
+        &self.graph
+    }
+
+    /// Rebuild a tree from its types, as returned by `graph`.
+    pub(crate) fn from_graph(graph: Vec<Type>) -> ObjectTree {
+        let types = graph.iter()
+            .enumerate()
+            .skip(1)
+            .map(|(i, ty)| (ty.path.clone(), NodeIndex::new(i)))
+            .collect();
+        ObjectTree { graph, types }
+    }
+
     /// Drop all code ASTs to attempt to reduce memory usage.
     pub fn drop_code(&mut self) {
         for node in self.graph.iter_mut() {
@@ -967,27 +986,25 @@ impl ObjectTreeBuilder {
         }
     }
 
+    pub(crate) fn allocate_symbol(&mut self) -> SymbolId {
+        self.symbols.allocate()
+    }
+
     pub(crate) fn declare_var(
         &mut self,
         ty: NodeIndex,
         name: &str,
-        location: Location,
         docs: DocCollection,
-        var_type: VarType,
         expression: Option<Expression>,
+        declaration: VarDeclaration,
     ) -> &mut TypeVar {
-        let id = self.symbols.allocate();
         self.insert_var(ty, name, VarValue {
-            location,
+            location: declaration.location,
             expression,
             docs,
             constant: None,
             being_evaluated: false,
-        }, Some(VarDeclaration {
-            var_type,
-            location,
-            id,
-        }))
+        }, Some(declaration))
     }
 
     pub(crate) fn override_var(
@@ -1083,7 +1100,7 @@ impl ObjectTreeBuilder {
         Ok(Some(node.vars.entry(prev.to_owned()).or_insert_with(|| TypeVar {
             value: VarValue {
                 location,
-                expression: suffix.into_initializer(),
+                expression: list_initializer(&suffix.list),
                 constant: None,
                 being_evaluated: false,
                 docs: comment,
@@ -1093,6 +1110,7 @@ impl ObjectTreeBuilder {
                     var_type: var_type.build(),
                     location,
                     id: symbols.allocate(),
+                    dimensions: suffix.list,
                 })
             } else {
                 None
@@ -1274,7 +1292,7 @@ fn is_decl(s: &str) -> bool {
 }
 
 /// Node identifier.
-#[derive(Copy, Clone, Default, PartialEq, PartialOrd, Eq, Ord, Hash, Debug)]
+#[derive(Copy, Clone, Default, PartialEq, PartialOrd, Eq, Ord, Hash, Debug, Serialize, Deserialize)]
 pub struct NodeIndex(u32);
 
 impl NodeIndex {
@@ -6,7 +6,7 @@ use std::ops::Range;
 
 use super::{DMError, Location, HasLocation, Context, Severity, FileId};
 use super::lexer::{LocatedToken, Token, Punctuation};
-use super::objtree::{ObjectTreeBuilder, ObjectTree, NodeIndex};
+use super::objtree::{ObjectTreeBuilder, ObjectTree, NodeIndex, VarDeclaration};
 use super::annotation::*;
 use super::ast::*;
 use super::docs::*;
@@ -70,6 +70,20 @@ where
     Ok(require!(parser.expression()))
 }
 
+/// Parse a token stream which must consist of exactly one expression.
+///
+/// Like `parse_expression`, but trailing tokens are a fatal error.
+pub fn parse_whole_expression<I>(context: &Context, location: Location, iter: I) -> Result<Expression, DMError>
+where
+    I: IntoIterator<Item=LocatedToken>,
+{
+    let mut parser = Parser::new(context, iter);
+    parser.location = location;
+    let expr = require!(parser.expression());
+    require!(parser.exact(Token::Eof));
+    Ok(expr)
+}
+
 // ----------------------------------------------------------------------------
 // Operator precedence table
 
@@ -200,7 +214,7 @@ oper_table! { BINARY_OPS;
     Conditional {
         (TernaryOp, Conditional = QuestionMark),
     }
-    // = += -= -= *= /= %= &= |= ^= <<= >>=
+    // = += -= -= *= /= %= &= |= ^= <<= >>= := &&= ||= ?=
     Assign {
         (AssignOp, Assign),
         (AssignOp, AddAssign),
@@ -216,6 +230,7 @@ oper_table! { BINARY_OPS;
         (AssignOp, AssignInto),
         (AssignOp, AndAssign),
         (AssignOp, OrAssign),
+        (AssignOp, NullAssign),
     }
     // "in" is special and has different precedence in different contexts
     In {
@@ -274,6 +289,21 @@ enum LoopContext {
     DoWhile,
 }
 
+/// Where a token starting at `location` ends, assuming it is written as it
+/// prints.
+fn token_end(mut location: Location, token: &Token) -> Location {
+    let len = match token {
+        Token::Punct(Punctuation::Newline) |
+        Token::Eof |
+        Token::DocComment(_) => 0,
+        Token::Punct(punct) => punct.single_quoted().len() - 2,
+        Token::Ident(ident, _) => ident.len(),
+        other => other.to_string().chars().count(),
+    };
+    location.column = location.column.saturating_add(len as u16);
+    location
+}
+
 /// A single-lookahead, recursive-descent DM parser.
 ///
 /// Results are accumulated into an inner `ObjectTree`. To parse an entire
@@ -289,6 +319,10 @@ pub struct Parser<'ctx, 'an, 'inp> {
     possible_indentation_error: bool,
     next: Option<Token>,
     location: Location,
+    /// Where the last token taken ends, and the one before it, for when a
+    /// token is put back.
+    token_end: Location,
+    prev_token_end: Location,
     expected: Vec<Cow<'static, str>>,
 
     docs_following: DocCollection,
@@ -321,6 +355,8 @@ impl<'ctx, 'an, 'inp> Parser<'ctx, 'an, 'inp> {
             possible_indentation_error: false,
             next: None,
             location: Default::default(),
+            token_end: Default::default(),
+            prev_token_end: Default::default(),
             expected: Vec::new(),
 
             docs_following: Default::default(),
@@ -474,6 +510,9 @@ impl<'ctx, 'an, 'inp> Parser<'ctx, 'an, 'inp> {
                 }
             }
         };
+        if let Ok(ref token) = tok {
+            self.prev_token_end = std::mem::replace(&mut self.token_end, token_end(self.location, token));
+        }
         let what = expected.into();
         if !what.is_empty() && !self.expected.contains(&what) {
             self.expected.push(what);
@@ -486,6 +525,7 @@ impl<'ctx, 'an, 'inp> Parser<'ctx, 'an, 'inp> {
             panic!("cannot put_back twice")
         }
         self.next = Some(tok);
+        self.token_end = self.prev_token_end;
     }
 
     fn updated_location(&mut self) -> Location {
@@ -792,6 +832,9 @@ impl<'ctx, 'an, 'inp> Parser<'ctx, 'an, 'inp> {
 
                 let node = self.tree.get_path(current).to_owned();
                 self.annotate(start, || Annotation::TreeBlock(reconstruct_path(&node, proc_kind, var_type.as_ref(), "")));
+                if proc_kind.is_none() && var_type.is_none() {
+                    self.annotate(entry_start, || type_symbol(&node));
+                }
                 SUCCESS
             }
             Punct(Assign) => {
@@ -809,6 +852,7 @@ impl<'ctx, 'an, 'inp> Parser<'ctx, 'an, 'inp> {
                     // will otherwise consume following whitespace resulting in a bad annotation range
                     let node = this.tree.get_path(current).to_owned();
                     this.annotate(entry_start, || Annotation::Variable(reconstruct_path(&node, proc_kind, var_type.as_ref(), last_part)));
+                    this.annotate(entry_start, || Annotation::Symbol(SymbolKind::Var, node.clone(), last_part.clone()));
 
                     require!(this.statement_terminator());
                     success(expr)
@@ -816,7 +860,13 @@ impl<'ctx, 'an, 'inp> Parser<'ctx, 'an, 'inp> {
 
                 if let Some(mut var_type) = var_type {
                     var_type.suffix(&var_suffix);
-                    self.tree.declare_var(current, last_part, location, docs, var_type.build(), Some(expression));
+                    let declaration = VarDeclaration {
+                        var_type: var_type.build(),
+                        location,
+                        id: self.tree.allocate_symbol(),
+                        dimensions: var_suffix.list,
+                    };
+                    self.tree.declare_var(current, last_part, docs, Some(expression), declaration);
                 } else {
                     self.tree.override_var(current, last_part, location, docs, expression);
                 }
@@ -848,7 +898,15 @@ impl<'ctx, 'an, 'inp> Parser<'ctx, 'an, 'inp> {
                         var_type.suffix(&var_suffix);
                         let node = self.tree.get_path(current).to_owned();
                         self.annotate(entry_start, || Annotation::Variable(reconstruct_path(&node, proc_kind, Some(&var_type), last_part)));
-                        self.tree.declare_var(current, last_part, self.location, docs, var_type.build(), var_suffix.into_initializer());
+                        self.annotate(entry_start, || Annotation::Symbol(SymbolKind::Var, node.clone(), last_part.clone()));
+                        let declaration = VarDeclaration {
+                            var_type: var_type.build(),
+                            location: self.location,
+                            id: self.tree.allocate_symbol(),
+                            dimensions: var_suffix.list,
+                        };
+                        let expression = list_initializer(&declaration.dimensions);
+                        self.tree.declare_var(current, last_part, docs, expression, declaration);
                     }
                 } else if ProcDeclKind::from_name(last_part).is_some() {
                     self.error("`proc;` item has no effect")
@@ -863,6 +921,8 @@ impl<'ctx, 'an, 'inp> Parser<'ctx, 'an, 'inp> {
                     let len = self.tree.get_path(current).chars().filter(|&c| c == '/').count() + path_len;
                     current = self.tree.subtype_or_add(self.location, current, last_part, len);
                     self.tree.extend_docs(current, docs);
+                    let node = self.tree.get_path(current).to_owned();
+                    self.annotate(entry_start, || type_symbol(&node));
                 }
 
                 SUCCESS
@@ -1012,6 +1072,8 @@ impl<'ctx, 'an, 'inp> Parser<'ctx, 'an, 'inp> {
                     let new_stack = reconstruct_path(&self.tree.get_path(current), proc_kind, None, name);
                     dest.insert(entry_start..body_start, Annotation::ProcHeader(new_stack.to_vec(), idx));
                     dest.insert(body_start..self.location, Annotation::ProcBody(new_stack.to_vec(), idx));
+                    let kind = if proc_kind == Some(ProcDeclKind::Verb) { SymbolKind::Verb } else { SymbolKind::Proc };
+                    dest.insert(entry_start..self.location, Annotation::Symbol(kind, self.tree.get_path(current).to_owned(), name.to_owned()));
                 }
                 if !absolute && self.context.config().code_standards.disallow_relative_proc_definitions {
                     DMError::new(location, "relatively pathed proc defined here")
@@ -1374,13 +1436,14 @@ impl<'ctx, 'an, 'inp> Parser<'ctx, 'an, 'inp> {
             require!(self.exact(Token::Punct(Punctuation::LBrace)));
             let mut cases = Vec::new();
             while let Some(()) = self.exact_ident("if")? {
+                let case_location = self.location();
                 require!(self.exact(Token::Punct(Punctuation::LParen)));
                 let what = require!(self.separated(Punctuation::Comma, Punctuation::RParen, None, Parser::case));
                 if what.is_empty() {
                     self.context.register_error(self.error("switch case cannot be empty"));
                 }
                 let block = require!(self.block(loop_ctx));
-                cases.push((Spanned::new(self.location(), what), block));
+                cases.push((Spanned::new(case_location, what), block));
             }
             let default = if let Some(()) = self.exact_ident("else")? {
                 Some(require!(self.block(loop_ctx)))
@@ -1537,7 +1600,7 @@ impl<'ctx, 'an, 'inp> Parser<'ctx, 'an, 'inp> {
                 let value = if let Some(()) = self.exact(Token::Punct(Punctuation::Assign))? {
                     Some(require!(self.expression()))
                 } else {
-                    var_suffix.into_initializer()
+                    list_initializer(&var_suffix.list)
                 };
                 let (input_types, in_list) = if !in_for {
                     require!(self.input_specifier())
@@ -1551,7 +1614,7 @@ impl<'ctx, 'an, 'inp> Parser<'ctx, 'an, 'inp> {
                         .register(self.context);
                 }
 
-                var_stmts.push(VarStatement { var_type: var_type.build(), name, value });
+                var_stmts.push(VarStatement { var_type: var_type.build(), name, value, dimensions: var_suffix.list });
                 if in_for || self.exact(Token::Punct(Punctuation::Comma))?.is_none() {
                     break;
                 }
@@ -1849,11 +1912,11 @@ impl<'ctx, 'an, 'inp> Parser<'ctx, 'an, 'inp> {
         let mut unary_ops = Vec::new();
         loop {
             match self.next("operator")? {
-                Token::Punct(Punctuation::Sub) => unary_ops.push(Spanned::new(self.location, Follow::Unary(UnaryOp::Neg))),
-                Token::Punct(Punctuation::Not) => unary_ops.push(Spanned::new(self.location, Follow::Unary(UnaryOp::Not))),
-                Token::Punct(Punctuation::BitNot) => unary_ops.push(Spanned::new(self.location, Follow::Unary(UnaryOp::BitNot))),
-                Token::Punct(Punctuation::PlusPlus) => unary_ops.push(Spanned::new(self.location, Follow::Unary(UnaryOp::PreIncr))),
-                Token::Punct(Punctuation::MinusMinus) => unary_ops.push(Spanned::new(self.location, Follow::Unary(UnaryOp::PreDecr))),
+                Token::Punct(Punctuation::Sub) => unary_ops.push(Spanned::with_end(self.location, self.token_end, Follow::Unary(UnaryOp::Neg))),
+                Token::Punct(Punctuation::Not) => unary_ops.push(Spanned::with_end(self.location, self.token_end, Follow::Unary(UnaryOp::Not))),
+                Token::Punct(Punctuation::BitNot) => unary_ops.push(Spanned::with_end(self.location, self.token_end, Follow::Unary(UnaryOp::BitNot))),
+                Token::Punct(Punctuation::PlusPlus) => unary_ops.push(Spanned::with_end(self.location, self.token_end, Follow::Unary(UnaryOp::PreIncr))),
+                Token::Punct(Punctuation::MinusMinus) => unary_ops.push(Spanned::with_end(self.location, self.token_end, Follow::Unary(UnaryOp::PreDecr))),
                 other => {
                     self.put_back(other);
                     break;
@@ -1872,8 +1935,8 @@ impl<'ctx, 'an, 'inp> Parser<'ctx, 'an, 'inp> {
         let mut follow = Vec::new();
         loop {
             match self.next("operator")? {
-                Token::Punct(Punctuation::PlusPlus) => follow.push(Spanned::new(self.location, Follow::Unary(UnaryOp::PostIncr))),
-                Token::Punct(Punctuation::MinusMinus) => follow.push(Spanned::new(self.location, Follow::Unary(UnaryOp::PostDecr))),
+                Token::Punct(Punctuation::PlusPlus) => follow.push(Spanned::with_end(self.location, self.token_end, Follow::Unary(UnaryOp::PostIncr))),
+                Token::Punct(Punctuation::MinusMinus) => follow.push(Spanned::with_end(self.location, self.token_end, Follow::Unary(UnaryOp::PostDecr))),
                 other => {
                     self.put_back(other);
                     match self.follow(&mut belongs_to, in_ternary)? {
@@ -2063,7 +2126,7 @@ impl<'ctx, 'an, 'inp> Parser<'ctx, 'an, 'inp> {
                 if let Some(ident) = self.ident()? {
                     // prefab
                     // TODO: arrange for this ident to end up in the prefab's annotation
-                    Term::Prefab(require!(self.prefab_ex(vec![(PathOp::Dot, ident)])))
+                    prefab_term(require!(self.prefab_ex(vec![(PathOp::Dot, ident)])))
                 } else if let Some(args) = self.arguments(&[], ".")? {
                     // .() call
                     Term::SelfCall(args)
@@ -2081,11 +2144,12 @@ impl<'ctx, 'an, 'inp> Parser<'ctx, 'an, 'inp> {
             t @ Token::Punct(Punctuation::Slash) |
             t @ Token::Punct(Punctuation::CloseColon) => {
                 self.put_back(t);
-                Term::Prefab(require!(self.prefab()))
+                prefab_term(require!(self.prefab()))
             },
 
             // term :: str_lit | num_lit
             Token::String(val) => Term::String(val),
+            Token::RawString(val) => Term::RawString(val),
             Token::Resource(val) => {
                 self.annotate_precise(start..start.add_columns(2 + val.len() as u16), || Annotation::Resource(val.as_str().into()));
                 Term::Resource(val)
@@ -2127,7 +2191,7 @@ impl<'ctx, 'an, 'inp> Parser<'ctx, 'an, 'inp> {
 
             other => return self.try_another(other),
         };
-        success(Spanned::new(start, term))
+        success(Spanned::with_end(start, self.token_end, term))
     }
 
     fn list_access(&mut self, belongs_to: &mut Vec<Ident>) -> Status<Spanned<Follow>> {
@@ -2143,7 +2207,7 @@ impl<'ctx, 'an, 'inp> Parser<'ctx, 'an, 'inp> {
         belongs_to.clear();
         let expr = require!(self.expression());
         require!(self.exact(Token::Punct(Punctuation::RBracket)));
-        success(Spanned::new(first_location, Follow::Index(kind, Box::new(expr))))
+        success(Spanned::with_end(first_location, self.token_end, Follow::Index(kind, Box::new(expr))))
     }
 
     fn follow(&mut self, belongs_to: &mut Vec<Ident>, in_ternary: bool) -> Status<Spanned<Follow>> {
@@ -2196,7 +2260,7 @@ impl<'ctx, 'an, 'inp> Parser<'ctx, 'an, 'inp> {
                 Follow::Field(kind, ident.into())
             },
         };
-        success(Spanned::new(first_location, follow))
+        success(Spanned::with_end(first_location, self.token_end, follow))
     }
 
     // TODO: somehow fix the fact that this is basically copy-pasted from
@@ -2356,3 +2420,19 @@ fn reconstruct_path(node: &str, proc_kind: Option<ProcDeclKind>, var_type: Optio
     }
     result
 }
+
+/// The symbol annotation for a type, split into its parent path and name.
+fn type_symbol(path: &str) -> Annotation {
+    let (parent, name) = path.rsplit_once('/').unwrap_or(("", path));
+    Annotation::Symbol(SymbolKind::Type, parent.to_owned(), name.to_owned())
+}
+
+/// Proc references like `.proc/name` are parsed as prefabs first.
+fn prefab_term(prefab: Box<Prefab>) -> Term {
+    if prefab.vars.is_empty() {
+        if let Some(reference) = ProcReference::from_path(&prefab.path) {
+            return Term::ProcReference(Box::new(reference));
+        }
+    }
+    Term::Prefab(prefab)
+}
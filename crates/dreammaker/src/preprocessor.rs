@@ -1,5 +1,5 @@
 //! The preprocessor.
-use std::collections::{HashMap, VecDeque};
+use std::collections::{BTreeMap, HashMap, VecDeque};
 use std::{io, fmt};
 use std::fs::File;
 use std::path::{Path, PathBuf};
@@ -118,6 +118,7 @@ impl DefineHistory {
             docs_out: Default::default(),
             in_interp_string: 0,
             annotations: None,
+            define_usage: None,
         }
     }
 
@@ -129,7 +130,7 @@ impl DefineHistory {
             include_stack: Default::default(),
             include_locations: Default::default(),
             history: Default::default(),  // TODO: support branching a second time
-            defines: DefineMap::from_history(self, self.last_input_loc),
+            defines: self.defines_at_end(),
             maps: Default::default(),
             skins: Default::default(),
             scripts: Default::default(),
@@ -143,8 +144,14 @@ impl DefineHistory {
             docs_out: Default::default(),
             in_interp_string: 0,
             annotations: None,
+            define_usage: None,
         }
     }
+
+    /// Collect the macros which are defined at the end of the environment.
+    pub fn defines_at_end(&self) -> DefineMap {
+        DefineMap::from_history(self, self.last_input_loc)
+    }
 }
 
 impl std::ops::Deref for DefineHistory {
@@ -161,6 +168,96 @@ impl std::ops::DerefMut for DefineHistory {
     }
 }
 
+/// One definition of a macro, along with everywhere it was expanded.
+#[derive(Debug, Clone)]
+pub struct DefineUsage {
+    pub name: String,
+    /// Where the definition was made.
+    pub location: Location,
+    pub define: Define,
+    /// Where the definition stopped applying because of an `#undef` or a
+    /// redefinition, if it did.
+    pub ended: Option<Location>,
+    /// Every expansion of this definition. Expansions inside other macros
+    /// are attributed to where the outermost macro was used.
+    pub uses: Vec<Location>,
+}
+
+impl DefineUsage {
+    /// The text of the macro's substitution.
+    pub fn body(&self) -> String {
+        let mut body = String::new();
+        let _ = crate::pretty_print(&mut body, self.define.substitution().iter().cloned(), false);
+        body.trim_end().to_owned()
+    }
+}
+
+/// Every macro definition seen during preprocessing, in the order they were
+/// made, with the places each was used.
+///
+/// Unlike a `DefineMap`, this keeps every definition a name has had over the
+/// course of the environment rather than only the active ones.
+#[derive(Debug, Clone, Default)]
+pub struct DefineUsageMap {
+    definitions: Vec<DefineUsage>,
+    by_name: HashMap<String, Vec<usize>, RandomState>,
+    by_use: BTreeMap<Location, usize>,
+}
+
+impl DefineUsageMap {
+    /// Iterate over every definition in the order they were made.
+    pub fn iter(&self) -> impl Iterator<Item=&DefineUsage> {
+        self.definitions.iter()
+    }
+
+    /// Iterate over every definition a name has had, in order.
+    pub fn get<'a>(&'a self, name: &str) -> impl Iterator<Item=&'a DefineUsage> + 'a {
+        self.by_name.get(name)
+            .into_iter()
+            .flat_map(move |indices| indices.iter().map(move |&i| &self.definitions[i]))
+    }
+
+    /// Find the definition which was expanded at the given location, ignoring
+    /// expansions inside other macros.
+    pub fn definition_used_at(&self, location: Location) -> Option<&DefineUsage> {
+        self.by_use.get(&location).map(|&i| &self.definitions[i])
+    }
+
+    fn find(&self, name: &str, location: Location) -> Option<usize> {
+        self.by_name.get(name)?.iter().rev().cloned().find(|&i| self.definitions[i].location == location)
+    }
+
+    fn define(&mut self, name: &str, location: Location, define: &Define) -> usize {
+        if let Some(i) = self.find(name, location) {
+            return i;
+        }
+        let i = self.definitions.len();
+        self.definitions.push(DefineUsage {
+            name: name.to_owned(),
+            location,
+            define: define.clone(),
+            ended: None,
+            uses: Vec::new(),
+        });
+        self.by_name.entry(name.to_owned()).or_default().push(i);
+        i
+    }
+
+    fn end(&mut self, name: &str, location: Location, at: Location) {
+        if let Some(i) = self.find(name, location) {
+            self.definitions[i].ended.get_or_insert(at);
+        }
+    }
+
+    fn expand(&mut self, name: &str, location: Location, define: &Define, at: Location, nested: bool) {
+        let i = self.define(name, location, define);
+        self.definitions[i].uses.push(at);
+        if !nested {
+            self.by_use.insert(at, i);
+        }
+    }
+}
+
 /// A map from macro names to their locations and definitions.
 ///
 /// Redefinitions of macros push to a stack, and undefining the macro returns
@@ -200,6 +297,12 @@ impl DefineMap {
         self.inner.get(key).and_then(|v| v.last())
     }
 
+    /// Iterate over the current definition of every macro, in no particular
+    /// order.
+    pub fn iter(&self) -> impl Iterator<Item=(&str, &(Location, Define))> {
+        self.inner.iter().filter_map(|(k, v)| v.last().map(|last| (k.as_str(), last)))
+    }
+
     /// Inserts a key-value pair into the map.
     ///
     /// Returns `None` if the key was not present, or its most recent location
@@ -256,6 +359,23 @@ impl DefineMap {
     */
 }
 
+/// Interpret the substitution of a `FILE_DIR` define as a directory path.
+///
+/// It may be either a quoted string or bare path tokens like `icons/obj`.
+fn file_dir_path(subst: &[Token]) -> Option<PathBuf> {
+    match subst {
+        [] => None,
+        [Token::String(path)] | [Token::RawString(path)] => Some(PathBuf::from(path.replace("\\", "/"))),
+        tokens => {
+            let mut path = String::new();
+            for token in tokens {
+                path.push_str(&token.to_string());
+            }
+            Some(PathBuf::from(path.replace("\\", "/")))
+        }
+    }
+}
+
 // ----------------------------------------------------------------------------
 // The stack of currently #included files
 
@@ -386,6 +506,7 @@ pub struct Preprocessor<'ctx> {
     ifdef_stack: Vec<Ifdef>,
     ifdef_history: IntervalTree<Location, bool>,
     annotations: Option<AnnotationTree>,
+    define_usage: Option<DefineUsageMap>,
 
     history: InnerDefineHistory,
     defines: DefineMap,
@@ -417,6 +538,7 @@ impl<'ctx> Preprocessor<'ctx> {
         // Buffer the entire environment file. Large environments take a while
         // to load and locking it for the whole time is somewhat inconvenient.
         let include = Include::from_path(context, env_file.clone())?;
+        context.resources_mut().set_root(env_file.parent().unwrap_or_else(|| Path::new("")));
 
         Ok(Preprocessor {
             context,
@@ -438,6 +560,7 @@ impl<'ctx> Preprocessor<'ctx> {
             docs_out: Default::default(),
             in_interp_string: 0,
             annotations: None,
+            define_usage: None,
         })
     }
 
@@ -447,6 +570,7 @@ impl<'ctx> Preprocessor<'ctx> {
             Cow::Owned(s) => Cow::Owned(s.into_bytes()),
         };
         let include = Include::from_buffer(context, env_file.clone(), cow_u8);
+        context.resources_mut().set_root(env_file.parent().unwrap_or_else(|| Path::new("")));
         Preprocessor {
             context,
             env_file,
@@ -467,6 +591,7 @@ impl<'ctx> Preprocessor<'ctx> {
             docs_out: Default::default(),
             in_interp_string: 0,
             annotations: None,
+            define_usage: None,
         }
     }
 
@@ -508,6 +633,11 @@ impl<'ctx> Preprocessor<'ctx> {
     }
     */
 
+    /// Replace the set of macros currently defined.
+    pub fn set_defines(&mut self, defines: DefineMap) {
+        self.defines = defines;
+    }
+
     /// Push a DM file to the top of this preprocessor's stack.
     pub fn push_file<R: io::Read + 'static>(&mut self, path: PathBuf, read: R) -> Result<FileId, DMError> {
         let idx = self.context.register_file(&path);
@@ -529,6 +659,16 @@ impl<'ctx> Preprocessor<'ctx> {
         self.annotations.take()
     }
 
+    /// Enable recording of every macro definition and expansion.
+    pub fn enable_define_usage(&mut self) {
+        self.define_usage = Some(DefineUsageMap::default());
+    }
+
+    /// Retrieve the recorded macro definitions and expansions.
+    pub fn take_define_usage(&mut self) -> Option<DefineUsageMap> {
+        self.define_usage.take()
+    }
+
     // ------------------------------------------------------------------------
     // Macro definition handling
 
@@ -544,6 +684,14 @@ impl<'ctx> Preprocessor<'ctx> {
         }
     }
 
+    fn record_expansion(&mut self, ident: &str, def_loc: Location) {
+        let at = self.last_input_loc;
+        let nested = self.include_stack.in_expansion();
+        if let (Some(usage), Some((_, define))) = (self.define_usage.as_mut(), self.defines.get(ident)) {
+            usage.expand(ident, def_loc, define, at, nested);
+        }
+    }
+
     fn in_environment(&self) -> bool {
         for include in self.include_stack.stack.iter().rev() {
             if let Include::File { ref path, .. } = *include {
@@ -685,10 +833,10 @@ impl<'ctx> Preprocessor<'ctx> {
             };
         }
         macro_rules! expect_token {
-            (($($i:ident),*) = $p:pat) => {
+            (($($i:ident),*) = $($p:pat)|+) => {
                 let ($($i,)*) = match next!() {
-                    $p => ($($i,)*),
-                    other => return Err(self.error(format!("unexpected token {:?}, expecting {}", other, stringify!($p))))
+                    $($p)|+ => ($($i,)*),
+                    other => return Err(self.error(format!("unexpected token {:?}, expecting {}", other, stringify!($($p)|+))))
                 };
             }
         }
@@ -741,7 +889,7 @@ impl<'ctx> Preprocessor<'ctx> {
                     // include searches relevant paths for files
                     "include" if disabled => {}
                     "include" => {
-                        expect_token!((path_str) = Token::String(path_str));
+                        expect_token!((path_str) = Token::String(path_str) | Token::RawString(path_str));
                         let include_loc = _last_expected_loc;
                         expect_token!(() = Token::Punct(Punctuation::Newline));
                         let path = PathBuf::from(path_str.replace("\\", "/"));
@@ -900,6 +1048,17 @@ impl<'ctx> Preprocessor<'ctx> {
                         };
                         // DEBUG can only be defined in the root .dme file
                         if define_name != "DEBUG" || self.in_environment() {
+                            if let Some(usage) = self.define_usage.as_mut() {
+                                if let Some((previous_loc, _)) = self.defines.get(&define_name) {
+                                    usage.end(&define_name, *previous_loc, define_name_loc);
+                                }
+                                usage.define(&define_name, define_name_loc, &define);
+                            }
+                            if define_name == "FILE_DIR" {
+                                if let Some(dir) = file_dir_path(define.substitution()) {
+                                    self.context.resources_mut().push_file_dir(dir);
+                                }
+                            }
                             if let Some(previous_loc) = self.defines.insert(define_name.clone(), (define_name_loc, define)) {
                                 // DM doesn't issue a warning for this, but it's usually a mistake, so let's.
                                 // FILE_DIR is handled specially and sometimes makes sense to define multiple times.
@@ -920,6 +1079,9 @@ impl<'ctx> Preprocessor<'ctx> {
                         self.check_danger_ident(&define_name, "undefined");
                         expect_token!(() = Token::Punct(Punctuation::Newline));
                         if let Some(previous) = self.defines.remove(&define_name) {
+                            if let Some(usage) = self.define_usage.as_mut() {
+                                usage.end(&define_name, previous.0, define_name_loc);
+                            }
                             self.move_to_history(define_name, previous);
                         } else {
                             DMError::new(define_name_loc, format!("macro undefined while not defined: {}", define_name))
@@ -1007,6 +1169,7 @@ impl<'ctx> Preprocessor<'ctx> {
                 match expansion {
                     Some((location, Define::Constant { subst, docs: _ })) => {
                         self.annotate_macro(ident, location);
+                        self.record_expansion(ident, location);
                         self.include_stack.stack.push(Include::Expansion {
                             //name: ident.to_owned(),
                             tokens: subst.into_iter().collect(),
@@ -1031,6 +1194,7 @@ impl<'ctx> Preprocessor<'ctx> {
                         }
 
                         self.annotate_macro(ident, location);
+                        self.record_expansion(ident, location);
 
                         // read arguments
                         let mut args = Vec::new();
@@ -0,0 +1,597 @@
+//! Rendering of AST nodes back into DM source code.
+//!
+//! The output parses back to an equivalent AST. Parentheses the source wrote
+//! explicitly are kept as `Term::Expr`, and more are inserted wherever the
+//! operator precedence of a constructed AST would otherwise be misread.
+//!
+//! Some details are not recorded in the AST and are lost in the process:
+//!
+//! * Comments, blank lines, and exact whitespace. Statements are written one
+//!   per line using the indentation in `PrintOptions`.
+//! * The spelling of literals: numbers are written in a canonical form, e.g.
+//!   `0x10` as `16`, and the choice between `"..."` and `{"..."}` strings is
+//!   recomputed. String contents are written as stored, escapes included.
+//! * Syntax the parser discards because it has no effect, e.g. the `as`
+//!   clause of a local `var`.
+use std::fmt::{self, Write};
+
+use crate::ast::*;
+use crate::lexer::{FormatFloat, RawQuote};
+
+/// Options controlling how statements are laid out.
+#[derive(Debug, Clone)]
+pub struct PrintOptions {
+    /// The text used for each level of indentation.
+    pub indent: String,
+    /// The indentation level of the outermost statements.
+    pub depth: usize,
+}
+
+impl Default for PrintOptions {
+    fn default() -> Self {
+        PrintOptions {
+            indent: "\t".to_owned(),
+            depth: 0,
+        }
+    }
+}
+
+/// Formatting helper which renders an expression as DM source.
+pub struct FormatExpr<'a>(pub &'a Expression);
+
+impl<'a> fmt::Display for FormatExpr<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_expr(f, self.0, false)
+    }
+}
+
+/// Formatting helper which renders a statement as DM source, followed by a
+/// newline.
+pub struct FormatStatement<'a>(pub &'a Statement, pub &'a PrintOptions);
+
+impl<'a> fmt::Display for FormatStatement<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        StatementPrinter { out: f, options: self.1 }.statement(self.0, self.1.depth)
+    }
+}
+
+/// Formatting helper which renders a block as DM source, one statement per
+/// line.
+pub struct FormatBlock<'a>(pub &'a [Spanned<Statement>], pub &'a PrintOptions);
+
+impl<'a> fmt::Display for FormatBlock<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        StatementPrinter { out: f, options: self.1 }.block(self.0, self.1.depth)
+    }
+}
+
+// ----------------------------------------------------------------------------
+// Expressions
+
+// Binding strengths, matching the parser's operator table: lower is tighter.
+const STRENGTH_CONDITIONAL: u8 = 12;
+const STRENGTH_ASSIGN: u8 = 13;
+const STRENGTH_IN: u8 = 14;
+
+fn binary_strength(op: BinaryOp) -> u8 {
+    use crate::ast::BinaryOp::*;
+    match op {
+        Pow => 1,
+        Mul | Div | Mod => 2,
+        Add | Sub => 3,
+        Less | Greater | LessEq | GreaterEq => 4,
+        LShift | RShift => 5,
+        Eq | NotEq | Equiv | NotEquiv => 6,
+        BitAnd => 7,
+        BitXor => 8,
+        BitOr => 9,
+        And => 10,
+        Or => 11,
+        In | To => STRENGTH_IN,
+    }
+}
+
+fn strength(expr: &Expression) -> u8 {
+    match expr {
+        Expression::Base { .. } => 0,
+        Expression::BinaryOp { op, .. } => binary_strength(*op),
+        Expression::TernaryOp { .. } => STRENGTH_CONDITIONAL,
+        Expression::AssignOp { .. } => STRENGTH_ASSIGN,
+    }
+}
+
+fn write_operand<W: Write>(f: &mut W, expr: &Expression, parens: bool, in_ternary: bool) -> fmt::Result {
+    if parens {
+        f.write_char('(')?;
+        write_expr(f, expr, false)?;
+        f.write_char(')')
+    } else {
+        write_expr(f, expr, in_ternary)
+    }
+}
+
+/// `in_ternary` is set within the arms of a conditional, where the parser
+/// reads a `:` as the start of the else arm rather than a field access.
+fn write_expr<W: Write>(f: &mut W, expr: &Expression, in_ternary: bool) -> fmt::Result {
+    match expr {
+        Expression::Base { term, follow } => write_base(f, &term.elem, follow, in_ternary),
+        Expression::BinaryOp { op: BinaryOp::In, lhs, rhs } => {
+            write_operand(f, lhs, false, in_ternary)?;
+            f.write_str(" in ")?;
+            // `a in b to c` can't be parenthesized as `a in (b to c)`.
+            let parens = match **rhs {
+                Expression::BinaryOp { op: BinaryOp::To, .. } => false,
+                ref other => strength(other) >= STRENGTH_IN,
+            };
+            write_operand(f, rhs, parens, in_ternary)
+        }
+        Expression::BinaryOp { op: BinaryOp::To, lhs, rhs } => {
+            write_operand(f, lhs, strength(lhs) >= STRENGTH_IN, in_ternary)?;
+            f.write_str(" to ")?;
+            write_operand(f, rhs, strength(rhs) >= STRENGTH_IN, in_ternary)
+        }
+        Expression::BinaryOp { op, lhs, rhs } => {
+            // Binary operators are left-associative.
+            let own = binary_strength(*op);
+            write_operand(f, lhs, strength(lhs) > own, in_ternary)?;
+            write!(f, " {} ", op)?;
+            write_operand(f, rhs, strength(rhs) >= own, in_ternary)
+        }
+        Expression::AssignOp { op, lhs, rhs } => {
+            // Assignment operators are right-associative.
+            write_operand(f, lhs, strength(lhs) >= STRENGTH_ASSIGN, in_ternary)?;
+            write!(f, " {} ", op)?;
+            write_operand(f, rhs, strength(rhs) > STRENGTH_ASSIGN, in_ternary)
+        }
+        Expression::TernaryOp { cond, if_, else_ } => {
+            write_operand(f, cond, strength(cond) >= STRENGTH_CONDITIONAL, in_ternary)?;
+            f.write_str(" ? ")?;
+            write_operand(f, if_, strength(if_) > STRENGTH_CONDITIONAL, true)?;
+            f.write_str(" : ")?;
+            write_operand(f, else_, strength(else_) > STRENGTH_CONDITIONAL, true)
+        }
+    }
+}
+
+fn is_prefix(op: UnaryOp) -> bool {
+    matches!(op, UnaryOp::Neg | UnaryOp::Not | UnaryOp::BitNot | UnaryOp::PreIncr | UnaryOp::PreDecr)
+}
+
+fn write_base<W: Write>(f: &mut W, term: &Term, follow: &[Spanned<Follow>], in_ternary: bool) -> fmt::Result {
+    let mut text = String::new();
+    write_term(&mut text, term)?;
+
+    // Negative literals already begin with a prefix operator.
+    let mut prefixed = match *term {
+        Term::Int(i) => i < 0,
+        Term::Float(f) => f.is_sign_negative(),
+        _ => false,
+    };
+    // Some terms would swallow a following `.field` into themselves.
+    let mut absorbs_field = match term {
+        Term::Int(_) | Term::Float(_) | Term::Prefab(_) | Term::ProcReference(_) => true,
+        Term::NewImplicit { args }
+        | Term::NewPrefab { args, .. }
+        | Term::NewMiniExpr { args, .. } => args.is_none(),
+        _ => false,
+    };
+    let mut has_colon = false;
+
+    // The parser stores prefix operators last, innermost first.
+    for each in follow {
+        match each.elem {
+            Follow::Unary(op) if is_prefix(op) => {
+                let name = op.name();
+                let first = text.chars().next();
+                // Keep `- -x` from lexing as `--x`.
+                let space = match first {
+                    Some(ch @ '-') | Some(ch @ '+') => name.ends_with(ch),
+                    _ => false,
+                };
+                text = format!("{}{}{}", name, if space { " " } else { "" }, text);
+                prefixed = true;
+            }
+            ref postfix => {
+                let is_field = matches!(postfix, Follow::Field(..) | Follow::Call(..));
+                if prefixed || (absorbs_field && is_field) {
+                    text = format!("({})", text);
+                    prefixed = false;
+                }
+                absorbs_field = false;
+                match postfix {
+                    Follow::Index(ListAccessKind::Normal, expr) => write!(text, "[{}]", FormatExpr(expr))?,
+                    Follow::Index(ListAccessKind::Safe, expr) => write!(text, "?[{}]", FormatExpr(expr))?,
+                    Follow::Field(kind, ident) => {
+                        has_colon |= *kind == PropertyAccessKind::Colon;
+                        write!(text, "{}{}", kind, ident)?;
+                    }
+                    Follow::Call(kind, ident, args) => {
+                        has_colon |= *kind == PropertyAccessKind::Colon;
+                        write!(text, "{}{}", kind, ident)?;
+                        write_args(&mut text, args)?;
+                    }
+                    Follow::Unary(op) => write!(text, "{}", op.name())?,
+                }
+            }
+        }
+    }
+
+    if in_ternary && has_colon {
+        write!(f, "({})", text)
+    } else {
+        f.write_str(&text)
+    }
+}
+
+fn write_args<W: Write>(f: &mut W, args: &[Expression]) -> fmt::Result {
+    f.write_char('(')?;
+    for (i, arg) in args.iter().enumerate() {
+        if i > 0 {
+            f.write_str(", ")?;
+        }
+        write_expr(f, arg, false)?;
+    }
+    f.write_char(')')
+}
+
+fn write_term<W: Write>(f: &mut W, term: &Term) -> fmt::Result {
+    match term {
+        Term::Null => f.write_str("null"),
+        Term::Int(i) => write!(f, "{}", i),
+        Term::Float(n) if n.is_finite() => write!(f, "{:?}", n),
+        Term::Float(n) => write!(f, "{}", FormatFloat(*n)),
+        Term::Ident(ident) => f.write_str(ident),
+        Term::String(text) => write_string(f, text, &[]),
+        Term::RawString(text) => write!(f, "{}", RawQuote(text)),
+        Term::Resource(path) => write!(f, "'{}'", path),
+        Term::As(input_type) if input_type.is_empty() => f.write_str("as()"),
+        Term::As(input_type) => write!(f, "as({})", input_type),
+        Term::Expr(expr) => write!(f, "({})", FormatExpr(expr)),
+        Term::Prefab(prefab) => write_prefab(f, prefab),
+        Term::ProcReference(reference) => write!(f, "{}", reference),
+        Term::InterpString(begin, parts) => write_string(f, begin, parts),
+        Term::Call(name, args) => {
+            f.write_str(name)?;
+            write_args(f, args)
+        }
+        Term::SelfCall(args) => {
+            f.write_char('.')?;
+            write_args(f, args)
+        }
+        Term::ParentCall(args) => {
+            f.write_str("..")?;
+            write_args(f, args)
+        }
+        Term::NewImplicit { args } => {
+            f.write_str("new")?;
+            write_new_args(f, args)
+        }
+        Term::NewPrefab { prefab, args } => {
+            f.write_str("new ")?;
+            write_prefab(f, prefab)?;
+            write_new_args(f, args)
+        }
+        Term::NewMiniExpr { expr, args } => {
+            write!(f, "new {}", expr.ident)?;
+            for field in expr.fields.iter() {
+                write!(f, "{}{}", field.kind, field.ident)?;
+            }
+            write_new_args(f, args)
+        }
+        Term::List(args) => {
+            f.write_str("list")?;
+            write_args(f, args)
+        }
+        Term::Input { args, input_type, in_list } => {
+            f.write_str("input")?;
+            write_args(f, args)?;
+            if let Some(input_type) = input_type {
+                write!(f, " as {}", input_type)?;
+            }
+            if let Some(in_list) = in_list {
+                write!(f, " in {}", FormatExpr(in_list))?;
+            }
+            Ok(())
+        }
+        Term::Locate { args, in_list } => {
+            f.write_str("locate")?;
+            write_args(f, args)?;
+            if let Some(in_list) = in_list {
+                write!(f, " in {}", FormatExpr(in_list))?;
+            }
+            Ok(())
+        }
+        Term::Pick(args) => {
+            f.write_str("pick(")?;
+            for (i, (weight, value)) in args.iter().enumerate() {
+                if i > 0 {
+                    f.write_str(", ")?;
+                }
+                if let Some(weight) = weight {
+                    write!(f, "{}; ", FormatExpr(weight))?;
+                }
+                write_expr(f, value, false)?;
+            }
+            f.write_char(')')
+        }
+        Term::DynamicCall(call_args, proc_args) => {
+            f.write_str("call")?;
+            write_args(f, call_args)?;
+            write_args(f, proc_args)
+        }
+    }
+}
+
+fn write_new_args<W: Write>(f: &mut W, args: &Option<Box<[Expression]>>) -> fmt::Result {
+    match args {
+        Some(args) => write_args(f, args),
+        None => Ok(()),
+    }
+}
+
+fn write_prefab<W: Write>(f: &mut W, prefab: &Prefab) -> fmt::Result {
+    write!(f, "{}", FormatTypePath(&prefab.path))?;
+    if !prefab.vars.is_empty() {
+        f.write_char('{')?;
+        for (i, (name, value)) in prefab.vars.iter().enumerate() {
+            if i > 0 {
+                f.write_str("; ")?;
+            }
+            write!(f, "{} = {}", name, FormatExpr(value))?;
+        }
+        f.write_char('}')?;
+    }
+    Ok(())
+}
+
+/// Whether stored string contents need the `{"..."}` form, because they
+/// contain a newline or an unescaped quote.
+fn needs_block_string(text: &str) -> bool {
+    let mut backslash = false;
+    for ch in text.chars() {
+        match ch {
+            '\n' => return true,
+            '"' if !backslash => return true,
+            _ => {}
+        }
+        backslash = ch == '\\' && !backslash;
+    }
+    false
+}
+
+fn write_string<W: Write>(f: &mut W, begin: &str, parts: &[(Option<Expression>, Box<str>)]) -> fmt::Result {
+    let block = needs_block_string(begin) || parts.iter().any(|(_, text)| needs_block_string(text));
+    f.write_str(if block { "{\"" } else { "\"" })?;
+    f.write_str(begin)?;
+    for (expr, text) in parts.iter() {
+        f.write_char('[')?;
+        if let Some(expr) = expr {
+            write_expr(f, expr, false)?;
+        }
+        f.write_char(']')?;
+        f.write_str(text)?;
+    }
+    f.write_str(if block { "\"}" } else { "\"" })
+}
+
+// ----------------------------------------------------------------------------
+// Statements
+
+struct StatementPrinter<'a, W> {
+    out: W,
+    options: &'a PrintOptions,
+}
+
+impl<'a, W: Write> StatementPrinter<'a, W> {
+    fn indent(&mut self, depth: usize) -> fmt::Result {
+        for _ in 0..depth {
+            self.out.write_str(&self.options.indent)?;
+        }
+        Ok(())
+    }
+
+    fn line(&mut self, depth: usize, text: fmt::Arguments) -> fmt::Result {
+        self.indent(depth)?;
+        self.out.write_fmt(text)?;
+        self.out.write_char('\n')
+    }
+
+    fn block(&mut self, block: &[Spanned<Statement>], depth: usize) -> fmt::Result {
+        for statement in block.iter() {
+            self.statement(&statement.elem, depth)?;
+        }
+        Ok(())
+    }
+
+    fn statement(&mut self, statement: &Statement, depth: usize) -> fmt::Result {
+        match statement {
+            Statement::While { condition, block } => {
+                self.line(depth, format_args!("while({})", FormatExpr(condition)))?;
+                self.block(block, depth + 1)
+            }
+            Statement::DoWhile { block, condition } => {
+                self.line(depth, format_args!("do"))?;
+                self.block(block, depth + 1)?;
+                self.line(depth, format_args!("while({})", FormatExpr(&condition.elem)))
+            }
+            Statement::If { arms, else_arm } => {
+                for (i, (condition, block)) in arms.iter().enumerate() {
+                    let keyword = if i == 0 { "if" } else { "else if" };
+                    self.line(depth, format_args!("{}({})", keyword, FormatExpr(&condition.elem)))?;
+                    self.block(block, depth + 1)?;
+                }
+                if let Some(block) = else_arm {
+                    self.line(depth, format_args!("else"))?;
+                    self.block(block, depth + 1)?;
+                }
+                Ok(())
+            }
+            Statement::ForInfinite { block } => {
+                self.line(depth, format_args!("for()"))?;
+                self.block(block, depth + 1)
+            }
+            Statement::ForLoop { init, test, inc, block } => {
+                let mut header = String::from("for(");
+                if let Some(init) = init {
+                    write_simple(&mut header, init)?;
+                }
+                header.push(';');
+                if let Some(test) = test {
+                    write!(header, " {}", FormatExpr(test))?;
+                }
+                header.push(';');
+                if let Some(inc) = inc {
+                    header.push(' ');
+                    write_simple(&mut header, inc)?;
+                }
+                header.push(')');
+                self.line(depth, format_args!("{}", header))?;
+                self.block(block, depth + 1)
+            }
+            Statement::ForList(for_list) => {
+                let mut header = String::from("for(");
+                write_loop_var(&mut header, for_list.var_type.as_ref(), &for_list.name)?;
+                if let Some(input_type) = for_list.input_type {
+                    write!(header, " as {}", input_type)?;
+                }
+                if let Some(in_list) = &for_list.in_list {
+                    write!(header, " in {}", FormatExpr(in_list))?;
+                }
+                header.push(')');
+                self.line(depth, format_args!("{}", header))?;
+                self.block(&for_list.block, depth + 1)
+            }
+            Statement::ForRange(for_range) => {
+                let mut header = String::from("for(");
+                write_loop_var(&mut header, for_range.var_type.as_ref(), &for_range.name)?;
+                header.push_str(" = ");
+                write_operand(&mut header, &for_range.start, strength(&for_range.start) >= STRENGTH_ASSIGN, false)?;
+                write!(header, " to {}", FormatExpr(&for_range.end))?;
+                if let Some(step) = &for_range.step {
+                    write!(header, " step {}", FormatExpr(step))?;
+                }
+                header.push(')');
+                self.line(depth, format_args!("{}", header))?;
+                self.block(&for_range.block, depth + 1)
+            }
+            Statement::Spawn { delay, block } => {
+                match delay {
+                    Some(delay) => self.line(depth, format_args!("spawn({})", FormatExpr(delay)))?,
+                    None => self.line(depth, format_args!("spawn"))?,
+                }
+                self.block(block, depth + 1)
+            }
+            Statement::Switch { input, cases, default } => {
+                self.line(depth, format_args!("switch({})", FormatExpr(input)))?;
+                for (case, block) in cases.iter() {
+                    let mut header = String::from("if(");
+                    for (i, each) in case.elem.iter().enumerate() {
+                        if i > 0 {
+                            header.push_str(", ");
+                        }
+                        match each {
+                            Case::Exact(expr) => write!(header, "{}", FormatExpr(expr))?,
+                            Case::Range(start, end) => write!(header, "{} to {}", FormatExpr(start), FormatExpr(end))?,
+                        }
+                    }
+                    header.push(')');
+                    self.line(depth + 1, format_args!("{}", header))?;
+                    self.block(block, depth + 2)?;
+                }
+                if let Some(block) = default {
+                    self.line(depth + 1, format_args!("else"))?;
+                    self.block(block, depth + 2)?;
+                }
+                Ok(())
+            }
+            Statement::TryCatch { try_block, catch_params, catch_block } => {
+                self.line(depth, format_args!("try"))?;
+                self.block(try_block, depth + 1)?;
+                if catch_params.is_empty() {
+                    self.line(depth, format_args!("catch"))?;
+                } else {
+                    let params: Vec<_> = catch_params.iter().map(|path| path.join("/")).collect();
+                    self.line(depth, format_args!("catch({})", params.join(", ")))?;
+                }
+                self.block(catch_block, depth + 1)
+            }
+            Statement::Label { name, block } => {
+                self.line(depth, format_args!("{}:", name))?;
+                self.block(block, depth + 1)
+            }
+            simple => {
+                self.indent(depth)?;
+                write_simple(&mut self.out, simple)?;
+                self.out.write_char('\n')
+            }
+        }
+    }
+}
+
+fn write_loop_var<W: Write>(f: &mut W, var_type: Option<&VarType>, name: &str) -> fmt::Result {
+    match var_type {
+        Some(var_type) => write!(f, "var/{}{}", var_type, name),
+        None => f.write_str(name),
+    }
+}
+
+fn write_var<W: Write>(f: &mut W, var: &VarStatement) -> fmt::Result {
+    if var.dimensions.is_empty() {
+        write!(f, "{}{}", var.var_type, var.name)?;
+    } else {
+        // The sizes add a `list/` to the type, which they'll add back.
+        write!(f, "{}", var.var_type.flags)?;
+        for bit in var.var_type.type_path.iter().skip(1) {
+            write!(f, "{}/", bit)?;
+        }
+        f.write_str(&var.name)?;
+        for size in var.dimensions.iter() {
+            match size {
+                Some(size) => write!(f, "[{}]", FormatExpr(size))?,
+                None => f.write_str("[]")?,
+            }
+        }
+        if var.has_implied_value() {
+            return Ok(());
+        }
+    }
+    if let Some(value) = &var.value {
+        write!(f, " = {}", FormatExpr(value))?;
+    }
+    Ok(())
+}
+
+/// Write a statement which fits on one line, as used in `for` headers.
+fn write_simple<W: Write>(f: &mut W, statement: &Statement) -> fmt::Result {
+    match statement {
+        Statement::Expr(expr) => write_expr(f, expr, false),
+        Statement::Return(None) => f.write_str("return"),
+        Statement::Return(Some(expr)) => write!(f, "return {}", FormatExpr(expr)),
+        Statement::Throw(expr) => write!(f, "throw {}", FormatExpr(expr)),
+        Statement::Var(var) => {
+            f.write_str("var/")?;
+            write_var(f, var)
+        }
+        Statement::Vars(vars) => {
+            f.write_str("var/")?;
+            for (i, var) in vars.iter().enumerate() {
+                if i > 0 {
+                    f.write_str(", ")?;
+                }
+                write_var(f, var)?;
+            }
+            Ok(())
+        }
+        Statement::Setting { name, mode, value } => write!(f, "set {} {} {}", name, mode, FormatExpr(value)),
+        Statement::Continue(None) => f.write_str("continue"),
+        Statement::Continue(Some(label)) => write!(f, "continue {}", label),
+        Statement::Break(None) => f.write_str("break"),
+        Statement::Break(Some(label)) => write!(f, "break {}", label),
+        Statement::Goto(label) => write!(f, "goto {}", label),
+        Statement::Del(expr) => write!(f, "del {}", FormatExpr(expr)),
+        Statement::Crash(None) => f.write_str("CRASH()"),
+        Statement::Crash(Some(expr)) => write!(f, "CRASH({})", FormatExpr(expr)),
+        // Block statements have no one-line form.
+        _ => Err(fmt::Error),
+    }
+}
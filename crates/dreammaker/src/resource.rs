@@ -0,0 +1,194 @@
+//! Resolution of `'resource'` literals against `FILE_DIR` directories.
+//!
+//! BYOND looks for a resource relative to the environment's directory and
+//! then relative to each `#define FILE_DIR` in the order they were defined.
+//! Names are matched case-insensitively, as on Windows. A `*` component in a
+//! `FILE_DIR` stands for every subdirectory at that position; such defines
+//! must be quoted, as in `#define FILE_DIR "icons/*"`, to avoid opening a
+//! comment. Backslashes in either are read as path separators.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+
+use ahash::RandomState;
+use serde::{Serialize, Deserialize};
+
+/// A directory entry as remembered by the listing cache.
+#[derive(Debug, Clone)]
+struct Entry {
+    name: String,
+    is_dir: bool,
+}
+
+/// The search path for resource literals.
+#[derive(Debug, Default, Clone)]
+pub struct ResourceDirs {
+    /// The directory containing the environment.
+    root: PathBuf,
+    /// Each `FILE_DIR`, relative to `root`, in definition order.
+    file_dirs: Vec<PathBuf>,
+    /// Report candidates without consulting the filesystem.
+    dry: bool,
+    /// Directory listings by path, or `None` if the directory is unreadable.
+    listings: RefCell<HashMap<PathBuf, Option<Vec<Entry>>, RandomState>>,
+}
+
+/// The persistent part of a `ResourceDirs`, as stored in the cache.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct SavedResourceDirs {
+    root: PathBuf,
+    file_dirs: Vec<PathBuf>,
+}
+
+impl ResourceDirs {
+    pub(crate) fn set_root(&mut self, root: &Path) {
+        self.root = root.to_owned();
+    }
+
+    pub(crate) fn push_file_dir(&mut self, dir: PathBuf) {
+        if !self.file_dirs.contains(&dir) {
+            self.file_dirs.push(dir);
+        }
+    }
+
+    pub(crate) fn set_dry(&mut self, dry: bool) {
+        self.dry = dry;
+    }
+
+    pub(crate) fn save(&self) -> SavedResourceDirs {
+        SavedResourceDirs {
+            root: self.root.clone(),
+            file_dirs: self.file_dirs.clone(),
+        }
+    }
+
+    pub(crate) fn restore(&mut self, saved: SavedResourceDirs) {
+        self.root = saved.root;
+        self.file_dirs = saved.file_dirs;
+        self.listings.get_mut().clear();
+    }
+
+    /// Whether resources are resolved without consulting the filesystem.
+    pub fn is_dry(&self) -> bool {
+        self.dry
+    }
+
+    /// The `FILE_DIR` directories, in search order.
+    pub fn file_dirs(&self) -> &[PathBuf] {
+        &self.file_dirs
+    }
+
+    /// Every path at which the given resource will be looked for, in order.
+    ///
+    /// In dry mode, wildcard directories are reported unexpanded.
+    pub fn candidates(&self, literal: &str) -> Vec<PathBuf> {
+        let literal = resource_path(literal);
+        let literal = literal.as_path();
+        let mut out = vec![self.root.join(literal)];
+        for dir in self.file_dirs.iter() {
+            if self.dry {
+                out.push(self.root.join(dir).join(literal));
+            } else {
+                for expanded in self.expand(dir) {
+                    out.push(expanded.join(literal));
+                }
+            }
+        }
+        out
+    }
+
+    /// Find the file a resource literal refers to.
+    ///
+    /// In dry mode, the first candidate is returned without being checked.
+    pub fn resolve(&self, literal: &str) -> Option<PathBuf> {
+        if self.dry {
+            return self.candidates(literal).into_iter().next();
+        }
+        let literal = resource_path(literal);
+        let literal = literal.as_path();
+        if literal.is_absolute() {
+            return Some(literal.to_owned()).filter(|path| path.exists());
+        }
+        std::iter::once(self.root.clone())
+            .chain(self.file_dirs.iter().flat_map(|dir| self.expand(dir)))
+            .find_map(|dir| self.find_within(dir, literal, false))
+    }
+
+    /// Expand any `*` components of a `FILE_DIR` into real directories.
+    fn expand(&self, dir: &Path) -> Vec<PathBuf> {
+        let mut current = vec![self.root.clone()];
+        for component in dir.components() {
+            let mut next = Vec::new();
+            for base in current {
+                match component {
+                    Component::CurDir => next.push(base),
+                    Component::Normal(name) if name == "*" => {
+                        self.with_listing(&base, |entries| {
+                            next.extend(entries.iter().filter(|e| e.is_dir).map(|e| base.join(&e.name)));
+                        });
+                    }
+                    Component::Normal(name) => {
+                        next.extend(self.find_within(base, Path::new(name), true));
+                    }
+                    other => next.push(base.join(other)),
+                }
+            }
+            current = next;
+        }
+        current
+    }
+
+    /// Case-insensitively find `relative` within `base`.
+    fn find_within(&self, base: PathBuf, relative: &Path, want_dir: bool) -> Option<PathBuf> {
+        let mut current = base;
+        let mut components = relative.components().peekable();
+        while let Some(component) = components.next() {
+            let last = components.peek().is_none();
+            let name = match component {
+                Component::Normal(name) => name.to_string_lossy(),
+                Component::CurDir => continue,
+                other => {
+                    current.push(other);
+                    continue;
+                }
+            };
+            let found = self.with_listing(&current, |entries| {
+                let usable = |e: &&Entry| e.is_dir || (last && !want_dir);
+                entries.iter().filter(usable).find(|e| e.name == name)
+                    .or_else(|| entries.iter().filter(usable).find(|e| e.name.eq_ignore_ascii_case(&name)))
+                    .map(|e| e.name.clone())
+            })?;
+            current.push(found);
+        }
+        Some(current)
+    }
+
+    /// Run a function against the cached listing of a directory.
+    fn with_listing<R>(&self, dir: &Path, f: impl FnOnce(&[Entry]) -> R) -> R {
+        let mut listings = self.listings.borrow_mut();
+        let listing = listings.entry(dir.to_owned()).or_insert_with(|| read_listing(dir));
+        f(listing.as_deref().unwrap_or(&[]))
+    }
+}
+
+/// The path a resource literal names, with DM's backslashes as separators.
+fn resource_path(literal: &str) -> PathBuf {
+    PathBuf::from(literal.replace("\\", "/"))
+}
+
+fn read_listing(dir: &Path) -> Option<Vec<Entry>> {
+    // An empty path means the current directory.
+    let dir = if dir.as_os_str().is_empty() { Path::new(".") } else { dir };
+    let mut entries = Vec::new();
+    for entry in dir.read_dir().ok()? {
+        let entry = entry.ok()?;
+        entries.push(Entry {
+            name: entry.file_name().to_string_lossy().into_owned(),
+            is_dir: entry.file_type().is_ok_and(|t| t.is_dir() || (t.is_symlink() && entry.path().is_dir())),
+        });
+    }
+    // Keep case-insensitive matches deterministic.
+    entries.sort_by(|a, b| a.name.cmp(&b.name));
+    Some(entries)
+}
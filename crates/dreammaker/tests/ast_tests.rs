@@ -32,3 +32,29 @@ fn check_semicolon_in_proc_parameters() {
         }
     });
 }
+
+#[test]
+fn list_sizes_are_kept() {
+    with_code("
+/obj/var/grid[2][3]
+/obj/var/list/given[4] = list()
+
+/proc/test()
+    var/slots[8]
+", |context, tree| {
+        context.assert_success();
+        let obj = tree.expect("/obj");
+        let grid = obj.get_var_declaration("grid").unwrap();
+        assert_eq!(grid.dimensions.len(), 2);
+        assert_eq!(obj.get_var_declaration("given").unwrap().dimensions.len(), 1);
+
+        let code = tree.root().get_proc("test").unwrap().get().code.clone().unwrap();
+        match &code[0].elem {
+            ast::Statement::Var(var) => {
+                assert_eq!(var.dimensions.len(), 1);
+                assert!(var.has_implied_value());
+            }
+            other => panic!("expected a var statement, got {:?}", other),
+        }
+    });
+}
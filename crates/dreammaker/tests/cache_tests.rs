@@ -0,0 +1,146 @@
+extern crate dreammaker as dm;
+
+use std::path::{Path, PathBuf};
+
+use dm::Context;
+use dm::objtree::ObjectTree;
+
+const VERSION: &str = "test";
+
+fn fixture(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("dm-cache-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("env.dme"), r#"
+#define HEALTH 100
+#include "mob.dm"
+"#.trim()).unwrap();
+    std::fs::write(dir.join("mob.dm"), r#"
+/mob/player
+    name = "player"
+    var/health = HEALTH
+    var/list/items = list("a" = 1)
+
+/mob/player/proc/heal(amount = 5)
+    health = min(health + amount, HEALTH)
+    return health
+
+/obj/thing/desc = @"a raw string"
+"#.trim()).unwrap();
+    dir
+}
+
+fn parse(context: &Context, dir: &Path) -> ObjectTree {
+    let pp = dm::preprocessor::Preprocessor::new(context, dir.join("env.dme")).unwrap();
+    let indents = dm::indents::IndentProcessor::new(context, pp);
+    let mut parser = dm::parser::Parser::new(context, indents);
+    parser.enable_procs();
+    let (fatal_errored, tree) = parser.parse_object_tree_2();
+    assert!(!fatal_errored);
+    tree
+}
+
+fn summarize(tree: &ObjectTree) -> Vec<String> {
+    let mut out = Vec::new();
+    tree.root().recurse(&mut |ty| {
+        out.push(format!("type {} parent {:?}", ty.path, ty.parent_type().map(|p| p.path.clone())));
+        for (name, var) in ty.vars.iter() {
+            out.push(format!("  var {} = {:?}", name, var.value.constant));
+        }
+        for (name, proc) in ty.procs.iter() {
+            out.push(format!("  proc {} x{} {:?}", name, proc.value.len(), proc.main_value().code));
+        }
+    });
+    out
+}
+
+#[test]
+fn round_trip() {
+    let dir = fixture("round-trip");
+    let dme = dir.join("env.dme");
+    let cache = dir.join("tree.cache");
+
+    let context = Context::default();
+    let tree = parse(&context, &dir);
+    dm::cache::write(&context, &tree, &dme, VERSION, &cache).unwrap();
+
+    let warm = Context::default();
+    let loaded = dm::cache::read(&warm, &dme, VERSION, &cache).expect("cache rejected");
+    assert_eq!(summarize(&tree), summarize(&loaded));
+    assert!(loaded.find("/mob/player").is_some());
+    assert_eq!(warm.file_path(tree.expect("/mob/player").location.file), PathBuf::from("mob.dm"));
+
+    // Writing the loaded tree again must produce the same bytes.
+    let again = dir.join("again.cache");
+    dm::cache::write(&warm, &loaded, &dme, VERSION, &again).unwrap();
+    assert_eq!(std::fs::read(&cache).unwrap(), std::fs::read(&again).unwrap());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn stale_cache_rejected() {
+    let dir = fixture("stale");
+    let dme = dir.join("env.dme");
+    let cache = dir.join("tree.cache");
+
+    let context = Context::default();
+    let tree = parse(&context, &dir);
+    dm::cache::write(&context, &tree, &dme, VERSION, &cache).unwrap();
+
+    assert!(dm::cache::read(&Context::default(), &dme, "other version", &cache).is_none());
+    // A context which has already registered files can't take the cached list.
+    assert!(dm::cache::read(&context, &dme, VERSION, &cache).is_none());
+
+    std::fs::write(dir.join("mob.dm"), "/mob/other\n").unwrap();
+    assert!(dm::cache::read(&Context::default(), &dme, VERSION, &cache).is_none());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn foreign_file_rejected() {
+    let dir = fixture("foreign");
+    let dme = dir.join("env.dme");
+    let cache = dir.join("tree.cache");
+
+    let context = Context::default();
+    let tree = parse(&context, &dir);
+    dm::cache::write(&context, &tree, &dme, VERSION, &cache).unwrap();
+    let bytes = std::fs::read(&cache).unwrap();
+    assert!(dm::cache::read(&Context::default(), &dme, VERSION, &cache).is_some());
+
+    // Another format number.
+    let mut other_format = bytes.clone();
+    other_format[8] ^= 0xff;
+    std::fs::write(&cache, &other_format).unwrap();
+    assert!(dm::cache::read(&Context::default(), &dme, VERSION, &cache).is_none());
+
+    // Not a cache at all.
+    std::fs::write(&cache, &bytes[12..]).unwrap();
+    assert!(dm::cache::read(&Context::default(), &dme, VERSION, &cache).is_none());
+    std::fs::write(&cache, b"DMC").unwrap();
+    assert!(dm::cache::read(&Context::default(), &dme, VERSION, &cache).is_none());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn diagnostics_keep_errortypes() {
+    let dir = fixture("errortypes");
+    let dme = dir.join("env.dme");
+    let cache = dir.join("tree.cache");
+    std::fs::write(&dme, "#define HEALTH 100\n#define HEALTH 50\n#include \"mob.dm\"\n").unwrap();
+
+    let context = Context::default();
+    let tree = parse(&context, &dir);
+    dm::cache::write(&context, &tree, &dme, VERSION, &cache).unwrap();
+    let cold: Vec<_> = context.errors().iter().map(|error| (error.description().to_owned(), error.errortype())).collect();
+    assert!(cold.iter().any(|(_, errortype)| *errortype == Some("macro_redefined")));
+
+    let warm = Context::default();
+    dm::cache::read(&warm, &dme, VERSION, &cache).expect("cache rejected");
+    let replayed: Vec<_> = warm.errors().iter().map(|error| (error.description().to_owned(), error.errortype())).collect();
+    assert_eq!(cold, replayed);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
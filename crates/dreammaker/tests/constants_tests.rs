@@ -110,3 +110,218 @@ fn rgb_hcy() {
         Constant::string("#000000"),
     );
 }
+
+#[test]
+fn raw_string_exact() {
+    assert_eq!(
+        eval(r#"@"C:\[dir]\" + @{"two
+lines"}"#).unwrap(),
+        Constant::string("C:\\[dir]\\two\nlines"),
+    );
+}
+
+fn parse_with_defines(code: &str) -> (dm::objtree::ObjectTree, dm::preprocessor::DefineMap) {
+    let context = dm::Context::default();
+    let mut pp = dm::preprocessor::Preprocessor::from_buffer(&context, "test.dm".into(), code.trim());
+    let indents = dm::indents::IndentProcessor::new(&context, &mut pp);
+    let mut parser = dm::parser::Parser::new(&context, indents);
+    parser.enable_procs();
+    let tree = parser.parse_object_tree();
+    context.assert_success();
+    (tree, pp.finalize().defines_at_end())
+}
+
+#[test]
+fn const_expression_in_type() {
+    let (tree, defines) = parse_with_defines(r#"
+#define SCALE 10
+/obj/item
+    var/const/BASE = 3
+    var/weight = BASE * 2
+/obj/item/thing
+    var/normal
+"#);
+    let ty = tree.expect("/obj/item/thing");
+    let eval = |text: &str| evaluate_const_expression(&tree, ty, text, &defines);
+
+    assert_eq!(eval("BASE + weight * SCALE").unwrap(), Constant::Float(63.));
+    assert_eq!(eval("defined(UNDEFINED) ? \"yes\" : \"no\"").unwrap(), Constant::string("no"));
+    assert_eq!(eval("normal").unwrap(), Constant::Null(Some(Vec::new().into_boxed_slice())));
+    match eval(".thing").unwrap() {
+        Constant::Prefab(pop) => assert_eq!(pop.to_string(), "/obj/item/thing"),
+        other => panic!("expected prefab, got {:?}", other),
+    }
+    match eval("/obj/item/thing{name = \"x\"}").unwrap() {
+        Constant::Prefab(pop) => assert_eq!(pop.to_string(), "/obj/item/thing {name = \"x\"}"),
+        other => panic!("expected prefab, got {:?}", other),
+    }
+}
+
+#[test]
+fn const_expression_errors() {
+    let (tree, defines) = parse_with_defines(r#"
+/obj/item
+    var/const/BASE = 3
+"#);
+    let ty = tree.expect("/obj/item");
+    let eval = |text: &str| evaluate_const_expression(&tree, ty, text, &defines);
+
+    let error = eval("BASE + missing").unwrap_err();
+    assert_eq!(error.description(), "unknown variable: missing");
+    assert_eq!((error.location().line, error.location().column), (1, 8));
+
+    let error = eval("1 2").unwrap_err();
+    assert_eq!((error.location().line, error.location().column), (1, 3));
+
+    assert!(eval("BASE = 4").is_err());
+}
+
+#[test]
+fn icon_args() {
+    for code in &[
+        "icon()",
+        "icon('a.dmi')",
+        "icon('a.dmi', \"state\")",
+        "icon('a.dmi', \"state\", 10)",
+        "icon('a.dmi', \"state\", 0, 3)",
+        "icon('a.dmi', \"state\", null, 1, 1)",
+        "icon('a.dmi', frame = 2, dir = 4)",
+    ] {
+        assert!(eval(code).is_ok(), "{}", code);
+    }
+    assert_eq!(
+        eval("icon(1)").unwrap_err().description(),
+        "malformed icon() call, argument 1 (icon) must be an icon or file and instead is 1",
+    );
+    assert_eq!(
+        eval("icon('a.dmi', \"state\", 3)").unwrap_err().description(),
+        "malformed icon() call, argument 3 (dir) must be a direction and instead is 3",
+    );
+    assert_eq!(
+        eval("icon('a.dmi', frame = 0.5)").unwrap_err().description(),
+        "malformed icon() call, argument 4 (frame) must be a whole number of 0 or more and instead is 0.5",
+    );
+    assert_eq!(
+        eval("icon('a.dmi', \"state\", 2, 1, 2)").unwrap_err().description(),
+        "malformed icon() call, argument 5 (moving) must be 0, 1, or null and instead is 2",
+    );
+    assert_eq!(
+        eval("icon('a.dmi', \"state\", 2, 1, 0, 0)").unwrap_err().description(),
+        "malformed icon() call, must have at most 5 arguments and instead has 6",
+    );
+}
+
+#[test]
+fn matrix_args() {
+    for code in &[
+        "matrix()",
+        "matrix(null)",
+        "matrix(1, 0, 0, 0, 1, 0)",
+        "matrix(null, 0)",
+        "matrix(45, 5)",
+        "matrix(null, 45, 5)",
+        "matrix(2, 2, 6)",
+        "matrix(null, 2, 2, 7 | 128)",
+        "matrix(null, null, 0.5, 8)",
+    ] {
+        assert!(eval(code).is_ok(), "{}", code);
+    }
+    assert_eq!(
+        eval("matrix(\"a\")").unwrap_err().description(),
+        "malformed matrix() call, argument 1 must be a matrix and instead is \"a\"",
+    );
+    assert_eq!(
+        eval("matrix(null, 20)").unwrap_err().description(),
+        "malformed matrix() call, argument 2 must be a MATRIX_* operation and instead is 20",
+    );
+    assert_eq!(
+        eval("matrix(null, null, 2, 3, 5)").unwrap_err().description(),
+        "malformed matrix() call, must have 0, 1, 2 to 4, or 6 arguments and instead has 5",
+    );
+    assert_eq!(
+        eval("matrix(null, 2, 2, 5)").unwrap_err().description(),
+        "malformed matrix() call, MATRIX_ROTATE must have 2 or 3 arguments and instead has 4",
+    );
+    assert_eq!(
+        eval("matrix(1, 0, 0, 0, 1, 'a.dmi')").unwrap_err().description(),
+        "malformed matrix() call, argument 6 must be a number and instead is 'a.dmi'",
+    );
+    assert_eq!(
+        eval("matrix(1, 0, 0, 0, 1, 0, 0)").unwrap_err().description(),
+        "malformed matrix() call, must have 0, 1, 2 to 4, or 6 arguments and instead has 7",
+    );
+}
+
+#[test]
+fn random_outcomes() {
+    assert_eq!(prob_outcome(0.), Some(false));
+    assert_eq!(prob_outcome(-5.), Some(false));
+    assert_eq!(prob_outcome(100.), Some(true));
+    assert_eq!(prob_outcome(50.), None);
+
+    assert_eq!(rand_range(&[6.]), Some((0., 6.)));
+    assert_eq!(rand_range(&[1.5, 4.5]), Some((2., 4.)));
+    assert_eq!(rand_range(&[6., 1.]), None);
+    assert_eq!(rand_range(&[]), None);
+
+    let range = (1., 6.);
+    assert_eq!(compare_range(range, dm::ast::BinaryOp::Greater, 6.), Some(false));
+    assert_eq!(compare_range(range, dm::ast::BinaryOp::GreaterEq, 1.), Some(true));
+    assert_eq!(compare_range(range, dm::ast::BinaryOp::Less, 4.), None);
+    assert_eq!(compare_range(range, dm::ast::BinaryOp::Eq, 2.5), Some(false));
+    assert_eq!(compare_range(range, dm::ast::BinaryOp::NotEq, 0.), Some(true));
+    assert_eq!(compare_range((3., 3.), dm::ast::BinaryOp::Eq, 3.), Some(true));
+}
+
+fn global_init_errors(code: &str) -> Vec<(u32, String)> {
+    let context = dm::Context::default();
+    let pp = dm::preprocessor::Preprocessor::from_buffer(&context, "test.dm".into(), code.trim());
+    let indents = dm::indents::IndentProcessor::new(&context, pp);
+    let mut parser = dm::parser::Parser::new(&context, indents);
+    parser.enable_procs();
+    parser.parse_object_tree();
+    let errors = context.errors();
+    errors.iter()
+        .filter(|error| matches!(error.errortype(), Some("global_init_cycle" | "global_init_order")))
+        .map(|error| (error.location().line, error.description().to_owned()))
+        .collect()
+}
+
+#[test]
+fn global_init_cycles() {
+    assert_eq!(global_init_errors(r#"
+var/global/a = b + 1
+var/global/b = a * 2
+var/global/self = self + 1
+/datum/thing/var/static/first = second
+/datum/thing/var/static/second = first
+"#), vec![
+        (1, "global a depends on its own value: a -> b -> a".to_owned()),
+        (3, "global self depends on its own value: self -> self".to_owned()),
+        (4, "global /datum/thing/var/first depends on its own value: /datum/thing/var/first -> /datum/thing/var/second -> /datum/thing/var/first".to_owned()),
+    ]);
+}
+
+#[test]
+fn global_init_order() {
+    assert_eq!(global_init_errors(r#"
+var/global/list/early = later.Copy()
+var/global/list/later = list(1, 2)
+/datum/thing
+    var/static/count = total + 1
+    var/instance = 2
+    var/static/from_instance = instance + 1
+var/global/total = rand(1, 2)
+var/global/via_global = global.after
+var/global/after = rand(1, 2)
+var/global/uses_compiled = compiled + 1
+var/global/compiled = 5 * 2
+var/const/LIMIT = 3
+var/global/uses_const = LIMIT + 1
+var/global/uses_earlier = total + 1
+"#), vec![
+        (1, "global early is initialized using later, which is declared later and is still null then".to_owned()),
+        (4, "global /datum/thing/var/count is initialized using total, which is declared later and is still null then".to_owned()),
+        (8, "global via_global is initialized using after, which is declared later and is still null then".to_owned()),
+    ]);
+}
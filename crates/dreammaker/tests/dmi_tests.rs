@@ -0,0 +1,59 @@
+extern crate dreammaker as dm;
+
+use dm::dmi::{Dirs, Frames, Metadata};
+
+/// A PNG holding only its header and, if not empty, a `Description` text
+/// chunk. Checksums are left zero, as they aren't verified.
+fn png_with_description(description: &str) -> Vec<u8> {
+    fn chunk(out: &mut Vec<u8>, kind: &[u8], data: &[u8]) {
+        out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        out.extend_from_slice(kind);
+        out.extend_from_slice(data);
+        out.extend_from_slice(&[0; 4]);
+    }
+    let mut out = b"\x89PNG\r\n\x1a\n".to_vec();
+    let mut header = Vec::new();
+    header.extend_from_slice(&64u32.to_be_bytes());
+    header.extend_from_slice(&32u32.to_be_bytes());
+    header.extend_from_slice(&[8, 6, 0, 0, 0]);
+    chunk(&mut out, b"IHDR", &header);
+    if !description.is_empty() {
+        chunk(&mut out, b"tEXt", format!("Description\0{}", description).as_bytes());
+    }
+    chunk(&mut out, b"IEND", &[]);
+    out
+}
+
+#[test]
+fn metadata_without_pixels() {
+    let png = png_with_description(r#"# BEGIN DMI
+version = 4.0
+	width = 32
+	height = 32
+state = "wrench"
+	dirs = 4
+	frames = 1
+state = "say \"hi\""
+	dirs = 1
+	frames = 2
+	delay = 1,3
+# END DMI
+"#);
+    let metadata = Metadata::from_png_bytes(&png).unwrap();
+    assert_eq!(metadata.states.len(), 2);
+    assert_eq!(metadata.states[0].dirs, Dirs::Four);
+    assert_eq!(metadata.states[1].name, "say \"hi\"");
+    assert_eq!(metadata.states[1].offset, 4);
+    assert_eq!(metadata.states[1].frames, Frames::Delays(vec![1., 3.]));
+    assert!(metadata.state_names.contains_key("wrench"));
+}
+
+#[test]
+fn malformed_metadata_is_an_error() {
+    assert!(Metadata::from_png_bytes(b"GIF89a").is_err());
+    assert!(Metadata::from_png_bytes(&png_with_description("# BEGIN DMI\nversion = 4.0\n\tdirs = 4\n")).is_err());
+
+    let plain = Metadata::from_png_bytes(&png_with_description("")).unwrap();
+    assert!(plain.states.is_empty());
+    assert_eq!((plain.width, plain.height), (64, 32));
+}
@@ -114,3 +114,66 @@ fn bitop_precedence() {
         }
     );
 }
+
+#[test]
+fn logical_assign_operators() {
+    for &(text, op) in &[("foo &&= bar || 1", AssignOp::AndAssign), ("foo ||= bar || 1", AssignOp::OrAssign), ("foo ?= bar || 1", AssignOp::NullAssign)] {
+        // foo op= (bar || 1)
+        assert_eq!(
+            parse_expr(text),
+            Expression::AssignOp {
+                op,
+                lhs: Box::new(Expression::from(Term::Ident("foo".to_owned()))),
+                rhs: Box::new(Expression::BinaryOp {
+                    op: BinaryOp::Or,
+                    lhs: Box::new(Expression::from(Term::Ident("bar".to_owned()))),
+                    rhs: Box::new(Expression::from(Term::Int(1))),
+                }),
+            }
+        );
+    }
+    // `?` followed by `=` elsewhere is still a ternary and a safe access
+    parse_expr("foo = bar ? 1 : 2");
+    parse_expr("foo = bar?.baz");
+}
+
+#[test]
+fn proc_references() {
+    fn reference(code: &str) -> ProcReference {
+        match Term::from(parse_expr(code)) {
+            Term::ProcReference(reference) => *reference,
+            other => panic!("{} parsed as {:?}", code, other),
+        }
+    }
+    fn path(s: &[(PathOp, &str)]) -> TypePath {
+        s.iter().map(|&(op, name)| (op, name.to_owned())).collect()
+    }
+
+    let r = reference(".proc/update_icon");
+    assert_eq!((r.ty, r.kind, r.name.as_str()), (None, ProcDeclKind::Proc, "update_icon"));
+    let r = reference(".verb/say");
+    assert_eq!((r.ty, r.kind, r.name.as_str()), (None, ProcDeclKind::Verb, "say"));
+    let r = reference("/obj/item/proc/attack");
+    assert_eq!(r.ty, Some(path(&[(PathOp::Slash, "obj"), (PathOp::Slash, "item")])));
+    assert_eq!(r.to_string(), "/obj/item/proc/attack");
+    let r = reference("/proc/global_thing");
+    assert_eq!(r.ty, Some(Vec::new()));
+
+    // Paths naming types or proc groups are still prefabs.
+    for code in &["/obj/item", ".proc", "/obj/proc", "/obj/proc/foo{x = 1}"] {
+        assert!(matches!(Term::from(parse_expr(code)), Term::Prefab(_)), "{}", code);
+    }
+}
+
+#[test]
+fn weighted_pick() {
+    let choices = match Term::from(parse_expr("pick(prob(30); /obj/item/a, 70; /obj/item/b, \"c\")")) {
+        Term::Pick(choices) => choices,
+        other => panic!("expected pick, got {:?}", other),
+    };
+    assert_eq!(choices.len(), 3);
+    assert!(matches!(choices[0].0.as_ref().and_then(|w| w.as_term()), Some(Term::Call(name, _)) if name == "prob"));
+    assert_eq!(choices[1].0, Some(Expression::from(Term::Int(70))));
+    assert!(matches!(choices[1].1.as_term(), Some(Term::Prefab(_))));
+    assert_eq!(choices[2].0, None);
+}
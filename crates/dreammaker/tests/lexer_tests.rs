@@ -67,7 +67,7 @@ fn empty_block_comment() {
 
 #[test]
 fn raw_strings() {
-    let desired = Token::String("content".to_owned());
+    let desired = Token::RawString("content".to_owned());
     let stuff = lex(r###"
 @"content"
 @xcontentx
@@ -83,3 +83,40 @@ fn raw_strings() {
         assert_eq!(each, &desired);
     }
 }
+
+#[test]
+fn string_forms() {
+    // each line of this fixture should lex to exactly one string token
+    let stuff: Vec<_> = lex(r###"
+"plain"
+"embedded \"quotes\""
+"trailing backslash\\"
+{"block "with" quotes"}
+{"block
+spanning lines"}
+{"ends with quote""}
+@"C:\new\[path]\"
+@{"raw "block"
+with [brackets]"}
+@(EOD)"}"EOD
+"###).into_iter().filter(|t| *t != Punct(Newline)).collect();
+    assert_eq!(stuff, vec![
+        String("plain".into()),
+        String(r#"embedded \"quotes\""#.into()),
+        String(r#"trailing backslash\\"#.into()),
+        String(r#"block "with" quotes"#.into()),
+        String("block\nspanning lines".into()),
+        String(r#"ends with quote""#.into()),
+        RawString(r#"C:\new\[path]\"#.into()),
+        RawString("raw \"block\"\nwith [brackets]".into()),
+        RawString(r#""}""#.into()),
+    ]);
+}
+
+#[test]
+fn raw_string_round_trip() {
+    for text in &["simple", "with \"quotes\"", "multi\nline", "both \"}\n"] {
+        let formatted = RawQuote(text).to_string();
+        assert_eq!(one_token(&formatted), RawString((*text).to_owned()), "{}", formatted);
+    }
+}
@@ -69,3 +69,84 @@ ok2
         Ident("ok2".into(), false),
     ]);
 }
+
+fn directive_errors(source: &'static str) -> Vec<(u32, u16, dm::Severity, std::string::String)> {
+    let ctx = dm::Context::default();
+    let pp = Preprocessor::from_buffer(&ctx, "macro_tests.rs".into(), source);
+    pp.for_each(drop);
+    let errors = ctx.errors();
+    errors.iter()
+        .map(|e| (e.location().line, e.location().column, e.severity(), e.description().to_owned()))
+        .collect()
+}
+
+#[test]
+fn warn_and_error_directives() {
+    assert_eq!(directive_errors(r#"
+#warn deprecated   include [do not use]
+#error you must define MAP_NAME \n
+"#), &[
+        (2, 1, dm::Severity::Warning, "#warn deprecated   include [do not use]".to_owned()),
+        (3, 1, dm::Severity::Error, "#error you must define MAP_NAME \\n".to_owned()),
+    ]);
+}
+
+#[test]
+fn disabled_warn_and_error_directives() {
+    assert_eq!(directive_errors(r#"
+#ifdef MAP_NAME
+#error never shown
+#endif
+#if 0
+#warn never shown
+#elif 1
+#warn shown
+#endif
+"#), &[
+        (8, 1, dm::Severity::Warning, "#warn shown".to_owned()),
+    ]);
+}
+
+#[test]
+fn define_usage_map() {
+    let ctx = dm::Context::default();
+    let mut pp = Preprocessor::from_buffer(&ctx, "macro_tests.rs".into(), r#"
+#define LEVEL 1
+#define DOUBLE(x) ((x) * 2)
+LEVEL
+DOUBLE(LEVEL)
+#undef LEVEL
+#define LEVEL 2
+LEVEL
+#define LEVEL 3
+DOUBLE
+DM_VERSION
+"#.trim());
+    pp.enable_define_usage();
+    pp.by_ref().for_each(drop);
+    let usage = pp.take_define_usage().unwrap();
+
+    let levels: Vec<_> = usage.get("LEVEL").collect();
+    assert_eq!(levels.len(), 3);
+    assert_eq!(levels.iter().map(|d| d.body()).collect::<Vec<_>>(), ["1", "2", "3"]);
+    assert_eq!(levels.iter().map(|d| d.location.line).collect::<Vec<_>>(), [1, 6, 8]);
+    assert_eq!(levels.iter().map(|d| d.ended.map(|l| l.line)).collect::<Vec<_>>(), [Some(5), Some(8), None]);
+    // The nested use is attributed to the outer expansion.
+    assert_eq!(levels[0].uses.iter().map(|l| l.line).collect::<Vec<_>>(), [3, 4]);
+    assert_eq!(levels[1].uses.iter().map(|l| l.line).collect::<Vec<_>>(), [7]);
+    assert!(levels[2].uses.is_empty());
+
+    // A function-like macro without arguments isn't an expansion.
+    let double: Vec<_> = usage.get("DOUBLE").collect();
+    assert_eq!(double.len(), 1);
+    assert_eq!(double[0].body(), "((x) * 2)");
+    assert!(matches!(double[0].define, Define::Function { ref params, .. } if params == &["x"]));
+    assert_eq!(double[0].uses.len(), 1);
+    assert_eq!(usage.definition_used_at(double[0].uses[0]).unwrap().name, "DOUBLE");
+
+    // Builtins are included once they are used.
+    let version: Vec<_> = usage.get("DM_VERSION").collect();
+    assert_eq!(version.len(), 1);
+    assert!(version[0].location.is_builtins());
+    assert_eq!(usage.get("DM_BUILD").count(), 0);
+}
@@ -0,0 +1,212 @@
+extern crate dreammaker as dm;
+
+use dm::Context;
+use dm::ast::*;
+use dm::indents::IndentProcessor;
+use dm::lexer::Lexer;
+use dm::parser::{parse_expression, Parser};
+use dm::printer::*;
+
+fn parse_expr(code: &str) -> Expression {
+    let context = Context::default();
+    let lexer = Lexer::new(&context, Default::default(), code.as_bytes());
+    let expr = parse_expression(&context, Default::default(), lexer).expect("failed to parse expression");
+    context.assert_success();
+    expr
+}
+
+fn parse_proc(code: &str) -> Block {
+    let context = Context::default();
+    let lexer = Lexer::new(&context, Default::default(), code.as_bytes());
+    let mut parser = Parser::new(&context, IndentProcessor::new(&context, lexer));
+    parser.enable_procs();
+    let tree = parser.parse_object_tree();
+    context.assert_success();
+    let code = tree.root().get_proc("test").expect("no /proc/test").get().code.clone();
+    code.expect("no code")
+}
+
+fn print_proc(block: &[Spanned<Statement>]) -> String {
+    let options = PrintOptions { depth: 1, ..Default::default() };
+    format!("/proc/test()\n{}", FormatBlock(block, &options))
+}
+
+fn round_trip_expr(code: &str) {
+    let expr = parse_expr(code);
+    let printed = FormatExpr(&expr).to_string();
+    assert_eq!(parse_expr(&printed), expr, "{} printed as {}", code, printed);
+}
+
+fn round_trip_proc(code: &str) {
+    let block = parse_proc(code);
+    let printed = print_proc(&block);
+    assert_eq!(parse_proc(&printed), block, "printed as:\n{}", printed);
+    // The printed form is a fixed point.
+    assert_eq!(print_proc(&parse_proc(&printed)), printed);
+}
+
+fn ident(name: &str) -> Expression {
+    Expression::from(Term::Ident(name.to_owned()))
+}
+
+fn binary(op: BinaryOp, lhs: Expression, rhs: Expression) -> Expression {
+    Expression::BinaryOp { op, lhs: Box::new(lhs), rhs: Box::new(rhs) }
+}
+
+#[test]
+fn expressions() {
+    for code in &[
+        "1 + 2 * 3 - 4 / 5 % 6",
+        "(1 + 2) * 3",
+        "a - (b - c)",
+        "2 ** 3 ** 4",
+        "a && b || !c && ~d",
+        "a = b += c ? d : e ? f : g",
+        "x in 1 to 10",
+        "!(x in list(1, 2, 3))",
+        "-x++ + --y",
+        "- -x",
+        "a.b?.c:d?:e[1]?[2].f(3)?.g()",
+        "foo(1, bar = 2, \"baz\" = list(\"a\" = 1))",
+        "new /obj{name = \"thing\"; desc = 5}(loc)",
+        "new .relative()",
+        "new a.b.c()",
+        "new",
+        ".()",
+        "..()",
+        "/datum/foo:bar.baz",
+        "input(usr, \"Pick\") as mob|obj in world",
+        "locate(/mob) in view(7)",
+        "pick(10; \"a\", 20; \"b\", \"c\")",
+        "call(src, \"proc\")(1, 2)",
+        "as(num)",
+        "0.5 + 1e+010 + 1.#INF + 2.0",
+        "'icon.dmi'",
+        "@\"raw [string]\"",
+        "{\"quote \" mark\"}",
+        "\"tab\\t and \\\"escapes\\\"\"",
+        "\"[x] is [y ? \"yes\" : \"no\"] []\"",
+        "a ? b : c",
+        ". = ..()",
+        "a ?= b",
+        "callback(.proc/update, /obj/proc/attack, /proc/global)",
+    ] {
+        round_trip_expr(code);
+    }
+}
+
+#[test]
+fn constructed_precedence() {
+    let a = || ident("a");
+    let b = || ident("b");
+    let c = || ident("c");
+    let cases = vec![
+        (binary(BinaryOp::Mul, binary(BinaryOp::Add, a(), b()), c()), "(a + b) * c"),
+        (binary(BinaryOp::Sub, a(), binary(BinaryOp::Sub, b(), c())), "a - (b - c)"),
+        (binary(BinaryOp::Sub, binary(BinaryOp::Sub, a(), b()), c()), "a - b - c"),
+        (Expression::AssignOp {
+            op: AssignOp::Assign,
+            lhs: Box::new(a()),
+            rhs: Box::new(binary(BinaryOp::In, b(), c())),
+        }, "a = (b in c)"),
+        (Expression::TernaryOp {
+            cond: Box::new(Expression::TernaryOp { cond: Box::new(a()), if_: Box::new(b()), else_: Box::new(c()) }),
+            if_: Box::new(b()),
+            else_: Box::new(c()),
+        }, "(a ? b : c) ? b : c"),
+        (Expression::Base {
+            term: Box::new(Spanned::new(Default::default(), Term::Ident("x".to_owned()))),
+            follow: vec![
+                Spanned::new(Default::default(), Follow::Unary(UnaryOp::Neg)),
+                Spanned::new(Default::default(), Follow::Field(PropertyAccessKind::Dot, "y".into())),
+            ].into_boxed_slice(),
+        }, "(-x).y"),
+        (Expression::from(Term::Prefab(Box::new(Prefab::from(vec![(PathOp::Slash, "obj".to_owned())])))), "/obj"),
+        (binary(BinaryOp::Eq, a(), Expression::from(Term::Int(5))), "a == 5"),
+    ];
+    for (expr, expected) in cases {
+        let printed = FormatExpr(&expr).to_string();
+        assert_eq!(printed, expected);
+        // Constructed trees re-parse to something that prints the same way.
+        assert_eq!(FormatExpr(&parse_expr(&printed)).to_string(), expected);
+    }
+}
+
+#[test]
+fn statements() {
+    round_trip_proc(r#"
+/proc/test(a, list/L)
+    var/x = 1
+    var/static/list/cache = list()
+    var/y, z = 2
+    var/list/sized[10]
+    var/grid[2][]
+    var/list/given[4] = list(1)
+    x += a
+    if (x > 1)
+        world.log << "big"
+    else if (x)
+        return
+    else
+        x = null
+    while (x < 10)
+        x++
+        if (x == 5)
+            continue
+        break
+    do
+        x--
+    while (x > 0)
+    for ()
+        break
+    for (var/i = 1; i <= 10; i++)
+        x += i
+    for (;;)
+        break
+    for (var/obj/O as obj in L)
+        del O
+    for (y in L)
+        world << y
+    for (var/j = 1 to 10 step 2)
+        x *= j
+    for (z in 1 to 3)
+        x /= z
+    spawn (10)
+        x = 0
+    spawn
+        x = 1
+    switch (x)
+        if (1, 2)
+            x = 3
+        if (4 to 6)
+            x = 7
+        else
+            x = 8
+    try
+        throw EXCEPTION("bad")
+    catch (var/exception/e)
+        world.log << e
+    try
+        x = 1
+    catch
+        x = 2
+    outer:
+        for (var/k in L)
+            goto outer
+    set name = "Test"
+    set src in usr
+    if (x)
+    else
+        CRASH("empty")
+    CRASH()
+    return x ? L[x] : null
+"#.trim());
+}
+
+#[test]
+fn indentation() {
+    let block = parse_proc("/proc/test()\n\tif(1)\n\t\treturn 2\n");
+    let options = PrintOptions { indent: "  ".to_owned(), depth: 0 };
+    assert_eq!(FormatBlock(&block, &options).to_string(), "if(1)\n  return 2\n");
+    assert_eq!(FormatStatement(&block[0].elem, &options).to_string(), "if(1)\n  return 2\n");
+}
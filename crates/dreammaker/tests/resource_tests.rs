@@ -0,0 +1,93 @@
+extern crate dreammaker as dm;
+
+use std::path::PathBuf;
+
+use dm::Context;
+
+fn load(context: &Context, dir: &std::path::Path) {
+    let pp = dm::preprocessor::Preprocessor::new(context, dir.join("env.dme")).unwrap();
+    let indents = dm::indents::IndentProcessor::new(context, pp);
+    dm::parser::Parser::new(context, indents).parse_object_tree();
+}
+
+const ENVIRONMENT: &str = r#"
+#define FILE_DIR .
+#define FILE_DIR "icons"
+#define FILE_DIR icons/obj
+#define FILE_DIR "sprites/*"
+"#;
+
+#[test]
+fn file_dir_search_order() {
+    let dir = std::env::temp_dir().join(format!("dm-resource-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("icons")).unwrap();
+    std::fs::create_dir_all(dir.join("sprites/mobs")).unwrap();
+    std::fs::write(dir.join("env.dme"), ENVIRONMENT.trim()).unwrap();
+    std::fs::write(dir.join("top.dmi"), "").unwrap();
+    std::fs::write(dir.join("icons/Item.dmi"), "").unwrap();
+    std::fs::write(dir.join("sprites/mobs/human.dmi"), "").unwrap();
+    std::fs::write(dir.join("sprites/mobs/top.dmi"), "").unwrap();
+
+    let context = Context::default();
+    load(&context, &dir);
+    context.assert_success();
+
+    assert_eq!(context.resources().file_dirs(), &[PathBuf::from("."), "icons".into(), "icons/obj".into(), "sprites/*".into()]);
+    assert_eq!(context.resolve_resource("top.dmi"), Some(dir.join("top.dmi")));
+    assert_eq!(context.resolve_resource("item.dmi"), Some(dir.join("icons/Item.dmi")));
+    assert_eq!(context.resolve_resource("human.dmi"), Some(dir.join("sprites/mobs/human.dmi")));
+    assert_eq!(context.resolve_resource("Sprites/Mobs/Human.dmi"), Some(dir.join("sprites/mobs/human.dmi")));
+    assert_eq!(context.resolve_resource("missing.dmi"), None);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn dry_run_candidates() {
+    let context = Context::default();
+    let pp = dm::preprocessor::Preprocessor::from_buffer(&context, "code/env.dme".into(), ENVIRONMENT.trim());
+    let indents = dm::indents::IndentProcessor::new(&context, pp);
+    dm::parser::Parser::new(&context, indents).parse_object_tree();
+    context.set_resource_dry_run(true);
+
+    assert_eq!(context.resources().candidates("a.dmi"), vec![
+        PathBuf::from("code/a.dmi"),
+        "code/./a.dmi".into(),
+        "code/icons/a.dmi".into(),
+        "code/icons/obj/a.dmi".into(),
+        "code/sprites/*/a.dmi".into(),
+    ]);
+    assert_eq!(context.resolve_resource("a.dmi"), Some(PathBuf::from("code/a.dmi")));
+}
+
+#[test]
+fn file_dir_raw_string() {
+    let context = Context::default();
+    let pp = dm::preprocessor::Preprocessor::from_buffer(&context, "env.dme".into(), "#define FILE_DIR @\"icons/raw\"");
+    let indents = dm::indents::IndentProcessor::new(&context, pp);
+    dm::parser::Parser::new(&context, indents).parse_object_tree();
+    context.assert_success();
+
+    assert_eq!(context.resources().file_dirs(), &[PathBuf::from("icons/raw")]);
+}
+
+#[test]
+fn backslash_separators() {
+    let dir = std::env::temp_dir().join(format!("dm-resource-backslash-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("icons/obj")).unwrap();
+    std::fs::create_dir_all(dir.join("sprites/mobs")).unwrap();
+    std::fs::write(dir.join("env.dme"), r#"#define FILE_DIR @"sprites\mobs""#).unwrap();
+    std::fs::write(dir.join("icons/obj/foo.dmi"), "").unwrap();
+    std::fs::write(dir.join("sprites/mobs/human.dmi"), "").unwrap();
+
+    let context = Context::default();
+    load(&context, &dir);
+    context.assert_success();
+
+    assert_eq!(context.resources().file_dirs(), &[PathBuf::from("sprites/mobs")]);
+    assert_eq!(context.resolve_resource(r"icons\obj\foo.dmi"), Some(dir.join("icons/obj/foo.dmi")));
+    assert_eq!(context.resolve_resource(r"Icons\Obj\Foo.dmi"), Some(dir.join("icons/obj/foo.dmi")));
+    assert_eq!(context.resolve_resource("human.dmi"), Some(dir.join("sprites/mobs/human.dmi")));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}